@@ -0,0 +1,293 @@
+use rusqlite::types::ValueRef;
+use rusqlite::{params, Connection, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use ts_rs::TS;
+
+// ============ PHI Audit Log ============
+//
+// Every command that reads or changes patient data appends a row to
+// `audit_log`. The table is append-only: triggers from migration 005 reject
+// any UPDATE or DELETE, and it has no foreign keys so entries outlive the
+// patients and rows they describe.
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Read,
+    Search,
+    Create,
+    Update,
+    Delete,
+    Merge,
+    Login,
+    LoginFailed,
+    Logout,
+    BreakGlass,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Read => "read",
+            AuditAction::Search => "search",
+            AuditAction::Create => "create",
+            AuditAction::Update => "update",
+            AuditAction::Delete => "delete",
+            AuditAction::Merge => "merge",
+            AuditAction::Login => "login",
+            AuditAction::LoginFailed => "login_failed",
+            AuditAction::Logout => "logout",
+            AuditAction::BreakGlass => "break_glass",
+        }
+    }
+}
+
+/// One access or change, before it is written
+#[derive(Debug)]
+pub struct AuditEvent<'a> {
+    pub action: AuditAction,
+    /// Table the entity lives in (`patients`, `encounters`, `allergies`, ...)
+    pub entity_type: &'a str,
+    pub entity_id: Option<i64>,
+    /// Whose chart this touched, so access reports can filter by patient
+    pub patient_id: Option<i64>,
+    /// Before/after diff for mutations, query text for searches, counts for list reads
+    pub detail: Option<Value>,
+}
+
+impl<'a> AuditEvent<'a> {
+    pub fn read(entity_type: &'a str, entity_id: Option<i64>, patient_id: Option<i64>) -> Self {
+        AuditEvent {
+            action: AuditAction::Read,
+            entity_type,
+            entity_id,
+            patient_id,
+            detail: None,
+        }
+    }
+
+    /// A search; `patient_ids` are the charts that showed up in the results
+    pub fn search(query: &str, patient_id: Option<i64>, patient_ids: &[i64]) -> Self {
+        AuditEvent {
+            action: AuditAction::Search,
+            entity_type: "search",
+            entity_id: None,
+            patient_id,
+            detail: Some(json!({ "query": query, "patient_ids": patient_ids })),
+        }
+    }
+
+    /// Sign-in and sign-out events, filed against the user rather than a chart
+    pub fn session(action: AuditAction, user_id: i64) -> Self {
+        AuditEvent {
+            action,
+            entity_type: "users",
+            entity_id: Some(user_id),
+            patient_id: None,
+            detail: None,
+        }
+    }
+
+    pub fn with_detail(mut self, detail: Value) -> Self {
+        self.detail = Some(detail);
+        self
+    }
+}
+
+/// A stored audit log row
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct AuditEntry {
+    #[ts(as = "f64")]
+    pub id: i64,
+    pub occurred_at: String,
+    #[ts(as = "Option<f64>")]
+    pub user_id: Option<i64>,
+    pub username: Option<String>,
+    pub action: String,
+    pub entity_type: String,
+    #[ts(as = "Option<f64>")]
+    pub entity_id: Option<i64>,
+    #[ts(as = "Option<f64>")]
+    pub patient_id: Option<i64>,
+    pub detail: Option<Value>,
+}
+
+/// Filters for access reports; every field is optional and they combine with AND
+#[derive(Debug, Default, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct AuditFilter {
+    #[ts(as = "Option<f64>")]
+    pub patient_id: Option<i64>,
+    #[ts(as = "Option<f64>")]
+    pub user_id: Option<i64>,
+    /// Inclusive start date, `YYYY-MM-DD`
+    pub from_date: Option<String>,
+    /// Inclusive end date, `YYYY-MM-DD`
+    pub to_date: Option<String>,
+    pub action: Option<AuditAction>,
+    #[ts(as = "Option<f64>")]
+    pub limit: Option<i64>,
+}
+
+/// Append an event attributed to `user_id` (`None` only for failed logins with an unknown username)
+pub fn record(conn: &Connection, user_id: Option<i64>, event: &AuditEvent) -> Result<()> {
+    let detail = event.detail.as_ref().map(|d| d.to_string());
+
+    conn.execute(
+        "INSERT INTO audit_log (user_id, action, entity_type, entity_id, patient_id, detail)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            user_id,
+            event.action.as_str(),
+            event.entity_type,
+            event.entity_id,
+            event.patient_id,
+            detail,
+        ],
+    )?;
+    Ok(())
+}
+
+/// Record a create, update or delete of row `id` in `table`
+///
+/// `before` is the row's `snapshot` taken ahead of the change (`None` for a
+/// create). The row is read again now, and only the columns that differ go
+/// into the diff.
+pub fn record_change(conn: &Connection, user_id: i64, action: AuditAction, table: &str, id: i64, before: Option<Value>) -> Result<()> {
+    let after = snapshot(conn, table, id)?;
+    let patient_id = owning_patient(table, before.as_ref().or(after.as_ref()));
+
+    record(
+        conn,
+        Some(user_id),
+        &AuditEvent {
+            action,
+            entity_type: table,
+            entity_id: Some(id),
+            patient_id,
+            detail: Some(diff(before, after)),
+        },
+    )
+}
+
+/// Columns never copied into the log, even when the row itself is audited
+const SECRET_COLUMNS: &[&str] = &["password_hash", "two_factor_secret"];
+
+/// The current row `id` in `table` as a JSON object, or `None` if it does not exist
+pub fn snapshot(conn: &Connection, table: &str, id: i64) -> Result<Option<Value>> {
+    let mut stmt = conn.prepare(&format!("SELECT * FROM {} WHERE id = ?1", table))?;
+    let names: Vec<String> = stmt.column_names().iter().map(|n| n.to_string()).collect();
+    let mut rows = stmt.query(params![id])?;
+
+    match rows.next()? {
+        Some(row) => {
+            let mut object = Map::new();
+            for (i, name) in names.into_iter().enumerate() {
+                if !SECRET_COLUMNS.contains(&name.as_str()) {
+                    object.insert(name, to_json(row.get_ref(i)?));
+                }
+            }
+            Ok(Some(Value::Object(object)))
+        }
+        None => Ok(None),
+    }
+}
+
+/// `{"before": ..., "after": ...}` holding only the fields that changed
+///
+/// Creates and deletes keep the whole row on the side that exists.
+pub fn diff(before: Option<Value>, after: Option<Value>) -> Value {
+    match (before, after) {
+        (Some(Value::Object(before)), Some(Value::Object(after))) => {
+            let mut old = Map::new();
+            let mut new = Map::new();
+            for (key, value) in &after {
+                let previous = before.get(key).cloned().unwrap_or(Value::Null);
+                if &previous != value && key != "updated_at" {
+                    old.insert(key.clone(), previous);
+                    new.insert(key.clone(), value.clone());
+                }
+            }
+            json!({ "before": old, "after": new })
+        }
+        (before, after) => json!({ "before": before, "after": after }),
+    }
+}
+
+/// Entries matching `filter`, newest first
+///
+/// Filtering by patient also finds searches whose results included that chart.
+pub fn query_audit_log(conn: &Connection, filter: &AuditFilter) -> Result<Vec<AuditEntry>> {
+    let mut stmt = conn.prepare(
+        "SELECT a.id, a.occurred_at, a.user_id, u.username, a.action, a.entity_type, a.entity_id, a.patient_id, a.detail
+         FROM audit_log a
+         LEFT JOIN users u ON u.id = a.user_id
+         WHERE (?1 IS NULL OR a.patient_id = ?1
+                OR (a.action = 'search' AND EXISTS (
+                    SELECT 1 FROM json_each(a.detail, '$.patient_ids') WHERE value = ?1)))
+           AND (?2 IS NULL OR a.user_id = ?2)
+           AND (?3 IS NULL OR date(a.occurred_at) >= date(?3))
+           AND (?4 IS NULL OR date(a.occurred_at) <= date(?4))
+           AND (?5 IS NULL OR a.action = ?5)
+         ORDER BY a.occurred_at DESC, a.id DESC
+         LIMIT ?6"
+    )?;
+
+    let entries = stmt.query_map(
+        params![
+            filter.patient_id,
+            filter.user_id,
+            filter.from_date,
+            filter.to_date,
+            filter.action.map(|a| a.as_str()),
+            filter.limit.unwrap_or(500),
+        ],
+        |row| {
+            let detail: Option<String> = row.get(8)?;
+            Ok(AuditEntry {
+                id: row.get(0)?,
+                occurred_at: row.get(1)?,
+                user_id: row.get(2)?,
+                username: row.get(3)?,
+                action: row.get(4)?,
+                entity_type: row.get(5)?,
+                entity_id: row.get(6)?,
+                patient_id: row.get(7)?,
+                detail: detail.and_then(|d| serde_json::from_str(&d).ok()),
+            })
+        },
+    )?;
+
+    entries.collect()
+}
+
+/// Charts `user_id` opened most recently, newest first, from their chart reads
+pub fn recent_patient_ids(conn: &Connection, user_id: i64, limit: i64) -> Result<Vec<i64>> {
+    let mut stmt = conn.prepare(
+        "SELECT patient_id FROM audit_log
+         WHERE user_id = ?1 AND action = 'read' AND entity_type = 'patients' AND patient_id IS NOT NULL
+         GROUP BY patient_id
+         ORDER BY MAX(id) DESC
+         LIMIT ?2",
+    )?;
+    let ids = stmt.query_map(params![user_id, limit], |row| row.get(0))?;
+    ids.collect()
+}
+
+fn owning_patient(table: &str, row: Option<&Value>) -> Option<i64> {
+    let key = if table == "patients" { "id" } else { "patient_id" };
+    row.and_then(|r| r.get(key)).and_then(Value::as_i64)
+}
+
+fn to_json(value: ValueRef) -> Value {
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(i) => json!(i),
+        ValueRef::Real(f) => json!(f),
+        ValueRef::Text(t) => Value::String(String::from_utf8_lossy(t).into_owned()),
+        ValueRef::Blob(b) => Value::String(format!("<{} bytes>", b.len())),
+    }
+}
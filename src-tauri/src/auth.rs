@@ -0,0 +1,270 @@
+use crate::db::{self, User};
+use crate::error::Error;
+use crate::rbac::{Permission, Role};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use rand::rngs::OsRng;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use ts_rs::TS;
+
+// ============ Password Hashing ============
+//
+// Passwords are hashed here with Argon2id and stored as PHC strings
+// (`$argon2id$v=19$m=...,t=...,p=...$salt$hash`), so the parameters travel
+// with each hash and can be raised later without breaking old logins.
+
+pub const MIN_PASSWORD_LEN: usize = 8;
+
+/// Consecutive failures before the account is locked
+pub const MAX_FAILED_ATTEMPTS: i64 = 5;
+
+/// How long a locked account stays locked
+pub const LOCKOUT_MINUTES: i64 = 15;
+
+/// A session with no command for this long is closed and must sign in again
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+/// Earlier builds stored whatever the frontend sent, which was `hashed_<password>`
+const LEGACY_HASH_PREFIX: &str = "hashed_";
+
+pub fn hash_password(password: &str) -> crate::error::Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| Error::internal(format!("Failed to hash password: {}", e)))
+}
+
+/// Check `password` against a stored hash, including the legacy pre-Argon2 format
+pub fn verify_password(password: &str, stored_hash: &str) -> bool {
+    match PasswordHash::new(stored_hash) {
+        Ok(parsed) => Argon2::default()
+            .verify_password(password.as_bytes(), &parsed)
+            .is_ok(),
+        Err(_) => stored_hash
+            .strip_prefix(LEGACY_HASH_PREFIX)
+            .is_some_and(|legacy| legacy == password),
+    }
+}
+
+/// Stored hashes that should be replaced with a fresh Argon2id hash on the next successful login
+fn needs_rehash(stored_hash: &str) -> bool {
+    !stored_hash.starts_with("$argon2id$")
+}
+
+pub fn validate_password(password: &str) -> crate::error::Result<()> {
+    if password.chars().count() < MIN_PASSWORD_LEN {
+        return Err(Error::invalid_field("password", format!("Password must be at least {} characters", MIN_PASSWORD_LEN)));
+    }
+    Ok(())
+}
+
+// ============ Sessions ============
+
+/// The signed-in user for this app instance
+///
+/// Lives only in memory; quitting the app or locking the database ends it.
+#[derive(Debug)]
+pub struct Session {
+    pub user_id: i64,
+    pub username: String,
+    /// Read at sign-in; a role change for the signed-in user updates it in place
+    pub role: Role,
+    pub started_at: String,
+    last_activity: Instant,
+}
+
+impl Session {
+    pub fn new(user: &User, started_at: String) -> Self {
+        Session {
+            user_id: user.id.unwrap_or(0),
+            username: user.username.clone(),
+            role: user.role,
+            started_at,
+            last_activity: Instant::now(),
+        }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.last_activity.elapsed() >= IDLE_TIMEOUT
+    }
+
+    pub fn touch(&mut self) {
+        self.last_activity = Instant::now();
+    }
+
+    pub fn info(&self) -> SessionInfo {
+        SessionInfo {
+            user_id: self.user_id,
+            username: self.username.clone(),
+            role: self.role,
+            permissions: self.role.permissions().to_vec(),
+            started_at: self.started_at.clone(),
+            idle_timeout_secs: IDLE_TIMEOUT.as_secs(),
+        }
+    }
+}
+
+/// What the frontend sees of the current session
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct SessionInfo {
+    #[ts(as = "f64")]
+    pub user_id: i64,
+    pub username: String,
+    pub role: Role,
+    /// So the frontend can hide what the backend would refuse anyway
+    pub permissions: Vec<Permission>,
+    pub started_at: String,
+    #[ts(as = "f64")]
+    pub idle_timeout_secs: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct AuthStatus {
+    /// False on a brand-new database, where the first account still has to be created
+    pub has_users: bool,
+    pub session: Option<SessionInfo>,
+}
+
+// ============ Login ============
+
+/// Why a login attempt was refused
+#[derive(Debug)]
+pub enum LoginError {
+    /// Unknown username or wrong password; deliberately the same message for both
+    InvalidCredentials { user_id: Option<i64> },
+    Locked { user_id: i64, until: String },
+    Database(rusqlite::Error),
+}
+
+impl std::fmt::Display for LoginError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoginError::InvalidCredentials { .. } => write!(f, "Invalid username or password"),
+            LoginError::Locked { until, .. } => {
+                write!(f, "Account is locked after too many failed attempts; try again after {}", until)
+            }
+            LoginError::Database(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for LoginError {
+    fn from(e: rusqlite::Error) -> Self {
+        LoginError::Database(e)
+    }
+}
+
+/// Check a username and password, applying the failed-attempt lockout
+///
+/// A correct password upgrades a legacy hash to Argon2id but does not clear
+/// the failure count; that waits for `record_successful_login`, so an account
+/// with two-factor keeps counting wrong codes toward the same lockout. The
+/// fifth consecutive failure locks the account for `LOCKOUT_MINUTES`;
+/// attempts during the lockout are refused without checking the password.
+pub fn authenticate(conn: &Connection, username: &str, password: &str) -> Result<User, LoginError> {
+    let user = match db::get_user_by_username(conn, username)? {
+        Some(user) => user,
+        None => return Err(LoginError::InvalidCredentials { user_id: None }),
+    };
+    let user_id = user.id.unwrap_or(0);
+    check_not_locked(conn, user_id)?;
+
+    if !verify_password(password, &user.password_hash) {
+        record_failed_attempt(conn, user_id)?;
+        return Err(LoginError::InvalidCredentials { user_id: Some(user_id) });
+    }
+
+    if needs_rehash(&user.password_hash) {
+        if let Ok(upgraded) = hash_password(password) {
+            db::update_user_password(conn, user_id, &upgraded)?;
+        }
+    }
+
+    Ok(user)
+}
+
+pub fn check_not_locked(conn: &Connection, user_id: i64) -> Result<(), LoginError> {
+    let locked_until: Option<String> = conn.query_row(
+        "SELECT locked_until FROM users
+         WHERE id = ?1 AND locked_until > datetime('now', 'localtime')",
+        params![user_id],
+        |row| row.get(0),
+    ).optional()?;
+    match locked_until {
+        Some(until) => Err(LoginError::Locked { user_id, until }),
+        None => Ok(()),
+    }
+}
+
+/// Count a wrong password or two-factor code, locking the account on the last allowed failure
+pub fn record_failed_attempt(conn: &Connection, user_id: i64) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE users SET
+            failed_login_attempts = failed_login_attempts + 1,
+            locked_until = CASE WHEN failed_login_attempts + 1 >= ?2
+                THEN datetime('now', 'localtime', ?3) ELSE NULL END
+         WHERE id = ?1",
+        params![user_id, MAX_FAILED_ATTEMPTS, format!("+{} minutes", LOCKOUT_MINUTES)],
+    )?;
+    conn.execute(
+        "UPDATE users SET failed_login_attempts = 0 WHERE id = ?1 AND locked_until IS NOT NULL",
+        params![user_id],
+    )?;
+    Ok(())
+}
+
+/// Clear the failure count once every factor has been checked
+pub fn record_successful_login(conn: &Connection, user_id: i64) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE users SET
+            failed_login_attempts = 0,
+            locked_until = NULL,
+            last_login_at = datetime('now', 'localtime')
+         WHERE id = ?1",
+        params![user_id],
+    )?;
+    Ok(())
+}
+
+// ============ Two-Factor Step ============
+
+/// How long the second step of a login stays open after the password was accepted
+pub const TWO_FACTOR_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// A login that passed the password check and is waiting for a TOTP or recovery code
+#[derive(Debug)]
+pub struct PendingLogin {
+    pub user: User,
+    started: Instant,
+}
+
+impl PendingLogin {
+    pub fn new(user: User) -> Self {
+        PendingLogin {
+            user,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.started.elapsed() >= TWO_FACTOR_TIMEOUT
+    }
+}
+
+/// Outcome of the password step: either a session, or a request for the second factor
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct LoginResult {
+    pub session: Option<SessionInfo>,
+    pub two_factor_required: bool,
+}
+
+/// Local timestamp in the same format SQLite's `datetime('now', 'localtime')` produces
+pub fn now_timestamp(conn: &Connection) -> rusqlite::Result<String> {
+    conn.query_row("SELECT datetime('now', 'localtime')", [], |row| row.get(0))
+}
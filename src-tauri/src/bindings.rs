@@ -0,0 +1,163 @@
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use syn::{Attribute, FnArg, GenericArgument, Item, Pat, PathArguments, ReturnType, Type};
+
+// ============ TypeScript Command Bindings ============
+//
+// `cargo test export_bindings` regenerates `src/bindings/`. ts-rs writes one
+// file per `#[ts(export)]` type; the test below adds `commands.ts`, a typed
+// wrapper around `invoke` for every `#[tauri::command]` in main.rs, so the
+// frontend never spells a command name or argument by hand.
+//
+// ts-rs would type 64-bit integers as `bigint`, but Tauri sends plain JSON
+// numbers, so those fields carry `#[ts(as = "f64")]`. Fields serde fills in
+// when missing are marked `optional`, which lets the frontend leave them out.
+
+const COMMANDS_FILE: &str = "commands.ts";
+
+#[test]
+fn export_bindings_commands() {
+    let file = syn::parse_file(include_str!("main.rs")).expect("main.rs should parse");
+    let mut imports = BTreeSet::new();
+    let mut wrappers = Vec::new();
+
+    for item in &file.items {
+        let Item::Fn(function) = item else {
+            continue;
+        };
+        if !function.attrs.iter().any(is_command) {
+            continue;
+        }
+
+        let mut params = Vec::new();
+        let mut args = Vec::new();
+        for input in &function.sig.inputs {
+            let FnArg::Typed(arg) = input else {
+                continue;
+            };
+            let Pat::Ident(pat) = &*arg.pat else {
+                continue;
+            };
+            if last_segment(&arg.ty).is_some_and(|s| s == "State") {
+                continue;
+            }
+            // Tauri matches command arguments by their camelCase name
+            let name = camel_case(&pat.ident.to_string());
+            params.push(format!("{}: {}", name, ts_type(&arg.ty, &mut imports)));
+            args.push(name);
+        }
+
+        let output = match &function.sig.output {
+            ReturnType::Default => "void".to_string(),
+            ReturnType::Type(_, ty) => ts_type(ok_type(ty), &mut imports),
+        };
+        let command = function.sig.ident.to_string();
+        let payload = if args.is_empty() {
+            String::new()
+        } else {
+            format!(", {{ {} }}", args.join(", "))
+        };
+        wrappers.push(format!(
+            "export function {}({}): Promise<{}> {{\n\treturn invoke<{}>('{}'{});\n}}\n",
+            camel_case(&command),
+            params.join(", "),
+            output,
+            output,
+            command,
+            payload
+        ));
+    }
+
+    let mut out = String::from(
+        "// This file was generated by `cargo test export_bindings` from src-tauri/src/main.rs. Do not edit this file manually.\n",
+    );
+    out.push_str("import { invoke } from '@tauri-apps/api/tauri';\n");
+    for name in &imports {
+        out.push_str(&format!("import type {{ {} }} from './{}';\n", name, name));
+    }
+    for wrapper in wrappers {
+        out.push('\n');
+        out.push_str(&wrapper);
+    }
+
+    let dir = PathBuf::from(std::env::var("TS_RS_EXPORT_DIR").unwrap_or_else(|_| "./bindings".to_string()));
+    std::fs::create_dir_all(&dir).expect("bindings directory should be writable");
+    std::fs::write(dir.join(COMMANDS_FILE), out).expect("commands.ts should be writable");
+}
+
+fn is_command(attr: &Attribute) -> bool {
+    let segments: Vec<String> = attr.path().segments.iter().map(|s| s.ident.to_string()).collect();
+    segments == ["tauri", "command"]
+}
+
+/// `T` for a command returning `Result<T, Error>`, otherwise the type itself
+fn ok_type(ty: &Type) -> &Type {
+    match (last_segment(ty).as_deref(), generic_args(ty).first()) {
+        (Some("Result"), Some(ok)) => ok,
+        _ => ty,
+    }
+}
+
+/// The TypeScript spelling of `ty`; struct and enum names are added to `imports`
+fn ts_type(ty: &Type, imports: &mut BTreeSet<String>) -> String {
+    match ty {
+        Type::Reference(reference) => ts_type(&reference.elem, imports),
+        Type::Tuple(tuple) if tuple.elems.is_empty() => "void".to_string(),
+        Type::Path(_) => {
+            let name = last_segment(ty).unwrap_or_default();
+            let args = generic_args(ty);
+            match (name.as_str(), args.first()) {
+                ("i8" | "i16" | "i32" | "i64" | "u8" | "u16" | "u32" | "u64" | "usize" | "f32" | "f64", _) => {
+                    "number".to_string()
+                }
+                ("String" | "str", _) => "string".to_string(),
+                ("bool", _) => "boolean".to_string(),
+                ("Option", Some(inner)) => format!("{} | null", ts_type(inner, imports)),
+                ("Vec", Some(inner)) => format!("Array<{}>", ts_type(inner, imports)),
+                _ => {
+                    imports.insert(name.clone());
+                    name
+                }
+            }
+        }
+        _ => panic!("commands may only take and return named types, references and `()`"),
+    }
+}
+
+fn last_segment(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(path) => path.path.segments.last().map(|s| s.ident.to_string()),
+        _ => None,
+    }
+}
+
+fn generic_args(ty: &Type) -> Vec<&Type> {
+    let Type::Path(path) = ty else {
+        return Vec::new();
+    };
+    let Some(PathArguments::AngleBracketed(args)) = path.path.segments.last().map(|s| &s.arguments) else {
+        return Vec::new();
+    };
+    args.args
+        .iter()
+        .filter_map(|arg| match arg {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        })
+        .collect()
+}
+
+fn camel_case(snake: &str) -> String {
+    let mut out = String::new();
+    for (i, word) in snake.split('_').enumerate() {
+        let mut chars = word.chars();
+        match chars.next() {
+            Some(first) if i > 0 => {
+                out.extend(first.to_uppercase());
+                out.push_str(chars.as_str());
+            }
+            _ => out.push_str(word),
+        }
+    }
+    out
+}
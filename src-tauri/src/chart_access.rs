@@ -0,0 +1,194 @@
+use crate::error::Error;
+use rusqlite::{params, Connection, OptionalExtension, Result};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// ============ Care Teams ============
+//
+// A chart with no care team is open to every role that can view clinical
+// data, which is how every chart behaved before care teams existed. Once a
+// patient has a care team, only its members can open the chart; anyone else
+// has to break the glass.
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct CareTeamMember {
+    #[ts(as = "f64")]
+    pub user_id: i64,
+    pub username: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub added_at: String,
+}
+
+pub fn get_care_team(conn: &Connection, patient_id: i64) -> Result<Vec<CareTeamMember>> {
+    let mut stmt = conn.prepare(
+        "SELECT u.id, u.username, u.first_name, u.last_name, ct.added_at
+         FROM patient_care_team ct
+         JOIN users u ON u.id = ct.user_id
+         WHERE ct.patient_id = ?1
+         ORDER BY u.last_name, u.first_name"
+    )?;
+
+    let members = stmt.query_map(params![patient_id], |row| {
+        Ok(CareTeamMember {
+            user_id: row.get(0)?,
+            username: row.get(1)?,
+            first_name: row.get(2)?,
+            last_name: row.get(3)?,
+            added_at: row.get(4)?,
+        })
+    })?;
+
+    members.collect()
+}
+
+pub fn add_care_team_member(conn: &Connection, patient_id: i64, user_id: i64, added_by: i64) -> Result<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO patient_care_team (patient_id, user_id, added_by) VALUES (?1, ?2, ?3)",
+        params![patient_id, user_id, added_by],
+    )?;
+    Ok(())
+}
+
+pub fn remove_care_team_member(conn: &Connection, patient_id: i64, user_id: i64) -> Result<()> {
+    conn.execute(
+        "DELETE FROM patient_care_team WHERE patient_id = ?1 AND user_id = ?2",
+        params![patient_id, user_id],
+    )?;
+    Ok(())
+}
+
+/// How `user_id` is allowed into a chart, or `None` if they would need to break the glass
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChartAccess {
+    /// The patient has no care team
+    Open,
+    CareTeam,
+    BreakGlass,
+}
+
+pub fn check_chart_access(conn: &Connection, user_id: i64, patient_id: i64) -> Result<Option<ChartAccess>> {
+    let (team_size, is_member): (i64, bool) = conn.query_row(
+        "SELECT COUNT(*), COALESCE(MAX(user_id = ?2), 0) FROM patient_care_team WHERE patient_id = ?1",
+        params![patient_id, user_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    if team_size == 0 {
+        return Ok(Some(ChartAccess::Open));
+    }
+    if is_member {
+        return Ok(Some(ChartAccess::CareTeam));
+    }
+    if active_grant(conn, user_id, patient_id)?.is_some() {
+        return Ok(Some(ChartAccess::BreakGlass));
+    }
+    Ok(None)
+}
+
+// ============ Break the Glass ============
+
+/// How long emergency access lasts before the user has to give a reason again
+pub const GRANT_HOURS: i64 = 4;
+
+/// Short enough to type in an emergency, long enough to rule out "x"
+pub const MIN_REASON_LEN: usize = 10;
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct BreakGlassGrant {
+    #[ts(as = "f64")]
+    pub id: i64,
+    #[ts(as = "f64")]
+    pub user_id: i64,
+    pub username: Option<String>,
+    #[ts(as = "f64")]
+    pub patient_id: i64,
+    pub patient_name: Option<String>,
+    pub reason: String,
+    pub granted_at: String,
+    pub expires_at: String,
+    pub reviewed_at: Option<String>,
+    #[ts(as = "Option<f64>")]
+    pub reviewed_by: Option<i64>,
+    pub review_note: Option<String>,
+}
+
+pub fn validate_reason(reason: &str) -> crate::error::Result<()> {
+    if reason.trim().chars().count() < MIN_REASON_LEN {
+        return Err(Error::invalid_field("reason", format!("Describe the emergency in at least {} characters", MIN_REASON_LEN)));
+    }
+    Ok(())
+}
+
+pub fn grant(conn: &Connection, user_id: i64, patient_id: i64, reason: &str) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO break_glass_access (user_id, patient_id, reason, expires_at)
+         VALUES (?1, ?2, ?3, datetime('now', 'localtime', ?4))",
+        params![user_id, patient_id, reason.trim(), format!("+{} hours", GRANT_HOURS)],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// The unexpired grant for this user and chart, if any
+pub fn active_grant(conn: &Connection, user_id: i64, patient_id: i64) -> Result<Option<i64>> {
+    conn.query_row(
+        "SELECT id FROM break_glass_access
+         WHERE user_id = ?1 AND patient_id = ?2 AND expires_at > datetime('now', 'localtime')
+         ORDER BY expires_at DESC LIMIT 1",
+        params![user_id, patient_id],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+pub fn get_grant(conn: &Connection, id: i64) -> Result<Option<BreakGlassGrant>> {
+    let mut stmt = conn.prepare(&format!("{} WHERE b.id = ?1", GRANT_SELECT))?;
+    stmt.query_row(params![id], grant_from_row).optional()
+}
+
+/// Every break-the-glass event, unreviewed first and newest first within that
+pub fn get_review_queue(conn: &Connection, include_reviewed: bool) -> Result<Vec<BreakGlassGrant>> {
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE (?1 OR b.reviewed_at IS NULL)
+         ORDER BY b.reviewed_at IS NOT NULL, b.granted_at DESC, b.id DESC",
+        GRANT_SELECT
+    ))?;
+    let grants = stmt.query_map(params![include_reviewed], grant_from_row)?;
+    grants.collect()
+}
+
+pub fn mark_reviewed(conn: &Connection, id: i64, reviewed_by: i64, note: Option<&str>) -> Result<()> {
+    conn.execute(
+        "UPDATE break_glass_access SET
+            reviewed_at = datetime('now', 'localtime'),
+            reviewed_by = ?2,
+            review_note = ?3
+         WHERE id = ?1",
+        params![id, reviewed_by, note],
+    )?;
+    Ok(())
+}
+
+const GRANT_SELECT: &str = "SELECT b.id, b.user_id, u.username, b.patient_id, p.first_name || ' ' || p.last_name,
+        b.reason, b.granted_at, b.expires_at, b.reviewed_at, b.reviewed_by, b.review_note
+     FROM break_glass_access b
+     LEFT JOIN users u ON u.id = b.user_id
+     LEFT JOIN patients p ON p.id = b.patient_id";
+
+fn grant_from_row(row: &rusqlite::Row) -> Result<BreakGlassGrant> {
+    Ok(BreakGlassGrant {
+        id: row.get(0)?,
+        user_id: row.get(1)?,
+        username: row.get(2)?,
+        patient_id: row.get(3)?,
+        patient_name: row.get(4)?,
+        reason: row.get(5)?,
+        granted_at: row.get(6)?,
+        expires_at: row.get(7)?,
+        reviewed_at: row.get(8)?,
+        reviewed_by: row.get(9)?,
+        review_note: row.get(10)?,
+    })
+}
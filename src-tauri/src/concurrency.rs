@@ -0,0 +1,41 @@
+use crate::error::Error;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+// ============ Optimistic Concurrency ============
+//
+// Every table that is edited as a whole object carries a `row_version` that
+// each update bumps. An update only applies when the version it sends is the
+// one currently stored; otherwise somebody saved in between, and the caller
+// gets a conflict holding the row as it is now instead of silently
+// overwriting it.
+
+/// The error for an update that matched no row at the expected version
+///
+/// `current` is the server copy the frontend offers for review, or `None` if the row was deleted.
+pub fn conflict<T: Serialize>(current: Option<T>) -> Error {
+    let message = match current {
+        Some(_) => "This record was changed by someone else since you opened it; review their changes and save again",
+        None => "This record was deleted by someone else since you opened it",
+    };
+    Error::Conflict {
+        message: message.to_string(),
+        current: current.and_then(|row| serde_json::to_value(row).ok()),
+    }
+}
+
+/// The `row_version` an update was sent at; every update to a versioned table must carry one
+///
+/// Without it there is nothing to compare against, and the save would
+/// silently overwrite whatever was stored in between.
+pub fn require_version(sent: Option<i64>) -> Result<i64, Error> {
+    sent.ok_or_else(|| Error::invalid_field("row_version", "The record's version is missing; reload it and save again"))
+}
+
+/// The `row_version` row `id` of `table` holds now, for an update command to return
+///
+/// Read back from the row rather than worked out from what the caller sent,
+/// which may have carried no version at all.
+pub fn stored_version(conn: &Connection, table: &str, id: i64) -> rusqlite::Result<i64> {
+    conn.query_row(&format!("SELECT row_version FROM {} WHERE id = ?1", table), params![id], |row| row.get(0))
+}
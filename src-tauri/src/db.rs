@@ -1,5 +1,13 @@
-use rusqlite::{Connection, Result, params};
-use std::sync::Mutex;
+use crate::pool::{self, DbPool, PooledConn};
+use crate::rbac::{self, Permission, Role};
+use crate::auth::{self, LoginResult, PendingLogin, Session, SessionInfo};
+use crate::error::Error;
+use crate::model::patient::PatientFilter;
+use crate::model::{self, bmc_get, bmc_list, PatientScope};
+use crate::search_query::SearchQuery;
+use crate::{audit, encryption, migrations, name_match, two_factor};
+use rusqlite::{Connection, OptionalExtension, Result, params};
+use std::sync::{Arc, Mutex, RwLock};
 use tauri::AppHandle;
 use std::path::PathBuf;
 
@@ -17,982 +25,869 @@ pub fn get_db_path(app_handle: &AppHandle) -> PathBuf {
 }
 
 /// Database state that will be managed by Tauri
-pub struct DbState(pub Mutex<Connection>);
-
-/// Initialize the database with SQLCipher encryption and create tables if they don't exist
 ///
-/// IMPORTANT for HIPAA compliance:
-/// - The encryption key should NOT be hardcoded in production
-/// - Use a secure key derivation from user credentials or secure storage
-/// - Consider using the OS keychain (keyring crate) to store the key
-pub fn init_db(db_path: &PathBuf, _encryption_key: &str) -> Result<Connection> {
-    let conn = Connection::open(db_path)?;
-
-    // TODO: Re-enable encryption for production!
-    // SQLCipher encryption is temporarily disabled for development.
-    // Uncomment the lines below to enable encryption:
-    //
-    // conn.pragma_update(None, "key", encryption_key)?;
-    // conn.pragma_update(None, "cipher_page_size", 4096)?;
-    // conn.pragma_update(None, "kdf_iter", 256000)?;
-    // conn.pragma_update(None, "cipher_hmac_algorithm", "HMAC_SHA512")?;
-    // conn.pragma_update(None, "cipher_kdf_algorithm", "PBKDF2_HMAC_SHA512")?;
-
-    // Create tables
-    conn.execute_batch(
-        "
-        -- Patients table
-        CREATE TABLE IF NOT EXISTS patients (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            first_name TEXT NOT NULL,
-            last_name TEXT NOT NULL,
-            dob TEXT NOT NULL,
-            sex TEXT NOT NULL,
-            gender TEXT,
-            address TEXT,
-            phone TEXT,
-            email TEXT,
-            photo_url TEXT,
-            ai_summary TEXT,
-            preferred_pharmacy TEXT,
-            insurance_provider TEXT,
-            insurance_policy_number TEXT,
-            insurance_group_number TEXT,
-            created_at TEXT DEFAULT (datetime('now', 'localtime')),
-            updated_at TEXT DEFAULT (datetime('now', 'localtime'))
-        );
-
-        -- Appointments table
-        CREATE TABLE IF NOT EXISTS appointments (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            patient_id INTEGER NOT NULL,
-            appointment_time TEXT NOT NULL,
-            duration_minutes INTEGER DEFAULT 30,
-            reason TEXT,
-            status TEXT DEFAULT 'scheduled',
-            notes TEXT,
-            created_at TEXT DEFAULT (datetime('now', 'localtime')),
-            updated_at TEXT DEFAULT (datetime('now', 'localtime')),
-            FOREIGN KEY (patient_id) REFERENCES patients(id)
-        );
-
-        -- Messages table
-        CREATE TABLE IF NOT EXISTS messages (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            patient_id INTEGER,
-            subject TEXT NOT NULL,
-            body TEXT NOT NULL,
-            is_read INTEGER DEFAULT 0,
-            created_at TEXT DEFAULT (datetime('now', 'localtime')),
-            FOREIGN KEY (patient_id) REFERENCES patients(id)
-        );
-
-        -- Diagnoses table
-        CREATE TABLE IF NOT EXISTS diagnoses (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            patient_id INTEGER NOT NULL,
-            name TEXT NOT NULL,
-            description TEXT,
-            icd_code TEXT,
-            onset_date TEXT,
-            status TEXT DEFAULT 'active',
-            category TEXT,
-            notes TEXT,
-            created_at TEXT DEFAULT (datetime('now', 'localtime')),
-            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
-        );
-
-        -- Medications table
-        CREATE TABLE IF NOT EXISTS medications (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            patient_id INTEGER NOT NULL,
-            name TEXT NOT NULL,
-            dose TEXT,
-            dosage TEXT,
-            frequency TEXT,
-            route TEXT,
-            prescriber TEXT,
-            start_date TEXT,
-            end_date TEXT,
-            status TEXT DEFAULT 'active',
-            notes TEXT,
-            created_at TEXT DEFAULT (datetime('now', 'localtime')),
-            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
-        );
-
-        -- Junction table for diagnosis-medication relationships
-        CREATE TABLE IF NOT EXISTS diagnosis_medications (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            diagnosis_id INTEGER NOT NULL,
-            medication_id INTEGER NOT NULL,
-            created_at TEXT DEFAULT (datetime('now', 'localtime')),
-            FOREIGN KEY (diagnosis_id) REFERENCES diagnoses(id) ON DELETE CASCADE,
-            FOREIGN KEY (medication_id) REFERENCES medications(id) ON DELETE CASCADE,
-            UNIQUE(diagnosis_id, medication_id)
-        );
-
-        -- Vitals table
-        CREATE TABLE IF NOT EXISTS vitals (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            patient_id INTEGER NOT NULL,
-            vital_type TEXT NOT NULL,
-            value REAL NOT NULL,
-            value_secondary REAL,
-            unit TEXT NOT NULL,
-            recorded_at TEXT NOT NULL,
-            created_at TEXT DEFAULT (datetime('now', 'localtime')),
-            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
-        );
-
-        -- Labs table
-        CREATE TABLE IF NOT EXISTS labs (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            patient_id INTEGER NOT NULL,
-            name TEXT NOT NULL,
-            test_name TEXT,
-            value REAL NOT NULL,
-            result TEXT,
-            unit TEXT,
-            reference_range_low REAL,
-            reference_range_high REAL,
-            is_abnormal INTEGER DEFAULT 0,
-            notes TEXT,
-            recorded_at TEXT NOT NULL,
-            created_at TEXT DEFAULT (datetime('now', 'localtime')),
-            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
-        );
-
-        -- Clinical scores table (PHQ-9, GAD-7, etc.)
-        CREATE TABLE IF NOT EXISTS clinical_scores (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            patient_id INTEGER NOT NULL,
-            score_type TEXT NOT NULL,
-            score INTEGER NOT NULL,
-            max_score INTEGER,
-            interpretation TEXT,
-            recorded_at TEXT NOT NULL,
-            created_at TEXT DEFAULT (datetime('now', 'localtime')),
-            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
-        );
-
-        -- Encounters table
-        CREATE TABLE IF NOT EXISTS encounters (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            patient_id INTEGER NOT NULL,
-            encounter_date TEXT NOT NULL,
-            encounter_type TEXT NOT NULL,
-            chief_complaint TEXT,
-            summary TEXT,
-            note_content TEXT,
-            provider TEXT,
-            location TEXT,
-            created_at TEXT DEFAULT (datetime('now', 'localtime')),
-            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
-        );
-
-        -- Allergies table
-        CREATE TABLE IF NOT EXISTS allergies (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            patient_id INTEGER NOT NULL,
-            allergen TEXT NOT NULL,
-            reaction TEXT,
-            severity TEXT,
-            onset_date TEXT,
-            status TEXT DEFAULT 'active',
-            created_at TEXT DEFAULT (datetime('now', 'localtime')),
-            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
-        );
-
-        -- Vaccinations table
-        CREATE TABLE IF NOT EXISTS vaccinations (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            patient_id INTEGER NOT NULL,
-            vaccine_name TEXT NOT NULL,
-            date_given TEXT NOT NULL,
-            lot_number TEXT,
-            site TEXT,
-            administered_by TEXT,
-            created_at TEXT DEFAULT (datetime('now', 'localtime')),
-            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
-        );
-
-        -- Social history table
-        CREATE TABLE IF NOT EXISTS social_history (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            patient_id INTEGER NOT NULL,
-            category TEXT NOT NULL,
-            detail TEXT NOT NULL,
-            status TEXT,
-            start_date TEXT,
-            end_date TEXT,
-            created_at TEXT DEFAULT (datetime('now', 'localtime')),
-            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
-        );
-
-        -- Family history table
-        CREATE TABLE IF NOT EXISTS family_history (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            patient_id INTEGER NOT NULL,
-            relation TEXT NOT NULL,
-            condition TEXT NOT NULL,
-            age_at_onset INTEGER,
-            notes TEXT,
-            created_at TEXT DEFAULT (datetime('now', 'localtime')),
-            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
-        );
-
-        -- To-dos table
-        CREATE TABLE IF NOT EXISTS todos (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            patient_id INTEGER NOT NULL,
-            diagnosis_id INTEGER,
-            description TEXT NOT NULL,
-            due_date TEXT,
-            priority TEXT DEFAULT 'normal',
-            status TEXT DEFAULT 'pending',
-            created_at TEXT DEFAULT (datetime('now', 'localtime')),
-            updated_at TEXT DEFAULT (datetime('now', 'localtime')),
-            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE,
-            FOREIGN KEY (diagnosis_id) REFERENCES diagnoses(id) ON DELETE SET NULL
-        );
-
-        -- Patient goals table
-        CREATE TABLE IF NOT EXISTS goals (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            patient_id INTEGER NOT NULL,
-            description TEXT NOT NULL,
-            target_date TEXT,
-            status TEXT DEFAULT 'in_progress',
-            progress INTEGER DEFAULT 0,
-            created_at TEXT DEFAULT (datetime('now', 'localtime')),
-            updated_at TEXT DEFAULT (datetime('now', 'localtime')),
-            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
-        );
-
-        -- Timeline events table
-        CREATE TABLE IF NOT EXISTS timeline_events (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            patient_id INTEGER NOT NULL,
-            event_type TEXT NOT NULL,
-            description TEXT NOT NULL,
-            event_date TEXT NOT NULL,
-            icon TEXT,
-            color TEXT,
-            related_id INTEGER,
-            related_table TEXT,
-            created_at TEXT DEFAULT (datetime('now', 'localtime')),
-            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
-        );
-
-        -- Users/Providers table
-        CREATE TABLE IF NOT EXISTS users (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            username TEXT NOT NULL UNIQUE,
-            password_hash TEXT NOT NULL,
-            first_name TEXT NOT NULL,
-            last_name TEXT NOT NULL,
-            degree_type TEXT,
-            specialty TEXT,
-            subspecialty TEXT,
-            npi_number TEXT,
-            photo_url TEXT,
-            bio TEXT,
-            created_at TEXT DEFAULT (datetime('now', 'localtime')),
-            updated_at TEXT DEFAULT (datetime('now', 'localtime'))
-        );
-
-        -- User education/training table
-        CREATE TABLE IF NOT EXISTS user_education (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            user_id INTEGER NOT NULL,
-            education_type TEXT NOT NULL,
-            institution TEXT NOT NULL,
-            degree TEXT,
-            field_of_study TEXT,
-            start_year INTEGER,
-            end_year INTEGER,
-            created_at TEXT DEFAULT (datetime('now', 'localtime')),
-            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
-        );
-
-        -- User badges/awards table
-        CREATE TABLE IF NOT EXISTS user_badges (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            user_id INTEGER NOT NULL,
-            badge_name TEXT NOT NULL,
-            badge_type TEXT NOT NULL,
-            description TEXT,
-            icon TEXT,
-            color TEXT,
-            awarded_date TEXT,
-            created_at TEXT DEFAULT (datetime('now', 'localtime')),
-            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
-        );
-
-        -- User settings table
-        CREATE TABLE IF NOT EXISTS user_settings (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            user_id INTEGER NOT NULL UNIQUE,
-            language TEXT DEFAULT 'en',
-            notifications_enabled INTEGER DEFAULT 1,
-            email_notifications INTEGER DEFAULT 1,
-            sms_notifications INTEGER DEFAULT 0,
-            two_factor_enabled INTEGER DEFAULT 0,
-            two_factor_secret TEXT,
-            zen_mode_default INTEGER DEFAULT 0,
-            created_at TEXT DEFAULT (datetime('now', 'localtime')),
-            updated_at TEXT DEFAULT (datetime('now', 'localtime')),
-            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
-        );
-
-        -- Patient lists (custom patient groupings per user)
-        CREATE TABLE IF NOT EXISTS patient_lists (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            user_id INTEGER NOT NULL,
-            name TEXT NOT NULL,
-            description TEXT,
-            color TEXT DEFAULT '#3B82F6',
-            icon TEXT DEFAULT 'fa-list',
-            is_default INTEGER DEFAULT 0,
-            sort_order INTEGER DEFAULT 0,
-            created_at TEXT DEFAULT (datetime('now', 'localtime')),
-            updated_at TEXT DEFAULT (datetime('now', 'localtime')),
-            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
-        );
-
-        -- Patient list members (join table)
-        CREATE TABLE IF NOT EXISTS patient_list_members (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            list_id INTEGER NOT NULL,
-            patient_id INTEGER NOT NULL,
-            added_at TEXT DEFAULT (datetime('now', 'localtime')),
-            notes TEXT,
-            FOREIGN KEY (list_id) REFERENCES patient_lists(id) ON DELETE CASCADE,
-            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE,
-            UNIQUE(list_id, patient_id)
-        );
-
-        -- Patient list columns (custom columns for each list)
-        CREATE TABLE IF NOT EXISTS patient_list_columns (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            list_id INTEGER NOT NULL,
-            column_key TEXT NOT NULL,
-            column_label TEXT NOT NULL,
-            column_type TEXT DEFAULT 'text',
-            is_visible INTEGER DEFAULT 1,
-            sort_order INTEGER DEFAULT 0,
-            width INTEGER DEFAULT 150,
-            FOREIGN KEY (list_id) REFERENCES patient_lists(id) ON DELETE CASCADE
-        );
-
-        -- Prescriptions table (tracks prescription history)
-        CREATE TABLE IF NOT EXISTS prescriptions (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            patient_id INTEGER NOT NULL,
-            medication_id INTEGER NOT NULL,
-            quantity INTEGER NOT NULL,
-            days_supply INTEGER NOT NULL,
-            refills INTEGER DEFAULT 0,
-            sig TEXT NOT NULL,
-            pharmacy TEXT,
-            prescriber_id INTEGER,
-            status TEXT DEFAULT 'sent',
-            prescribed_date TEXT DEFAULT (datetime('now', 'localtime')),
-            filled_date TEXT,
-            notes TEXT,
-            created_at TEXT DEFAULT (datetime('now', 'localtime')),
-            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE,
-            FOREIGN KEY (medication_id) REFERENCES medications(id) ON DELETE CASCADE,
-            FOREIGN KEY (prescriber_id) REFERENCES users(id) ON DELETE SET NULL
-        );
-
-        -- Indexes for performance
-        CREATE INDEX IF NOT EXISTS idx_prescriptions_patient ON prescriptions(patient_id);
-        CREATE INDEX IF NOT EXISTS idx_prescriptions_medication ON prescriptions(medication_id);
-        CREATE INDEX IF NOT EXISTS idx_diagnoses_patient ON diagnoses(patient_id);
-        CREATE INDEX IF NOT EXISTS idx_medications_patient ON medications(patient_id);
-        CREATE INDEX IF NOT EXISTS idx_vitals_patient_date ON vitals(patient_id, recorded_at);
-        CREATE INDEX IF NOT EXISTS idx_labs_patient_date ON labs(patient_id, recorded_at);
-        CREATE INDEX IF NOT EXISTS idx_scores_patient_date ON clinical_scores(patient_id, recorded_at);
-        CREATE INDEX IF NOT EXISTS idx_encounters_patient_date ON encounters(patient_id, encounter_date);
-        CREATE INDEX IF NOT EXISTS idx_timeline_patient_date ON timeline_events(patient_id, event_date);
-        CREATE INDEX IF NOT EXISTS idx_user_education_user ON user_education(user_id);
-        CREATE INDEX IF NOT EXISTS idx_user_badges_user ON user_badges(user_id);
-        CREATE INDEX IF NOT EXISTS idx_patient_lists_user ON patient_lists(user_id);
-        CREATE INDEX IF NOT EXISTS idx_patient_list_members_list ON patient_list_members(list_id);
-        CREATE INDEX IF NOT EXISTS idx_patient_list_columns_list ON patient_list_columns(list_id);
-
-        -- FTS5 Full-Text Search virtual tables for fast global search
-        CREATE VIRTUAL TABLE IF NOT EXISTS patients_fts USING fts5(
-            patient_id UNINDEXED,
-            first_name,
-            last_name,
-            phone,
-            email,
-            address,
-            ai_summary,
-            content='patients',
-            content_rowid='id'
-        );
-
-        CREATE VIRTUAL TABLE IF NOT EXISTS encounters_fts USING fts5(
-            encounter_id UNINDEXED,
-            patient_id UNINDEXED,
-            encounter_type,
-            chief_complaint,
-            summary,
-            note_content,
-            provider,
-            content='encounters',
-            content_rowid='id'
-        );
-
-        CREATE VIRTUAL TABLE IF NOT EXISTS diagnoses_fts USING fts5(
-            diagnosis_id UNINDEXED,
-            patient_id UNINDEXED,
-            icd_code,
-            description,
-            category,
-            notes,
-            content='diagnoses',
-            content_rowid='id'
-        );
-
-        CREATE VIRTUAL TABLE IF NOT EXISTS medications_fts USING fts5(
-            medication_id UNINDEXED,
-            patient_id UNINDEXED,
-            name,
-            dosage,
-            frequency,
-            prescriber,
-            notes,
-            content='medications',
-            content_rowid='id'
-        );
-
-        CREATE VIRTUAL TABLE IF NOT EXISTS labs_fts USING fts5(
-            lab_id UNINDEXED,
-            patient_id UNINDEXED,
-            test_name,
-            result,
-            unit,
-            notes,
-            content='labs',
-            content_rowid='id'
-        );
-
-        -- Triggers to keep FTS tables in sync with source tables
-        CREATE TRIGGER IF NOT EXISTS patients_ai AFTER INSERT ON patients BEGIN
-            INSERT INTO patients_fts(rowid, patient_id, first_name, last_name, phone, email, address, ai_summary)
-            VALUES (new.id, new.id, new.first_name, new.last_name, new.phone, new.email, new.address, new.ai_summary);
-        END;
-
-        CREATE TRIGGER IF NOT EXISTS patients_ad AFTER DELETE ON patients BEGIN
-            INSERT INTO patients_fts(patients_fts, rowid, patient_id, first_name, last_name, phone, email, address, ai_summary)
-            VALUES ('delete', old.id, old.id, old.first_name, old.last_name, old.phone, old.email, old.address, old.ai_summary);
-        END;
-
-        CREATE TRIGGER IF NOT EXISTS patients_au AFTER UPDATE ON patients BEGIN
-            INSERT INTO patients_fts(patients_fts, rowid, patient_id, first_name, last_name, phone, email, address, ai_summary)
-            VALUES ('delete', old.id, old.id, old.first_name, old.last_name, old.phone, old.email, old.address, old.ai_summary);
-            INSERT INTO patients_fts(rowid, patient_id, first_name, last_name, phone, email, address, ai_summary)
-            VALUES (new.id, new.id, new.first_name, new.last_name, new.phone, new.email, new.address, new.ai_summary);
-        END;
-
-        CREATE TRIGGER IF NOT EXISTS encounters_ai AFTER INSERT ON encounters BEGIN
-            INSERT INTO encounters_fts(rowid, encounter_id, patient_id, encounter_type, chief_complaint, summary, note_content, provider)
-            VALUES (new.id, new.id, new.patient_id, new.encounter_type, new.chief_complaint, new.summary, new.note_content, new.provider);
-        END;
-
-        CREATE TRIGGER IF NOT EXISTS encounters_ad AFTER DELETE ON encounters BEGIN
-            INSERT INTO encounters_fts(encounters_fts, rowid, encounter_id, patient_id, encounter_type, chief_complaint, summary, note_content, provider)
-            VALUES ('delete', old.id, old.id, old.patient_id, old.encounter_type, old.chief_complaint, old.summary, old.note_content, old.provider);
-        END;
-
-        CREATE TRIGGER IF NOT EXISTS encounters_au AFTER UPDATE ON encounters BEGIN
-            INSERT INTO encounters_fts(encounters_fts, rowid, encounter_id, patient_id, encounter_type, chief_complaint, summary, note_content, provider)
-            VALUES ('delete', old.id, old.id, old.patient_id, old.encounter_type, old.chief_complaint, old.summary, old.note_content, old.provider);
-            INSERT INTO encounters_fts(rowid, encounter_id, patient_id, encounter_type, chief_complaint, summary, note_content, provider)
-            VALUES (new.id, new.id, new.patient_id, new.encounter_type, new.chief_complaint, new.summary, new.note_content, new.provider);
-        END;
-
-        CREATE TRIGGER IF NOT EXISTS diagnoses_ai AFTER INSERT ON diagnoses BEGIN
-            INSERT INTO diagnoses_fts(rowid, diagnosis_id, patient_id, icd_code, description, category, notes)
-            VALUES (new.id, new.id, new.patient_id, new.icd_code, new.description, new.category, new.notes);
-        END;
-
-        CREATE TRIGGER IF NOT EXISTS diagnoses_ad AFTER DELETE ON diagnoses BEGIN
-            INSERT INTO diagnoses_fts(diagnoses_fts, rowid, diagnosis_id, patient_id, icd_code, description, category, notes)
-            VALUES ('delete', old.id, old.id, old.patient_id, old.icd_code, old.description, old.category, old.notes);
-        END;
-
-        CREATE TRIGGER IF NOT EXISTS diagnoses_au AFTER UPDATE ON diagnoses BEGIN
-            INSERT INTO diagnoses_fts(diagnoses_fts, rowid, diagnosis_id, patient_id, icd_code, description, category, notes)
-            VALUES ('delete', old.id, old.id, old.patient_id, old.icd_code, old.description, old.category, old.notes);
-            INSERT INTO diagnoses_fts(rowid, diagnosis_id, patient_id, icd_code, description, category, notes)
-            VALUES (new.id, new.id, new.patient_id, new.icd_code, new.description, new.category, new.notes);
-        END;
-
-        CREATE TRIGGER IF NOT EXISTS medications_ai AFTER INSERT ON medications BEGIN
-            INSERT INTO medications_fts(rowid, medication_id, patient_id, name, dosage, frequency, prescriber, notes)
-            VALUES (new.id, new.id, new.patient_id, new.name, new.dosage, new.frequency, new.prescriber, new.notes);
-        END;
-
-        CREATE TRIGGER IF NOT EXISTS medications_ad AFTER DELETE ON medications BEGIN
-            INSERT INTO medications_fts(medications_fts, rowid, medication_id, patient_id, name, dosage, frequency, prescriber, notes)
-            VALUES ('delete', old.id, old.id, old.patient_id, old.name, old.dosage, old.frequency, old.prescriber, old.notes);
-        END;
-
-        CREATE TRIGGER IF NOT EXISTS medications_au AFTER UPDATE ON medications BEGIN
-            INSERT INTO medications_fts(medications_fts, rowid, medication_id, patient_id, name, dosage, frequency, prescriber, notes)
-            VALUES ('delete', old.id, old.id, old.patient_id, old.name, old.dosage, old.frequency, old.prescriber, old.notes);
-            INSERT INTO medications_fts(rowid, medication_id, patient_id, name, dosage, frequency, prescriber, notes)
-            VALUES (new.id, new.id, new.patient_id, new.name, new.dosage, new.frequency, new.prescriber, new.notes);
-        END;
-
-        CREATE TRIGGER IF NOT EXISTS labs_ai AFTER INSERT ON labs BEGIN
-            INSERT INTO labs_fts(rowid, lab_id, patient_id, test_name, result, unit, notes)
-            VALUES (new.id, new.id, new.patient_id, new.test_name, new.result, new.unit, new.notes);
-        END;
-
-        CREATE TRIGGER IF NOT EXISTS labs_ad AFTER DELETE ON labs BEGIN
-            INSERT INTO labs_fts(labs_fts, rowid, lab_id, patient_id, test_name, result, unit, notes)
-            VALUES ('delete', old.id, old.id, old.patient_id, old.test_name, old.result, old.unit, old.notes);
-        END;
-
-        CREATE TRIGGER IF NOT EXISTS labs_au AFTER UPDATE ON labs BEGIN
-            INSERT INTO labs_fts(labs_fts, rowid, lab_id, patient_id, test_name, result, unit, notes)
-            VALUES ('delete', old.id, old.id, old.patient_id, old.test_name, old.result, old.unit, old.notes);
-            INSERT INTO labs_fts(rowid, lab_id, patient_id, test_name, result, unit, notes)
-            VALUES (new.id, new.id, new.patient_id, new.test_name, new.result, new.unit, new.notes);
-        END;
-        "
-    )?;
-
-    // Add columns to existing patients table if they don't exist
-    // SQLite doesn't have IF NOT EXISTS for ALTER TABLE, so we check first
-    let has_photo_url: bool = conn
-        .prepare("SELECT photo_url FROM patients LIMIT 1")
-        .is_ok();
-    if !has_photo_url {
-        let _ = conn.execute("ALTER TABLE patients ADD COLUMN photo_url TEXT", []);
-        let _ = conn.execute("ALTER TABLE patients ADD COLUMN ai_summary TEXT", []);
-    }
-
-    // Add pharmacy/insurance columns to patients table if they don't exist
-    let has_pharmacy: bool = conn
-        .prepare("SELECT preferred_pharmacy FROM patients LIMIT 1")
-        .is_ok();
-    if !has_pharmacy {
-        let _ = conn.execute("ALTER TABLE patients ADD COLUMN preferred_pharmacy TEXT", []);
-        let _ = conn.execute("ALTER TABLE patients ADD COLUMN insurance_provider TEXT", []);
-        let _ = conn.execute("ALTER TABLE patients ADD COLUMN insurance_policy_number TEXT", []);
-        let _ = conn.execute("ALTER TABLE patients ADD COLUMN insurance_group_number TEXT", []);
-    }
-
-    // Add category column to diagnoses table if it doesn't exist
-    let has_category: bool = conn
-        .prepare("SELECT category FROM diagnoses LIMIT 1")
-        .is_ok();
-    if !has_category {
-        let _ = conn.execute("ALTER TABLE diagnoses ADD COLUMN category TEXT", []);
-    }
-
-    // Add zen_mode_default column to user_settings table if it doesn't exist
-    let has_zen_mode: bool = conn
-        .prepare("SELECT zen_mode_default FROM user_settings LIMIT 1")
-        .is_ok();
-    if !has_zen_mode {
-        let _ = conn.execute("ALTER TABLE user_settings ADD COLUMN zen_mode_default INTEGER DEFAULT 0", []);
+/// The app starts locked: no pool exists until the user supplies the
+/// passphrase through `unlock`. Commands borrow a connection through
+/// `reader()` or `writer()`, which fail cleanly while locked. Reads run in
+/// parallel on WAL-mode reader connections; all writes go through one writer.
+///
+/// Commands that touch clinical data also need a signed-in user: they call
+/// `acting_user()` first, which fails when nobody is signed in or the session
+/// has gone idle. A user with two-factor enabled sits in `pending_login`
+/// between the password and the code.
+pub struct DbState {
+    path: PathBuf,
+    pool: RwLock<Option<Arc<DbPool>>>,
+    session: Mutex<Option<Session>>,
+    pending_login: Mutex<Option<PendingLogin>>,
+}
+
+impl DbState {
+    pub fn new(path: PathBuf) -> Self {
+        DbState {
+            path,
+            pool: RwLock::new(None),
+            session: Mutex::new(None),
+            pending_login: Mutex::new(None),
+        }
     }
 
-    // Rebuild FTS indexes for existing data
-    rebuild_fts_indexes(&conn)?;
-
-    Ok(conn)
-}
-
-// ============ FTS Search Functions ============
-
-/// Rebuild FTS indexes from existing data
-pub fn rebuild_fts_indexes(conn: &Connection) -> Result<()> {
-    // Clear and rebuild patients_fts
-    let _ = conn.execute("DELETE FROM patients_fts", []);
-    let _ = conn.execute(
-        "INSERT INTO patients_fts(rowid, patient_id, first_name, last_name, phone, email, address, ai_summary)
-         SELECT id, id, first_name, last_name, phone, email, address, ai_summary FROM patients",
-        []
-    );
+    fn pool(&self) -> crate::error::Result<Arc<DbPool>> {
+        let guard = self.pool.read()?;
+        guard.clone().ok_or_else(|| Error::Locked("Database is locked".to_string()))
+    }
 
-    // Clear and rebuild encounters_fts
-    let _ = conn.execute("DELETE FROM encounters_fts", []);
-    let _ = conn.execute(
-        "INSERT INTO encounters_fts(rowid, encounter_id, patient_id, encounter_type, chief_complaint, summary, note_content, provider)
-         SELECT id, id, patient_id, encounter_type, chief_complaint, summary, note_content, provider FROM encounters",
-        []
-    );
+    /// A read-only connection for commands that only query
+    pub fn reader(&self) -> crate::error::Result<PooledConn> {
+        self.pool()?.reader()
+    }
 
-    // Clear and rebuild diagnoses_fts
-    let _ = conn.execute("DELETE FROM diagnoses_fts", []);
-    let _ = conn.execute(
-        "INSERT INTO diagnoses_fts(rowid, diagnosis_id, patient_id, icd_code, description, category, notes)
-         SELECT id, id, patient_id, icd_code, description, category, notes FROM diagnoses",
-        []
-    );
+    /// The writer connection, for any command that inserts, updates or deletes
+    pub fn writer(&self) -> crate::error::Result<PooledConn> {
+        self.pool()?.writer()
+    }
 
-    // Clear and rebuild medications_fts
-    let _ = conn.execute("DELETE FROM medications_fts", []);
-    let _ = conn.execute(
-        "INSERT INTO medications_fts(rowid, medication_id, patient_id, name, dosage, frequency, prescriber, notes)
-         SELECT id, id, patient_id, name, dosage, frequency, prescriber, notes FROM medications",
-        []
-    );
+    /// Append to the audit log from a command that only holds a reader
+    pub fn audit(&self, user_id: i64, event: &audit::AuditEvent) -> crate::error::Result<()> {
+        let conn = self.writer()?;
+        audit::record(&conn, Some(user_id), event).map_err(Error::from)
+    }
 
-    // Clear and rebuild labs_fts
-    let _ = conn.execute("DELETE FROM labs_fts", []);
-    let _ = conn.execute(
-        "INSERT INTO labs_fts(rowid, lab_id, patient_id, test_name, result, unit, notes)
-         SELECT id, id, patient_id, test_name, result, unit, notes FROM labs",
-        []
-    );
+    // ============ Sessions ============
 
-    Ok(())
-}
+    /// The signed-in user's id, refreshing the idle timer
+    ///
+    /// An idle session is ended here, so the next command after the timeout
+    /// fails and the frontend falls back to the sign-in screen.
+    pub fn acting_user(&self) -> crate::error::Result<i64> {
+        self.acting_session().map(|(user_id, _)| user_id)
+    }
 
-/// Search result item with type information
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct SearchResult {
-    pub result_type: String,  // "patient", "encounter", "diagnosis", "medication", "lab"
-    pub id: i64,
-    pub patient_id: Option<i64>,
-    pub title: String,
-    pub subtitle: Option<String>,
-    pub snippet: Option<String>,
-    pub rank: f64,
-}
+    /// The signed-in user's id, provided their role grants `permission`
+    pub fn authorize(&self, permission: Permission) -> crate::error::Result<i64> {
+        let (user_id, role) = self.acting_session()?;
+        if !role.can(permission) {
+            return Err(rbac::denied(role, permission));
+        }
+        Ok(user_id)
+    }
 
-/// Global search across all FTS tables
-pub fn global_search(conn: &Connection, query: &str, limit: i64) -> Result<Vec<SearchResult>> {
-    let mut results: Vec<SearchResult> = Vec::new();
+    /// Whether the signed-in role has `permission`, for commands that redact rather than refuse
+    pub fn can(&self, permission: Permission) -> bool {
+        self.session
+            .lock()
+            .ok()
+            .and_then(|guard| guard.as_ref().map(|s| s.role.can(permission)))
+            .unwrap_or(false)
+    }
 
-    // Escape the query for FTS5 - wrap each word with quotes to handle special chars
-    let escaped_query = query
-        .split_whitespace()
-        .map(|word| format!("\"{}\"*", word.replace("\"", "\"\"")))
-        .collect::<Vec<_>>()
-        .join(" ");
+    fn acting_session(&self) -> crate::error::Result<(i64, Role)> {
+        let mut guard = self.session.lock()?;
+        match guard.as_mut() {
+            None => Err(Error::Unauthenticated("Not signed in".to_string())),
+            Some(session) if session.is_idle() => {
+                *guard = None;
+                Err(Error::Unauthenticated("Session expired after inactivity; sign in again".to_string()))
+            }
+            Some(session) => {
+                session.touch();
+                Ok((session.user_id, session.role))
+            }
+        }
+    }
 
-    if escaped_query.is_empty() {
-        return Ok(results);
+    /// Apply a role change to the live session if it belongs to `user_id`
+    pub fn refresh_session_role(&self, user_id: i64, role: Role) {
+        if let Ok(mut guard) = self.session.lock() {
+            if let Some(session) = guard.as_mut().filter(|s| s.user_id == user_id) {
+                session.role = role;
+            }
+        }
     }
 
-    // Search patients
-    let mut stmt = conn.prepare(
-        "SELECT patient_id, first_name, last_name, phone, snippet(patients_fts, 4, '<mark>', '</mark>', '...', 32), rank
-         FROM patients_fts WHERE patients_fts MATCH ?1
-         ORDER BY rank LIMIT ?2"
-    )?;
+    pub fn session_info(&self) -> Option<SessionInfo> {
+        let guard = self.session.lock().ok()?;
+        guard.as_ref().filter(|s| !s.is_idle()).map(Session::info)
+    }
 
-    let patient_results = stmt.query_map(params![&escaped_query, limit], |row| {
-        Ok(SearchResult {
-            result_type: "patient".to_string(),
-            id: row.get(0)?,
-            patient_id: Some(row.get(0)?),
-            title: format!("{} {}", row.get::<_, String>(1)?, row.get::<_, String>(2)?),
-            subtitle: row.get(3).ok(),
-            snippet: row.get(4).ok(),
-            rank: row.get(5)?,
+    pub fn auth_status(&self) -> crate::error::Result<auth::AuthStatus> {
+        let conn = self.reader()?;
+        Ok(auth::AuthStatus {
+            has_users: count_users(&conn)? > 0,
+            session: self.session_info(),
         })
-    })?;
+    }
 
-    for result in patient_results {
-        if let Ok(r) = result {
-            results.push(r);
+    /// Check credentials and start a session; every attempt is audited
+    ///
+    /// When the user has two-factor enabled no session starts yet: the login
+    /// waits in `pending_login` for `verify_two_factor`.
+    pub fn login(&self, username: &str, password: &str) -> crate::error::Result<LoginResult> {
+        let conn = self.writer()?;
+        *self.pending_login.lock()? = None;
+
+        match auth::authenticate(&conn, username, password) {
+            Ok(user) => {
+                let user_id = user.id.unwrap_or(0);
+                if two_factor::get_state(&conn, user_id)?.enabled {
+                    *self.pending_login.lock()? = Some(PendingLogin::new(user));
+                    return Ok(LoginResult {
+                        session: None,
+                        two_factor_required: true,
+                    });
+                }
+                let info = self.start_session(&conn, &user, None)?;
+                Ok(LoginResult {
+                    session: Some(info),
+                    two_factor_required: false,
+                })
+            }
+            Err(e) => Err(record_login_failure(&conn, username, e)),
         }
     }
 
-    // Search encounters
-    let mut stmt = conn.prepare(
-        "SELECT encounter_id, patient_id, encounter_type, chief_complaint, snippet(encounters_fts, 5, '<mark>', '</mark>', '...', 32), rank
-         FROM encounters_fts WHERE encounters_fts MATCH ?1
-         ORDER BY rank LIMIT ?2"
-    )?;
-
-    let encounter_results = stmt.query_map(params![&escaped_query, limit], |row| {
-        Ok(SearchResult {
-            result_type: "encounter".to_string(),
-            id: row.get(0)?,
-            patient_id: Some(row.get(1)?),
-            title: row.get::<_, Option<String>>(3)?.unwrap_or_else(|| row.get::<_, String>(2).unwrap_or_default()),
-            subtitle: Some(row.get::<_, String>(2)?),
-            snippet: row.get(4).ok(),
-            rank: row.get(5)?,
-        })
-    })?;
+    /// Finish a two-factor login with a TOTP code or an unused recovery code
+    ///
+    /// Wrong codes count toward the same lockout as wrong passwords.
+    pub fn verify_two_factor(&self, code: &str) -> crate::error::Result<SessionInfo> {
+        let conn = self.writer()?;
+        let mut pending = self.pending_login.lock()?;
+        let user = match pending.take() {
+            Some(p) if !p.is_expired() => p.user,
+            Some(_) => return Err(Error::Unauthenticated("Sign-in timed out; enter your password again".to_string())),
+            None => return Err(Error::Unauthenticated("No sign-in is waiting for a two-factor code".to_string())),
+        };
+        let user_id = user.id.unwrap_or(0);
+
+        if let Err(e) = auth::check_not_locked(&conn, user_id) {
+            return Err(record_login_failure(&conn, &user.username, e));
+        }
 
-    for result in encounter_results {
-        if let Ok(r) = result {
-            results.push(r);
+        match two_factor::verify_second_factor(&conn, user_id, code)? {
+            Some(factor) => self.start_session(&conn, &user, Some(factor)),
+            None => {
+                auth::record_failed_attempt(&conn, user_id)?;
+                let message = "Invalid two-factor code".to_string();
+                let event = audit::AuditEvent::session(audit::AuditAction::LoginFailed, user_id)
+                    .with_detail(serde_json::json!({ "username": user.username, "reason": message }));
+                audit::record(&conn, Some(user_id), &event)?;
+                // Keep the password step so the user can retry, unless that failure locked the account
+                if auth::check_not_locked(&conn, user_id).is_ok() {
+                    *pending = Some(PendingLogin::new(user));
+                }
+                Err(Error::Unauthenticated(message))
+            }
         }
     }
 
-    // Search diagnoses
-    let mut stmt = conn.prepare(
-        "SELECT diagnosis_id, patient_id, icd_code, description, snippet(diagnoses_fts, 3, '<mark>', '</mark>', '...', 32), rank
-         FROM diagnoses_fts WHERE diagnoses_fts MATCH ?1
-         ORDER BY rank LIMIT ?2"
-    )?;
+    fn start_session(&self, conn: &Connection, user: &User, factor: Option<two_factor::SecondFactor>) -> crate::error::Result<SessionInfo> {
+        let user_id = user.id.unwrap_or(0);
+        auth::record_successful_login(conn, user_id)?;
+        let started_at = auth::now_timestamp(conn)?;
+        let session = Session::new(user, started_at);
+        let info = session.info();
 
-    let diagnosis_results = stmt.query_map(params![&escaped_query, limit], |row| {
-        Ok(SearchResult {
-            result_type: "diagnosis".to_string(),
-            id: row.get(0)?,
-            patient_id: Some(row.get(1)?),
-            title: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
-            subtitle: row.get(2).ok(),
-            snippet: row.get(4).ok(),
-            rank: row.get(5)?,
-        })
-    })?;
+        let mut event = audit::AuditEvent::session(audit::AuditAction::Login, user_id);
+        if let Some(factor) = factor {
+            event = event.with_detail(serde_json::json!({ "two_factor": factor }));
+        }
+        audit::record(conn, Some(user_id), &event)?;
+        *self.session.lock()? = Some(session);
+        Ok(info)
+    }
 
-    for result in diagnosis_results {
-        if let Ok(r) = result {
-            results.push(r);
+    /// Create the very first account on a new database and sign it in
+    pub fn create_first_user(&self, user: User, password: &str) -> crate::error::Result<SessionInfo> {
+        auth::validate_password(password)?;
+        let username = user.username.clone();
+        {
+            let conn = self.writer()?;
+            if count_users(&conn)? > 0 {
+                return Err(Error::invalid("An account already exists; sign in instead"));
+            }
+            // Someone has to be able to create the other accounts
+            let user = User {
+                password_hash: auth::hash_password(password)?,
+                role: Role::Admin,
+                ..user
+            };
+            create_user(&conn, &user)?;
         }
+        self.login(&username, password)?
+            .session
+            .ok_or_else(|| Error::internal("Sign-in did not complete"))
     }
 
-    // Search medications
-    let mut stmt = conn.prepare(
-        "SELECT medication_id, patient_id, name, dosage, snippet(medications_fts, 2, '<mark>', '</mark>', '...', 32), rank
-         FROM medications_fts WHERE medications_fts MATCH ?1
-         ORDER BY rank LIMIT ?2"
-    )?;
+    pub fn logout(&self) -> crate::error::Result<()> {
+        *self.pending_login.lock()? = None;
+        let ended = self.session.lock()?.take();
+        if let Some(session) = ended {
+            let conn = self.writer()?;
+            audit::record(&conn, Some(session.user_id), &audit::AuditEvent::session(audit::AuditAction::Logout, session.user_id))?;
+        }
+        Ok(())
+    }
 
-    let med_results = stmt.query_map(params![&escaped_query, limit], |row| {
-        Ok(SearchResult {
-            result_type: "medication".to_string(),
-            id: row.get(0)?,
-            patient_id: Some(row.get(1)?),
-            title: row.get::<_, String>(2)?,
-            subtitle: row.get(3).ok(),
-            snippet: row.get(4).ok(),
-            rank: row.get(5)?,
-        })
-    })?;
+    pub fn is_unlocked(&self) -> bool {
+        self.pool.read().map(|p| p.is_some()).unwrap_or(false)
+    }
 
-    for result in med_results {
-        if let Ok(r) = result {
-            results.push(r);
+    pub fn encryption_status(&self) -> encryption::EncryptionStatus {
+        encryption::get_encryption_status(&self.path, self.is_unlocked())
+    }
+
+    /// Open the database with `passphrase`
+    ///
+    /// On first run this creates an encrypted database, and a legacy plaintext
+    /// `rchart.db` is encrypted in place with the passphrase before opening.
+    pub fn unlock(&self, passphrase: &str) -> crate::error::Result<()> {
+        let mut guard = self.pool.write()?;
+        if guard.is_some() {
+            return Ok(());
         }
+
+        let status = encryption::get_encryption_status(&self.path, false);
+        if !status.encrypted {
+            encryption::validate_passphrase(passphrase)?;
+        }
+
+        let pool = open_pool(&self.path, passphrase).map_err(|e| {
+            if is_wrong_key(e.as_ref()) {
+                Error::invalid_field("passphrase", "Incorrect passphrase")
+            } else {
+                Error::Database(e.to_string())
+            }
+        })?;
+        *guard = Some(pool);
+        Ok(())
     }
 
-    // Search labs
-    let mut stmt = conn.prepare(
-        "SELECT lab_id, patient_id, test_name, result, snippet(labs_fts, 2, '<mark>', '</mark>', '...', 32), rank
-         FROM labs_fts WHERE labs_fts MATCH ?1
-         ORDER BY rank LIMIT ?2"
-    )?;
+    /// Drop the pool; the key goes with the last connection once in-flight commands finish
+    ///
+    /// Any session ends too, so unlocking again always means signing in again.
+    pub fn lock(&self) -> crate::error::Result<()> {
+        self.logout()?;
+        let mut guard = self.pool.write()?;
+        *guard = None;
+        Ok(())
+    }
 
-    let lab_results = stmt.query_map(params![&escaped_query, limit], |row| {
-        Ok(SearchResult {
-            result_type: "lab".to_string(),
-            id: row.get(0)?,
-            patient_id: Some(row.get(1)?),
-            title: row.get::<_, String>(2)?,
-            subtitle: row.get(3).ok(),
-            snippet: row.get(4).ok(),
-            rank: row.get(5)?,
-        })
+    /// Re-encrypt the database under a new passphrase after checking the current one
+    ///
+    /// The reader connections are still keyed with the old passphrase, so reads
+    /// already in flight are let finish and the readers retired before the rekey
+    /// starts, and the whole pool is reopened once it succeeds.
+    pub fn change_passphrase(&self, current_passphrase: &str, new_passphrase: &str) -> crate::error::Result<()> {
+        encryption::validate_passphrase(new_passphrase)?;
+        let mut guard = self.pool.write()?;
+        let pool = guard.clone().ok_or_else(|| Error::Locked("Database is locked".to_string()))?;
+
+        // Prove the caller knows the current passphrase with a separate connection
+        encryption::open_encrypted(&self.path, current_passphrase)
+            .map_err(|_| Error::invalid_field("current_passphrase", "Current passphrase is incorrect"))?;
+
+        pool.drain_readers()?;
+        let rekeyed = pool.writer().and_then(|writer| encryption::rekey(&writer, new_passphrase).map_err(Error::from));
+        drop(pool);
+
+        *guard = None;
+        let reopen_with = if rekeyed.is_ok() { new_passphrase } else { current_passphrase };
+        let pool = open_pool(&self.path, reopen_with).map_err(|e| Error::Database(e.to_string()))?;
+        *guard = Some(pool);
+        rekeyed
+    }
+}
+
+/// Audit a refused login and turn it into the message shown to the user
+fn record_login_failure(conn: &Connection, username: &str, e: auth::LoginError) -> Error {
+    let user_id = match e {
+        auth::LoginError::InvalidCredentials { user_id } => user_id,
+        auth::LoginError::Locked { user_id, .. } => Some(user_id),
+        auth::LoginError::Database(db_err) => return db_err.into(),
+    };
+    let event = audit::AuditEvent {
+        action: audit::AuditAction::LoginFailed,
+        entity_type: "users",
+        entity_id: user_id,
+        patient_id: None,
+        detail: Some(serde_json::json!({ "username": username, "reason": e.to_string() })),
+    };
+    match audit::record(conn, user_id, &event) {
+        Ok(()) => Error::Unauthenticated(e.to_string()),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+/// Open the writer (running migrations on it) and then the read connections
+///
+/// Readers are keyed in parallel since each one pays the full KDF cost.
+fn open_pool(db_path: &PathBuf, passphrase: &str) -> std::result::Result<Arc<DbPool>, Box<dyn std::error::Error>> {
+    let writer = init_db(db_path, passphrase)?;
+    pool::configure_writer(&writer)?;
+
+    let readers = std::thread::scope(|s| {
+        let handles: Vec<_> = (0..pool::READER_COUNT)
+            .map(|_| {
+                s.spawn(|| -> Result<Connection> {
+                    let conn = encryption::open_encrypted(db_path, passphrase)?;
+                    pool::configure_reader(&conn)?;
+                    Ok(conn)
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().expect("reader setup thread panicked"))
+            .collect::<Result<Vec<_>>>()
     })?;
 
-    for result in lab_results {
-        if let Ok(r) = result {
-            results.push(r);
-        }
+    Ok(DbPool::new(writer, readers))
+}
+
+fn is_wrong_key(e: &(dyn std::error::Error + 'static)) -> bool {
+    matches!(
+        e.downcast_ref::<rusqlite::Error>(),
+        Some(rusqlite::Error::SqliteFailure(err, _)) if err.code == rusqlite::ErrorCode::NotADatabase
+    )
+}
+
+/// Open the SQLCipher database with `passphrase` and apply any pending schema migrations
+///
+/// A plaintext database left by an older build is encrypted in place first.
+/// The passphrase is stretched by SQLCipher's KDF (see `encryption`); it is
+/// never written anywhere.
+pub fn init_db(db_path: &PathBuf, passphrase: &str) -> std::result::Result<Connection, Box<dyn std::error::Error>> {
+    if db_path.exists() && encryption::is_plaintext(db_path)? {
+        encryption::encrypt_plaintext_database(db_path, passphrase)?;
     }
 
-    // Sort all results by rank (lower is better in FTS5)
-    results.sort_by(|a, b| a.rank.partial_cmp(&b.rank).unwrap_or(std::cmp::Ordering::Equal));
+    let mut conn = encryption::open_encrypted(db_path, passphrase)?;
 
-    // Limit total results
-    results.truncate(limit as usize);
+    // Create or upgrade the schema. Each migration runs in its own transaction
+    // and a failure aborts startup rather than leaving a half-migrated file.
+    migrations::run_migrations(&mut conn)?;
 
-    Ok(results)
+    // Rebuild FTS indexes for existing data
+    rebuild_fts_indexes(&conn)?;
+
+    Ok(conn)
 }
 
-/// Search within a specific patient's data
-pub fn search_patient_data(conn: &Connection, patient_id: i64, query: &str, limit: i64) -> Result<Vec<SearchResult>> {
-    let mut results: Vec<SearchResult> = Vec::new();
+// ============ FTS Search Functions ============
+
+/// One FTS5 index, how its hits are shown and how much they count
+///
+/// `title`, `subtitle` and `date` are SQL over the source row, aliased `s`;
+/// `join` pulls in anything else the labels need. `date` is what `before:`
+/// and `after:` compare against.
+///
+/// `columns` lists the index's columns in the order migration 013 created
+/// them, each with its bm25 weight; a hit in a name counts for more than one
+/// in free-text notes. `weight` scales the whole table against the others.
+struct SearchIndex {
+    result_type: &'static str,
+    table: &'static str,
+    title: &'static str,
+    subtitle: &'static str,
+    join: &'static str,
+    date: &'static str,
+    weight: f64,
+    columns: &'static [(&'static str, f64)],
+}
+
+/// Multiplier for hits in a chart the user opened recently
+const RECENT_PATIENT_BOOST: f64 = 1.5;
+
+/// How many of the user's most recently opened charts get `RECENT_PATIENT_BOOST`
+pub const RECENT_PATIENT_COUNT: i64 = 20;
+
+/// Added to a patient whose chart number, date of birth or phone is exactly a search term;
+/// more than any text hit can score, so the record being looked up comes first
+const EXACT_MATCH_BOOST: f64 = 2.0;
+
+/// Every table `global_search` covers; each has a `<table>_fts` index (migration 013)
+const SEARCH_INDEXES: &[SearchIndex] = &[
+    SearchIndex {
+        result_type: "patient",
+        table: "patients",
+        title: "s.first_name || ' ' || s.last_name",
+        subtitle: "s.phone",
+        join: "",
+        date: "s.created_at",
+        weight: 1.0,
+        columns: &[
+            ("first_name", 10.0),
+            ("last_name", 10.0),
+            ("phone", 5.0),
+            ("email", 5.0),
+            ("address", 2.0),
+            ("ai_summary", 1.0),
+        ],
+    },
+    SearchIndex {
+        result_type: "encounter",
+        table: "encounters",
+        title: "COALESCE(s.chief_complaint, s.encounter_type)",
+        subtitle: "s.encounter_type",
+        join: "",
+        date: "s.encounter_date",
+        weight: 0.8,
+        columns: &[
+            ("patient_id", 0.0),
+            ("encounter_type", 2.0),
+            ("chief_complaint", 5.0),
+            ("summary", 3.0),
+            ("note_content", 1.0),
+            ("provider", 2.0),
+        ],
+    },
+    SearchIndex {
+        result_type: "diagnosis",
+        table: "diagnoses",
+        title: "s.name",
+        subtitle: "s.icd_code",
+        join: "",
+        date: "s.onset_date",
+        weight: 0.8,
+        columns: &[
+            ("patient_id", 0.0),
+            ("name", 10.0),
+            ("icd_code", 8.0),
+            ("description", 3.0),
+            ("category", 2.0),
+            ("notes", 1.0),
+        ],
+    },
+    SearchIndex {
+        result_type: "medication",
+        table: "medications",
+        title: "s.name",
+        subtitle: "COALESCE(s.dose, s.dosage)",
+        join: "",
+        date: "s.start_date",
+        weight: 0.6,
+        columns: &[
+            ("patient_id", 0.0),
+            ("name", 10.0),
+            ("dose", 1.0),
+            ("dosage", 1.0),
+            ("frequency", 1.0),
+            ("route", 1.0),
+            ("prescriber", 2.0),
+            ("notes", 1.0),
+        ],
+    },
+    SearchIndex {
+        result_type: "lab",
+        table: "labs",
+        title: "COALESCE(s.name, s.test_name)",
+        subtitle: "COALESCE(s.result, s.value || COALESCE(' ' || s.unit, ''))",
+        join: "",
+        date: "s.recorded_at",
+        weight: 0.6,
+        columns: &[
+            ("patient_id", 0.0),
+            ("name", 10.0),
+            ("test_name", 10.0),
+            ("result", 3.0),
+            ("unit", 1.0),
+            ("notes", 1.0),
+        ],
+    },
+    SearchIndex {
+        result_type: "allergy",
+        table: "allergies",
+        title: "s.allergen",
+        subtitle: "s.reaction",
+        join: "",
+        date: "s.onset_date",
+        weight: 0.7,
+        columns: &[("patient_id", 0.0), ("allergen", 10.0), ("reaction", 4.0), ("severity", 2.0)],
+    },
+    SearchIndex {
+        result_type: "vaccination",
+        table: "vaccinations",
+        title: "s.vaccine_name",
+        subtitle: "s.date_given",
+        join: "",
+        date: "s.date_given",
+        weight: 0.5,
+        columns: &[
+            ("patient_id", 0.0),
+            ("vaccine_name", 10.0),
+            ("lot_number", 3.0),
+            ("site", 1.0),
+            ("administered_by", 2.0),
+        ],
+    },
+    SearchIndex {
+        result_type: "social_history",
+        table: "social_history",
+        title: "s.detail",
+        subtitle: "s.category",
+        join: "",
+        date: "s.start_date",
+        weight: 0.5,
+        columns: &[("patient_id", 0.0), ("category", 4.0), ("detail", 6.0), ("status", 2.0)],
+    },
+    SearchIndex {
+        result_type: "family_history",
+        table: "family_history",
+        title: "s.condition",
+        subtitle: "s.relation",
+        join: "",
+        date: "s.created_at",
+        weight: 0.5,
+        columns: &[("patient_id", 0.0), ("relation", 4.0), ("condition", 8.0), ("notes", 1.0)],
+    },
+    SearchIndex {
+        result_type: "todo",
+        table: "todos",
+        title: "s.description",
+        subtitle: "s.due_date",
+        join: "",
+        date: "COALESCE(s.due_date, s.created_at)",
+        weight: 0.5,
+        columns: &[("patient_id", 0.0), ("description", 6.0), ("priority", 1.0), ("status", 1.0)],
+    },
+    SearchIndex {
+        result_type: "prescription",
+        table: "prescriptions",
+        title: "COALESCE(m.name, 'Prescription')",
+        subtitle: "s.sig",
+        join: "LEFT JOIN medications m ON m.id = s.medication_id",
+        date: "s.prescribed_date",
+        weight: 0.5,
+        columns: &[("patient_id", 0.0), ("sig", 4.0), ("pharmacy", 2.0), ("notes", 1.0)],
+    },
+    SearchIndex {
+        result_type: "message",
+        table: "messages",
+        title: "s.subject",
+        subtitle: "s.created_at",
+        join: "",
+        date: "s.created_at",
+        weight: 0.4,
+        columns: &[("patient_id", 0.0), ("subject", 6.0), ("body", 2.0)],
+    },
+];
+
+/// Every `result_type` a search can return, for `type:`
+pub fn search_result_types() -> Vec<&'static str> {
+    SEARCH_INDEXES.iter().map(|i| i.result_type).collect()
+}
+
+/// Tables that have a `<table>_fts` search index
+pub fn search_tables() -> Vec<&'static str> {
+    SEARCH_INDEXES.iter().map(|i| i.table).collect()
+}
+
+/// Rebuild every FTS index from its source table
+pub fn rebuild_fts_indexes(conn: &Connection) -> Result<()> {
+    for index in SEARCH_INDEXES {
+        let fts = format!("{}_fts", index.table);
+        conn.execute(&format!("INSERT INTO {}({}) VALUES ('rebuild')", fts, fts), [])?;
+    }
+    Ok(())
+}
+
+/// Search result item with type information
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
+pub struct SearchResult {
+    /// `result_type` of the `SEARCH_INDEXES` entry that matched (`patient`, `lab`, `allergy`, ...)
+    pub result_type: String,
+    #[ts(as = "f64")]
+    pub id: i64,
+    #[ts(as = "Option<f64>")]
+    pub patient_id: Option<i64>,
+    /// Full name of the chart the hit is in
+    pub patient_name: Option<String>,
+    pub title: String,
+    pub subtitle: Option<String>,
+    pub snippet: Option<String>,
+    /// Relevance after weighting and boosts; higher is better and comparable across result types
+    pub score: f64,
+}
+
+/// The hits in one patient's chart, for "3 matches in John Smith's chart"
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
+pub struct SearchGroup {
+    /// `None` for hits that belong to no chart, such as general messages
+    #[ts(as = "Option<f64>")]
+    pub patient_id: Option<i64>,
+    pub patient_name: Option<String>,
+    /// The best score in the group
+    pub score: f64,
+    pub results: Vec<SearchResult>,
+}
 
-    let escaped_query = query
+/// Turn free text into an FTS5 query: every word must match, as a prefix
+fn fts_query(query: &str) -> String {
+    query
         .split_whitespace()
         .map(|word| format!("\"{}\"*", word.replace("\"", "\"\"")))
         .collect::<Vec<_>>()
-        .join(" ");
+        .join(" ")
+}
 
-    if escaped_query.is_empty() {
-        return Ok(results);
+/// Up to `limit` hits from one index, optionally only within one patient's chart
+///
+/// bm25 scores depend on how many rows and columns an index has, so the raw
+/// numbers from two tables cannot be compared. Each hit's score is instead its
+/// share of the best hit in the same table, times the table's `weight`.
+fn search_index(conn: &Connection, index: &SearchIndex, query: &SearchQuery, patient_id: Option<i64>, limit: i64) -> Result<Vec<SearchResult>> {
+    let fts = format!("{}_fts", index.table);
+    let patient_column = if index.table == "patients" { "s.id" } else { "s.patient_id" };
+    let weights = index.columns.iter().map(|(_, w)| w.to_string()).collect::<Vec<_>>().join(", ");
+    let mut stmt = conn.prepare(&format!(
+        "SELECT s.id, {patient}, pt.first_name || ' ' || pt.last_name, COALESCE({title}, ''), {subtitle},
+                snippet({fts}, -1, '<mark>', '</mark>', '...', 32), -bm25({fts}, {weights}) AS relevance
+         FROM {fts}
+         JOIN {table} s ON s.id = {fts}.rowid
+         {join}
+         LEFT JOIN patients pt ON pt.id = {patient}
+         WHERE {fts} MATCH ?1
+           AND (?2 IS NULL OR {patient} = ?2)
+           AND (?3 IS NULL OR {patient} IN (
+                SELECT id FROM patients WHERE (first_name || ' ' || last_name) LIKE '%' || ?3 || '%'))
+           AND (?4 IS NULL OR date({date}) < ?4)
+           AND (?5 IS NULL OR date({date}) > ?5)
+         ORDER BY relevance DESC LIMIT ?6",
+        patient = patient_column,
+        title = index.title,
+        subtitle = index.subtitle,
+        date = index.date,
+        fts = fts,
+        table = index.table,
+        join = index.join,
+        weights = weights,
+    ))?;
+
+    let mut results = stmt
+        .query_map(params![query.fts, patient_id, query.patient, query.before, query.after, limit], |row| {
+            Ok(SearchResult {
+                result_type: index.result_type.to_string(),
+                id: row.get(0)?,
+                patient_id: row.get(1)?,
+                patient_name: row.get(2)?,
+                title: row.get(3)?,
+                subtitle: row.get(4)?,
+                snippet: row.get(5)?,
+                score: row.get(6)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    let best = results.first().map_or(0.0, |r| r.score);
+    for result in &mut results {
+        result.score = if best > 0.0 { index.weight * result.score / best } else { 0.0 };
     }
+    Ok(results)
+}
 
-    // Search encounters for this patient
-    let mut stmt = conn.prepare(
-        "SELECT encounter_id, encounter_type, chief_complaint, snippet(encounters_fts, 5, '<mark>', '</mark>', '...', 32), rank
-         FROM encounters_fts WHERE encounters_fts MATCH ?1 AND patient_id = ?2
-         ORDER BY rank LIMIT ?3"
-    )?;
+/// One identifier to look a patient up by; exactly one of the fields is set
+#[derive(Default)]
+struct ExactLookup {
+    id: Option<i64>,
+    dob: Option<String>,
+    phone: Option<String>,
+    reason: &'static str,
+}
 
-    let encounter_results = stmt.query_map(params![&escaped_query, patient_id, limit], |row| {
-        Ok(SearchResult {
-            result_type: "encounter".to_string(),
-            id: row.get(0)?,
-            patient_id: Some(patient_id),
-            title: row.get::<_, Option<String>>(2)?.unwrap_or_else(|| row.get::<_, String>(1).unwrap_or_default()),
-            subtitle: Some(row.get::<_, String>(1)?),
-            snippet: row.get(3).ok(),
-            rank: row.get(4)?,
-        })
-    })?;
+/// Patients whose chart number, date of birth or phone number is exactly one of the query's terms
+///
+/// There is no separate MRN yet, so the chart number is the patient id.
+fn exact_patient_matches(conn: &Connection, query: &SearchQuery) -> Result<Vec<SearchResult>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, first_name || ' ' || last_name, phone FROM patients
+         WHERE status != 'merged'
+           AND (?1 IS NULL OR id = ?1)
+           AND (?2 IS NULL OR dob = ?2)
+           AND (?3 IS NULL OR REPLACE(REPLACE(REPLACE(REPLACE(REPLACE(REPLACE(phone,
+                    ' ', ''), '-', ''), '(', ''), ')', ''), '.', ''), '+', '') LIKE '%' || ?3)
+           AND (?4 IS NULL OR (first_name || ' ' || last_name) LIKE '%' || ?4 || '%')",
+    )?;
+
+    let mut found: Vec<SearchResult> = Vec::new();
+    for term in &query.terms {
+        let digits: String = term.chars().filter(char::is_ascii_digit).collect();
+        let is_number = !digits.is_empty() && term.chars().all(|c| c.is_ascii_digit() || " -().+".contains(c));
+        let mut lookups = Vec::new();
+        if let Some(dob) = name_match::normalize_date(term) {
+            lookups.push(ExactLookup { dob: Some(dob), reason: "Date of birth matches", ..Default::default() });
+        } else if is_number {
+            if digits == *term {
+                if let Ok(id) = digits.parse::<i64>() {
+                    lookups.push(ExactLookup { id: Some(id), reason: "Chart number matches", ..Default::default() });
+                }
+            }
+            // Without the area code a local number is seven digits
+            if digits.len() >= 7 {
+                lookups.push(ExactLookup { phone: Some(digits.clone()), reason: "Phone number matches", ..Default::default() });
+            }
+        }
 
-    for result in encounter_results {
-        if let Ok(r) = result {
-            results.push(r);
+        for ExactLookup { id, dob, phone, reason } in lookups {
+            let rows = stmt.query_map(params![id, dob, phone, query.patient], |row| {
+                let name: String = row.get(1)?;
+                Ok(SearchResult {
+                    result_type: "patient".to_string(),
+                    id: row.get(0)?,
+                    patient_id: Some(row.get(0)?),
+                    patient_name: Some(name.clone()),
+                    title: name,
+                    subtitle: row.get(2)?,
+                    snippet: Some(reason.to_string()),
+                    score: EXACT_MATCH_BOOST,
+                })
+            })?;
+            for row in rows {
+                let row = row?;
+                if !found.iter().any(|f| f.id == row.id) {
+                    found.push(row);
+                }
+            }
         }
     }
+    Ok(found)
+}
 
-    // Search diagnoses for this patient
-    let mut stmt = conn.prepare(
-        "SELECT diagnosis_id, icd_code, description, snippet(diagnoses_fts, 3, '<mark>', '</mark>', '...', 32), rank
-         FROM diagnoses_fts WHERE diagnoses_fts MATCH ?1 AND patient_id = ?2
-         ORDER BY rank LIMIT ?3"
-    )?;
-
-    let diagnosis_results = stmt.query_map(params![&escaped_query, patient_id, limit], |row| {
-        Ok(SearchResult {
-            result_type: "diagnosis".to_string(),
-            id: row.get(0)?,
-            patient_id: Some(patient_id),
-            title: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
-            subtitle: row.get(1).ok(),
-            snippet: row.get(3).ok(),
-            rank: row.get(4)?,
-        })
-    })?;
+/// Search every index allowed by `query`, with boosts, best first
+fn ranked_search(conn: &Connection, query: &SearchQuery, patient_id: Option<i64>, recent: &[i64], limit: i64) -> Result<Vec<SearchResult>> {
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
 
-    for result in diagnosis_results {
-        if let Ok(r) = result {
-            results.push(r);
+    let mut results = Vec::new();
+    for index in SEARCH_INDEXES.iter().filter(|i| query.includes(i.result_type)) {
+        if patient_id.is_some() && index.table == "patients" {
+            continue;
         }
+        results.extend(search_index(conn, index, query, patient_id, limit)?);
     }
 
-    // Search medications for this patient
-    let mut stmt = conn.prepare(
-        "SELECT medication_id, name, dosage, snippet(medications_fts, 2, '<mark>', '</mark>', '...', 32), rank
-         FROM medications_fts WHERE medications_fts MATCH ?1 AND patient_id = ?2
-         ORDER BY rank LIMIT ?3"
-    )?;
-
-    let med_results = stmt.query_map(params![&escaped_query, patient_id, limit], |row| {
-        Ok(SearchResult {
-            result_type: "medication".to_string(),
-            id: row.get(0)?,
-            patient_id: Some(patient_id),
-            title: row.get::<_, String>(1)?,
-            subtitle: row.get(2).ok(),
-            snippet: row.get(3).ok(),
-            rank: row.get(4)?,
-        })
-    })?;
+    if patient_id.is_none() && query.includes("patient") {
+        for exact in exact_patient_matches(conn, query)? {
+            match results.iter_mut().find(|r| r.result_type == "patient" && r.id == exact.id) {
+                Some(existing) => existing.score += EXACT_MATCH_BOOST,
+                None => results.push(exact),
+            }
+        }
+    }
 
-    for result in med_results {
-        if let Ok(r) = result {
-            results.push(r);
+    for result in &mut results {
+        if result.patient_id.is_some_and(|id| recent.contains(&id)) {
+            result.score *= RECENT_PATIENT_BOOST;
         }
     }
 
-    // Search labs for this patient
-    let mut stmt = conn.prepare(
-        "SELECT lab_id, test_name, result, snippet(labs_fts, 2, '<mark>', '</mark>', '...', 32), rank
-         FROM labs_fts WHERE labs_fts MATCH ?1 AND patient_id = ?2
-         ORDER BY rank LIMIT ?3"
-    )?;
+    results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    results.truncate(limit.max(0) as usize);
+    Ok(results)
+}
 
-    let lab_results = stmt.query_map(params![&escaped_query, patient_id, limit], |row| {
-        Ok(SearchResult {
-            result_type: "lab".to_string(),
-            id: row.get(0)?,
-            patient_id: Some(patient_id),
-            title: row.get::<_, String>(1)?,
-            subtitle: row.get(2).ok(),
-            snippet: row.get(3).ok(),
-            rank: row.get(4)?,
-        })
-    })?;
+/// Global search across all FTS tables
+///
+/// Hits in the charts listed in `recent` (most recently opened first) are boosted.
+pub fn global_search(conn: &Connection, query: &SearchQuery, recent: &[i64], limit: i64) -> Result<Vec<SearchResult>> {
+    ranked_search(conn, query, None, recent, limit)
+}
 
-    for result in lab_results {
-        if let Ok(r) = result {
-            results.push(r);
+/// Search within a specific patient's data
+pub fn search_patient_data(conn: &Connection, patient_id: i64, query: &SearchQuery, limit: i64) -> Result<Vec<SearchResult>> {
+    ranked_search(conn, query, Some(patient_id), &[], limit)
+}
+
+/// Gather ranked results by chart; groups keep the order of their best hit
+pub fn group_by_patient(results: Vec<SearchResult>) -> Vec<SearchGroup> {
+    let mut groups: Vec<SearchGroup> = Vec::new();
+    for result in results {
+        match groups.iter_mut().find(|g| g.patient_id == result.patient_id) {
+            Some(group) => group.results.push(result),
+            None => groups.push(SearchGroup {
+                patient_id: result.patient_id,
+                patient_name: result.patient_name.clone(),
+                score: result.score,
+                results: vec![result],
+            }),
         }
     }
-
-    // Sort by rank
-    results.sort_by(|a, b| a.rank.partial_cmp(&b.rank).unwrap_or(std::cmp::Ordering::Equal));
-    results.truncate(limit as usize);
-
-    Ok(results)
+    // The chart itself leads its own group
+    for group in &mut groups {
+        group.results.sort_by_key(|r| r.result_type != "patient");
+    }
+    groups
 }
 
 /// Quick patient search (for patient list filtering)
+///
+/// FTS prefix hits come first. When they do not fill `limit`, close
+/// misspellings and sound-alikes from `name_match` follow, best first.
 pub fn quick_search_patients(conn: &Connection, query: &str, limit: i64) -> Result<Vec<Patient>> {
     if query.trim().is_empty() {
-        return get_all_patients(conn);
+        return bmc_list(conn, &PatientFilter::default());
     }
 
-    let escaped_query = query
-        .split_whitespace()
-        .map(|word| format!("\"{}\"*", word.replace("\"", "\"\"")))
-        .collect::<Vec<_>>()
-        .join(" ");
-
-    let mut stmt = conn.prepare(
-        "SELECT p.id, p.first_name, p.last_name, p.dob, p.sex, p.gender, p.address, p.phone, p.email,
-                p.photo_url, p.ai_summary, p.preferred_pharmacy, p.insurance_provider,
-                p.insurance_policy_number, p.insurance_group_number
-         FROM patients p
-         INNER JOIN patients_fts fts ON p.id = fts.patient_id
-         WHERE patients_fts MATCH ?1
-         ORDER BY fts.rank
-         LIMIT ?2"
-    )?;
-
-    let patients = stmt.query_map(params![&escaped_query, limit], |row| {
-        Ok(Patient {
-            id: Some(row.get(0)?),
-            first_name: row.get(1)?,
-            last_name: row.get(2)?,
-            dob: row.get(3)?,
-            sex: row.get(4)?,
-            gender: row.get(5)?,
-            address: row.get(6)?,
-            phone: row.get(7)?,
-            email: row.get(8)?,
-            photo_url: row.get(9)?,
-            ai_summary: row.get(10)?,
-            preferred_pharmacy: row.get(11)?,
-            insurance_provider: row.get(12)?,
-            insurance_policy_number: row.get(13)?,
-            insurance_group_number: row.get(14)?,
-        })
-    })?;
+    let mut stmt = conn.prepare(&format!(
+        "{}
+         INNER JOIN patients_fts ON p.id = patients_fts.rowid
+         WHERE patients_fts MATCH ?1 AND p.status != 'merged'
+         ORDER BY patients_fts.rank
+         LIMIT ?2",
+        model::bmc_base::select_sql::<Patient>(Some("p"))
+    ))?;
+
+    let mut patients = stmt
+        .query_map(params![fts_query(query), limit], model::bmc_base::entity_from_row::<Patient>)?
+        .collect::<Result<Vec<_>>>()?;
+
+    let limit = limit.max(0) as usize;
+    if patients.len() < limit {
+        let fuzzy = name_match::NameQuery::from_text(query);
+        for found in name_match::find_patient_matches(conn, &fuzzy, name_match::SEARCH_THRESHOLD, limit, None)? {
+            if patients.len() == limit {
+                break;
+            }
+            if !patients.iter().any(|p| p.id == found.patient.id) {
+                patients.push(found.patient);
+            }
+        }
+    }
 
-    patients.collect()
+    Ok(patients)
 }
 
 // ============ Patient CRUD Operations ============
 
 use serde::{Deserialize, Serialize};
+use ts_rs::TS;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
 pub struct Patient {
+    #[ts(as = "Option<f64>")]
     pub id: Option<i64>,
     pub first_name: String,
     pub last_name: String,
@@ -1008,11 +903,24 @@ pub struct Patient {
     pub insurance_provider: Option<String>,
     pub insurance_policy_number: Option<String>,
     pub insurance_group_number: Option<String>,
+    /// `active`, `inactive`, `deceased` or `merged`; set by the lifecycle commands, not by create/update
+    #[ts(optional = nullable)]
+    pub status: Option<String>,
+    /// When the status last changed (date of death for `deceased`)
+    #[ts(optional = nullable)]
+    pub status_date: Option<String>,
+    /// The version this copy was read at; updates from an older copy are rejected as conflicts
+    #[serde(default)]
+    #[ts(optional = nullable, as = "Option<f64>")]
+    pub row_version: Option<i64>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
 pub struct Appointment {
+    #[ts(as = "Option<f64>")]
     pub id: Option<i64>,
+    #[ts(as = "f64")]
     pub patient_id: i64,
     pub appointment_time: String,
     pub duration_minutes: Option<i32>,
@@ -1021,9 +929,12 @@ pub struct Appointment {
     pub notes: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
 pub struct AppointmentWithPatient {
+    #[ts(as = "f64")]
     pub id: i64,
+    #[ts(as = "f64")]
     pub patient_id: i64,
     pub patient_name: String,
     pub appointment_time: String,
@@ -1034,9 +945,12 @@ pub struct AppointmentWithPatient {
 
 // ============ New Structs for Patient Detail Page ============
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
 pub struct Diagnosis {
+    #[ts(as = "Option<f64>")]
     pub id: Option<i64>,
+    #[ts(as = "f64")]
     pub patient_id: i64,
     pub name: String,
     pub icd_code: Option<String>,
@@ -1045,9 +959,12 @@ pub struct Diagnosis {
     pub category: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
 pub struct Medication {
+    #[ts(as = "Option<f64>")]
     pub id: Option<i64>,
+    #[ts(as = "f64")]
     pub patient_id: i64,
     pub name: String,
     pub dose: Option<String>,
@@ -1058,39 +975,69 @@ pub struct Medication {
     pub status: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
 pub struct DiagnosisWithMedications {
     pub diagnosis: Diagnosis,
+    #[ts(as = "Vec<f64>")]
     pub medication_ids: Vec<i64>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
 pub struct Vital {
+    #[ts(as = "Option<f64>")]
     pub id: Option<i64>,
+    #[ts(as = "f64")]
     pub patient_id: i64,
     pub vital_type: String,
     pub value: f64,
     pub value_secondary: Option<f64>,
     pub unit: String,
     pub recorded_at: String,
+    /// Calculated from other readings taken at the same time; see `vitals`
+    #[serde(default, deserialize_with = "crate::store::int_bool")]
+    #[ts(optional = nullable)]
+    pub is_derived: Option<bool>,
+    #[serde(default)]
+    #[ts(optional = nullable, as = "Option<f64>")]
+    pub row_version: Option<i64>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
 pub struct Lab {
+    #[ts(as = "Option<f64>")]
     pub id: Option<i64>,
+    #[ts(as = "f64")]
     pub patient_id: i64,
     pub name: String,
     pub value: f64,
     pub unit: Option<String>,
     pub reference_range_low: Option<f64>,
     pub reference_range_high: Option<f64>,
+    #[serde(default, deserialize_with = "crate::store::int_bool")]
+    #[ts(optional = nullable)]
     pub is_abnormal: Option<bool>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub interpretation: Option<LabInterpretation>,
     pub recorded_at: String,
+    /// The order this result answers; set through `orders::attach_lab`, never by a plain update
+    #[serde(default)]
+    #[ts(optional = nullable, as = "Option<f64>")]
+    pub order_id: Option<i64>,
+    #[serde(default)]
+    #[ts(optional = nullable, as = "Option<f64>")]
+    pub row_version: Option<i64>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
 pub struct ClinicalScore {
+    #[ts(as = "Option<f64>")]
     pub id: Option<i64>,
+    #[ts(as = "f64")]
     pub patient_id: i64,
     pub score_type: String,
     pub score: i32,
@@ -1099,9 +1046,12 @@ pub struct ClinicalScore {
     pub recorded_at: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
 pub struct Encounter {
+    #[ts(as = "Option<f64>")]
     pub id: Option<i64>,
+    #[ts(as = "f64")]
     pub patient_id: i64,
     pub encounter_date: String,
     pub encounter_type: String,
@@ -1110,47 +1060,114 @@ pub struct Encounter {
     pub note_content: Option<String>,
     pub provider: Option<String>,
     pub location: Option<String>,
+    /// Set by `sign_encounter`; a signed note can no longer be edited, only amended
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub signed_at: Option<String>,
+    #[serde(default)]
+    #[ts(optional = nullable, as = "Option<f64>")]
+    pub signed_by: Option<i64>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub signed_by_name: Option<String>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub cosigned_at: Option<String>,
+    #[serde(default)]
+    #[ts(optional = nullable, as = "Option<f64>")]
+    pub cosigned_by: Option<i64>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub cosigned_by_name: Option<String>,
+    /// Filled in by `get_encounter_by_id` only; chart lists leave it empty
+    #[serde(default)]
+    pub addenda: Vec<EncounterAddendum>,
+    #[serde(default)]
+    #[ts(optional = nullable, as = "Option<f64>")]
+    pub row_version: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
+pub struct EncounterAddendum {
+    #[ts(as = "Option<f64>")]
+    pub id: Option<i64>,
+    #[ts(as = "f64")]
+    pub encounter_id: i64,
+    #[ts(as = "Option<f64>")]
+    pub author_id: Option<i64>,
+    pub author_name: Option<String>,
+    pub content: String,
+    pub created_at: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
 pub struct Allergy {
+    #[ts(as = "Option<f64>")]
     pub id: Option<i64>,
+    #[ts(as = "f64")]
     pub patient_id: i64,
     pub allergen: String,
     pub reaction: Option<String>,
     pub severity: Option<String>,
+    #[serde(default)]
+    #[ts(optional = nullable, as = "Option<f64>")]
+    pub row_version: Option<i64>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
 pub struct Vaccination {
+    #[ts(as = "Option<f64>")]
     pub id: Option<i64>,
+    #[ts(as = "f64")]
     pub patient_id: i64,
     pub vaccine_name: String,
     pub date_given: String,
+    #[serde(default)]
+    #[ts(optional = nullable, as = "Option<f64>")]
+    pub row_version: Option<i64>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
 pub struct SocialHistory {
+    #[ts(as = "Option<f64>")]
     pub id: Option<i64>,
+    #[ts(as = "f64")]
     pub patient_id: i64,
     pub category: String,
     pub detail: String,
     pub status: Option<String>,
+    #[serde(default)]
+    #[ts(optional = nullable, as = "Option<f64>")]
+    pub row_version: Option<i64>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
 pub struct FamilyHistory {
+    #[ts(as = "Option<f64>")]
     pub id: Option<i64>,
+    #[ts(as = "f64")]
     pub patient_id: i64,
     pub relation: String,
     pub condition: String,
     pub age_at_onset: Option<i32>,
+    #[serde(default)]
+    #[ts(optional = nullable, as = "Option<f64>")]
+    pub row_version: Option<i64>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
 pub struct Todo {
+    #[ts(as = "Option<f64>")]
     pub id: Option<i64>,
+    #[ts(as = "f64")]
     pub patient_id: i64,
+    #[ts(as = "Option<f64>")]
     pub diagnosis_id: Option<i64>,
     pub description: String,
     pub due_date: Option<String>,
@@ -1158,9 +1175,12 @@ pub struct Todo {
     pub status: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
 pub struct Goal {
+    #[ts(as = "Option<f64>")]
     pub id: Option<i64>,
+    #[ts(as = "f64")]
     pub patient_id: i64,
     pub description: String,
     pub target_date: Option<String>,
@@ -1168,9 +1188,12 @@ pub struct Goal {
     pub progress: Option<i32>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
 pub struct TimelineEvent {
+    #[ts(as = "Option<f64>")]
     pub id: Option<i64>,
+    #[ts(as = "f64")]
     pub patient_id: i64,
     pub event_type: String,
     pub description: String,
@@ -1181,16 +1204,21 @@ pub struct TimelineEvent {
 
 // ============ Prescription Struct ============
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
 pub struct Prescription {
+    #[ts(as = "Option<f64>")]
     pub id: Option<i64>,
+    #[ts(as = "f64")]
     pub patient_id: i64,
+    #[ts(as = "f64")]
     pub medication_id: i64,
     pub quantity: i32,
     pub days_supply: i32,
     pub refills: i32,
     pub sig: String,
     pub pharmacy: Option<String>,
+    #[ts(as = "Option<f64>")]
     pub prescriber_id: Option<i64>,
     pub status: Option<String>,
     pub prescribed_date: Option<String>,
@@ -1200,10 +1228,15 @@ pub struct Prescription {
 
 // ============ User/Provider Structs ============
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
 pub struct User {
+    #[ts(as = "Option<f64>")]
     pub id: Option<i64>,
     pub username: String,
+    /// Argon2id PHC string; never sent to the frontend
+    #[serde(default, skip_serializing)]
+    #[ts(skip)]
     pub password_hash: String,
     pub first_name: String,
     pub last_name: String,
@@ -1213,11 +1246,20 @@ pub struct User {
     pub npi_number: Option<String>,
     pub photo_url: Option<String>,
     pub bio: Option<String>,
+    /// Changed only through `set_user_role`; `update_user` leaves it alone
+    #[serde(default)]
+    pub role: Role,
+    #[serde(default)]
+    #[ts(optional = nullable, as = "Option<f64>")]
+    pub row_version: Option<i64>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
 pub struct UserEducation {
+    #[ts(as = "Option<f64>")]
     pub id: Option<i64>,
+    #[ts(as = "f64")]
     pub user_id: i64,
     pub education_type: String,
     pub institution: String,
@@ -1227,9 +1269,12 @@ pub struct UserEducation {
     pub end_year: Option<i32>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
 pub struct UserBadge {
+    #[ts(as = "Option<f64>")]
     pub id: Option<i64>,
+    #[ts(as = "f64")]
     pub user_id: i64,
     pub badge_name: String,
     pub badge_type: String,
@@ -1239,9 +1284,12 @@ pub struct UserBadge {
     pub awarded_date: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
 pub struct UserSettings {
+    #[ts(as = "Option<f64>")]
     pub id: Option<i64>,
+    #[ts(as = "f64")]
     pub user_id: i64,
     pub language: Option<String>,
     pub notifications_enabled: Option<bool>,
@@ -1249,9 +1297,13 @@ pub struct UserSettings {
     pub sms_notifications: Option<bool>,
     pub two_factor_enabled: Option<bool>,
     pub zen_mode_default: Option<bool>,
+    #[serde(default)]
+    #[ts(optional = nullable, as = "Option<f64>")]
+    pub row_version: Option<i64>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
 pub struct UserFullData {
     pub user: User,
     pub education: Vec<UserEducation>,
@@ -1259,7 +1311,8 @@ pub struct UserFullData {
     pub settings: UserSettings,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
 pub struct PatientFullData {
     pub patient: Patient,
     pub diagnoses: Vec<DiagnosisWithMedications>,
@@ -1277,105 +1330,149 @@ pub struct PatientFullData {
     pub timeline_events: Vec<TimelineEvent>,
 }
 
-pub fn create_patient(conn: &Connection, patient: &Patient) -> Result<i64> {
+// ============ Patient Lifecycle ============
+
+/// Statuses a patient can be moved to by `set_patient_status`
+///
+/// `merged` is only ever set by `merge_patients`.
+pub const PATIENT_STATUSES: &[&str] = &["active", "inactive", "deceased"];
+
+/// Tables whose rows belong to a patient through `patient_id`
+///
+/// Merging re-parents every one of these; deleting removes them. Keep this in
+/// step with the schema when a new per-patient table is added.
+const PATIENT_CHILD_TABLES: &[&str] = &[
+    "appointments",
+    "messages",
+    "diagnoses",
+    "medications",
+    "vitals",
+    "labs",
+    "clinical_scores",
+    "encounters",
+    "allergies",
+    "vaccinations",
+    "social_history",
+    "family_history",
+    "todos",
+    "goals",
+    "timeline_events",
+    "prescriptions",
+    "patient_list_members",
+    "patient_care_team",
+    "encounter_addenda",
+    "encounter_revisions",
+    "orders",
+];
+
+/// Child tables whose foreign key has no `ON DELETE CASCADE`; rows here block a hard delete
+const PATIENT_DELETE_BLOCKERS: &[&str] = &["appointments", "messages"];
+
+/// Soft-deactivate, mark deceased or reactivate a patient
+///
+/// `status_date` defaults to today; for `deceased` it should be the date of death.
+pub fn set_patient_status(conn: &Connection, patient_id: i64, status: &str, status_date: Option<&str>) -> Result<()> {
     conn.execute(
-        "INSERT INTO patients (first_name, last_name, dob, sex, gender, address, phone, email, preferred_pharmacy, insurance_provider, insurance_policy_number, insurance_group_number)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
-        params![
-            patient.first_name,
-            patient.last_name,
-            patient.dob,
-            patient.sex,
-            patient.gender,
-            patient.address,
-            patient.phone,
-            patient.email,
-            patient.preferred_pharmacy,
-            patient.insurance_provider,
-            patient.insurance_policy_number,
-            patient.insurance_group_number,
-        ],
+        "UPDATE patients SET
+            status = ?1,
+            status_date = COALESCE(?2, date('now', 'localtime')),
+            updated_at = datetime('now', 'localtime')
+         WHERE id = ?3 AND status != 'merged'",
+        params![status, status_date, patient_id],
     )?;
-    Ok(conn.last_insert_rowid())
+    Ok(())
 }
 
-pub fn get_all_patients(conn: &Connection) -> Result<Vec<Patient>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, first_name, last_name, dob, sex, gender, address, phone, email, photo_url, ai_summary, preferred_pharmacy, insurance_provider, insurance_policy_number, insurance_group_number FROM patients"
-    )?;
-
-    let patients = stmt.query_map([], |row| {
-        Ok(Patient {
-            id: Some(row.get(0)?),
-            first_name: row.get(1)?,
-            last_name: row.get(2)?,
-            dob: row.get(3)?,
-            sex: row.get(4)?,
-            gender: row.get(5)?,
-            address: row.get(6)?,
-            phone: row.get(7)?,
-            email: row.get(8)?,
-            photo_url: row.get(9)?,
-            ai_summary: row.get(10)?,
-            preferred_pharmacy: row.get(11)?,
-            insurance_provider: row.get(12)?,
-            insurance_policy_number: row.get(13)?,
-            insurance_group_number: row.get(14)?,
-        })
-    })?;
-
-    patients.collect()
+/// Rows that would be orphaned by a hard delete, as `(table, count)` pairs
+///
+/// Appointments and messages are deliberately not cascaded; a patient with any
+/// of those should be deactivated instead.
+pub fn get_patient_delete_blockers(conn: &Connection, patient_id: i64) -> Result<Vec<(String, i64)>> {
+    let mut blockers = Vec::new();
+    for table in PATIENT_DELETE_BLOCKERS {
+        let count: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM {} WHERE patient_id = ?1", table),
+            params![patient_id],
+            |row| row.get(0),
+        )?;
+        if count > 0 {
+            blockers.push((table.to_string(), count));
+        }
+    }
+    Ok(blockers)
 }
 
-pub fn get_patient_by_id(conn: &Connection, id: i64) -> Result<Option<Patient>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, first_name, last_name, dob, sex, gender, address, phone, email, photo_url, ai_summary, preferred_pharmacy, insurance_provider, insurance_policy_number, insurance_group_number
-         FROM patients WHERE id = ?1"
+/// Permanently remove a patient and everything under an `ON DELETE CASCADE` key
+///
+/// The cascade is carried out explicitly so it happens whether or not
+/// `foreign_keys` is enabled on the connection. Callers check
+/// `get_patient_delete_blockers` first and run this inside the transaction
+/// that also records the deletion.
+pub fn delete_patient(conn: &Connection, patient_id: i64) -> Result<()> {
+    conn.execute(
+        "DELETE FROM diagnosis_medications
+         WHERE diagnosis_id IN (SELECT id FROM diagnoses WHERE patient_id = ?1)
+            OR medication_id IN (SELECT id FROM medications WHERE patient_id = ?1)",
+        params![patient_id],
     )?;
-
-    let mut rows = stmt.query(params![id])?;
-
-    if let Some(row) = rows.next()? {
-        Ok(Some(Patient {
-            id: Some(row.get(0)?),
-            first_name: row.get(1)?,
-            last_name: row.get(2)?,
-            dob: row.get(3)?,
-            sex: row.get(4)?,
-            gender: row.get(5)?,
-            address: row.get(6)?,
-            phone: row.get(7)?,
-            email: row.get(8)?,
-            photo_url: row.get(9)?,
-            ai_summary: row.get(10)?,
-            preferred_pharmacy: row.get(11)?,
-            insurance_provider: row.get(12)?,
-            insurance_policy_number: row.get(13)?,
-            insurance_group_number: row.get(14)?,
-        }))
-    } else {
-        Ok(None)
+    for table in PATIENT_CHILD_TABLES {
+        if PATIENT_DELETE_BLOCKERS.contains(table) {
+            continue;
+        }
+        conn.execute(&format!("DELETE FROM {} WHERE patient_id = ?1", table), params![patient_id])?;
     }
+    // Merged duplicates that pointed here keep their tombstone but lose the link
+    conn.execute("UPDATE patients SET merged_into_id = NULL WHERE merged_into_id = ?1", params![patient_id])?;
+    conn.execute("DELETE FROM patients WHERE id = ?1", params![patient_id])?;
+    Ok(())
 }
 
-// ============ Appointment CRUD Operations ============
-
-pub fn create_appointment(conn: &Connection, appointment: &Appointment) -> Result<i64> {
+/// Fold a duplicate registration into the surviving patient record
+///
+/// Every child row is re-parented onto `survivor_id`. List memberships the
+/// survivor already has are dropped rather than duplicated, and so are
+/// care team members already on the survivor's team. The duplicate row
+/// stays behind with status `merged` and `merged_into_id` pointing at the
+/// survivor, so old references can still be followed. Callers run this inside
+/// the transaction that also records the merge.
+pub fn merge_patients(conn: &Connection, survivor_id: i64, duplicate_id: i64) -> Result<()> {
+    conn.execute(
+        "DELETE FROM patient_list_members
+         WHERE patient_id = ?1
+           AND list_id IN (SELECT list_id FROM patient_list_members WHERE patient_id = ?2)",
+        params![duplicate_id, survivor_id],
+    )?;
+    conn.execute(
+        "DELETE FROM patient_care_team
+         WHERE patient_id = ?1
+           AND user_id IN (SELECT user_id FROM patient_care_team WHERE patient_id = ?2)",
+        params![duplicate_id, survivor_id],
+    )?;
+    for table in PATIENT_CHILD_TABLES {
+        conn.execute(
+            &format!("UPDATE {} SET patient_id = ?1 WHERE patient_id = ?2", table),
+            params![survivor_id, duplicate_id],
+        )?;
+    }
+    // Anything previously merged into the duplicate now points at the survivor
     conn.execute(
-        "INSERT INTO appointments (patient_id, appointment_time, duration_minutes, reason, status, notes)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-        params![
-            appointment.patient_id,
-            appointment.appointment_time,
-            appointment.duration_minutes.unwrap_or(30),
-            appointment.reason,
-            appointment.status.as_deref().unwrap_or("scheduled"),
-            appointment.notes,
-        ],
+        "UPDATE patients SET merged_into_id = ?1 WHERE merged_into_id = ?2",
+        params![survivor_id, duplicate_id],
     )?;
-    Ok(conn.last_insert_rowid())
+    conn.execute(
+        "UPDATE patients SET
+            status = 'merged',
+            status_date = date('now', 'localtime'),
+            merged_into_id = ?1,
+            updated_at = datetime('now', 'localtime')
+         WHERE id = ?2",
+        params![survivor_id, duplicate_id],
+    )?;
+    Ok(())
 }
 
+// ============ Appointment CRUD Operations ============
+
 pub fn get_appointments_for_date(conn: &Connection, date: &str) -> Result<Vec<AppointmentWithPatient>> {
     let mut stmt = conn.prepare(
         "SELECT a.id, a.patient_id, p.first_name || ' ' || p.last_name,
@@ -1429,7 +1526,7 @@ pub fn get_all_appointments(conn: &Connection) -> Result<Vec<AppointmentWithPati
 
 pub fn get_patient_full_data(conn: &Connection, patient_id: i64) -> Result<Option<PatientFullData>> {
     // Get patient
-    let patient = match get_patient_by_id(conn, patient_id)? {
+    let patient = match bmc_get::<Patient>(conn, patient_id)? {
         Some(p) => p,
         None => return Ok(None),
     };
@@ -1438,40 +1535,40 @@ pub fn get_patient_full_data(conn: &Connection, patient_id: i64) -> Result<Optio
     let diagnoses = get_diagnoses_with_medications(conn, patient_id)?;
 
     // Get all medications
-    let medications = get_medications_for_patient(conn, patient_id)?;
+    let medications = bmc_list(conn, &PatientScope::active(patient_id))?;
 
     // Get vitals
-    let vitals = get_vitals_for_patient(conn, patient_id)?;
+    let vitals = bmc_list(conn, &PatientScope::all(patient_id))?;
 
     // Get labs
-    let labs = get_labs_for_patient(conn, patient_id)?;
+    let labs = bmc_list(conn, &PatientScope::all(patient_id))?;
 
     // Get clinical scores
-    let clinical_scores = get_clinical_scores_for_patient(conn, patient_id)?;
+    let clinical_scores = bmc_list(conn, &PatientScope::all(patient_id))?;
 
     // Get encounters
     let encounters = get_encounters_for_patient(conn, patient_id)?;
 
     // Get allergies
-    let allergies = get_allergies_for_patient(conn, patient_id)?;
+    let allergies = bmc_list(conn, &PatientScope::active(patient_id))?;
 
     // Get vaccinations
-    let vaccinations = get_vaccinations_for_patient(conn, patient_id)?;
+    let vaccinations = bmc_list(conn, &PatientScope::all(patient_id))?;
 
     // Get social history
-    let social_history = get_social_history_for_patient(conn, patient_id)?;
+    let social_history = bmc_list(conn, &PatientScope::all(patient_id))?;
 
     // Get family history
-    let family_history = get_family_history_for_patient(conn, patient_id)?;
+    let family_history = bmc_list(conn, &PatientScope::all(patient_id))?;
 
     // Get todos
-    let todos = get_todos_for_patient(conn, patient_id)?;
+    let todos = bmc_list(conn, &PatientScope::all(patient_id))?;
 
     // Get goals
-    let goals = get_goals_for_patient(conn, patient_id)?;
+    let goals = bmc_list(conn, &PatientScope::all(patient_id))?;
 
     // Get timeline events
-    let timeline_events = get_timeline_events_for_patient(conn, patient_id)?;
+    let timeline_events = bmc_list(conn, &PatientScope::all(patient_id))?;
 
     Ok(Some(PatientFullData {
         patient,
@@ -1482,41 +1579,19 @@ pub fn get_patient_full_data(conn: &Connection, patient_id: i64) -> Result<Optio
         clinical_scores,
         encounters,
         allergies,
-        vaccinations,
-        social_history,
-        family_history,
-        todos,
-        goals,
-        timeline_events,
-    }))
-}
-
-// ============ Diagnosis CRUD Operations ============
-
-pub fn get_diagnoses_for_patient(conn: &Connection, patient_id: i64) -> Result<Vec<Diagnosis>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, patient_id, name, icd_code, onset_date, status, category
-         FROM diagnoses WHERE patient_id = ?1 AND status = 'active'
-         ORDER BY onset_date DESC"
-    )?;
-
-    let diagnoses = stmt.query_map(params![patient_id], |row| {
-        Ok(Diagnosis {
-            id: Some(row.get(0)?),
-            patient_id: row.get(1)?,
-            name: row.get(2)?,
-            icd_code: row.get(3)?,
-            onset_date: row.get(4)?,
-            status: row.get(5)?,
-            category: row.get(6)?,
-        })
-    })?;
-
-    diagnoses.collect()
+        vaccinations,
+        social_history,
+        family_history,
+        todos,
+        goals,
+        timeline_events,
+    }))
 }
 
+// ============ Diagnoses ============
+
 pub fn get_diagnoses_with_medications(conn: &Connection, patient_id: i64) -> Result<Vec<DiagnosisWithMedications>> {
-    let diagnoses = get_diagnoses_for_patient(conn, patient_id)?;
+    let diagnoses: Vec<Diagnosis> = bmc_list(conn, &PatientScope::active(patient_id))?;
 
     let mut result = Vec::new();
     for diagnosis in diagnoses {
@@ -1538,114 +1613,18 @@ pub fn get_diagnoses_with_medications(conn: &Connection, patient_id: i64) -> Res
     Ok(result)
 }
 
-// ============ Medication CRUD Operations ============
-
-pub fn get_medications_for_patient(conn: &Connection, patient_id: i64) -> Result<Vec<Medication>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, patient_id, name, dose, frequency, route, start_date, end_date, status
-         FROM medications WHERE patient_id = ?1 AND status = 'active'
-         ORDER BY start_date DESC"
-    )?;
-
-    let medications = stmt.query_map(params![patient_id], |row| {
-        Ok(Medication {
-            id: Some(row.get(0)?),
-            patient_id: row.get(1)?,
-            name: row.get(2)?,
-            dose: row.get(3)?,
-            frequency: row.get(4)?,
-            route: row.get(5)?,
-            start_date: row.get(6)?,
-            end_date: row.get(7)?,
-            status: row.get(8)?,
-        })
-    })?;
-
-    medications.collect()
-}
-
-// ============ Vitals CRUD Operations ============
-
-pub fn get_vitals_for_patient(conn: &Connection, patient_id: i64) -> Result<Vec<Vital>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, patient_id, vital_type, value, value_secondary, unit, recorded_at
-         FROM vitals WHERE patient_id = ?1
-         ORDER BY recorded_at ASC"
-    )?;
-
-    let vitals = stmt.query_map(params![patient_id], |row| {
-        Ok(Vital {
-            id: Some(row.get(0)?),
-            patient_id: row.get(1)?,
-            vital_type: row.get(2)?,
-            value: row.get(3)?,
-            value_secondary: row.get(4)?,
-            unit: row.get(5)?,
-            recorded_at: row.get(6)?,
-        })
-    })?;
-
-    vitals.collect()
-}
-
-// ============ Labs CRUD Operations ============
-
-pub fn get_labs_for_patient(conn: &Connection, patient_id: i64) -> Result<Vec<Lab>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, patient_id, name, value, unit, reference_range_low, reference_range_high, is_abnormal, recorded_at
-         FROM labs WHERE patient_id = ?1
-         ORDER BY recorded_at ASC"
-    )?;
-
-    let labs = stmt.query_map(params![patient_id], |row| {
-        let is_abnormal_int: Option<i32> = row.get(7)?;
-        Ok(Lab {
-            id: Some(row.get(0)?),
-            patient_id: row.get(1)?,
-            name: row.get(2)?,
-            value: row.get(3)?,
-            unit: row.get(4)?,
-            reference_range_low: row.get(5)?,
-            reference_range_high: row.get(6)?,
-            is_abnormal: is_abnormal_int.map(|v| v != 0),
-            recorded_at: row.get(8)?,
-        })
-    })?;
-
-    labs.collect()
-}
-
-// ============ Clinical Scores CRUD Operations ============
-
-pub fn get_clinical_scores_for_patient(conn: &Connection, patient_id: i64) -> Result<Vec<ClinicalScore>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, patient_id, score_type, score, max_score, interpretation, recorded_at
-         FROM clinical_scores WHERE patient_id = ?1
-         ORDER BY recorded_at ASC"
-    )?;
-
-    let scores = stmt.query_map(params![patient_id], |row| {
-        Ok(ClinicalScore {
-            id: Some(row.get(0)?),
-            patient_id: row.get(1)?,
-            score_type: row.get(2)?,
-            score: row.get(3)?,
-            max_score: row.get(4)?,
-            interpretation: row.get(5)?,
-            recorded_at: row.get(6)?,
-        })
-    })?;
-
-    scores.collect()
-}
-
 // ============ Encounters CRUD Operations ============
 
 pub fn get_encounters_for_patient(conn: &Connection, patient_id: i64) -> Result<Vec<Encounter>> {
     let mut stmt = conn.prepare(
-        "SELECT id, patient_id, encounter_date, encounter_type, chief_complaint, summary, note_content, provider, location
-         FROM encounters WHERE patient_id = ?1
-         ORDER BY encounter_date DESC"
+        "SELECT e.id, e.patient_id, e.encounter_date, e.encounter_type, e.chief_complaint, e.summary, e.note_content, e.provider, e.location,
+                e.signed_at, e.signed_by, s.first_name || ' ' || s.last_name,
+                e.cosigned_at, e.cosigned_by, c.first_name || ' ' || c.last_name, e.row_version
+         FROM encounters e
+         LEFT JOIN users s ON s.id = e.signed_by
+         LEFT JOIN users c ON c.id = e.cosigned_by
+         WHERE e.patient_id = ?1
+         ORDER BY e.encounter_date DESC"
     )?;
 
     let encounters = stmt.query_map(params![patient_id], |row| {
@@ -1659,6 +1638,14 @@ pub fn get_encounters_for_patient(conn: &Connection, patient_id: i64) -> Result<
             note_content: row.get(6)?,
             provider: row.get(7)?,
             location: row.get(8)?,
+            signed_at: row.get(9)?,
+            signed_by: row.get(10)?,
+            signed_by_name: row.get(11)?,
+            cosigned_at: row.get(12)?,
+            cosigned_by: row.get(13)?,
+            cosigned_by_name: row.get(14)?,
+            addenda: Vec::new(),
+            row_version: row.get(15)?,
         })
     })?;
 
@@ -1667,14 +1654,19 @@ pub fn get_encounters_for_patient(conn: &Connection, patient_id: i64) -> Result<
 
 pub fn get_encounter_by_id(conn: &Connection, encounter_id: i64) -> Result<Option<Encounter>> {
     let mut stmt = conn.prepare(
-        "SELECT id, patient_id, encounter_date, encounter_type, chief_complaint, summary, note_content, provider, location
-         FROM encounters WHERE id = ?1"
+        "SELECT e.id, e.patient_id, e.encounter_date, e.encounter_type, e.chief_complaint, e.summary, e.note_content, e.provider, e.location,
+                e.signed_at, e.signed_by, s.first_name || ' ' || s.last_name,
+                e.cosigned_at, e.cosigned_by, c.first_name || ' ' || c.last_name, e.row_version
+         FROM encounters e
+         LEFT JOIN users s ON s.id = e.signed_by
+         LEFT JOIN users c ON c.id = e.cosigned_by
+         WHERE e.id = ?1"
     )?;
 
     let mut rows = stmt.query(params![encounter_id])?;
 
     if let Some(row) = rows.next()? {
-        Ok(Some(Encounter {
+        let mut encounter = Encounter {
             id: Some(row.get(0)?),
             patient_id: row.get(1)?,
             encounter_date: row.get(2)?,
@@ -1684,7 +1676,17 @@ pub fn get_encounter_by_id(conn: &Connection, encounter_id: i64) -> Result<Optio
             note_content: row.get(6)?,
             provider: row.get(7)?,
             location: row.get(8)?,
-        }))
+            signed_at: row.get(9)?,
+            signed_by: row.get(10)?,
+            signed_by_name: row.get(11)?,
+            cosigned_at: row.get(12)?,
+            cosigned_by: row.get(13)?,
+            cosigned_by_name: row.get(14)?,
+            addenda: Vec::new(),
+            row_version: row.get(15)?,
+        };
+        encounter.addenda = get_addenda_for_encounter(conn, encounter_id)?;
+        Ok(Some(encounter))
     } else {
         Ok(None)
     }
@@ -1708,8 +1710,8 @@ pub fn create_encounter(conn: &Connection, encounter: &Encounter) -> Result<i64>
     Ok(conn.last_insert_rowid())
 }
 
-pub fn update_encounter(conn: &Connection, encounter: &Encounter) -> Result<()> {
-    conn.execute(
+pub fn update_encounter(conn: &Connection, encounter: &Encounter) -> Result<bool> {
+    let changed = conn.execute(
         "UPDATE encounters SET
             encounter_date = ?1,
             encounter_type = ?2,
@@ -1717,8 +1719,9 @@ pub fn update_encounter(conn: &Connection, encounter: &Encounter) -> Result<()>
             summary = ?4,
             note_content = ?5,
             provider = ?6,
-            location = ?7
-         WHERE id = ?8",
+            location = ?7,
+            row_version = row_version + 1
+         WHERE id = ?8 AND row_version = ?9",
         params![
             encounter.encounter_date,
             encounter.encounter_type,
@@ -1728,252 +1731,281 @@ pub fn update_encounter(conn: &Connection, encounter: &Encounter) -> Result<()>
             encounter.provider,
             encounter.location,
             encounter.id,
+            encounter.row_version,
         ],
     )?;
-    Ok(())
-}
-
-// ============ Allergies CRUD Operations ============
-
-pub fn get_allergies_for_patient(conn: &Connection, patient_id: i64) -> Result<Vec<Allergy>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, patient_id, allergen, reaction, severity
-         FROM allergies WHERE patient_id = ?1 AND status = 'active'"
-    )?;
-
-    let allergies = stmt.query_map(params![patient_id], |row| {
-        Ok(Allergy {
-            id: Some(row.get(0)?),
-            patient_id: row.get(1)?,
-            allergen: row.get(2)?,
-            reaction: row.get(3)?,
-            severity: row.get(4)?,
-        })
-    })?;
-
-    allergies.collect()
+    Ok(changed > 0)
 }
 
-pub fn create_allergy(conn: &Connection, allergy: &Allergy) -> Result<i64> {
-    conn.execute(
-        "INSERT INTO allergies (patient_id, allergen, reaction, severity, status)
-         VALUES (?1, ?2, ?3, ?4, 'active')",
-        params![allergy.patient_id, allergy.allergen, allergy.reaction, allergy.severity],
-    )?;
-    Ok(conn.last_insert_rowid())
-}
+// ============ Encounter Signing & Addenda ============
 
-pub fn update_allergy(conn: &Connection, allergy: &Allergy) -> Result<()> {
+/// Stamp the signer and time; the note is locked from then on
+pub fn sign_encounter(conn: &Connection, encounter_id: i64, user_id: i64) -> Result<()> {
     conn.execute(
-        "UPDATE allergies SET allergen = ?1, reaction = ?2, severity = ?3 WHERE id = ?4",
-        params![allergy.allergen, allergy.reaction, allergy.severity, allergy.id],
+        "UPDATE encounters SET signed_at = datetime('now', 'localtime'), signed_by = ?2
+         WHERE id = ?1 AND signed_at IS NULL",
+        params![encounter_id, user_id],
     )?;
     Ok(())
 }
 
-pub fn delete_allergy(conn: &Connection, id: i64) -> Result<()> {
-    conn.execute("DELETE FROM allergies WHERE id = ?1", params![id])?;
-    Ok(())
-}
-
-// ============ Vaccinations CRUD Operations ============
-
-pub fn get_vaccinations_for_patient(conn: &Connection, patient_id: i64) -> Result<Vec<Vaccination>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, patient_id, vaccine_name, date_given
-         FROM vaccinations WHERE patient_id = ?1
-         ORDER BY date_given DESC"
-    )?;
-
-    let vaccinations = stmt.query_map(params![patient_id], |row| {
-        Ok(Vaccination {
-            id: Some(row.get(0)?),
-            patient_id: row.get(1)?,
-            vaccine_name: row.get(2)?,
-            date_given: row.get(3)?,
-        })
-    })?;
-
-    vaccinations.collect()
-}
-
-pub fn create_vaccination(conn: &Connection, vax: &Vaccination) -> Result<i64> {
-    conn.execute(
-        "INSERT INTO vaccinations (patient_id, vaccine_name, date_given)
-         VALUES (?1, ?2, ?3)",
-        params![vax.patient_id, vax.vaccine_name, vax.date_given],
-    )?;
-    Ok(conn.last_insert_rowid())
-}
-
-pub fn update_vaccination(conn: &Connection, vax: &Vaccination) -> Result<()> {
+/// Second signature, e.g. a supervising physician on a resident's or nurse's note
+pub fn cosign_encounter(conn: &Connection, encounter_id: i64, user_id: i64) -> Result<()> {
     conn.execute(
-        "UPDATE vaccinations SET vaccine_name = ?1, date_given = ?2 WHERE id = ?3",
-        params![vax.vaccine_name, vax.date_given, vax.id],
+        "UPDATE encounters SET cosigned_at = datetime('now', 'localtime'), cosigned_by = ?2
+         WHERE id = ?1 AND signed_at IS NOT NULL AND cosigned_at IS NULL",
+        params![encounter_id, user_id],
     )?;
     Ok(())
 }
 
-pub fn delete_vaccination(conn: &Connection, id: i64) -> Result<()> {
-    conn.execute("DELETE FROM vaccinations WHERE id = ?1", params![id])?;
-    Ok(())
-}
-
-// ============ Social History CRUD Operations ============
-
-pub fn get_social_history_for_patient(conn: &Connection, patient_id: i64) -> Result<Vec<SocialHistory>> {
+/// Addenda oldest first, so they read as a dated sequence under the note
+pub fn get_addenda_for_encounter(conn: &Connection, encounter_id: i64) -> Result<Vec<EncounterAddendum>> {
     let mut stmt = conn.prepare(
-        "SELECT id, patient_id, category, detail, status
-         FROM social_history WHERE patient_id = ?1"
+        "SELECT a.id, a.encounter_id, a.author_id, u.first_name || ' ' || u.last_name, a.content, a.created_at
+         FROM encounter_addenda a
+         LEFT JOIN users u ON u.id = a.author_id
+         WHERE a.encounter_id = ?1
+         ORDER BY a.created_at, a.id"
     )?;
 
-    let history = stmt.query_map(params![patient_id], |row| {
-        Ok(SocialHistory {
+    let addenda = stmt.query_map(params![encounter_id], |row| {
+        Ok(EncounterAddendum {
             id: Some(row.get(0)?),
-            patient_id: row.get(1)?,
-            category: row.get(2)?,
-            detail: row.get(3)?,
-            status: row.get(4)?,
+            encounter_id: row.get(1)?,
+            author_id: row.get(2)?,
+            author_name: row.get(3)?,
+            content: row.get(4)?,
+            created_at: row.get(5)?,
         })
     })?;
 
-    history.collect()
+    addenda.collect()
 }
 
-pub fn create_social_history(conn: &Connection, history: &SocialHistory) -> Result<i64> {
+pub fn create_addendum(conn: &Connection, encounter_id: i64, author_id: i64, content: &str) -> Result<i64> {
     conn.execute(
-        "INSERT INTO social_history (patient_id, category, detail, status)
-         VALUES (?1, ?2, ?3, ?4)",
-        params![history.patient_id, history.category, history.detail, history.status],
+        "INSERT INTO encounter_addenda (encounter_id, patient_id, author_id, content)
+         SELECT id, patient_id, ?2, ?3 FROM encounters WHERE id = ?1",
+        params![encounter_id, author_id, content],
     )?;
     Ok(conn.last_insert_rowid())
 }
 
-pub fn update_social_history(conn: &Connection, history: &SocialHistory) -> Result<()> {
-    conn.execute(
-        "UPDATE social_history SET category = ?1, detail = ?2, status = ?3 WHERE id = ?4",
-        params![history.category, history.detail, history.status, history.id],
-    )?;
-    Ok(())
-}
-
-pub fn delete_social_history(conn: &Connection, id: i64) -> Result<()> {
-    conn.execute("DELETE FROM social_history WHERE id = ?1", params![id])?;
-    Ok(())
-}
-
-// ============ Family History CRUD Operations ============
-
-pub fn get_family_history_for_patient(conn: &Connection, patient_id: i64) -> Result<Vec<FamilyHistory>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, patient_id, relation, condition, age_at_onset
-         FROM family_history WHERE patient_id = ?1"
-    )?;
-
-    let history = stmt.query_map(params![patient_id], |row| {
-        Ok(FamilyHistory {
-            id: Some(row.get(0)?),
-            patient_id: row.get(1)?,
-            relation: row.get(2)?,
-            condition: row.get(3)?,
-            age_at_onset: row.get(4)?,
-        })
-    })?;
-
-    history.collect()
+// ============ Lab Results ============
+//
+// A result's flag is never taken from the client. On every create and update
+// the matching row of `lab_reference_ranges` (migration 016) is looked up for
+// the patient's sex and age on the day the specimen was taken, copied onto the
+// result, and used to interpret the value. The copy is deliberate: editing the
+// table later does not re-flag results that were read against the old range.
+
+/// Where a result falls against its reference range
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum LabInterpretation {
+    Normal,
+    Low,
+    High,
+    CriticalLow,
+    CriticalHigh,
+}
+
+impl LabInterpretation {
+    pub fn is_abnormal(self) -> bool {
+        self != LabInterpretation::Normal
+    }
 }
 
-pub fn create_family_history(conn: &Connection, history: &FamilyHistory) -> Result<i64> {
-    conn.execute(
-        "INSERT INTO family_history (patient_id, relation, condition, age_at_onset)
-         VALUES (?1, ?2, ?3, ?4)",
-        params![history.patient_id, history.relation, history.condition, history.age_at_onset],
-    )?;
-    Ok(conn.last_insert_rowid())
+/// Normal and critical limits for one test; NULL columns match any unit, sex or age
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
+pub struct LabReferenceRange {
+    #[serde(default)]
+    #[ts(as = "Option<f64>", optional = nullable)]
+    pub id: Option<i64>,
+    pub test_name: String,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub unit: Option<String>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub sex: Option<String>,
+    #[serde(default)]
+    #[ts(as = "Option<f64>", optional = nullable)]
+    pub age_min_years: Option<i64>,
+    /// Exclusive, so 0–18 and 18– do not overlap
+    #[serde(default)]
+    #[ts(as = "Option<f64>", optional = nullable)]
+    pub age_max_years: Option<i64>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub low: Option<f64>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub high: Option<f64>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub critical_low: Option<f64>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub critical_high: Option<f64>,
+}
+
+impl LabReferenceRange {
+    pub fn interpret(&self, value: f64) -> LabInterpretation {
+        let below = |limit: Option<f64>| limit.is_some_and(|l| value < l);
+        let above = |limit: Option<f64>| limit.is_some_and(|l| value > l);
+        if below(self.critical_low) {
+            LabInterpretation::CriticalLow
+        } else if above(self.critical_high) {
+            LabInterpretation::CriticalHigh
+        } else if below(self.low) {
+            LabInterpretation::Low
+        } else if above(self.high) {
+            LabInterpretation::High
+        } else {
+            LabInterpretation::Normal
+        }
+    }
 }
 
-pub fn update_family_history(conn: &Connection, history: &FamilyHistory) -> Result<()> {
-    conn.execute(
-        "UPDATE family_history SET relation = ?1, condition = ?2, age_at_onset = ?3 WHERE id = ?4",
-        params![history.relation, history.condition, history.age_at_onset, history.id],
-    )?;
-    Ok(())
-}
+const REFERENCE_RANGE_COLUMNS: &str =
+    "r.id, r.test_name, r.unit, r.sex, r.age_min_years, r.age_max_years, r.low, r.high, r.critical_low, r.critical_high";
 
-pub fn delete_family_history(conn: &Connection, id: i64) -> Result<()> {
-    conn.execute("DELETE FROM family_history WHERE id = ?1", params![id])?;
-    Ok(())
+fn reference_range_from_row(row: &rusqlite::Row) -> Result<LabReferenceRange> {
+    Ok(LabReferenceRange {
+        id: row.get(0)?,
+        test_name: row.get(1)?,
+        unit: row.get(2)?,
+        sex: row.get(3)?,
+        age_min_years: row.get(4)?,
+        age_max_years: row.get(5)?,
+        low: row.get(6)?,
+        high: row.get(7)?,
+        critical_low: row.get(8)?,
+        critical_high: row.get(9)?,
+    })
 }
 
-// ============ Todos CRUD Operations ============
-
-pub fn get_todos_for_patient(conn: &Connection, patient_id: i64) -> Result<Vec<Todo>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, patient_id, diagnosis_id, description, due_date, priority, status
-         FROM todos WHERE patient_id = ?1
-         ORDER BY due_date ASC"
-    )?;
-
-    let todos = stmt.query_map(params![patient_id], |row| {
-        Ok(Todo {
-            id: Some(row.get(0)?),
-            patient_id: row.get(1)?,
-            diagnosis_id: row.get(2)?,
-            description: row.get(3)?,
-            due_date: row.get(4)?,
-            priority: row.get(5)?,
-            status: row.get(6)?,
-        })
-    })?;
-
-    todos.collect()
+/// The range that applies to `patient_id` for `test` in `unit` on `recorded_at`
+///
+/// Names and units compare case-insensitively. A result whose unit matches no
+/// range gets none rather than one meant for another unit. When several rows
+/// apply, one for the patient's sex beats one for anyone, then one for this
+/// unit beats a unitless one, then the narrowest age band wins.
+pub fn find_reference_range(
+    conn: &Connection,
+    patient_id: i64,
+    test: &str,
+    unit: Option<&str>,
+    recorded_at: &str,
+) -> Result<Option<LabReferenceRange>> {
+    let sql = format!(
+        "SELECT {}
+         FROM lab_reference_ranges r,
+              (SELECT sex,
+                      CAST(strftime('%Y', ?4) AS INTEGER) - CAST(strftime('%Y', dob) AS INTEGER)
+                        - (strftime('%m-%d', ?4) < strftime('%m-%d', dob)) AS age
+               FROM patients WHERE id = ?1) p
+         WHERE r.test_name = TRIM(?2) COLLATE NOCASE
+           AND (r.unit IS NULL OR r.unit = TRIM(?3) COLLATE NOCASE)
+           AND (r.sex IS NULL OR r.sex = p.sex COLLATE NOCASE)
+           AND (r.age_min_years IS NULL OR p.age >= r.age_min_years)
+           AND (r.age_max_years IS NULL OR p.age < r.age_max_years)
+         ORDER BY r.sex IS NULL, r.unit IS NULL,
+                  COALESCE(r.age_max_years, 200) - COALESCE(r.age_min_years, 0), r.id
+         LIMIT 1",
+        REFERENCE_RANGE_COLUMNS
+    );
+    conn.query_row(&sql, params![patient_id, test, unit, recorded_at], reference_range_from_row)
+        .optional()
 }
 
-// ============ Goals CRUD Operations ============
-
-pub fn get_goals_for_patient(conn: &Connection, patient_id: i64) -> Result<Vec<Goal>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, patient_id, description, target_date, status, progress
-         FROM goals WHERE patient_id = ?1
-         ORDER BY target_date ASC"
-    )?;
-
-    let goals = stmt.query_map(params![patient_id], |row| {
-        Ok(Goal {
-            id: Some(row.get(0)?),
-            patient_id: row.get(1)?,
-            description: row.get(2)?,
-            target_date: row.get(3)?,
-            status: row.get(4)?,
-            progress: row.get(5)?,
-        })
-    })?;
-
-    goals.collect()
+/// `lab` with its reference range, flag and interpretation replaced by what the server decides
+///
+/// Without a matching range the result carries no range or interpretation and is not flagged.
+pub fn interpret_lab(conn: &Connection, patient_id: i64, mut lab: Lab) -> Result<Lab> {
+    let range = find_reference_range(conn, patient_id, &lab.name, lab.unit.as_deref(), &lab.recorded_at)?;
+    let interpretation = range.as_ref().map(|r| r.interpret(lab.value));
+    lab.reference_range_low = range.as_ref().and_then(|r| r.low);
+    lab.reference_range_high = range.as_ref().and_then(|r| r.high);
+    lab.interpretation = interpretation;
+    lab.is_abnormal = interpretation.map(LabInterpretation::is_abnormal);
+    Ok(lab)
+}
+
+pub fn create_lab(conn: &Connection, lab: Lab) -> Result<i64> {
+    let lab = interpret_lab(conn, lab.patient_id, lab)?;
+    Ok(model::bmc_create(conn, &lab)?.id)
+}
+
+/// Whether lab `id` was there to update; the patient is taken from the stored row
+/// Overwrite lab `id`, re-flagged against the stored patient; `false` if the row is gone or `lab` carries a stale `row_version`
+pub fn update_lab(conn: &Connection, id: i64, lab: Lab) -> crate::error::Result<bool> {
+    let Some(existing) = bmc_get::<Lab>(conn, id)? else {
+        return Ok(false);
+    };
+    let lab = interpret_lab(conn, existing.patient_id, lab)?;
+    Ok(model::bmc_update(conn, id, &lab)?.is_some())
+}
+
+pub fn delete_lab(conn: &Connection, id: i64) -> Result<bool> {
+    Ok(model::bmc_delete::<Lab>(conn, id)?.is_some())
+}
+
+/// Every configured range, ordered by test
+pub fn get_reference_ranges(conn: &Connection) -> Result<Vec<LabReferenceRange>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM lab_reference_ranges r
+         ORDER BY r.test_name COLLATE NOCASE, r.unit, r.sex, r.age_min_years, r.id",
+        REFERENCE_RANGE_COLUMNS
+    ))?;
+    let ranges = stmt.query_map([], reference_range_from_row)?;
+    ranges.collect()
+}
+
+/// Insert `range`, or overwrite the row its `id` names; the id, or `None` if that row is gone
+pub fn save_reference_range(conn: &Connection, range: &LabReferenceRange) -> Result<Option<i64>> {
+    let trimmed = |value: &Option<String>| value.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(str::to_string);
+    let values = params![
+        range.test_name.trim(),
+        trimmed(&range.unit),
+        trimmed(&range.sex).map(|s| s.to_uppercase()),
+        range.age_min_years,
+        range.age_max_years,
+        range.low,
+        range.high,
+        range.critical_low,
+        range.critical_high,
+        range.id,
+    ];
+    match range.id {
+        Some(id) => {
+            let changed = conn.execute(
+                "UPDATE lab_reference_ranges
+                 SET test_name = ?1, unit = ?2, sex = ?3, age_min_years = ?4, age_max_years = ?5,
+                     low = ?6, high = ?7, critical_low = ?8, critical_high = ?9
+                 WHERE id = ?10",
+                values,
+            )?;
+            Ok((changed > 0).then_some(id))
+        }
+        None => {
+            conn.execute(
+                "INSERT INTO lab_reference_ranges
+                     (test_name, unit, sex, age_min_years, age_max_years, low, high, critical_low, critical_high)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                &values[..9],
+            )?;
+            Ok(Some(conn.last_insert_rowid()))
+        }
+    }
 }
 
-// ============ Timeline Events CRUD Operations ============
-
-pub fn get_timeline_events_for_patient(conn: &Connection, patient_id: i64) -> Result<Vec<TimelineEvent>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, patient_id, event_type, description, event_date, icon, color
-         FROM timeline_events WHERE patient_id = ?1
-         ORDER BY event_date ASC"
-    )?;
-
-    let events = stmt.query_map(params![patient_id], |row| {
-        Ok(TimelineEvent {
-            id: Some(row.get(0)?),
-            patient_id: row.get(1)?,
-            event_type: row.get(2)?,
-            description: row.get(3)?,
-            event_date: row.get(4)?,
-            icon: row.get(5)?,
-            color: row.get(6)?,
-        })
-    })?;
-
-    events.collect()
+pub fn delete_reference_range(conn: &Connection, id: i64) -> Result<bool> {
+    Ok(conn.execute("DELETE FROM lab_reference_ranges WHERE id = ?1", params![id])? > 0)
 }
 
 // ============ Clear Patient Detail Data ============
@@ -1994,6 +2026,7 @@ pub fn clear_patient_detail_data(conn: &Connection, patient_id: i64) -> Result<(
     conn.execute("DELETE FROM todos WHERE patient_id = ?1", params![patient_id])?;
     conn.execute("DELETE FROM goals WHERE patient_id = ?1", params![patient_id])?;
     conn.execute("DELETE FROM timeline_events WHERE patient_id = ?1", params![patient_id])?;
+    conn.execute("DELETE FROM orders WHERE patient_id = ?1", params![patient_id])?;
     Ok(())
 }
 
@@ -2001,8 +2034,8 @@ pub fn clear_patient_detail_data(conn: &Connection, patient_id: i64) -> Result<(
 
 pub fn create_user(conn: &Connection, user: &User) -> Result<i64> {
     conn.execute(
-        "INSERT INTO users (username, password_hash, first_name, last_name, degree_type, specialty, subspecialty, npi_number, photo_url, bio)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        "INSERT INTO users (username, password_hash, first_name, last_name, degree_type, specialty, subspecialty, npi_number, photo_url, bio, role)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
         params![
             user.username,
             user.password_hash,
@@ -2014,6 +2047,7 @@ pub fn create_user(conn: &Connection, user: &User) -> Result<i64> {
             user.npi_number,
             user.photo_url,
             user.bio,
+            user.role,
         ],
     )?;
     Ok(conn.last_insert_rowid())
@@ -2021,7 +2055,7 @@ pub fn create_user(conn: &Connection, user: &User) -> Result<i64> {
 
 pub fn get_user_by_id(conn: &Connection, id: i64) -> Result<Option<User>> {
     let mut stmt = conn.prepare(
-        "SELECT id, username, password_hash, first_name, last_name, degree_type, specialty, subspecialty, npi_number, photo_url, bio
+        "SELECT id, username, password_hash, first_name, last_name, degree_type, specialty, subspecialty, npi_number, photo_url, bio, role, row_version
          FROM users WHERE id = ?1"
     )?;
 
@@ -2040,20 +2074,21 @@ pub fn get_user_by_id(conn: &Connection, id: i64) -> Result<Option<User>> {
             npi_number: row.get(8)?,
             photo_url: row.get(9)?,
             bio: row.get(10)?,
+            role: row.get(11)?,
+            row_version: row.get(12)?,
         }))
     } else {
         Ok(None)
     }
 }
 
-pub fn get_current_user(conn: &Connection) -> Result<Option<User>> {
-    // For now, get the first user (single-user app)
+pub fn get_user_by_username(conn: &Connection, username: &str) -> Result<Option<User>> {
     let mut stmt = conn.prepare(
-        "SELECT id, username, password_hash, first_name, last_name, degree_type, specialty, subspecialty, npi_number, photo_url, bio
-         FROM users ORDER BY id LIMIT 1"
+        "SELECT id, username, password_hash, first_name, last_name, degree_type, specialty, subspecialty, npi_number, photo_url, bio, role, row_version
+         FROM users WHERE username = ?1 COLLATE NOCASE"
     )?;
 
-    let mut rows = stmt.query([])?;
+    let mut rows = stmt.query(params![username])?;
 
     if let Some(row) = rows.next()? {
         Ok(Some(User {
@@ -2068,14 +2103,59 @@ pub fn get_current_user(conn: &Connection) -> Result<Option<User>> {
             npi_number: row.get(8)?,
             photo_url: row.get(9)?,
             bio: row.get(10)?,
+            role: row.get(11)?,
+            row_version: row.get(12)?,
         }))
     } else {
         Ok(None)
     }
 }
 
-pub fn update_user(conn: &Connection, user: &User) -> Result<()> {
+pub fn count_users(conn: &Connection) -> Result<i64> {
+    conn.query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0))
+}
+
+pub fn get_all_users(conn: &Connection) -> Result<Vec<User>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, username, password_hash, first_name, last_name, degree_type, specialty, subspecialty, npi_number, photo_url, bio, role, row_version
+         FROM users ORDER BY last_name, first_name"
+    )?;
+
+    let users = stmt.query_map([], |row| {
+        Ok(User {
+            id: Some(row.get(0)?),
+            username: row.get(1)?,
+            password_hash: row.get(2)?,
+            first_name: row.get(3)?,
+            last_name: row.get(4)?,
+            degree_type: row.get(5)?,
+            specialty: row.get(6)?,
+            subspecialty: row.get(7)?,
+            npi_number: row.get(8)?,
+            photo_url: row.get(9)?,
+            bio: row.get(10)?,
+            role: row.get(11)?,
+            row_version: row.get(12)?,
+        })
+    })?;
+
+    users.collect()
+}
+
+pub fn count_admins(conn: &Connection) -> Result<i64> {
+    conn.query_row("SELECT COUNT(*) FROM users WHERE role = 'admin'", [], |row| row.get(0))
+}
+
+pub fn set_user_role(conn: &Connection, user_id: i64, role: Role) -> Result<()> {
     conn.execute(
+        "UPDATE users SET role = ?1, updated_at = datetime('now', 'localtime') WHERE id = ?2",
+        params![role, user_id],
+    )?;
+    Ok(())
+}
+
+pub fn update_user(conn: &Connection, user: &User) -> Result<bool> {
+    let changed = conn.execute(
         "UPDATE users SET
             username = ?1,
             first_name = ?2,
@@ -2086,8 +2166,9 @@ pub fn update_user(conn: &Connection, user: &User) -> Result<()> {
             npi_number = ?7,
             photo_url = ?8,
             bio = ?9,
-            updated_at = datetime('now', 'localtime')
-         WHERE id = ?10",
+            updated_at = datetime('now', 'localtime'),
+            row_version = row_version + 1
+         WHERE id = ?10 AND row_version = ?11",
         params![
             user.username,
             user.first_name,
@@ -2099,9 +2180,10 @@ pub fn update_user(conn: &Connection, user: &User) -> Result<()> {
             user.photo_url,
             user.bio,
             user.id,
+            user.row_version,
         ],
     )?;
-    Ok(())
+    Ok(changed > 0)
 }
 
 pub fn update_user_password(conn: &Connection, user_id: i64, new_password_hash: &str) -> Result<()> {
@@ -2200,7 +2282,7 @@ pub fn create_user_badge(conn: &Connection, badge: &UserBadge) -> Result<i64> {
 
 pub fn get_settings_for_user(conn: &Connection, user_id: i64) -> Result<Option<UserSettings>> {
     let mut stmt = conn.prepare(
-        "SELECT id, user_id, language, notifications_enabled, email_notifications, sms_notifications, two_factor_enabled, zen_mode_default
+        "SELECT id, user_id, language, notifications_enabled, email_notifications, sms_notifications, two_factor_enabled, zen_mode_default, row_version
          FROM user_settings WHERE user_id = ?1"
     )?;
 
@@ -2222,6 +2304,7 @@ pub fn get_settings_for_user(conn: &Connection, user_id: i64) -> Result<Option<U
             sms_notifications: sms_int.map(|v| v != 0),
             two_factor_enabled: tfa_int.map(|v| v != 0),
             zen_mode_default: zen_int.map(|v| v != 0),
+            row_version: row.get(8)?,
         }))
     } else {
         // Create default settings if none exist
@@ -2238,32 +2321,34 @@ pub fn get_settings_for_user(conn: &Connection, user_id: i64) -> Result<Option<U
             sms_notifications: Some(false),
             two_factor_enabled: Some(false),
             zen_mode_default: Some(false),
+            row_version: Some(1),
         }))
     }
 }
 
-pub fn update_user_settings(conn: &Connection, settings: &UserSettings) -> Result<()> {
-    conn.execute(
+/// `two_factor_enabled` is ignored here; it only changes through the two-factor enrollment commands
+pub fn update_user_settings(conn: &Connection, settings: &UserSettings) -> Result<bool> {
+    let changed = conn.execute(
         "UPDATE user_settings SET
             language = ?1,
             notifications_enabled = ?2,
             email_notifications = ?3,
             sms_notifications = ?4,
-            two_factor_enabled = ?5,
-            zen_mode_default = ?6,
-            updated_at = datetime('now', 'localtime')
-         WHERE user_id = ?7",
+            zen_mode_default = ?5,
+            updated_at = datetime('now', 'localtime'),
+            row_version = row_version + 1
+         WHERE user_id = ?6 AND row_version = ?7",
         params![
             settings.language,
             settings.notifications_enabled.map(|b| if b { 1 } else { 0 }),
             settings.email_notifications.map(|b| if b { 1 } else { 0 }),
             settings.sms_notifications.map(|b| if b { 1 } else { 0 }),
-            settings.two_factor_enabled.map(|b| if b { 1 } else { 0 }),
             settings.zen_mode_default.map(|b| if b { 1 } else { 0 }),
             settings.user_id,
+            settings.row_version,
         ],
     )?;
-    Ok(())
+    Ok(changed > 0)
 }
 
 // ============ User Full Data (Aggregated) ============
@@ -2285,34 +2370,7 @@ pub fn get_user_full_data(conn: &Connection, user_id: i64) -> Result<Option<User
         sms_notifications: Some(false),
         two_factor_enabled: Some(false),
         zen_mode_default: Some(false),
-    });
-
-    Ok(Some(UserFullData {
-        user,
-        education,
-        badges,
-        settings,
-    }))
-}
-
-pub fn get_current_user_full_data(conn: &Connection) -> Result<Option<UserFullData>> {
-    let user = match get_current_user(conn)? {
-        Some(u) => u,
-        None => return Ok(None),
-    };
-
-    let user_id = user.id.unwrap_or(0);
-    let education = get_education_for_user(conn, user_id)?;
-    let badges = get_badges_for_user(conn, user_id)?;
-    let settings = get_settings_for_user(conn, user_id)?.unwrap_or(UserSettings {
-        id: None,
-        user_id,
-        language: Some("en".to_string()),
-        notifications_enabled: Some(true),
-        email_notifications: Some(true),
-        sms_notifications: Some(false),
-        two_factor_enabled: Some(false),
-        zen_mode_default: Some(false),
+        row_version: None,
     });
 
     Ok(Some(UserFullData {
@@ -2327,8 +2385,7 @@ pub fn get_current_user_full_data(conn: &Connection) -> Result<Option<UserFullDa
 
 pub fn seed_user_data(conn: &Connection) -> Result<()> {
     // Check if a user already exists
-    let existing = get_current_user(conn)?;
-    if existing.is_some() {
+    if count_users(conn)? > 0 {
         return Ok(());
     }
 
@@ -2345,6 +2402,8 @@ pub fn seed_user_data(conn: &Connection) -> Result<()> {
         npi_number: Some("1234567890".to_string()),
         photo_url: None,
         bio: Some("Dr. Madeline Chu is a board-certified child and adolescent psychiatrist with a passion for helping young patients and their families navigate mental health challenges. She believes in a collaborative, evidence-based approach that incorporates both therapeutic interventions and, when appropriate, medication management. Outside of clinical practice, she enjoys hiking, watercolor painting, and volunteering at local community mental health organizations.".to_string()),
+        role: Role::Admin,
+        row_version: None,
     };
 
     let user_id = create_user(conn, &user)?;
@@ -2819,16 +2878,23 @@ pub fn seed_patient_detail_test_data(conn: &Connection, patient_id: i64, force_r
 // Patient Lists
 // ============================================================================
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
 pub struct PatientList {
+    #[ts(as = "Option<f64>")]
     pub id: Option<i64>,
+    #[ts(as = "f64")]
     pub user_id: i64,
     pub name: String,
     pub description: Option<String>,
     pub color: Option<String>,
     pub icon: Option<String>,
     pub is_default: bool,
+    #[ts(as = "f64")]
     pub sort_order: i64,
+    #[serde(default)]
+    #[ts(optional = nullable, as = "Option<f64>")]
+    pub row_version: Option<i64>,
 }
 
 #[allow(dead_code)]
@@ -2841,19 +2907,25 @@ pub struct PatientListMember {
     pub notes: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
 pub struct PatientListColumn {
+    #[ts(as = "Option<f64>")]
     pub id: Option<i64>,
+    #[ts(as = "f64")]
     pub list_id: i64,
     pub column_key: String,
     pub column_label: String,
     pub column_type: Option<String>,
     pub is_visible: bool,
+    #[ts(as = "f64")]
     pub sort_order: i64,
+    #[ts(as = "Option<f64>")]
     pub width: Option<i64>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
 pub struct PatientListWithPatients {
     pub list: PatientList,
     pub columns: Vec<PatientListColumn>,
@@ -2903,7 +2975,7 @@ pub fn create_patient_list(conn: &Connection, list: &PatientList) -> Result<i64>
 
 pub fn get_patient_lists_for_user(conn: &Connection, user_id: i64) -> Result<Vec<PatientList>> {
     let mut stmt = conn.prepare(
-        "SELECT id, user_id, name, description, color, icon, is_default, sort_order
+        "SELECT id, user_id, name, description, color, icon, is_default, sort_order, row_version
          FROM patient_lists WHERE user_id = ?1 ORDER BY sort_order, name"
     )?;
 
@@ -2917,6 +2989,7 @@ pub fn get_patient_lists_for_user(conn: &Connection, user_id: i64) -> Result<Vec
             icon: row.get(5)?,
             is_default: row.get(6)?,
             sort_order: row.get(7)?,
+            row_version: row.get(8)?,
         })
     })?.collect::<Result<Vec<_>>>()?;
 
@@ -2925,7 +2998,7 @@ pub fn get_patient_lists_for_user(conn: &Connection, user_id: i64) -> Result<Vec
 
 pub fn get_patient_list_by_id(conn: &Connection, list_id: i64) -> Result<Option<PatientList>> {
     let mut stmt = conn.prepare(
-        "SELECT id, user_id, name, description, color, icon, is_default, sort_order
+        "SELECT id, user_id, name, description, color, icon, is_default, sort_order, row_version
          FROM patient_lists WHERE id = ?1"
     )?;
 
@@ -2940,19 +3013,20 @@ pub fn get_patient_list_by_id(conn: &Connection, list_id: i64) -> Result<Option<
             icon: row.get(5)?,
             is_default: row.get(6)?,
             sort_order: row.get(7)?,
+            row_version: row.get(8)?,
         }))
     } else {
         Ok(None)
     }
 }
 
-pub fn update_patient_list(conn: &Connection, list: &PatientList) -> Result<()> {
-    conn.execute(
-        "UPDATE patient_lists SET name = ?1, description = ?2, color = ?3, icon = ?4, sort_order = ?5, updated_at = datetime('now', 'localtime')
-         WHERE id = ?6",
-        params![list.name, list.description, list.color, list.icon, list.sort_order, list.id],
+pub fn update_patient_list(conn: &Connection, list: &PatientList) -> Result<bool> {
+    let changed = conn.execute(
+        "UPDATE patient_lists SET name = ?1, description = ?2, color = ?3, icon = ?4, sort_order = ?5, updated_at = datetime('now', 'localtime'), row_version = row_version + 1
+         WHERE id = ?6 AND row_version = ?7",
+        params![list.name, list.description, list.color, list.icon, list.sort_order, list.id, list.row_version],
     )?;
-    Ok(())
+    Ok(changed > 0)
 }
 
 pub fn delete_patient_list(conn: &Connection, list_id: i64) -> Result<()> {
@@ -3018,7 +3092,7 @@ pub fn remove_patient_from_list(conn: &Connection, list_id: i64, patient_id: i64
 pub fn get_patients_in_list(conn: &Connection, list_id: i64) -> Result<Vec<Patient>> {
     let mut stmt = conn.prepare(
         "SELECT p.id, p.first_name, p.last_name, p.dob, p.sex, p.gender, p.address, p.phone, p.email, p.photo_url, p.ai_summary,
-                p.preferred_pharmacy, p.insurance_provider, p.insurance_policy_number, p.insurance_group_number
+                p.preferred_pharmacy, p.insurance_provider, p.insurance_policy_number, p.insurance_group_number, p.status, p.status_date, p.row_version
          FROM patients p
          INNER JOIN patient_list_members plm ON p.id = plm.patient_id
          WHERE plm.list_id = ?1
@@ -3042,6 +3116,9 @@ pub fn get_patients_in_list(conn: &Connection, list_id: i64) -> Result<Vec<Patie
             insurance_provider: row.get(12)?,
             insurance_policy_number: row.get(13)?,
             insurance_group_number: row.get(14)?,
+            status: row.get(15)?,
+            status_date: row.get(16)?,
+            row_version: row.get(17)?,
         })
     })?.collect::<Result<Vec<_>>>()?;
 
@@ -3244,6 +3321,7 @@ pub fn seed_patient_lists(conn: &Connection, user_id: i64) -> Result<()> {
         icon: Some("fa-stethoscope".to_string()),
         is_default: true,
         sort_order: 0,
+        row_version: None,
     };
     let resident_list_id = create_patient_list(conn, &resident_list)?;
 
@@ -3262,6 +3340,7 @@ pub fn seed_patient_lists(conn: &Connection, user_id: i64) -> Result<()> {
         icon: Some("fa-hand-dots".to_string()),
         is_default: false,
         sort_order: 1,
+        row_version: None,
     };
     let pain_list_id = create_patient_list(conn, &pain_list)?;
 
@@ -3280,6 +3359,7 @@ pub fn seed_patient_lists(conn: &Connection, user_id: i64) -> Result<()> {
         icon: Some("fa-hospital".to_string()),
         is_default: false,
         sort_order: 2,
+        row_version: None,
     };
     let yellow_list_id = create_patient_list(conn, &yellow_list)?;
 
@@ -3298,6 +3378,7 @@ pub fn seed_patient_lists(conn: &Connection, user_id: i64) -> Result<()> {
         icon: Some("fa-brain".to_string()),
         is_default: false,
         sort_order: 3,
+        row_version: None,
     };
     let psych_list_id = create_patient_list(conn, &psych_list)?;
 
@@ -3308,3 +3389,100 @@ pub fn seed_patient_lists(conn: &Connection, user_id: i64) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(low: Option<f64>, high: Option<f64>, critical_low: Option<f64>, critical_high: Option<f64>) -> LabReferenceRange {
+        LabReferenceRange {
+            id: None,
+            test_name: "Potassium".to_string(),
+            unit: Some("mmol/L".to_string()),
+            sex: None,
+            age_min_years: None,
+            age_max_years: None,
+            low,
+            high,
+            critical_low,
+            critical_high,
+        }
+    }
+
+    fn migrated() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrations::run_migrations(&mut conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO patients (id, first_name, last_name, dob, sex) VALUES
+                (1, 'Ann', 'Lee', '1980-06-15', 'F'),
+                (2, 'Bo', 'Kim', '1980-06-15', 'M'),
+                (3, 'Cy', 'Park', '2008-06-15', 'M');",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn low_end(conn: &Connection, patient_id: i64, test: &str, unit: Option<&str>, recorded_at: &str) -> Option<f64> {
+        find_reference_range(conn, patient_id, test, unit, recorded_at).unwrap().and_then(|r| r.low)
+    }
+
+    #[test]
+    fn limits_themselves_fall_in_the_milder_band() {
+        let potassium = range(Some(3.5), Some(5.0), Some(2.5), Some(6.5));
+        let cases = [
+            (2.4, LabInterpretation::CriticalLow),
+            (2.5, LabInterpretation::Low),
+            (3.4, LabInterpretation::Low),
+            (3.5, LabInterpretation::Normal),
+            (5.0, LabInterpretation::Normal),
+            (5.1, LabInterpretation::High),
+            (6.5, LabInterpretation::High),
+            (6.6, LabInterpretation::CriticalHigh),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(potassium.interpret(value), expected, "potassium {}", value);
+        }
+    }
+
+    #[test]
+    fn missing_limits_never_flag() {
+        let hdl = range(Some(40.0), None, None, None);
+        assert_eq!(hdl.interpret(39.9), LabInterpretation::Low);
+        assert_eq!(hdl.interpret(400.0), LabInterpretation::Normal);
+
+        let no_critical = range(Some(0.4), Some(4.0), None, None);
+        assert_eq!(no_critical.interpret(0.0), LabInterpretation::Low);
+        assert_eq!(no_critical.interpret(100.0), LabInterpretation::High);
+        assert!(!LabInterpretation::Normal.is_abnormal());
+        assert!(LabInterpretation::CriticalLow.is_abnormal());
+    }
+
+    #[test]
+    fn range_follows_the_patients_sex() {
+        let conn = migrated();
+        assert_eq!(low_end(&conn, 1, "Hemoglobin", Some("g/dL"), "2026-01-01"), Some(12.0));
+        assert_eq!(low_end(&conn, 2, "Hemoglobin", Some("g/dL"), "2026-01-01"), Some(13.5));
+    }
+
+    #[test]
+    fn test_and_unit_match_loosely_but_a_different_unit_has_no_range() {
+        let conn = migrated();
+        assert_eq!(low_end(&conn, 1, " potassium ", Some("MMOL/L"), "2026-01-01"), Some(3.5));
+        assert!(find_reference_range(&conn, 1, "Potassium", Some("mEq/dL"), "2026-01-01").unwrap().is_none());
+        assert!(find_reference_range(&conn, 1, "Potassium", None, "2026-01-01").unwrap().is_none());
+    }
+
+    #[test]
+    fn age_band_applies_until_the_birthday_that_ends_it() {
+        let conn = migrated();
+        conn.execute(
+            "INSERT INTO lab_reference_ranges (test_name, unit, age_min_years, age_max_years, low, high)
+             VALUES ('WBC', 'K/uL', 0, 18, 5.0, 14.5)",
+            [],
+        )
+        .unwrap();
+        assert_eq!(low_end(&conn, 3, "WBC", Some("K/uL"), "2026-06-14"), Some(5.0));
+        assert_eq!(low_end(&conn, 3, "WBC", Some("K/uL"), "2026-06-15"), Some(4.5));
+        assert_eq!(low_end(&conn, 2, "WBC", Some("K/uL"), "2026-06-14"), Some(4.5));
+    }
+}
@@ -0,0 +1,142 @@
+use crate::error::Error;
+use rusqlite::{params, Connection, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use ts_rs::TS;
+
+// ============ SQLCipher Key Management ============
+//
+// The passphrase is never used as the raw page key. SQLCipher stretches it with
+// PBKDF2-HMAC-SHA512 (KDF_ITERATIONS rounds) using a random per-database salt
+// kept in the first 16 bytes of the file, so the same passphrase gives a
+// different key on every install and nothing secret is stored on disk.
+
+/// Shortest passphrase we accept when encrypting or rekeying
+pub const MIN_PASSPHRASE_LEN: usize = 8;
+
+const KDF_ITERATIONS: u32 = 256_000;
+const CIPHER_PAGE_SIZE: u32 = 4096;
+
+/// Every unencrypted SQLite file starts with this header; SQLCipher files start with the salt
+const PLAINTEXT_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct EncryptionStatus {
+    /// `rchart.db` exists on disk
+    pub database_exists: bool,
+    /// The file on disk is SQLCipher-encrypted (false for a legacy plaintext file or no file yet)
+    pub encrypted: bool,
+    /// A keyed connection is open and commands can run
+    pub unlocked: bool,
+}
+
+/// Key a freshly opened connection. Must run before anything else touches the database.
+pub fn apply_key(conn: &Connection, passphrase: &str) -> Result<()> {
+    conn.pragma_update(None, "key", passphrase)?;
+    conn.pragma_update(None, "cipher_page_size", CIPHER_PAGE_SIZE)?;
+    conn.pragma_update(None, "kdf_iter", KDF_ITERATIONS)?;
+    conn.pragma_update(None, "cipher_hmac_algorithm", "HMAC_SHA512")?;
+    conn.pragma_update(None, "cipher_kdf_algorithm", "PBKDF2_HMAC_SHA512")?;
+    Ok(())
+}
+
+/// Force SQLCipher to decrypt the first page so a wrong passphrase fails here
+/// (with `SQLITE_NOTADB`) instead of on the first real query
+pub fn verify_key(conn: &Connection) -> Result<()> {
+    conn.query_row("SELECT count(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0))?;
+    Ok(())
+}
+
+/// Open and key a connection, failing if the passphrase is wrong
+pub fn open_encrypted(db_path: &Path, passphrase: &str) -> Result<Connection> {
+    let conn = Connection::open(db_path)?;
+    apply_key(&conn, passphrase)?;
+    verify_key(&conn)?;
+    Ok(conn)
+}
+
+/// Whether the file at `db_path` is a plain, unencrypted SQLite database
+pub fn is_plaintext(db_path: &Path) -> std::io::Result<bool> {
+    let mut header = [0u8; 16];
+    let mut file = fs::File::open(db_path)?;
+    match file.read_exact(&mut header) {
+        Ok(()) => Ok(&header == PLAINTEXT_HEADER),
+        // An empty or truncated file has no pages yet; treat it as new rather than plaintext
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn get_encryption_status(db_path: &Path, unlocked: bool) -> EncryptionStatus {
+    let database_exists = db_path.exists();
+    let encrypted = database_exists && !is_plaintext(db_path).unwrap_or(false);
+    EncryptionStatus {
+        database_exists,
+        encrypted,
+        unlocked,
+    }
+}
+
+pub fn validate_passphrase(passphrase: &str) -> crate::error::Result<()> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(Error::invalid_field("passphrase", format!("Passphrase must be at least {} characters", MIN_PASSPHRASE_LEN)));
+    }
+    Ok(())
+}
+
+/// Convert a legacy plaintext `rchart.db` into a SQLCipher database keyed with `passphrase`
+///
+/// The encrypted copy is written next to the original with `sqlcipher_export`,
+/// verified, and then renamed over it, so a crash part way through leaves the
+/// original untouched. The plaintext file and its WAL/SHM sidecars are gone
+/// afterwards.
+pub fn encrypt_plaintext_database(db_path: &Path, passphrase: &str) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let tmp_path = sidecar_path(db_path, "encrypting");
+    if tmp_path.exists() {
+        fs::remove_file(&tmp_path)?;
+    }
+
+    {
+        let plain = Connection::open(db_path)?;
+        // Fold any WAL content into the main file so the export sees everything
+        plain.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        plain.execute(
+            "ATTACH DATABASE ?1 AS encrypted KEY ?2",
+            params![tmp_path.to_string_lossy(), passphrase],
+        )?;
+        plain.execute_batch(&format!(
+            "PRAGMA encrypted.cipher_page_size = {};
+             PRAGMA encrypted.kdf_iter = {};
+             PRAGMA encrypted.cipher_hmac_algorithm = HMAC_SHA512;
+             PRAGMA encrypted.cipher_kdf_algorithm = PBKDF2_HMAC_SHA512;",
+            CIPHER_PAGE_SIZE, KDF_ITERATIONS
+        ))?;
+        plain.query_row("SELECT sqlcipher_export('encrypted')", [], |_| Ok(()))?;
+        plain.execute("DETACH DATABASE encrypted", [])?;
+    }
+
+    // Make sure the copy really opens with this passphrase before replacing anything
+    open_encrypted(&tmp_path, passphrase)?;
+
+    fs::rename(&tmp_path, db_path)?;
+    for suffix in ["-wal", "-shm"] {
+        let sidecar = PathBuf::from(format!("{}{}", db_path.to_string_lossy(), suffix));
+        if sidecar.exists() {
+            fs::remove_file(sidecar)?;
+        }
+    }
+    Ok(())
+}
+
+/// Re-encrypt every page under a new passphrase. `conn` must already be unlocked.
+pub fn rekey(conn: &Connection, new_passphrase: &str) -> Result<()> {
+    conn.pragma_update(None, "rekey", new_passphrase)?;
+    verify_key(conn)
+}
+
+fn sidecar_path(db_path: &Path, suffix: &str) -> PathBuf {
+    PathBuf::from(format!("{}.{}", db_path.to_string_lossy(), suffix))
+}
@@ -0,0 +1,213 @@
+use rusqlite::ffi;
+use serde::ser::{SerializeStruct, Serializer};
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+use std::sync::PoisonError;
+use ts_rs::TS;
+
+// ============ Command Errors ============
+//
+// Every command returns this instead of a bare string. It reaches the
+// frontend as `{ kind, message, field, retryable }` (plus `current` for a
+// conflict), so the UI can tell a missing record from a bad field from a
+// busy database without parsing the message. Validation errors also carry
+// `errors`, one entry per refused field, so a form can mark every bad input
+// at once.
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    NotFound(String),
+    /// Input that was refused; never empty
+    Validation(Vec<FieldError>),
+    /// A stale write; `current` is the record as it is now, or `None` if it was deleted
+    Conflict { message: String, current: Option<Value> },
+    /// Nobody is signed in, the session expired, or the credentials were wrong
+    Unauthenticated(String),
+    /// The signed-in role does not have the permission
+    Forbidden(String),
+    /// The chart is outside the user's care team; breaking the glass opens it
+    Restricted(String),
+    /// The encrypted database has not been unlocked with its passphrase
+    Locked(String),
+    /// SQLite was busy or no connection came free in time; the same call may succeed shortly
+    Busy(String),
+    Database(String),
+    Internal(String),
+}
+
+/// One refused input, with the offending field when it is known
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct FieldError {
+    pub field: Option<String>,
+    pub message: String,
+}
+
+impl Error {
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Error::NotFound(message.into())
+    }
+
+    pub fn invalid(message: impl Into<String>) -> Self {
+        Error::Validation(vec![FieldError { field: None, message: message.into() }])
+    }
+
+    pub fn invalid_field(field: &str, message: impl Into<String>) -> Self {
+        Error::Validation(vec![FieldError { field: Some(field.to_string()), message: message.into() }])
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Error::Forbidden(message.into())
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Error::Internal(message.into())
+    }
+
+    /// Stable identifier the frontend switches on
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::NotFound(_) => "not_found",
+            Error::Validation(_) => "validation",
+            Error::Conflict { .. } => "conflict",
+            Error::Unauthenticated(_) => "unauthenticated",
+            Error::Forbidden(_) => "forbidden",
+            Error::Restricted(_) => "restricted",
+            Error::Locked(_) => "locked",
+            Error::Busy(_) => "busy",
+            Error::Database(_) => "database",
+            Error::Internal(_) => "internal",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Error::NotFound(message)
+            | Error::Unauthenticated(message)
+            | Error::Forbidden(message)
+            | Error::Restricted(message)
+            | Error::Locked(message)
+            | Error::Busy(message)
+            | Error::Database(message)
+            | Error::Internal(message) => message,
+            Error::Validation(errors) => errors.first().map_or("Invalid input", |e| e.message.as_str()),
+            Error::Conflict { message, .. } => message,
+        }
+    }
+
+    pub fn field(&self) -> Option<&str> {
+        match self {
+            Error::Validation(errors) => errors.first().and_then(|e| e.field.as_deref()),
+            _ => None,
+        }
+    }
+
+    /// Whether repeating the same call unchanged might succeed
+    pub fn retryable(&self) -> bool {
+        matches!(self, Error::Busy(_))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Error", 5)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", self.message())?;
+        state.serialize_field("field", &self.field())?;
+        state.serialize_field("retryable", &self.retryable())?;
+        match self {
+            Error::Validation(errors) => state.serialize_field("errors", errors)?,
+            Error::Conflict { current, .. } => state.serialize_field("current", current)?,
+            _ => state.skip_field("errors")?,
+        }
+        state.end()
+    }
+}
+
+// ============ Mapping SQLite Errors ============
+
+impl From<rusqlite::Error> for Error {
+    fn from(e: rusqlite::Error) -> Self {
+        match e {
+            rusqlite::Error::QueryReturnedNoRows => Error::not_found("Record not found"),
+            rusqlite::Error::SqliteFailure(ref failure, ref detail) => {
+                let detail = detail.clone().unwrap_or_default();
+                match failure.code {
+                    rusqlite::ErrorCode::ConstraintViolation => constraint_error(failure.extended_code, &detail),
+                    rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked => {
+                        Error::Busy("The database is busy; try again".to_string())
+                    }
+                    _ => Error::Database(e.to_string()),
+                }
+            }
+            other => Error::Database(other.to_string()),
+        }
+    }
+}
+
+/// Turn a constraint failure into a validation error naming the column where SQLite gives one
+fn constraint_error(extended_code: i32, detail: &str) -> Error {
+    match extended_code {
+        ffi::SQLITE_CONSTRAINT_NOTNULL => {
+            let column = constrained_column(detail);
+            let message = match column {
+                Some(column) => format!("{} is required", capitalize(&column.replace('_', " "))),
+                None => "A required field is missing".to_string(),
+            };
+            Error::Validation(vec![FieldError { field: column.map(str::to_string), message }])
+        }
+        ffi::SQLITE_CONSTRAINT_UNIQUE | ffi::SQLITE_CONSTRAINT_PRIMARYKEY => {
+            let column = constrained_column(detail);
+            let message = match column {
+                Some(column) => format!("A record with this {} already exists", column.replace('_', " ")),
+                None => "This record already exists".to_string(),
+            };
+            Error::Validation(vec![FieldError { field: column.map(str::to_string), message }])
+        }
+        ffi::SQLITE_CONSTRAINT_FOREIGNKEY => {
+            Error::invalid("This change refers to a record that does not exist, or removes one that others still refer to")
+        }
+        ffi::SQLITE_CONSTRAINT_CHECK => Error::invalid(format!("A value is not allowed ({})", detail)),
+        // RAISE(ABORT, ...) in a trigger; the message is written for people already
+        ffi::SQLITE_CONSTRAINT_TRIGGER => Error::invalid(detail),
+        _ => Error::invalid(detail),
+    }
+}
+
+/// `users.username` out of "UNIQUE constraint failed: users.username"; the first column for composite keys
+fn constrained_column(detail: &str) -> Option<&str> {
+    let columns = detail.split_once(": ")?.1;
+    let first = columns.split(", ").next()?;
+    first.rsplit('.').next()
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+impl<T> From<PoisonError<T>> for Error {
+    fn from(e: PoisonError<T>) -> Self {
+        Error::Internal(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Internal(e.to_string())
+    }
+}
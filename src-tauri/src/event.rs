@@ -0,0 +1,125 @@
+use rusqlite::Connection;
+use serde::Serialize;
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Manager};
+use ts_rs::TS;
+
+// ============ Model Change Events ============
+//
+// Every create, update or delete queues a `ModelEvent`. A write made inside a
+// transaction is held with that transaction and only kept once SQLite reports
+// the commit; a rollback drops it. The kept events are sent to all windows
+// when the writer connection goes back to the pool, so a listener that
+// refetches on the event reads the new data rather than racing the write,
+// and a command that failed announces nothing it undid.
+//
+// Each event goes out twice: once as `model-change` for windows that follow
+// everything (patient list, calendar, dashboard), and once on the owning
+// patient's own channel for an open chart.
+
+/// Name of the event carrying every change
+pub const MODEL_EVENT: &str = "model-change";
+
+/// Name of the event carrying only changes to one patient's chart
+pub fn patient_event(patient_id: i64) -> String {
+    format!("{}:patient:{}", MODEL_EVENT, patient_id)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelAction {
+    Create,
+    Update,
+    Delete,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[ts(export)]
+pub struct ModelEvent {
+    /// Table the row lives in (`patients`, `allergies`, `appointments`, ...)
+    pub entity: &'static str,
+    #[ts(as = "f64")]
+    pub id: i64,
+    pub action: ModelAction,
+    /// Whose chart the row belongs to; a patient's own row carries its id
+    #[ts(as = "Option<f64>")]
+    pub patient_id: Option<i64>,
+}
+
+static APP: OnceLock<AppHandle> = OnceLock::new();
+static PENDING: Mutex<Pending> = Mutex::new(Pending {
+    open: Vec::new(),
+    committed: Vec::new(),
+});
+
+/// Events waiting on the writer, split by whether their write is durable yet
+struct Pending {
+    /// Written inside a transaction that has not finished
+    open: Vec<ModelEvent>,
+    /// Written and committed, waiting for the command to hand the writer back
+    committed: Vec<ModelEvent>,
+}
+
+fn push_once(events: &mut Vec<ModelEvent>, event: ModelEvent) {
+    // Several steps of one command can touch the same row
+    if !events.contains(&event) {
+        events.push(event);
+    }
+}
+
+/// Start delivering events; until then they are dropped when flushed
+pub fn init(app: AppHandle) {
+    let _ = APP.set(app);
+}
+
+/// Hold `event` for a write just made on `conn`
+///
+/// Outside a transaction the statement has already committed; inside one the
+/// event waits for `committed` or `rolled_back`.
+pub fn queue(conn: &Connection, event: ModelEvent) {
+    if let Ok(mut pending) = PENDING.lock() {
+        if conn.is_autocommit() {
+            push_once(&mut pending.committed, event);
+        } else {
+            push_once(&mut pending.open, event);
+        }
+    }
+}
+
+/// The writer's transaction committed: keep what it queued
+pub fn committed() {
+    if let Ok(mut pending) = PENDING.lock() {
+        for event in std::mem::take(&mut pending.open) {
+            push_once(&mut pending.committed, event);
+        }
+    }
+}
+
+/// The writer's transaction rolled back: forget what it queued
+pub fn rolled_back() {
+    if let Ok(mut pending) = PENDING.lock() {
+        pending.open.clear();
+    }
+}
+
+/// Send everything committed so far
+pub fn flush() {
+    let events = match PENDING.lock() {
+        Ok(mut pending) => {
+            // A transaction still open here never committed
+            pending.open.clear();
+            std::mem::take(&mut pending.committed)
+        }
+        Err(_) => return,
+    };
+    let Some(app) = APP.get() else {
+        return;
+    };
+    for event in events {
+        if let Some(patient_id) = event.patient_id {
+            let _ = app.emit_all(&patient_event(patient_id), event.clone());
+        }
+        let _ = app.emit_all(MODEL_EVENT, event);
+    }
+}
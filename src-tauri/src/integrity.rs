@@ -0,0 +1,219 @@
+use crate::audit::{self, AuditAction};
+use crate::db;
+use crate::pool;
+use rusqlite::{params, Connection, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use ts_rs::TS;
+
+// ============ Database Integrity ============
+//
+// Foreign keys are enforced on every connection now, but databases written
+// before that can still hold rows whose parent is gone, and SQLite only checks
+// references as rows change. `check` reports those orphans along with storage
+// corruption and search indexes that have drifted from their tables; `repair`
+// fixes what it can and moves the rest into `quarantined_rows` (migration 014),
+// auditing every row it touches.
+
+/// Quarantining a parent orphans its children, so repair goes round again;
+/// this bounds it at the deepest chain in the schema with room to spare
+const MAX_REPAIR_PASSES: usize = 8;
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct IntegrityReport {
+    /// What `PRAGMA integrity_check` found wrong with the file itself; empty when it is sound
+    pub storage_errors: Vec<String>,
+    pub orphans: Vec<OrphanedRows>,
+    /// Tables whose `<table>_fts` search index no longer matches their rows
+    pub stale_search_indexes: Vec<String>,
+}
+
+/// Rows of `table` whose `column` names a `parent` row that does not exist
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct OrphanedRows {
+    pub table: String,
+    pub column: String,
+    pub parent: String,
+    /// The key's `ON DELETE` action: `CASCADE`, `SET NULL` or `NO ACTION`
+    pub on_delete: String,
+    /// Whether the column allows NULL, so the reference can be cleared instead of the row removed
+    pub nullable: bool,
+    #[ts(as = "Vec<f64>")]
+    pub row_ids: Vec<i64>,
+}
+
+impl OrphanedRows {
+    /// Clearing the reference keeps the row; that is right when the schema would have cleared it
+    /// anyway, or when the row stands on its own (a note whose signer's account is gone)
+    fn can_clear(&self) -> bool {
+        self.nullable && self.on_delete != "CASCADE"
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum OrphanFix {
+    /// Clear dangling references where the row still makes sense without them; quarantine the rest
+    Repair,
+    /// Quarantine every orphaned row, leaving the decision to whoever reviews `quarantined_rows`
+    Quarantine,
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct RepairSummary {
+    #[ts(as = "f64")]
+    pub references_cleared: i64,
+    #[ts(as = "f64")]
+    pub rows_quarantined: i64,
+    pub search_indexes_rebuilt: Vec<String>,
+    /// A fresh check after the repair; storage errors cannot be fixed from inside the app
+    pub report: IntegrityReport,
+}
+
+/// Run every check without changing anything
+///
+/// The search index check is issued as an FTS5 command (an `INSERT`), so this
+/// needs the writer even though it only reads.
+pub fn check(conn: &Connection) -> Result<IntegrityReport> {
+    Ok(IntegrityReport {
+        storage_errors: storage_errors(conn)?,
+        orphans: find_orphans(conn)?,
+        stale_search_indexes: stale_search_indexes(conn)?,
+    })
+}
+
+/// Fix orphans as `fix` says and rebuild stale search indexes, attributing each change to `user_id`
+pub fn repair(conn: &Connection, user_id: i64, fix: OrphanFix) -> Result<RepairSummary> {
+    // With enforcement on, deleting an orphan would cascade to its children
+    // unrecorded; with it off they become orphans for the next pass instead.
+    conn.pragma_update(None, "foreign_keys", false)?;
+    let fixed = fix_orphans(conn, user_id, fix);
+    pool::enforce_foreign_keys(conn)?;
+    let (references_cleared, rows_quarantined) = fixed?;
+
+    let search_indexes_rebuilt = stale_search_indexes(conn)?;
+    for table in &search_indexes_rebuilt {
+        let fts = format!("{}_fts", table);
+        conn.execute(&format!("INSERT INTO {}({}) VALUES ('rebuild')", fts, fts), [])?;
+    }
+
+    Ok(RepairSummary {
+        references_cleared,
+        rows_quarantined,
+        search_indexes_rebuilt,
+        report: check(conn)?,
+    })
+}
+
+/// `(references cleared, rows quarantined)`; one transaction per pass
+fn fix_orphans(conn: &Connection, user_id: i64, fix: OrphanFix) -> Result<(i64, i64)> {
+    let mut cleared = 0;
+    let mut quarantined = 0;
+    for _ in 0..MAX_REPAIR_PASSES {
+        let orphans = find_orphans(conn)?;
+        if orphans.is_empty() {
+            break;
+        }
+        let tx = conn.unchecked_transaction()?;
+        for set in &orphans {
+            for &id in &set.row_ids {
+                if fix == OrphanFix::Repair && set.can_clear() {
+                    cleared += clear_reference(&tx, user_id, set, id)? as i64;
+                } else {
+                    quarantined += quarantine_row(&tx, user_id, set, id)? as i64;
+                }
+            }
+        }
+        tx.commit()?;
+    }
+    Ok((cleared, quarantined))
+}
+
+fn storage_errors(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+    let messages = stmt.query_map([], |row| row.get::<_, String>(0))?.collect::<Result<Vec<_>>>()?;
+    Ok(messages.into_iter().filter(|m| m != "ok").collect())
+}
+
+/// Every `foreign_key_check` violation, grouped by the key it breaks
+fn find_orphans(conn: &Connection) -> Result<Vec<OrphanedRows>> {
+    let mut stmt = conn.prepare(
+        r#"SELECT v."table", v.rowid, v.parent, fk."from", fk.on_delete, NOT col."notnull"
+           FROM pragma_foreign_key_check AS v
+           JOIN pragma_foreign_key_list(v."table") AS fk ON fk.id = v.fkid AND fk.seq = 0
+           JOIN pragma_table_info(v."table") AS col ON col.name = fk."from"
+           WHERE v.rowid IS NOT NULL
+           ORDER BY v."table", fk."from", v.rowid"#,
+    )?;
+    let mut rows = stmt.query([])?;
+
+    let mut grouped: BTreeMap<(String, String), OrphanedRows> = BTreeMap::new();
+    while let Some(row) = rows.next()? {
+        let table: String = row.get(0)?;
+        let column: String = row.get(3)?;
+        let id: i64 = row.get(1)?;
+        grouped
+            .entry((table.clone(), column.clone()))
+            .or_insert(OrphanedRows {
+                table,
+                column,
+                parent: row.get(2)?,
+                on_delete: row.get(4)?,
+                nullable: row.get(5)?,
+                row_ids: Vec::new(),
+            })
+            .row_ids
+            .push(id);
+    }
+    Ok(grouped.into_values().collect())
+}
+
+/// FTS5's own check, comparing each index against its content table
+fn stale_search_indexes(conn: &Connection) -> Result<Vec<String>> {
+    let mut stale = Vec::new();
+    for table in db::search_tables() {
+        let fts = format!("{}_fts", table);
+        match conn.execute(&format!("INSERT INTO {}({}, rank) VALUES ('integrity-check', 1)", fts, fts), []) {
+            Ok(_) => {}
+            Err(rusqlite::Error::SqliteFailure(e, _)) if e.code == rusqlite::ErrorCode::DatabaseCorrupt => {
+                stale.push(table.to_string());
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(stale)
+}
+
+/// Whether row `id` was still there to change; an earlier key's fix may have removed it
+fn clear_reference(conn: &Connection, user_id: i64, set: &OrphanedRows, id: i64) -> Result<bool> {
+    let Some(before) = audit::snapshot(conn, &set.table, id)? else {
+        return Ok(false);
+    };
+    conn.execute(&format!("UPDATE {} SET {} = NULL WHERE id = ?1", set.table, set.column), params![id])?;
+    audit::record_change(conn, user_id, AuditAction::Update, &set.table, id, Some(before))?;
+    Ok(true)
+}
+
+fn quarantine_row(conn: &Connection, user_id: i64, set: &OrphanedRows, id: i64) -> Result<bool> {
+    let Some(row) = audit::snapshot(conn, &set.table, id)? else {
+        return Ok(false);
+    };
+    conn.execute(
+        "INSERT INTO quarantined_rows (quarantined_by, source_table, source_id, row_data, reason)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            user_id,
+            set.table,
+            id,
+            row.to_string(),
+            format!("{} points at a missing {} row", set.column, set.parent),
+        ],
+    )?;
+    conn.execute(&format!("DELETE FROM {} WHERE id = ?1", set.table), params![id])?;
+    audit::record_change(conn, user_id, AuditAction::Delete, &set.table, id, Some(row))?;
+    Ok(true)
+}
@@ -4,17 +4,58 @@
     windows_subsystem = "windows"
 )]
 
+mod audit;
+mod auth;
+#[cfg(test)]
+mod bindings;
+mod chart_access;
+mod concurrency;
 mod db;
-
-use db::{DbState, Patient, Appointment, AppointmentWithPatient, PatientFullData, Encounter, User, UserFullData, UserSettings, SearchResult, Prescription, Allergy, Vaccination, SocialHistory, FamilyHistory};
+mod encryption;
+mod error;
+mod event;
+mod integrity;
+mod migrations;
+mod model;
+mod name_match;
+mod note_history;
+mod orders;
+mod pool;
+mod rbac;
+mod search_query;
+mod store;
+mod two_factor;
+mod validation;
+mod vitals;
+
+use audit::{AuditAction, AuditEntry, AuditEvent, AuditFilter};
+use auth::{AuthStatus, LoginResult, SessionInfo};
+use chart_access::{BreakGlassGrant, CareTeamMember, ChartAccess};
+use error::Error;
+use event::ModelAction;
+use integrity::{IntegrityReport, OrphanFix, RepairSummary};
+use model::patient::PatientFilter;
+use model::{bmc_create, bmc_delete, bmc_get, bmc_list, bmc_owner, bmc_update, fire_model_event, Entity, ModelMutateResultData};
+use name_match::{NameQuery, PatientMatch};
+use note_history::{EncounterRevision, RevisionDiff};
+use orders::{Order, OrderStep};
+use rbac::{Permission, Role};
+use search_query::SearchQuery;
+use db::{DbState, Patient, Appointment, AppointmentWithPatient, PatientFullData, Encounter, EncounterAddendum, User, UserFullData, UserSettings, SearchResult, SearchGroup, Prescription, Vital, Lab, LabReferenceRange, Allergy, Vaccination, SocialHistory, FamilyHistory};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use tauri::{State, Manager};
-use std::sync::Mutex;
+use two_factor::TwoFactorEnrollment;
+use ts_rs::TS;
+use validation::Validate;
+use vitals::VitalsSet;
 
 // ============ Legacy Appointment struct (for backwards compatibility) ============
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export)]
 pub struct LegacyAppointment {
     pub name: String,
+    #[ts(as = "f64")]
     pub age: u64,
     pub sex: String,
     pub time: String,
@@ -27,20 +68,20 @@ fn main() {
             let db_path = db::get_db_path(&app.handle());
             println!("Database path: {:?}", db_path);
 
-            // IMPORTANT: In production, this key should come from:
-            // - User authentication (derived from password)
-            // - OS keychain/secure storage
-            // - Environment variable (for development only)
-            // DO NOT hardcode in production!
-            let encryption_key = std::env::var("RCHART_DB_KEY")
-                .unwrap_or_else(|_| "CHANGE_THIS_KEY_IN_PRODUCTION".to_string());
-
-            // Initialize the encrypted database
-            let conn = db::init_db(&db_path, &encryption_key)
-                .expect("Failed to initialize database");
+            // The database stays locked until the frontend calls db_unlock with the
+            // user's passphrase. RCHART_DB_KEY unlocks at startup for development only.
+            let state = DbState::new(db_path);
+            if let Ok(passphrase) = std::env::var("RCHART_DB_KEY") {
+                state
+                    .unlock(&passphrase)
+                    .unwrap_or_else(|e| panic!("Failed to initialize database: {}", e));
+            }
 
             // Manage the database connection state
-            app.manage(DbState(Mutex::new(conn)));
+            app.manage(state);
+
+            // Push model changes to the windows
+            event::init(app.handle());
 
             Ok(())
         })
@@ -49,14 +90,37 @@ fn main() {
             get_month,
             greet,
             get_appointments,
+            // Encryption / unlock commands
+            db_get_encryption_status,
+            db_unlock,
+            db_lock,
+            db_change_passphrase,
+            // Maintenance commands
+            db_check_integrity,
+            db_repair_integrity,
+            // Authentication commands
+            db_get_auth_status,
+            db_login,
+            db_logout,
+            db_verify_two_factor,
+            db_begin_two_factor_enrollment,
+            db_confirm_two_factor_enrollment,
+            db_disable_two_factor,
+            db_create_first_user,
             // New database commands
             db_create_patient,
+            db_find_duplicate_patients,
             db_get_all_patients,
             db_get_patient,
+            db_update_patient,
+            db_set_patient_status,
+            db_delete_patient,
+            db_merge_patients,
             db_create_appointment,
             db_get_appointments_for_date,
             db_get_all_appointments,
             db_seed_test_data,
+            db_get_schema_status,
             // Patient detail page commands
             db_get_patient_full,
             db_get_encounter,
@@ -64,6 +128,14 @@ fn main() {
             // Encounter CRUD commands
             db_create_encounter,
             db_update_encounter,
+            // Note signing commands
+            db_sign_encounter,
+            db_cosign_encounter,
+            db_create_addendum,
+            db_get_addenda,
+            db_get_encounter_revisions,
+            db_diff_encounter_revisions,
+            db_restore_encounter_revision,
             // User/Provider commands
             db_get_current_user,
             db_get_user_full,
@@ -71,6 +143,10 @@ fn main() {
             db_update_user_settings,
             db_update_password,
             db_seed_user_data,
+            // User administration commands
+            db_get_users,
+            db_create_user,
+            db_set_user_role,
             // Patient list commands
             db_get_patient_lists,
             db_get_patient_list,
@@ -82,13 +158,44 @@ fn main() {
             db_remove_patient_from_list,
             db_update_list_columns,
             db_seed_patient_lists,
+            // Care team / break-the-glass commands
+            db_get_care_team,
+            db_add_care_team_member,
+            db_remove_care_team_member,
+            db_break_glass,
+            db_get_break_glass_queue,
+            db_review_break_glass,
             // Search commands
             db_global_search,
             db_search_patient_data,
             db_quick_search_patients,
+            // Audit log commands
+            db_get_audit_log,
             // Prescription commands
             db_create_prescriptions,
             db_get_prescriptions,
+            // Vitals commands
+            db_record_vitals,
+            db_create_vital,
+            db_update_vital,
+            db_delete_vital,
+            // Lab commands
+            db_create_lab,
+            db_update_lab,
+            db_delete_lab,
+            db_get_lab_reference_ranges,
+            db_save_lab_reference_range,
+            db_delete_lab_reference_range,
+            // Order commands
+            db_create_order,
+            db_get_orders,
+            db_get_outstanding_orders,
+            db_collect_order,
+            db_attach_lab_to_order,
+            db_detach_lab_from_order,
+            db_record_imaging_result,
+            db_review_order,
+            db_cancel_order,
             // History CRUD commands
             db_create_allergy,
             db_update_allergy,
@@ -143,50 +250,252 @@ fn get_appointments() -> Vec<LegacyAppointment> {
     ]
 }
 
+// ============ Encryption / Unlock Commands ============
+
+#[tauri::command]
+fn db_get_encryption_status(state: State<DbState>) -> encryption::EncryptionStatus {
+    state.encryption_status()
+}
+
+#[tauri::command]
+fn db_unlock(state: State<DbState>, passphrase: String) -> Result<(), Error> {
+    state.unlock(&passphrase)
+}
+
+#[tauri::command]
+fn db_lock(state: State<DbState>) -> Result<(), Error> {
+    state.lock()
+}
+
+#[tauri::command]
+fn db_change_passphrase(state: State<DbState>, current_passphrase: String, new_passphrase: String) -> Result<(), Error> {
+    state.authorize(Permission::ManageDatabase)?;
+    state.change_passphrase(&current_passphrase, &new_passphrase)
+}
+
+// ============ Maintenance Commands ============
+
+#[tauri::command]
+fn db_check_integrity(state: State<DbState>) -> Result<IntegrityReport, Error> {
+    state.authorize(Permission::ManageDatabase)?;
+    let conn = state.writer()?;
+    integrity::check(&conn).map_err(Error::from)
+}
+
+/// Clear or quarantine orphaned rows and rebuild stale search indexes; every row touched is audited
+#[tauri::command]
+fn db_repair_integrity(state: State<DbState>, fix: OrphanFix) -> Result<RepairSummary, Error> {
+    let user_id = state.authorize(Permission::ManageDatabase)?;
+    let conn = state.writer()?;
+    integrity::repair(&conn, user_id, fix).map_err(Error::from)
+}
+
+// ============ Authentication Commands ============
+
+#[tauri::command]
+fn db_get_auth_status(state: State<DbState>) -> Result<AuthStatus, Error> {
+    state.auth_status()
+}
+
+#[tauri::command]
+fn db_login(state: State<DbState>, username: String, password: String) -> Result<LoginResult, Error> {
+    state.login(&username, &password)
+}
+
+#[tauri::command]
+fn db_verify_two_factor(state: State<DbState>, code: String) -> Result<SessionInfo, Error> {
+    state.verify_two_factor(&code)
+}
+
+#[tauri::command]
+fn db_logout(state: State<DbState>) -> Result<(), Error> {
+    state.logout()
+}
+
+#[tauri::command]
+fn db_create_first_user(state: State<DbState>, user: User, password: String) -> Result<SessionInfo, Error> {
+    state.create_first_user(user, &password)
+}
+
 // ============ New Database Commands ============
 
+/// Queue a change event for a write that went through hand-written SQL
+fn model_changed(conn: &rusqlite::Connection, entity: &'static str, action: ModelAction, id: i64, patient_id: Option<i64>) {
+    fire_model_event(conn, entity, action, ModelMutateResultData { id, patient_id });
+}
+
 #[tauri::command]
-fn db_create_patient(state: State<DbState>, patient: Patient) -> Result<i64, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::create_patient(&conn, &patient).map_err(|e| e.to_string())
+fn db_create_patient(state: State<DbState>, patient: Patient) -> Result<i64, Error> {
+    let user_id = state.authorize(Permission::EditPatients)?;
+    patient.validate()?;
+    let conn = state.writer()?;
+    let tx = conn.unchecked_transaction()?;
+    let id = bmc_create(&tx, &patient)?.id;
+    audit::record_change(&tx, user_id, AuditAction::Create, "patients", id, None)?;
+    tx.commit()?;
+    Ok(id)
 }
 
+/// Existing patients who may be the same person as `patient`, for a warning before it is registered
 #[tauri::command]
-fn db_get_all_patients(state: State<DbState>) -> Result<Vec<Patient>, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::get_all_patients(&conn).map_err(|e| e.to_string())
+fn db_find_duplicate_patients(state: State<DbState>, patient: Patient) -> Result<Vec<PatientMatch>, Error> {
+    let user_id = state.authorize(Permission::EditPatients)?;
+    let conn = state.reader()?;
+    let query = NameQuery::from_patient(&patient);
+    let matches = name_match::find_patient_matches(&conn, &query, name_match::DUPLICATE_THRESHOLD, 10, patient.id)?;
+    let patient_ids: Vec<i64> = matches.iter().filter_map(|m| m.patient.id).collect();
+    let searched = format!("{} {} {}", patient.first_name, patient.last_name, patient.dob);
+    state.audit(user_id, &AuditEvent::search(&searched, None, &patient_ids))?;
+    Ok(matches)
 }
 
 #[tauri::command]
-fn db_get_patient(state: State<DbState>, id: i64) -> Result<Option<Patient>, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::get_patient_by_id(&conn, id).map_err(|e| e.to_string())
+fn db_get_all_patients(state: State<DbState>) -> Result<Vec<Patient>, Error> {
+    let user_id = state.authorize(Permission::ViewPatients)?;
+    let conn = state.reader()?;
+    let patients = bmc_list::<Patient>(&conn, &PatientFilter::default())?;
+    state.audit(user_id, &AuditEvent::read("patients", None, None).with_detail(json!({ "count": patients.len() })))?;
+    Ok(patients)
 }
 
 #[tauri::command]
-fn db_create_appointment(state: State<DbState>, appointment: Appointment) -> Result<i64, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::create_appointment(&conn, &appointment).map_err(|e| e.to_string())
+fn db_get_patient(state: State<DbState>, id: i64) -> Result<Option<Patient>, Error> {
+    let user_id = state.authorize(Permission::ViewPatients)?;
+    let conn = state.reader()?;
+    let patient = bmc_get::<Patient>(&conn, id)?;
+    state.audit(user_id, &AuditEvent::read("patients", Some(id), Some(id)))?;
+    Ok(patient)
+}
+
+#[tauri::command]
+fn db_update_patient(state: State<DbState>, patient: Patient) -> Result<i64, Error> {
+    let user_id = state.authorize(Permission::EditPatients)?;
+    let id = patient.id.ok_or_else(|| Error::invalid_field("id", "Patient id is required"))?;
+    patient.validate()?;
+    let conn = state.writer()?;
+    let tx = conn.unchecked_transaction()?;
+    let before = audit::snapshot(&tx, "patients", id)?;
+    if bmc_update(&tx, id, &patient)?.is_none() {
+        return Err(concurrency::conflict(before));
+    }
+    audit::record_change(&tx, user_id, AuditAction::Update, "patients", id, before)?;
+    tx.commit()?;
+    concurrency::stored_version(&conn, "patients", id).map_err(Error::from)
 }
 
 #[tauri::command]
-fn db_get_appointments_for_date(state: State<DbState>, date: String) -> Result<Vec<AppointmentWithPatient>, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::get_appointments_for_date(&conn, &date).map_err(|e| e.to_string())
+fn db_set_patient_status(state: State<DbState>, patient_id: i64, status: String, status_date: Option<String>) -> Result<(), Error> {
+    let user_id = state.authorize(Permission::EditPatients)?;
+    if !db::PATIENT_STATUSES.contains(&status.as_str()) {
+        return Err(Error::invalid_field("status", format!("Unknown patient status '{}'", status)));
+    }
+    let conn = state.writer()?;
+    let tx = conn.unchecked_transaction()?;
+    let patient = bmc_get::<Patient>(&tx, patient_id)?
+        .ok_or_else(|| Error::not_found(format!("Patient {} not found", patient_id)))?;
+    if patient.status.as_deref() == Some("merged") {
+        return Err(Error::invalid(format!("Patient {} has been merged and its status can no longer change", patient_id)));
+    }
+    let before = audit::snapshot(&tx, "patients", patient_id)?;
+    db::set_patient_status(&tx, patient_id, &status, status_date.as_deref())?;
+    model_changed(&tx, "patients", ModelAction::Update, patient_id, Some(patient_id));
+    audit::record_change(&tx, user_id, AuditAction::Update, "patients", patient_id, before)?;
+    tx.commit().map_err(Error::from)
 }
 
 #[tauri::command]
-fn db_get_all_appointments(state: State<DbState>) -> Result<Vec<AppointmentWithPatient>, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::get_all_appointments(&conn).map_err(|e| e.to_string())
+fn db_delete_patient(state: State<DbState>, patient_id: i64) -> Result<(), Error> {
+    let user_id = state.authorize(Permission::ManagePatientRecords)?;
+    let conn = state.writer()?;
+    let blockers = db::get_patient_delete_blockers(&conn, patient_id)?;
+    if !blockers.is_empty() {
+        let summary: Vec<String> = blockers
+            .iter()
+            .map(|(table, count)| format!("{} {}", count, table))
+            .collect();
+        return Err(Error::invalid(format!(
+            "Patient still has {}; deactivate the patient instead",
+            summary.join(", ")
+        )));
+    }
+    let tx = conn.unchecked_transaction()?;
+    let before = audit::snapshot(&tx, "patients", patient_id)?;
+    db::delete_patient(&tx, patient_id)?;
+    model_changed(&tx, "patients", ModelAction::Delete, patient_id, Some(patient_id));
+    audit::record_change(&tx, user_id, AuditAction::Delete, "patients", patient_id, before)?;
+    tx.commit().map_err(Error::from)
 }
 
 #[tauri::command]
-fn db_seed_test_data(state: State<DbState>) -> Result<String, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
+fn db_merge_patients(state: State<DbState>, survivor_id: i64, duplicate_id: i64) -> Result<(), Error> {
+    let user_id = state.authorize(Permission::ManagePatientRecords)?;
+    if survivor_id == duplicate_id {
+        return Err(Error::invalid("Cannot merge a patient into itself"));
+    }
+    let conn = state.writer()?;
+    for id in [survivor_id, duplicate_id] {
+        let patient = bmc_get::<Patient>(&conn, id)?
+            .ok_or_else(|| Error::not_found(format!("Patient {} not found", id)))?;
+        if patient.status.as_deref() == Some("merged") {
+            return Err(Error::invalid(format!("Patient {} has already been merged", id)));
+        }
+    }
+    let tx = conn.unchecked_transaction()?;
+    db::merge_patients(&tx, survivor_id, duplicate_id)?;
+
+    // One entry per chart so an access report for either patient shows the merge
+    let detail = json!({ "survivor_id": survivor_id, "duplicate_id": duplicate_id });
+    for id in [survivor_id, duplicate_id] {
+        let event = AuditEvent {
+            action: AuditAction::Merge,
+            entity_type: "patients",
+            entity_id: Some(id),
+            patient_id: Some(id),
+            detail: Some(detail.clone()),
+        };
+        audit::record(&tx, Some(user_id), &event)?;
+        model_changed(&tx, "patients", ModelAction::Update, id, Some(id));
+    }
+    tx.commit().map_err(Error::from)
+}
+
+#[tauri::command]
+fn db_create_appointment(state: State<DbState>, appointment: Appointment) -> Result<i64, Error> {
+    let user_id = state.authorize(Permission::ManageSchedule)?;
+    appointment.validate()?;
+    let conn = state.writer()?;
+    let tx = conn.unchecked_transaction()?;
+    let id = bmc_create(&tx, &appointment)?.id;
+    audit::record_change(&tx, user_id, AuditAction::Create, "appointments", id, None)?;
+    tx.commit()?;
+    Ok(id)
+}
+
+#[tauri::command]
+fn db_get_appointments_for_date(state: State<DbState>, date: String) -> Result<Vec<AppointmentWithPatient>, Error> {
+    let user_id = state.authorize(Permission::ViewSchedule)?;
+    let conn = state.reader()?;
+    let appointments = db::get_appointments_for_date(&conn, &date)?;
+    state.audit(user_id, &AuditEvent::read("appointments", None, None).with_detail(json!({ "date": date, "count": appointments.len() })))?;
+    Ok(appointments)
+}
+
+#[tauri::command]
+fn db_get_all_appointments(state: State<DbState>) -> Result<Vec<AppointmentWithPatient>, Error> {
+    let user_id = state.authorize(Permission::ViewSchedule)?;
+    let conn = state.reader()?;
+    let appointments = db::get_all_appointments(&conn)?;
+    state.audit(user_id, &AuditEvent::read("appointments", None, None).with_detail(json!({ "count": appointments.len() })))?;
+    Ok(appointments)
+}
+
+#[tauri::command]
+fn db_seed_test_data(state: State<DbState>) -> Result<String, Error> {
+    state.authorize(Permission::ManagePatientRecords)?;
+    let conn = state.writer()?;
 
     // Check if we already have patients
-    let existing = db::get_all_patients(&conn).map_err(|e| e.to_string())?;
+    let existing = bmc_list::<Patient>(&conn, &PatientFilter::default())?;
     if !existing.is_empty() {
         return Ok(format!("Database already has {} patients", existing.len()));
     }
@@ -209,6 +518,9 @@ fn db_seed_test_data(state: State<DbState>) -> Result<String, String> {
             insurance_provider: Some("Medicare".to_string()),
             insurance_policy_number: Some("1EG4-TE5-MK72".to_string()),
             insurance_group_number: None,
+            status: None,
+            status_date: None,
+            row_version: None,
         },
         Patient {
             id: None,
@@ -226,6 +538,9 @@ fn db_seed_test_data(state: State<DbState>) -> Result<String, String> {
             insurance_provider: Some("Blue Cross Blue Shield".to_string()),
             insurance_policy_number: Some("XYZ123456789".to_string()),
             insurance_group_number: Some("GRP-HENRY-001".to_string()),
+            status: None,
+            status_date: None,
+            row_version: None,
         },
         Patient {
             id: None,
@@ -243,6 +558,9 @@ fn db_seed_test_data(state: State<DbState>) -> Result<String, String> {
             insurance_provider: Some("Medicare".to_string()),
             insurance_policy_number: Some("1AB2-CD3-EF45".to_string()),
             insurance_group_number: None,
+            status: None,
+            status_date: None,
+            row_version: None,
         },
         Patient {
             id: None,
@@ -260,6 +578,9 @@ fn db_seed_test_data(state: State<DbState>) -> Result<String, String> {
             insurance_provider: Some("Aetna".to_string()),
             insurance_policy_number: Some("W12345678".to_string()),
             insurance_group_number: Some("0054321".to_string()),
+            status: None,
+            status_date: None,
+            row_version: None,
         },
         Patient {
             id: None,
@@ -277,6 +598,9 @@ fn db_seed_test_data(state: State<DbState>) -> Result<String, String> {
             insurance_provider: Some("Medicaid".to_string()),
             insurance_policy_number: Some("MED987654321".to_string()),
             insurance_group_number: None,
+            status: None,
+            status_date: None,
+            row_version: None,
         },
         Patient {
             id: None,
@@ -294,6 +618,9 @@ fn db_seed_test_data(state: State<DbState>) -> Result<String, String> {
             insurance_provider: Some("United Healthcare".to_string()),
             insurance_policy_number: Some("UHC-998877665".to_string()),
             insurance_group_number: Some("GRP-AUTO-500".to_string()),
+            status: None,
+            status_date: None,
+            row_version: None,
         },
         Patient {
             id: None,
@@ -311,6 +638,9 @@ fn db_seed_test_data(state: State<DbState>) -> Result<String, String> {
             insurance_provider: Some("Cigna".to_string()),
             insurance_policy_number: Some("CIG-456789012".to_string()),
             insurance_group_number: Some("GRP-LAW-200".to_string()),
+            status: None,
+            status_date: None,
+            row_version: None,
         },
         Patient {
             id: None,
@@ -328,6 +658,9 @@ fn db_seed_test_data(state: State<DbState>) -> Result<String, String> {
             insurance_provider: Some("Medicare".to_string()),
             insurance_policy_number: Some("1HJ7-KL8-MN90".to_string()),
             insurance_group_number: None,
+            status: None,
+            status_date: None,
+            row_version: None,
         },
         Patient {
             id: None,
@@ -345,6 +678,9 @@ fn db_seed_test_data(state: State<DbState>) -> Result<String, String> {
             insurance_provider: Some("Blue Cross Blue Shield".to_string()),
             insurance_policy_number: Some("BCB-112233445".to_string()),
             insurance_group_number: Some("GRP-TECH-100".to_string()),
+            status: None,
+            status_date: None,
+            row_version: None,
         },
         Patient {
             id: None,
@@ -362,6 +698,9 @@ fn db_seed_test_data(state: State<DbState>) -> Result<String, String> {
             insurance_provider: Some("Medicaid".to_string()),
             insurance_policy_number: Some("MED-223344556".to_string()),
             insurance_group_number: None,
+            status: None,
+            status_date: None,
+            row_version: None,
         },
         Patient {
             id: None,
@@ -379,6 +718,9 @@ fn db_seed_test_data(state: State<DbState>) -> Result<String, String> {
             insurance_provider: Some("Aetna".to_string()),
             insurance_policy_number: Some("AET-667788990".to_string()),
             insurance_group_number: Some("GRP-SMB-300".to_string()),
+            status: None,
+            status_date: None,
+            row_version: None,
         },
         Patient {
             id: None,
@@ -396,6 +738,9 @@ fn db_seed_test_data(state: State<DbState>) -> Result<String, String> {
             insurance_provider: Some("United Healthcare".to_string()),
             insurance_policy_number: Some("UHC-778899001".to_string()),
             insurance_group_number: Some("GRP-EDU-150".to_string()),
+            status: None,
+            status_date: None,
+            row_version: None,
         },
         Patient {
             id: None,
@@ -413,6 +758,9 @@ fn db_seed_test_data(state: State<DbState>) -> Result<String, String> {
             insurance_provider: Some("Medicare".to_string()),
             insurance_policy_number: Some("1QR2-ST3-UV45".to_string()),
             insurance_group_number: None,
+            status: None,
+            status_date: None,
+            row_version: None,
         },
         Patient {
             id: None,
@@ -430,6 +778,9 @@ fn db_seed_test_data(state: State<DbState>) -> Result<String, String> {
             insurance_provider: Some("Medicaid".to_string()),
             insurance_policy_number: Some("MED-445566778".to_string()),
             insurance_group_number: None,
+            status: None,
+            status_date: None,
+            row_version: None,
         },
         Patient {
             id: None,
@@ -447,6 +798,9 @@ fn db_seed_test_data(state: State<DbState>) -> Result<String, String> {
             insurance_provider: Some("Cigna".to_string()),
             insurance_policy_number: Some("CIG-889900112".to_string()),
             insurance_group_number: Some("GRP-ARCH-050".to_string()),
+            status: None,
+            status_date: None,
+            row_version: None,
         },
         Patient {
             id: None,
@@ -464,6 +818,9 @@ fn db_seed_test_data(state: State<DbState>) -> Result<String, String> {
             insurance_provider: Some("Medicare".to_string()),
             insurance_policy_number: Some("1WX2-YZ3-AB45".to_string()),
             insurance_group_number: None,
+            status: None,
+            status_date: None,
+            row_version: None,
         },
         Patient {
             id: None,
@@ -481,6 +838,9 @@ fn db_seed_test_data(state: State<DbState>) -> Result<String, String> {
             insurance_provider: Some("Blue Cross Blue Shield".to_string()),
             insurance_policy_number: Some("BCB-990011223".to_string()),
             insurance_group_number: Some("GRP-UNI-025".to_string()),
+            status: None,
+            status_date: None,
+            row_version: None,
         },
         Patient {
             id: None,
@@ -498,6 +858,9 @@ fn db_seed_test_data(state: State<DbState>) -> Result<String, String> {
             insurance_provider: Some("Aetna".to_string()),
             insurance_policy_number: Some("AET-112233445".to_string()),
             insurance_group_number: Some("GRP-RET-100".to_string()),
+            status: None,
+            status_date: None,
+            row_version: None,
         },
         Patient {
             id: None,
@@ -515,6 +878,9 @@ fn db_seed_test_data(state: State<DbState>) -> Result<String, String> {
             insurance_provider: Some("United Healthcare".to_string()),
             insurance_policy_number: Some("UHC-334455667".to_string()),
             insurance_group_number: Some("GRP-AUTO-250".to_string()),
+            status: None,
+            status_date: None,
+            row_version: None,
         },
         Patient {
             id: None,
@@ -532,261 +898,1371 @@ fn db_seed_test_data(state: State<DbState>) -> Result<String, String> {
             insurance_provider: Some("Blue Cross Blue Shield".to_string()),
             insurance_policy_number: Some("BCB-556677889".to_string()),
             insurance_group_number: Some("GRP-FAM-075".to_string()),
+            status: None,
+            status_date: None,
+            row_version: None,
         },
     ];
 
     let mut created = 0;
     for patient in &test_patients {
-        db::create_patient(&conn, patient).map_err(|e| e.to_string())?;
+        bmc_create(&conn, patient)?;
         created += 1;
     }
 
     Ok(format!("Seeded {} test patients", created))
 }
 
+#[tauri::command]
+fn db_get_schema_status(state: State<DbState>) -> Result<migrations::SchemaStatus, Error> {
+    let conn = state.reader()?;
+    migrations::get_schema_status(&conn).map_err(Error::from)
+}
+
 // ============ Patient Detail Page Commands ============
 
 #[tauri::command]
-fn db_get_patient_full(state: State<DbState>, id: i64) -> Result<Option<PatientFullData>, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::get_patient_full_data(&conn, id).map_err(|e| e.to_string())
+fn db_get_patient_full(state: State<DbState>, id: i64) -> Result<Option<PatientFullData>, Error> {
+    let user_id = state.authorize(Permission::ViewClinical)?;
+    let conn = state.reader()?;
+    let access = require_chart_access(&conn, user_id, id)?;
+    let mut data = db::get_patient_full_data(&conn, id)?;
+    if !state.can(Permission::ViewNotes) {
+        if let Some(data) = data.as_mut() {
+            data.encounters.iter_mut().for_each(redact_note);
+        }
+    }
+    state.audit(user_id, &AuditEvent::read("patients", Some(id), Some(id)).with_detail(json!({ "view": "full_chart", "access": access })))?;
+    Ok(data)
 }
 
 #[tauri::command]
-fn db_get_encounter(state: State<DbState>, encounter_id: i64) -> Result<Option<Encounter>, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::get_encounter_by_id(&conn, encounter_id).map_err(|e| e.to_string())
+fn db_get_encounter(state: State<DbState>, encounter_id: i64) -> Result<Option<Encounter>, Error> {
+    let user_id = state.authorize(Permission::ViewClinical)?;
+    let conn = state.reader()?;
+    let mut encounter = db::get_encounter_by_id(&conn, encounter_id)?;
+    if let Some(e) = &encounter {
+        require_chart_access(&conn, user_id, e.patient_id)?;
+    }
+    if !state.can(Permission::ViewNotes) {
+        encounter.iter_mut().for_each(redact_note);
+    }
+    let patient_id = encounter.as_ref().map(|e| e.patient_id);
+    state.audit(user_id, &AuditEvent::read("encounters", Some(encounter_id), patient_id))?;
+    Ok(encounter)
+}
+
+/// How the user may open this chart; a chart outside their care team needs break-the-glass first
+fn require_chart_access(conn: &rusqlite::Connection, user_id: i64, patient_id: i64) -> Result<ChartAccess, Error> {
+    chart_access::check_chart_access(conn, user_id, patient_id)?
+        .ok_or_else(|| {
+            Error::Restricted(format!(
+                "Patient {} is outside your care team; record a reason to open the chart in an emergency",
+                patient_id
+            ))
+        })
+}
+
+/// Refuse a write to row `id` of `E` unless its chart is open to the user
+///
+/// The patient comes from the stored row, never the payload. A row that is
+/// already gone is left for the write itself to report.
+fn require_row_access<E: Entity>(conn: &rusqlite::Connection, user_id: i64, id: i64) -> Result<(), Error> {
+    if let Some(patient_id) = bmc_owner::<E>(conn, id)? {
+        require_chart_access(conn, user_id, patient_id)?;
+    }
+    Ok(())
+}
+
+/// Blank the note body for roles that may see an encounter happened but not what was written
+fn redact_note(encounter: &mut Encounter) {
+    encounter.note_content = None;
+    encounter.addenda.clear();
 }
 
 #[tauri::command]
-fn db_seed_patient_detail_test_data(state: State<DbState>, patient_id: i64, force_reseed: Option<bool>) -> Result<String, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::seed_patient_detail_test_data(&conn, patient_id, force_reseed.unwrap_or(false)).map_err(|e| e.to_string())?;
+fn db_seed_patient_detail_test_data(state: State<DbState>, patient_id: i64, force_reseed: Option<bool>) -> Result<String, Error> {
+    state.authorize(Permission::ManagePatientRecords)?;
+    let conn = state.writer()?;
+    db::seed_patient_detail_test_data(&conn, patient_id, force_reseed.unwrap_or(false))?;
     Ok(format!("Seeded detail data for patient {}", patient_id))
 }
 
 #[tauri::command]
-fn db_create_encounter(state: State<DbState>, encounter: Encounter) -> Result<i64, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::create_encounter(&conn, &encounter).map_err(|e| e.to_string())
+fn db_create_encounter(state: State<DbState>, encounter: Encounter) -> Result<i64, Error> {
+    let user_id = state.authorize(Permission::WriteNotes)?;
+    let conn = state.writer()?;
+    require_chart_access(&conn, user_id, encounter.patient_id)?;
+    let tx = conn.unchecked_transaction()?;
+    let id = db::create_encounter(&tx, &encounter)?;
+    note_history::record_revision(&tx, id, user_id, None)?;
+    audit::record_change(&tx, user_id, AuditAction::Create, "encounters", id, None)?;
+    model_changed(&tx, "encounters", ModelAction::Create, id, Some(encounter.patient_id));
+    tx.commit()?;
+    Ok(id)
+}
+
+#[tauri::command]
+fn db_update_encounter(state: State<DbState>, encounter: Encounter) -> Result<i64, Error> {
+    let user_id = state.authorize(Permission::WriteNotes)?;
+    let id = encounter.id.ok_or_else(|| Error::invalid_field("id", "Encounter id is required"))?;
+    concurrency::require_version(encounter.row_version)?;
+    let conn = state.writer()?;
+    // The signed check, the edit and its revision stand or fall together
+    let tx = conn.unchecked_transaction()?;
+    let existing = signable_encounter(&tx, user_id, id)?;
+    if existing.signed_at.is_some() {
+        return Err(Error::invalid("This note is signed and can no longer be edited; add an addendum instead"));
+    }
+    let before = audit::snapshot(&tx, "encounters", id)?;
+    if !db::update_encounter(&tx, &encounter)? {
+        return Err(concurrency::conflict(before));
+    }
+    note_history::record_revision(&tx, id, user_id, None)?;
+    audit::record_change(&tx, user_id, AuditAction::Update, "encounters", id, before)?;
+    model_changed(&tx, "encounters", ModelAction::Update, id, Some(existing.patient_id));
+    tx.commit()?;
+    concurrency::stored_version(&conn, "encounters", id).map_err(Error::from)
+}
+
+/// The encounter, provided it exists and its chart is open to the user
+fn signable_encounter(conn: &rusqlite::Connection, user_id: i64, encounter_id: i64) -> Result<Encounter, Error> {
+    let encounter = db::get_encounter_by_id(conn, encounter_id)?
+        .ok_or_else(|| Error::not_found("Encounter not found"))?;
+    require_chart_access(conn, user_id, encounter.patient_id)?;
+    Ok(encounter)
+}
+
+// ============ Note Signing Commands ============
+
+#[tauri::command]
+fn db_sign_encounter(state: State<DbState>, encounter_id: i64) -> Result<Encounter, Error> {
+    let user_id = state.authorize(Permission::WriteNotes)?;
+    let conn = state.writer()?;
+    let encounter = signable_encounter(&conn, user_id, encounter_id)?;
+    if encounter.signed_at.is_some() {
+        return Err(Error::invalid("This note is already signed"));
+    }
+    if encounter.note_content.as_deref().is_none_or(|n| n.trim().is_empty()) {
+        return Err(Error::invalid("Cannot sign an empty note"));
+    }
+    let tx = conn.unchecked_transaction()?;
+    let before = audit::snapshot(&tx, "encounters", encounter_id)?;
+    db::sign_encounter(&tx, encounter_id, user_id)?;
+    audit::record_change(&tx, user_id, AuditAction::Update, "encounters", encounter_id, before)?;
+    model_changed(&tx, "encounters", ModelAction::Update, encounter_id, Some(encounter.patient_id));
+    tx.commit()?;
+    signable_encounter(&conn, user_id, encounter_id)
+}
+
+#[tauri::command]
+fn db_cosign_encounter(state: State<DbState>, encounter_id: i64) -> Result<Encounter, Error> {
+    let user_id = state.authorize(Permission::CosignNotes)?;
+    let conn = state.writer()?;
+    let encounter = signable_encounter(&conn, user_id, encounter_id)?;
+    if encounter.signed_at.is_none() {
+        return Err(Error::invalid("The note has to be signed before it can be co-signed"));
+    }
+    if encounter.cosigned_at.is_some() {
+        return Err(Error::invalid("This note is already co-signed"));
+    }
+    if encounter.signed_by == Some(user_id) {
+        return Err(Error::invalid("You cannot co-sign your own note"));
+    }
+    let tx = conn.unchecked_transaction()?;
+    let before = audit::snapshot(&tx, "encounters", encounter_id)?;
+    db::cosign_encounter(&tx, encounter_id, user_id)?;
+    audit::record_change(&tx, user_id, AuditAction::Update, "encounters", encounter_id, before)?;
+    model_changed(&tx, "encounters", ModelAction::Update, encounter_id, Some(encounter.patient_id));
+    tx.commit()?;
+    signable_encounter(&conn, user_id, encounter_id)
+}
+
+/// Append a dated correction or late entry to a signed note
+#[tauri::command]
+fn db_create_addendum(state: State<DbState>, encounter_id: i64, content: String) -> Result<i64, Error> {
+    let user_id = state.authorize(Permission::WriteNotes)?;
+    if content.trim().is_empty() {
+        return Err(Error::invalid_field("content", "Addendum cannot be empty"));
+    }
+    let conn = state.writer()?;
+    let encounter = signable_encounter(&conn, user_id, encounter_id)?;
+    if encounter.signed_at.is_none() {
+        return Err(Error::invalid("The note is not signed yet; edit it directly"));
+    }
+    let tx = conn.unchecked_transaction()?;
+    let id = db::create_addendum(&tx, encounter_id, user_id, content.trim())?;
+    audit::record_change(&tx, user_id, AuditAction::Create, "encounter_addenda", id, None)?;
+    model_changed(&tx, "encounter_addenda", ModelAction::Create, id, Some(encounter.patient_id));
+    tx.commit()?;
+    Ok(id)
+}
+
+#[tauri::command]
+fn db_get_addenda(state: State<DbState>, encounter_id: i64) -> Result<Vec<EncounterAddendum>, Error> {
+    let user_id = state.authorize(Permission::ViewNotes)?;
+    let conn = state.reader()?;
+    let encounter = signable_encounter(&conn, user_id, encounter_id)?;
+    let addenda = db::get_addenda_for_encounter(&conn, encounter_id)?;
+    state.audit(user_id, &AuditEvent::read("encounter_addenda", None, Some(encounter.patient_id)).with_detail(json!({ "encounter_id": encounter_id })))?;
+    Ok(addenda)
+}
+
+// ============ Note Revision Commands ============
+
+#[tauri::command]
+fn db_get_encounter_revisions(state: State<DbState>, encounter_id: i64) -> Result<Vec<EncounterRevision>, Error> {
+    let user_id = state.authorize(Permission::ViewNotes)?;
+    let conn = state.reader()?;
+    let encounter = signable_encounter(&conn, user_id, encounter_id)?;
+    let revisions = note_history::get_revisions(&conn, encounter_id)?;
+    state.audit(user_id, &AuditEvent::read("encounter_revisions", None, Some(encounter.patient_id)).with_detail(json!({ "encounter_id": encounter_id })))?;
+    Ok(revisions)
 }
 
+/// The revision, provided it belongs to `encounter_id`
+fn revision_of(conn: &rusqlite::Connection, encounter_id: i64, revision_id: i64) -> Result<EncounterRevision, Error> {
+    note_history::get_revision(conn, revision_id)?
+        .filter(|r| r.encounter_id == encounter_id)
+        .ok_or_else(|| Error::not_found(format!("Revision {} does not belong to this encounter", revision_id)))
+}
+
+/// What changed from `from_revision_id` to `to_revision_id`
 #[tauri::command]
-fn db_update_encounter(state: State<DbState>, encounter: Encounter) -> Result<(), String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::update_encounter(&conn, &encounter).map_err(|e| e.to_string())
+fn db_diff_encounter_revisions(state: State<DbState>, encounter_id: i64, from_revision_id: i64, to_revision_id: i64) -> Result<RevisionDiff, Error> {
+    let user_id = state.authorize(Permission::ViewNotes)?;
+    let conn = state.reader()?;
+    let encounter = signable_encounter(&conn, user_id, encounter_id)?;
+    let from = revision_of(&conn, encounter_id, from_revision_id)?;
+    let to = revision_of(&conn, encounter_id, to_revision_id)?;
+    state.audit(user_id, &AuditEvent::read("encounter_revisions", None, Some(encounter.patient_id)).with_detail(json!({ "encounter_id": encounter_id, "diff": [from.id, to.id] })))?;
+    Ok(note_history::diff_revisions(&from, &to))
+}
+
+/// Bring back an earlier revision's text; the restore is itself recorded as a new revision
+#[tauri::command]
+fn db_restore_encounter_revision(state: State<DbState>, encounter_id: i64, revision_id: i64) -> Result<Encounter, Error> {
+    let user_id = state.authorize(Permission::WriteNotes)?;
+    let conn = state.writer()?;
+    let encounter = signable_encounter(&conn, user_id, encounter_id)?;
+    if encounter.signed_at.is_some() {
+        return Err(Error::invalid("This note is signed and can no longer be edited; add an addendum instead"));
+    }
+    let revision = revision_of(&conn, encounter_id, revision_id)?;
+
+    let tx = conn.unchecked_transaction()?;
+    let before = audit::snapshot(&tx, "encounters", encounter_id)?;
+    note_history::restore_revision(&tx, &revision)?;
+    note_history::record_revision(&tx, encounter_id, user_id, Some(revision.id))?;
+    audit::record_change(&tx, user_id, AuditAction::Update, "encounters", encounter_id, before)?;
+    tx.commit()?;
+    model_changed(&conn, "encounters", ModelAction::Update, encounter_id, Some(encounter.patient_id));
+
+    signable_encounter(&conn, user_id, encounter_id)
 }
 
 // ============ User/Provider Commands ============
 
 #[tauri::command]
-fn db_get_current_user(state: State<DbState>) -> Result<Option<UserFullData>, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::get_current_user_full_data(&conn).map_err(|e| e.to_string())
+fn db_get_current_user(state: State<DbState>) -> Result<Option<UserFullData>, Error> {
+    let user_id = state.acting_user()?;
+    let conn = state.writer()?;
+    db::get_user_full_data(&conn, user_id).map_err(Error::from)
+}
+
+#[tauri::command]
+fn db_get_user_full(state: State<DbState>, id: i64) -> Result<Option<UserFullData>, Error> {
+    // Another user's record and settings are for administrators only
+    if id != state.acting_user()? {
+        state.authorize(Permission::ManageUsers)?;
+    }
+    let conn = state.writer()?;
+    db::get_user_full_data(&conn, id).map_err(Error::from)
+}
+
+#[tauri::command]
+fn db_update_user(state: State<DbState>, user: User) -> Result<i64, Error> {
+    let user_id = state.acting_user()?;
+    if user.id != Some(user_id) {
+        return Err(Error::forbidden("You can only edit your own profile"));
+    }
+    concurrency::require_version(user.row_version)?;
+    let conn = state.writer()?;
+    let tx = conn.unchecked_transaction()?;
+    let before = audit::snapshot(&tx, "users", user_id)?;
+    if !db::update_user(&tx, &user)? {
+        let current = db::get_user_by_id(&tx, user_id)?;
+        return Err(concurrency::conflict(current));
+    }
+    audit::record_change(&tx, user_id, AuditAction::Update, "users", user_id, before)?;
+    model_changed(&tx, "users", ModelAction::Update, user_id, None);
+    tx.commit()?;
+    concurrency::stored_version(&conn, "users", user_id).map_err(Error::from)
+}
+
+#[tauri::command]
+fn db_update_user_settings(state: State<DbState>, settings: UserSettings) -> Result<i64, Error> {
+    let user_id = state.acting_user()?;
+    if settings.user_id != user_id {
+        return Err(Error::forbidden("You can only change your own settings"));
+    }
+    concurrency::require_version(settings.row_version)?;
+    let conn = state.writer()?;
+    let tx = conn.unchecked_transaction()?;
+    let Some(id) = db::get_settings_for_user(&tx, user_id)?.and_then(|current| current.id) else {
+        return Err(Error::not_found("Settings not found"));
+    };
+    let before = audit::snapshot(&tx, "user_settings", id)?;
+    if !db::update_user_settings(&tx, &settings)? {
+        let current = db::get_settings_for_user(&tx, user_id)?;
+        return Err(concurrency::conflict(current));
+    }
+    audit::record_change(&tx, user_id, AuditAction::Update, "user_settings", id, before)?;
+    model_changed(&tx, "user_settings", ModelAction::Update, id, None);
+    tx.commit()?;
+    concurrency::stored_version(&conn, "user_settings", id).map_err(Error::from)
+}
+
+// ============ User Administration Commands ============
+
+#[tauri::command]
+fn db_get_users(state: State<DbState>) -> Result<Vec<User>, Error> {
+    state.authorize(Permission::ManageUsers)?;
+    let conn = state.reader()?;
+    db::get_all_users(&conn).map_err(Error::from)
+}
+
+/// Create another account; the role comes from `user.role`
+#[tauri::command]
+fn db_create_user(state: State<DbState>, user: User, password: String) -> Result<i64, Error> {
+    let user_id = state.authorize(Permission::ManageUsers)?;
+    auth::validate_password(&password)?;
+    let conn = state.writer()?;
+    if db::get_user_by_username(&conn, &user.username)?.is_some() {
+        return Err(Error::invalid_field("username", format!("Username '{}' is already taken", user.username)));
+    }
+    let user = User {
+        id: None,
+        password_hash: auth::hash_password(&password)?,
+        ..user
+    };
+    let tx = conn.unchecked_transaction()?;
+    let id = db::create_user(&tx, &user)?;
+    db::get_settings_for_user(&tx, id)?;
+    audit::record_change(&tx, user_id, AuditAction::Create, "users", id, None)?;
+    model_changed(&tx, "users", ModelAction::Create, id, None);
+    tx.commit()?;
+    Ok(id)
+}
+
+#[tauri::command]
+fn db_set_user_role(state: State<DbState>, user_id: i64, role: Role) -> Result<(), Error> {
+    let acting_id = state.authorize(Permission::ManageUsers)?;
+    let conn = state.writer()?;
+    let user = db::get_user_by_id(&conn, user_id)?
+        .ok_or_else(|| Error::not_found("User not found"))?;
+    if user.role == Role::Admin && role != Role::Admin && db::count_admins(&conn)? <= 1 {
+        return Err(Error::invalid("Cannot remove the last administrator"));
+    }
+    let tx = conn.unchecked_transaction()?;
+    let before = audit::snapshot(&tx, "users", user_id)?;
+    db::set_user_role(&tx, user_id, role)?;
+    audit::record_change(&tx, acting_id, AuditAction::Update, "users", user_id, before)?;
+    model_changed(&tx, "users", ModelAction::Update, user_id, None);
+    tx.commit()?;
+    state.refresh_session_role(user_id, role);
+    Ok(())
 }
 
+// ============ Two-Factor Commands ============
+
+/// Start enrollment: a new pending secret, shown once as a QR code and as text
 #[tauri::command]
-fn db_get_user_full(state: State<DbState>, id: i64) -> Result<Option<UserFullData>, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::get_user_full_data(&conn, id).map_err(|e| e.to_string())
+fn db_begin_two_factor_enrollment(state: State<DbState>) -> Result<TwoFactorEnrollment, Error> {
+    let user_id = state.acting_user()?;
+    let conn = state.writer()?;
+    let user = db::get_user_by_id(&conn, user_id)?
+        .ok_or_else(|| Error::not_found("User not found"))?;
+    if two_factor::get_state(&conn, user_id)?.enabled {
+        return Err(Error::invalid("Two-factor authentication is already enabled"));
+    }
+
+    let secret = two_factor::generate_secret();
+    two_factor::begin_enrollment(&conn, user_id, &secret)?;
+    let otpauth_uri = two_factor::otpauth_uri(&secret, &user.username);
+    Ok(TwoFactorEnrollment {
+        qr_svg: two_factor::qr_svg(&otpauth_uri)?,
+        secret,
+        otpauth_uri,
+    })
 }
 
+/// Turn two-factor on once the authenticator app produces a valid code; returns the recovery codes
 #[tauri::command]
-fn db_update_user(state: State<DbState>, user: User) -> Result<(), String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::update_user(&conn, &user).map_err(|e| e.to_string())
+fn db_confirm_two_factor_enrollment(state: State<DbState>, code: String) -> Result<Vec<String>, Error> {
+    let user_id = state.acting_user()?;
+    let conn = state.writer()?;
+    let pending = two_factor::get_state(&conn, user_id)?;
+    let secret = match (pending.enabled, pending.secret) {
+        (false, Some(secret)) => secret,
+        (true, _) => return Err(Error::invalid("Two-factor authentication is already enabled")),
+        (false, None) => return Err(Error::invalid("Start two-factor setup first")),
+    };
+    let step = two_factor::verify_code(&secret, &code).ok_or_else(|| Error::invalid_field("code", "Invalid code; check the time on your device and try again"))?;
+
+    let tx = conn.unchecked_transaction()?;
+    two_factor::set_enabled(&tx, user_id, step)?;
+    let codes = two_factor::generate_recovery_codes(&tx, user_id)?;
+    let event = AuditEvent::session(AuditAction::Update, user_id).with_detail(json!({ "two_factor_enabled": true }));
+    audit::record(&tx, Some(user_id), &event)?;
+    tx.commit()?;
+    Ok(codes)
 }
 
+/// Turn two-factor off; needs the password and a current code (or a recovery code)
 #[tauri::command]
-fn db_update_user_settings(state: State<DbState>, settings: UserSettings) -> Result<(), String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::update_user_settings(&conn, &settings).map_err(|e| e.to_string())
+fn db_disable_two_factor(state: State<DbState>, password: String, code: String) -> Result<(), Error> {
+    let user_id = state.acting_user()?;
+    let conn = state.writer()?;
+    let user = db::get_user_by_id(&conn, user_id)?
+        .ok_or_else(|| Error::not_found("User not found"))?;
+    if !two_factor::get_state(&conn, user_id)?.enabled {
+        return Err(Error::invalid("Two-factor authentication is not enabled"));
+    }
+    if !auth::verify_password(&password, &user.password_hash) {
+        return Err(Error::invalid_field("password", "Password is incorrect"));
+    }
+    if two_factor::verify_second_factor(&conn, user_id, &code)?.is_none() {
+        return Err(Error::invalid_field("code", "Invalid two-factor code"));
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    two_factor::disable(&tx, user_id)?;
+    let event = AuditEvent::session(AuditAction::Update, user_id).with_detail(json!({ "two_factor_enabled": false }));
+    audit::record(&tx, Some(user_id), &event)?;
+    tx.commit().map_err(Error::from)
 }
 
 #[tauri::command]
-fn db_update_password(state: State<DbState>, user_id: i64, new_password_hash: String) -> Result<(), String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::update_user_password(&conn, user_id, &new_password_hash).map_err(|e| e.to_string())
+fn db_update_password(state: State<DbState>, current_password: String, new_password: String) -> Result<(), Error> {
+    let user_id = state.acting_user()?;
+    auth::validate_password(&new_password)?;
+    let conn = state.writer()?;
+    let user = db::get_user_by_id(&conn, user_id)?
+        .ok_or_else(|| Error::not_found("User not found"))?;
+    // The current password is guessed against the same lockout as a sign-in
+    match auth::check_not_locked(&conn, user_id) {
+        Ok(()) => {}
+        Err(auth::LoginError::Database(e)) => return Err(e.into()),
+        Err(e) => return Err(Error::forbidden(e.to_string())),
+    }
+    if !auth::verify_password(&current_password, &user.password_hash) {
+        auth::record_failed_attempt(&conn, user_id)?;
+        return Err(Error::invalid_field("current_password", "Current password is incorrect"));
+    }
+    let hash = auth::hash_password(&new_password)?;
+    let tx = conn.unchecked_transaction()?;
+    db::update_user_password(&tx, user_id, &hash)?;
+    // The hash itself never goes into the log, so record that it changed
+    let event = AuditEvent::session(AuditAction::Update, user_id).with_detail(json!({ "password_changed": true }));
+    audit::record(&tx, Some(user_id), &event)?;
+    tx.commit().map_err(Error::from)
 }
 
 #[tauri::command]
-fn db_seed_user_data(state: State<DbState>) -> Result<String, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::seed_user_data(&conn).map_err(|e| e.to_string())?;
+fn db_seed_user_data(state: State<DbState>) -> Result<String, Error> {
+    state.acting_user()?;
+    let conn = state.writer()?;
+    db::seed_user_data(&conn)?;
     Ok("User data seeded successfully".to_string())
 }
 
 // ============ Patient List Commands ============
 
+/// Refuse list `list_id` unless it is the user's own; administrators may manage anyone's
+///
+/// A list that does not exist is left for the command itself to report.
+fn require_list_owner(state: &DbState, conn: &rusqlite::Connection, user_id: i64, list_id: i64) -> Result<(), Error> {
+    match db::get_patient_list_by_id(conn, list_id)? {
+        Some(list) if list.user_id != user_id => require_list_user(state, user_id, list.user_id),
+        _ => Ok(()),
+    }
+}
+
+fn require_list_user(state: &DbState, user_id: i64, owner_id: i64) -> Result<(), Error> {
+    if owner_id != user_id && !state.can(Permission::ManageUsers) {
+        return Err(Error::forbidden("That patient list belongs to another user"));
+    }
+    Ok(())
+}
+
 #[tauri::command]
-fn db_get_patient_lists(state: State<DbState>, user_id: i64) -> Result<Vec<db::PatientList>, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::get_patient_lists_for_user(&conn, user_id).map_err(|e| e.to_string())
+fn db_get_patient_lists(state: State<DbState>) -> Result<Vec<db::PatientList>, Error> {
+    let user_id = state.authorize(Permission::ViewPatients)?;
+    let conn = state.reader()?;
+    db::get_patient_lists_for_user(&conn, user_id).map_err(Error::from)
 }
 
 #[tauri::command]
-fn db_get_patient_list(state: State<DbState>, list_id: i64) -> Result<Option<db::PatientListWithPatients>, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::get_patient_list_with_patients(&conn, list_id).map_err(|e| e.to_string())
+fn db_get_patient_list(state: State<DbState>, list_id: i64) -> Result<Option<db::PatientListWithPatients>, Error> {
+    let user_id = state.authorize(Permission::ViewPatients)?;
+    let conn = state.reader()?;
+    require_list_owner(&state, &conn, user_id, list_id)?;
+    let list = db::get_patient_list_with_patients(&conn, list_id)?;
+    let count = list.as_ref().map(|l| l.patients.len()).unwrap_or(0);
+    state.audit(user_id, &AuditEvent::read("patient_lists", Some(list_id), None).with_detail(json!({ "count": count })))?;
+    Ok(list)
 }
 
 #[tauri::command]
-fn db_create_patient_list(state: State<DbState>, list: db::PatientList) -> Result<i64, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::create_patient_list(&conn, &list).map_err(|e| e.to_string())
+fn db_create_patient_list(state: State<DbState>, list: db::PatientList) -> Result<i64, Error> {
+    let user_id = state.authorize(Permission::ViewPatients)?;
+    require_list_user(&state, user_id, list.user_id)?;
+    let conn = state.writer()?;
+    let id = db::create_patient_list(&conn, &list)?;
+    model_changed(&conn, "patient_lists", ModelAction::Create, id, None);
+    Ok(id)
 }
 
 #[tauri::command]
-fn db_update_patient_list(state: State<DbState>, list: db::PatientList) -> Result<(), String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::update_patient_list(&conn, &list).map_err(|e| e.to_string())
+fn db_update_patient_list(state: State<DbState>, list: db::PatientList) -> Result<i64, Error> {
+    let user_id = state.authorize(Permission::ViewPatients)?;
+    let id = list.id.ok_or_else(|| Error::invalid_field("id", "List id is required"))?;
+    concurrency::require_version(list.row_version)?;
+    let conn = state.writer()?;
+    require_list_owner(&state, &conn, user_id, id)?;
+    if !db::update_patient_list(&conn, &list)? {
+        let current = db::get_patient_list_by_id(&conn, id)?;
+        return Err(concurrency::conflict(current));
+    }
+    model_changed(&conn, "patient_lists", ModelAction::Update, id, None);
+    concurrency::stored_version(&conn, "patient_lists", id).map_err(Error::from)
 }
 
 #[tauri::command]
-fn db_delete_patient_list(state: State<DbState>, list_id: i64) -> Result<(), String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::delete_patient_list(&conn, list_id).map_err(|e| e.to_string())
+fn db_delete_patient_list(state: State<DbState>, list_id: i64) -> Result<(), Error> {
+    let user_id = state.authorize(Permission::ViewPatients)?;
+    let conn = state.writer()?;
+    require_list_owner(&state, &conn, user_id, list_id)?;
+    db::delete_patient_list(&conn, list_id)?;
+    model_changed(&conn, "patient_lists", ModelAction::Delete, list_id, None);
+    Ok(())
 }
 
 #[tauri::command]
-fn db_get_patients_in_list(state: State<DbState>, list_id: i64) -> Result<Vec<db::Patient>, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::get_patients_in_list(&conn, list_id).map_err(|e| e.to_string())
+fn db_get_patients_in_list(state: State<DbState>, list_id: i64) -> Result<Vec<db::Patient>, Error> {
+    let user_id = state.authorize(Permission::ViewPatients)?;
+    let conn = state.reader()?;
+    require_list_owner(&state, &conn, user_id, list_id)?;
+    let patients = db::get_patients_in_list(&conn, list_id)?;
+    state.audit(user_id, &AuditEvent::read("patient_lists", Some(list_id), None).with_detail(json!({ "count": patients.len() })))?;
+    Ok(patients)
 }
 
 #[tauri::command]
-fn db_add_patient_to_list(state: State<DbState>, list_id: i64, patient_id: i64, notes: Option<String>) -> Result<i64, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::add_patient_to_list(&conn, list_id, patient_id, notes.as_deref()).map_err(|e| e.to_string())
+fn db_add_patient_to_list(state: State<DbState>, list_id: i64, patient_id: i64, notes: Option<String>) -> Result<i64, Error> {
+    let user_id = state.authorize(Permission::ViewPatients)?;
+    let conn = state.writer()?;
+    require_list_owner(&state, &conn, user_id, list_id)?;
+    let id = db::add_patient_to_list(&conn, list_id, patient_id, notes.as_deref())?;
+    model_changed(&conn, "patient_lists", ModelAction::Update, list_id, Some(patient_id));
+    let event = AuditEvent {
+        action: AuditAction::Create,
+        entity_type: "patient_list_members",
+        entity_id: None,
+        patient_id: Some(patient_id),
+        detail: Some(json!({ "list_id": list_id })),
+    };
+    audit::record(&conn, Some(user_id), &event)?;
+    Ok(id)
 }
 
 #[tauri::command]
-fn db_remove_patient_from_list(state: State<DbState>, list_id: i64, patient_id: i64) -> Result<(), String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::remove_patient_from_list(&conn, list_id, patient_id).map_err(|e| e.to_string())
+fn db_remove_patient_from_list(state: State<DbState>, list_id: i64, patient_id: i64) -> Result<(), Error> {
+    let user_id = state.authorize(Permission::ViewPatients)?;
+    let conn = state.writer()?;
+    require_list_owner(&state, &conn, user_id, list_id)?;
+    db::remove_patient_from_list(&conn, list_id, patient_id)?;
+    model_changed(&conn, "patient_lists", ModelAction::Update, list_id, Some(patient_id));
+    let event = AuditEvent {
+        action: AuditAction::Delete,
+        entity_type: "patient_list_members",
+        entity_id: None,
+        patient_id: Some(patient_id),
+        detail: Some(json!({ "list_id": list_id })),
+    };
+    audit::record(&conn, Some(user_id), &event).map_err(Error::from)
 }
 
 #[tauri::command]
-fn db_update_list_columns(state: State<DbState>, list_id: i64, columns: Vec<db::PatientListColumn>) -> Result<(), String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::update_list_columns(&conn, list_id, &columns).map_err(|e| e.to_string())
+fn db_update_list_columns(state: State<DbState>, list_id: i64, columns: Vec<db::PatientListColumn>) -> Result<(), Error> {
+    let user_id = state.authorize(Permission::ViewPatients)?;
+    let conn = state.writer()?;
+    require_list_owner(&state, &conn, user_id, list_id)?;
+    db::update_list_columns(&conn, list_id, &columns)?;
+    model_changed(&conn, "patient_lists", ModelAction::Update, list_id, None);
+    Ok(())
 }
 
 #[tauri::command]
-fn db_seed_patient_lists(state: State<DbState>, user_id: i64) -> Result<String, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::seed_patient_lists(&conn, user_id).map_err(|e| e.to_string())?;
+fn db_seed_patient_lists(state: State<DbState>) -> Result<String, Error> {
+    let user_id = state.authorize(Permission::ViewPatients)?;
+    let conn = state.writer()?;
+    db::seed_patient_lists(&conn, user_id)?;
     Ok("Patient lists seeded successfully".to_string())
 }
 
+// ============ Audit Log Commands ============
+
+#[tauri::command]
+fn db_get_audit_log(state: State<DbState>, filter: AuditFilter) -> Result<Vec<AuditEntry>, Error> {
+    state.authorize(Permission::ViewAuditLog)?;
+    let conn = state.reader()?;
+    audit::query_audit_log(&conn, &filter).map_err(Error::from)
+}
+
+/// Distinct charts that appear in a set of search results, for the audit entry
+fn search_result_patients(results: &[SearchResult]) -> Vec<i64> {
+    let mut ids: Vec<i64> = results.iter().filter_map(|r| r.patient_id).collect();
+    ids.sort_unstable();
+    ids.dedup();
+    ids
+}
+
+/// Drop hits the user may not see
+///
+/// Encounter hits can come from note text, so they go for roles that cannot
+/// read notes. Clinical hits from charts outside the user's care team go too;
+/// the patient's name still shows, as it would in the patient list.
+fn restrict_search_results(state: &DbState, conn: &rusqlite::Connection, user_id: i64, results: &mut Vec<SearchResult>) -> Result<(), Error> {
+    if !state.can(Permission::ViewNotes) {
+        results.retain(|r| r.result_type != "encounter");
+    }
+    let mut kept = Vec::with_capacity(results.len());
+    for result in results.drain(..) {
+        let visible = match result.patient_id {
+            Some(patient_id) if result.result_type != "patient" => {
+                chart_access::check_chart_access(conn, user_id, patient_id)?.is_some()
+            }
+            _ => true,
+        };
+        if visible {
+            kept.push(result);
+        }
+    }
+    *results = kept;
+    Ok(())
+}
+
+// ============ Care Team / Break-the-Glass Commands ============
+
+#[tauri::command]
+fn db_get_care_team(state: State<DbState>, patient_id: i64) -> Result<Vec<CareTeamMember>, Error> {
+    let user_id = state.authorize(Permission::ViewPatients)?;
+    let conn = state.reader()?;
+    // Who treats a restricted patient is itself part of the chart
+    require_chart_access(&conn, user_id, patient_id)?;
+    chart_access::get_care_team(&conn, patient_id).map_err(Error::from)
+}
+
+#[tauri::command]
+fn db_add_care_team_member(state: State<DbState>, patient_id: i64, member_id: i64) -> Result<(), Error> {
+    let user_id = state.authorize(Permission::EditPatients)?;
+    if member_id == user_id && !state.can(Permission::ManageUsers) {
+        return Err(Error::forbidden("Ask an administrator or a member of the care team to add you"));
+    }
+    let conn = state.writer()?;
+    require_care_team_manager(&state, &conn, user_id, patient_id)?;
+    let tx = conn.unchecked_transaction()?;
+    chart_access::add_care_team_member(&tx, patient_id, member_id, user_id)?;
+    model_changed(&tx, "patient_care_team", ModelAction::Create, member_id, Some(patient_id));
+    let event = AuditEvent {
+        action: AuditAction::Create,
+        entity_type: "patient_care_team",
+        entity_id: Some(member_id),
+        patient_id: Some(patient_id),
+        detail: Some(json!({ "user_id": member_id })),
+    };
+    audit::record(&tx, Some(user_id), &event)?;
+    tx.commit().map_err(Error::from)
+}
+
+#[tauri::command]
+fn db_remove_care_team_member(state: State<DbState>, patient_id: i64, member_id: i64) -> Result<(), Error> {
+    let user_id = state.authorize(Permission::EditPatients)?;
+    let conn = state.writer()?;
+    require_care_team_manager(&state, &conn, user_id, patient_id)?;
+    let tx = conn.unchecked_transaction()?;
+    chart_access::remove_care_team_member(&tx, patient_id, member_id)?;
+    model_changed(&tx, "patient_care_team", ModelAction::Delete, member_id, Some(patient_id));
+    let event = AuditEvent {
+        action: AuditAction::Delete,
+        entity_type: "patient_care_team",
+        entity_id: Some(member_id),
+        patient_id: Some(patient_id),
+        detail: Some(json!({ "user_id": member_id })),
+    };
+    audit::record(&tx, Some(user_id), &event)?;
+    tx.commit().map_err(Error::from)
+}
+
+/// Refuse a care team change unless the user is an administrator or already on the team
+///
+/// Anyone else would be able to put themselves on a restricted chart without
+/// breaking the glass, or take its treating clinicians off it.
+fn require_care_team_manager(state: &DbState, conn: &rusqlite::Connection, user_id: i64, patient_id: i64) -> Result<(), Error> {
+    if state.can(Permission::ManageUsers) {
+        return Ok(());
+    }
+    match chart_access::check_chart_access(conn, user_id, patient_id)? {
+        Some(ChartAccess::CareTeam) => Ok(()),
+        _ => Err(Error::forbidden("Only an administrator or a member of the care team can change it")),
+    }
+}
+
+/// Emergency access to a chart outside the user's care team, for `GRANT_HOURS`
+#[tauri::command]
+fn db_break_glass(state: State<DbState>, patient_id: i64, reason: String) -> Result<BreakGlassGrant, Error> {
+    let user_id = state.authorize(Permission::ViewClinical)?;
+    chart_access::validate_reason(&reason)?;
+    let conn = state.writer()?;
+    bmc_get::<Patient>(&conn, patient_id)?
+        .ok_or_else(|| Error::not_found("Patient not found"))?;
+    match chart_access::check_chart_access(&conn, user_id, patient_id)? {
+        Some(ChartAccess::BreakGlass) | None => {}
+        Some(_) => return Err(Error::invalid("You already have access to this chart")),
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    let id = chart_access::grant(&tx, user_id, patient_id, &reason)?;
+    let event = AuditEvent {
+        action: AuditAction::BreakGlass,
+        entity_type: "break_glass_access",
+        entity_id: Some(id),
+        patient_id: Some(patient_id),
+        detail: Some(json!({ "reason": reason.trim() })),
+    };
+    audit::record(&tx, Some(user_id), &event)?;
+    tx.commit()?;
+
+    chart_access::get_grant(&conn, id)?
+        .ok_or_else(|| Error::internal("Grant was not saved"))
+}
+
+/// Break-the-glass events for administrator review; unreviewed only unless `include_reviewed`
+#[tauri::command]
+fn db_get_break_glass_queue(state: State<DbState>, include_reviewed: Option<bool>) -> Result<Vec<BreakGlassGrant>, Error> {
+    state.authorize(Permission::ViewAuditLog)?;
+    let conn = state.reader()?;
+    chart_access::get_review_queue(&conn, include_reviewed.unwrap_or(false)).map_err(Error::from)
+}
+
+#[tauri::command]
+fn db_review_break_glass(state: State<DbState>, grant_id: i64, note: Option<String>) -> Result<(), Error> {
+    let user_id = state.authorize(Permission::ViewAuditLog)?;
+    let conn = state.writer()?;
+    let grant = chart_access::get_grant(&conn, grant_id)?
+        .ok_or_else(|| Error::not_found("Break-the-glass event not found"))?;
+    if grant.reviewed_at.is_some() {
+        return Err(Error::invalid("This event has already been reviewed"));
+    }
+    chart_access::mark_reviewed(&conn, grant_id, user_id, note.as_deref())?;
+    let event = AuditEvent {
+        action: AuditAction::Update,
+        entity_type: "break_glass_access",
+        entity_id: Some(grant_id),
+        patient_id: Some(grant.patient_id),
+        detail: Some(json!({ "reviewed": true, "note": note })),
+    };
+    audit::record(&conn, Some(user_id), &event).map_err(Error::from)
+}
+
 // ============ Search Commands ============
 
 #[tauri::command]
-fn db_global_search(state: State<DbState>, query: String, limit: Option<i64>) -> Result<Vec<SearchResult>, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::global_search(&conn, &query, limit.unwrap_or(20)).map_err(|e| e.to_string())
+fn db_global_search(state: State<DbState>, query: String, limit: Option<i64>) -> Result<Vec<SearchGroup>, Error> {
+    let user_id = state.authorize(Permission::ViewClinical)?;
+    let parsed = SearchQuery::parse(&query)?;
+    let conn = state.reader()?;
+    let recent = audit::recent_patient_ids(&conn, user_id, db::RECENT_PATIENT_COUNT)?;
+    let mut results = db::global_search(&conn, &parsed, &recent, limit.unwrap_or(20))?;
+    restrict_search_results(&state, &conn, user_id, &mut results)?;
+    state.audit(user_id, &AuditEvent::search(&query, None, &search_result_patients(&results)))?;
+    Ok(db::group_by_patient(results))
 }
 
 #[tauri::command]
-fn db_search_patient_data(state: State<DbState>, patient_id: i64, query: String, limit: Option<i64>) -> Result<Vec<SearchResult>, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::search_patient_data(&conn, patient_id, &query, limit.unwrap_or(20)).map_err(|e| e.to_string())
+fn db_search_patient_data(state: State<DbState>, patient_id: i64, query: String, limit: Option<i64>) -> Result<Vec<SearchResult>, Error> {
+    let user_id = state.authorize(Permission::ViewClinical)?;
+    let parsed = SearchQuery::parse(&query)?;
+    let conn = state.reader()?;
+    require_chart_access(&conn, user_id, patient_id)?;
+    let mut results = db::search_patient_data(&conn, patient_id, &parsed, limit.unwrap_or(20))?;
+    restrict_search_results(&state, &conn, user_id, &mut results)?;
+    state.audit(user_id, &AuditEvent::search(&query, Some(patient_id), &[patient_id]))?;
+    Ok(results)
 }
 
 #[tauri::command]
-fn db_quick_search_patients(state: State<DbState>, query: String, limit: Option<i64>) -> Result<Vec<Patient>, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::quick_search_patients(&conn, &query, limit.unwrap_or(50)).map_err(|e| e.to_string())
+fn db_quick_search_patients(state: State<DbState>, query: String, limit: Option<i64>) -> Result<Vec<Patient>, Error> {
+    let user_id = state.authorize(Permission::ViewPatients)?;
+    let conn = state.reader()?;
+    let patients = db::quick_search_patients(&conn, &query, limit.unwrap_or(50))?;
+    let patient_ids: Vec<i64> = patients.iter().filter_map(|p| p.id).collect();
+    state.audit(user_id, &AuditEvent::search(&query, None, &patient_ids))?;
+    Ok(patients)
 }
 
 // ============ Prescription Commands ============
 
 #[tauri::command]
-fn db_create_prescriptions(state: State<DbState>, prescriptions: Vec<Prescription>) -> Result<Vec<i64>, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::create_prescriptions_batch(&conn, &prescriptions).map_err(|e| e.to_string())
+fn db_create_prescriptions(state: State<DbState>, prescriptions: Vec<Prescription>) -> Result<Vec<i64>, Error> {
+    let user_id = state.authorize(Permission::Prescribe)?;
+    for prescription in &prescriptions {
+        prescription.validate()?;
+    }
+    let conn = state.writer()?;
+    for prescription in &prescriptions {
+        require_chart_access(&conn, user_id, prescription.patient_id)?;
+    }
+    let tx = conn.unchecked_transaction()?;
+    let ids = db::create_prescriptions_batch(&tx, &prescriptions)?;
+    for (id, prescription) in ids.iter().zip(&prescriptions) {
+        audit::record_change(&tx, user_id, AuditAction::Create, "prescriptions", *id, None)?;
+        model_changed(&tx, "prescriptions", ModelAction::Create, *id, Some(prescription.patient_id));
+    }
+    tx.commit()?;
+    Ok(ids)
 }
 
 #[tauri::command]
-fn db_get_prescriptions(state: State<DbState>, patient_id: i64) -> Result<Vec<Prescription>, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::get_prescriptions_for_patient(&conn, patient_id).map_err(|e| e.to_string())
+fn db_get_prescriptions(state: State<DbState>, patient_id: i64) -> Result<Vec<Prescription>, Error> {
+    let user_id = state.authorize(Permission::ViewClinical)?;
+    let conn = state.reader()?;
+    require_chart_access(&conn, user_id, patient_id)?;
+    let prescriptions = db::get_prescriptions_for_patient(&conn, patient_id)?;
+    state.audit(user_id, &AuditEvent::read("prescriptions", None, Some(patient_id)))?;
+    Ok(prescriptions)
+}
+
+// ============ Vitals Commands ============
+
+/// Record a whole rooming intake at once; BMI, MAP and BSA are added from it
+#[tauri::command]
+fn db_record_vitals(state: State<DbState>, vitals: VitalsSet) -> Result<Vec<Vital>, Error> {
+    let user_id = state.authorize(Permission::EditClinical)?;
+    vitals.validate()?;
+    let conn = state.writer()?;
+    require_chart_access(&conn, user_id, vitals.patient_id)?;
+    vitals::record_set(&conn, user_id, &vitals).map_err(Error::from)
+}
+
+#[tauri::command]
+fn db_create_vital(state: State<DbState>, vital: Vital) -> Result<i64, Error> {
+    let user_id = state.authorize(Permission::EditClinical)?;
+    vital.validate()?;
+    let conn = state.writer()?;
+    require_chart_access(&conn, user_id, vital.patient_id)?;
+    let tx = conn.unchecked_transaction()?;
+    let id = bmc_create(&tx, &vital)?.id;
+    audit::record_change(&tx, user_id, AuditAction::Create, "vitals", id, None)?;
+    vitals::refresh_derived(&tx, user_id, vital.patient_id, &vital.recorded_at)?;
+    tx.commit()?;
+    Ok(id)
+}
+
+#[tauri::command]
+fn db_update_vital(state: State<DbState>, vital: Vital) -> Result<i64, Error> {
+    let user_id = state.authorize(Permission::EditClinical)?;
+    let id = vital.id.ok_or_else(|| Error::invalid_field("id", "Vital id is required"))?;
+    vital.validate()?;
+    let conn = state.writer()?;
+    let existing = measured_vital(&conn, user_id, id)?;
+    let tx = conn.unchecked_transaction()?;
+    let before = audit::snapshot(&tx, "vitals", id)?;
+    if bmc_update(&tx, id, &vital)?.is_none() {
+        return Err(concurrency::conflict(before));
+    }
+    audit::record_change(&tx, user_id, AuditAction::Update, "vitals", id, before)?;
+    // Moving a reading to another time changes two sets
+    vitals::refresh_derived(&tx, user_id, existing.patient_id, &existing.recorded_at)?;
+    if vital.recorded_at != existing.recorded_at {
+        vitals::refresh_derived(&tx, user_id, existing.patient_id, &vital.recorded_at)?;
+    }
+    tx.commit()?;
+    concurrency::stored_version(&conn, "vitals", id).map_err(Error::from)
+}
+
+#[tauri::command]
+fn db_delete_vital(state: State<DbState>, id: i64) -> Result<(), Error> {
+    let user_id = state.authorize(Permission::EditClinical)?;
+    let conn = state.writer()?;
+    let existing = measured_vital(&conn, user_id, id)?;
+    let tx = conn.unchecked_transaction()?;
+    let before = audit::snapshot(&tx, "vitals", id)?;
+    bmc_delete::<Vital>(&tx, id)?;
+    audit::record_change(&tx, user_id, AuditAction::Delete, "vitals", id, before)?;
+    vitals::refresh_derived(&tx, user_id, existing.patient_id, &existing.recorded_at)?;
+    tx.commit().map_err(Error::from)
+}
+
+/// Vital `id` in a chart open to the user, refused if it is one of the calculated values that follow the readings they come from
+fn measured_vital(conn: &rusqlite::Connection, user_id: i64, id: i64) -> Result<Vital, Error> {
+    let vital = bmc_get::<Vital>(conn, id)?.ok_or_else(|| Error::not_found("Vital not found"))?;
+    require_chart_access(conn, user_id, vital.patient_id)?;
+    if vital.is_derived == Some(true) {
+        return Err(Error::invalid(format!(
+            "{} is calculated from the other vitals taken at the same time; change those instead",
+            vital.vital_type
+        )));
+    }
+    Ok(vital)
+}
+
+// ============ Lab Commands ============
+
+/// Returns the new id; the reference range and abnormal flag are set from `lab_reference_ranges`
+#[tauri::command]
+fn db_create_lab(state: State<DbState>, lab: Lab) -> Result<i64, Error> {
+    let user_id = state.authorize(Permission::EditClinical)?;
+    lab.validate()?;
+    let conn = state.writer()?;
+    require_chart_access(&conn, user_id, lab.patient_id)?;
+    let order = match lab.order_id {
+        Some(order_id) => {
+            let order = orders::get_order(&conn, order_id)?.ok_or_else(|| Error::not_found("Order not found"))?;
+            orders::check_attachable(&order, &lab)?;
+            Some(order)
+        }
+        None => None,
+    };
+    let tx = conn.unchecked_transaction()?;
+    let id = db::create_lab(&tx, lab)?;
+    audit::record_change(&tx, user_id, AuditAction::Create, "labs", id, None)?;
+    if let Some(order) = order {
+        attach_to_order(&tx, user_id, &order, id)?;
+    }
+    tx.commit()?;
+    Ok(id)
+}
+
+#[tauri::command]
+fn db_update_lab(state: State<DbState>, lab: Lab) -> Result<i64, Error> {
+    let user_id = state.authorize(Permission::EditClinical)?;
+    let id = lab.id.ok_or_else(|| Error::invalid_field("id", "Lab id is required"))?;
+    lab.validate()?;
+    let conn = state.writer()?;
+    require_row_access::<Lab>(&conn, user_id, id)?;
+    let tx = conn.unchecked_transaction()?;
+    let existing = bmc_get::<Lab>(&tx, id)?;
+    let before = audit::snapshot(&tx, "labs", id)?;
+    if !db::update_lab(&tx, id, lab)? {
+        return Err(concurrency::conflict(before));
+    }
+    audit::record_change(&tx, user_id, AuditAction::Update, "labs", id, before)?;
+    if let (Some(old), Some(new)) = (existing, bmc_get::<Lab>(&tx, id)?) {
+        reopen_review_if_result_changed(&tx, user_id, &old, &new)?;
+    }
+    tx.commit()?;
+    concurrency::stored_version(&conn, "labs", id).map_err(Error::from)
+}
+
+#[tauri::command]
+fn db_delete_lab(state: State<DbState>, id: i64) -> Result<(), Error> {
+    let user_id = state.authorize(Permission::EditClinical)?;
+    let conn = state.writer()?;
+    require_row_access::<Lab>(&conn, user_id, id)?;
+    let order = match bmc_get::<Lab>(&conn, id)?.and_then(|lab| lab.order_id) {
+        Some(order_id) => orders::get_order(&conn, order_id)?,
+        None => None,
+    };
+    let tx = conn.unchecked_transaction()?;
+    let before = audit::snapshot(&tx, "labs", id)?;
+    db::delete_lab(&tx, id)?;
+    audit::record_change(&tx, user_id, AuditAction::Delete, "labs", id, before)?;
+    if let Some(order) = order {
+        let before = audit::snapshot(&tx, "orders", order.id.unwrap_or_default())?;
+        orders::reopen_if_unresulted(&tx, &order)?;
+        record_order_change(&tx, user_id, &order, before)?;
+    }
+    tx.commit().map_err(Error::from)
+}
+
+#[tauri::command]
+fn db_get_lab_reference_ranges(state: State<DbState>) -> Result<Vec<LabReferenceRange>, Error> {
+    state.authorize(Permission::ViewClinical)?;
+    let conn = state.reader()?;
+    db::get_reference_ranges(&conn).map_err(Error::from)
+}
+
+/// Create a range, or replace the one `range.id` names; results already recorded keep the range they were read against
+#[tauri::command]
+fn db_save_lab_reference_range(state: State<DbState>, range: LabReferenceRange) -> Result<i64, Error> {
+    let user_id = state.authorize(Permission::ManageDatabase)?;
+    range.validate()?;
+    let conn = state.writer()?;
+    let before = match range.id {
+        Some(id) => audit::snapshot(&conn, "lab_reference_ranges", id)?,
+        None => None,
+    };
+    let id = db::save_reference_range(&conn, &range)?
+        .ok_or_else(|| Error::not_found("Reference range not found"))?;
+    let action = if range.id.is_some() { AuditAction::Update } else { AuditAction::Create };
+    audit::record_change(&conn, user_id, action, "lab_reference_ranges", id, before)?;
+    Ok(id)
+}
+
+#[tauri::command]
+fn db_delete_lab_reference_range(state: State<DbState>, id: i64) -> Result<(), Error> {
+    let user_id = state.authorize(Permission::ManageDatabase)?;
+    let conn = state.writer()?;
+    let before = audit::snapshot(&conn, "lab_reference_ranges", id)?;
+    if db::delete_reference_range(&conn, id)? {
+        audit::record_change(&conn, user_id, AuditAction::Delete, "lab_reference_ranges", id, before)?;
+    }
+    Ok(())
+}
+
+// ============ Order Commands ============
+
+#[tauri::command]
+fn db_create_order(state: State<DbState>, order: Order) -> Result<i64, Error> {
+    let user_id = state.authorize(Permission::PlaceOrders)?;
+    order.validate()?;
+    let conn = state.writer()?;
+    require_chart_access(&conn, user_id, order.patient_id)?;
+    orders::check_links(&conn, &order)?;
+    let tx = conn.unchecked_transaction()?;
+    let id = orders::create_order(&tx, user_id, &order)?;
+    audit::record_change(&tx, user_id, AuditAction::Create, "orders", id, None)?;
+    tx.commit()?;
+    Ok(id)
+}
+
+/// Every order in the chart, newest first
+#[tauri::command]
+fn db_get_orders(state: State<DbState>, patient_id: i64) -> Result<Vec<Order>, Error> {
+    let user_id = state.authorize(Permission::ViewClinical)?;
+    let conn = state.reader()?;
+    require_chart_access(&conn, user_id, patient_id)?;
+    let orders = orders::get_orders_for_patient(&conn, patient_id)?;
+    state.audit(user_id, &AuditEvent::read("orders", None, Some(patient_id)))?;
+    Ok(orders)
+}
+
+/// Orders still waiting on a result or its review, for one chart or, without `patient_id`, every chart the user may open
+#[tauri::command]
+fn db_get_outstanding_orders(state: State<DbState>, patient_id: Option<i64>) -> Result<Vec<Order>, Error> {
+    let user_id = state.authorize(Permission::ViewClinical)?;
+    let conn = state.reader()?;
+    if let Some(patient_id) = patient_id {
+        require_chart_access(&conn, user_id, patient_id)?;
+    }
+    let mut outstanding = Vec::new();
+    for order in orders::get_outstanding_orders(&conn, patient_id)? {
+        if patient_id.is_some() || chart_access::check_chart_access(&conn, user_id, order.patient_id)?.is_some() {
+            outstanding.push(order);
+        }
+    }
+    state.audit(user_id, &AuditEvent::read("orders", None, patient_id).with_detail(json!({ "outstanding": outstanding.len() })))?;
+    Ok(outstanding)
+}
+
+/// Specimen drawn or study done; `collected_at` defaults to now
+#[tauri::command]
+fn db_collect_order(state: State<DbState>, id: i64, collected_at: Option<String>) -> Result<(), Error> {
+    let user_id = state.authorize(Permission::EditClinical)?;
+    orders::validate_collected_at(collected_at.as_deref())?;
+    let conn = state.writer()?;
+    let order = order_for_step(&conn, user_id, id, OrderStep::Collect)?;
+    let tx = conn.unchecked_transaction()?;
+    let before = audit::snapshot(&tx, "orders", id)?;
+    orders::mark_collected(&tx, &order, collected_at.as_deref())?;
+    record_order_change(&tx, user_id, &order, before)?;
+    tx.commit().map_err(Error::from)
+}
+
+/// Attach an existing lab result to the order it answers
+#[tauri::command]
+fn db_attach_lab_to_order(state: State<DbState>, order_id: i64, lab_id: i64) -> Result<(), Error> {
+    let user_id = state.authorize(Permission::EditClinical)?;
+    let conn = state.writer()?;
+    let order = orders::get_order(&conn, order_id)?.ok_or_else(|| Error::not_found("Order not found"))?;
+    let lab = bmc_get::<Lab>(&conn, lab_id)?.ok_or_else(|| Error::not_found("Lab result not found"))?;
+    require_chart_access(&conn, user_id, order.patient_id)?;
+    orders::check_attachable(&order, &lab)?;
+    let tx = conn.unchecked_transaction()?;
+    attach_to_order(&tx, user_id, &order, lab_id)?;
+    tx.commit().map_err(Error::from)
+}
+
+/// Undo an attachment made to the wrong order; an order left without results goes back to collected
+#[tauri::command]
+fn db_detach_lab_from_order(state: State<DbState>, lab_id: i64) -> Result<(), Error> {
+    let user_id = state.authorize(Permission::EditClinical)?;
+    let conn = state.writer()?;
+    let lab = bmc_get::<Lab>(&conn, lab_id)?.ok_or_else(|| Error::not_found("Lab result not found"))?;
+    require_chart_access(&conn, user_id, lab.patient_id)?;
+    let Some(order_id) = lab.order_id else {
+        return Ok(());
+    };
+    let order = orders::get_order(&conn, order_id)?.ok_or_else(|| Error::not_found("Order not found"))?;
+    let tx = conn.unchecked_transaction()?;
+    let lab_before = audit::snapshot(&tx, "labs", lab_id)?;
+    let order_before = audit::snapshot(&tx, "orders", order_id)?;
+    orders::detach_lab(&tx, &order, &lab)?;
+    audit::record_change(&tx, user_id, AuditAction::Update, "labs", lab_id, lab_before)?;
+    record_order_change(&tx, user_id, &order, order_before)?;
+    tx.commit().map_err(Error::from)
+}
+
+/// Imaging has no result rows of its own; the report's impression is kept on the order
+#[tauri::command]
+fn db_record_imaging_result(state: State<DbState>, id: i64, summary: String) -> Result<(), Error> {
+    let user_id = state.authorize(Permission::EditClinical)?;
+    orders::require_text("summary", "Impression", &summary)?;
+    let conn = state.writer()?;
+    let order = order_for_step(&conn, user_id, id, OrderStep::RecordImagingResult)?;
+    let tx = conn.unchecked_transaction()?;
+    let before = audit::snapshot(&tx, "orders", id)?;
+    orders::record_imaging_result(&tx, &order, &summary)?;
+    record_order_change(&tx, user_id, &order, before)?;
+    tx.commit().map_err(Error::from)
+}
+
+/// Sign off a result as seen, taking the order off the outstanding lists
+#[tauri::command]
+fn db_review_order(state: State<DbState>, id: i64) -> Result<(), Error> {
+    let user_id = state.authorize(Permission::PlaceOrders)?;
+    let conn = state.writer()?;
+    let order = order_for_step(&conn, user_id, id, OrderStep::Review)?;
+    let tx = conn.unchecked_transaction()?;
+    let before = audit::snapshot(&tx, "orders", id)?;
+    orders::review(&tx, &order, user_id)?;
+    record_order_change(&tx, user_id, &order, before)?;
+    tx.commit().map_err(Error::from)
+}
+
+#[tauri::command]
+fn db_cancel_order(state: State<DbState>, id: i64, reason: String) -> Result<(), Error> {
+    let user_id = state.authorize(Permission::PlaceOrders)?;
+    orders::require_text("reason", "Reason", &reason)?;
+    let conn = state.writer()?;
+    let order = order_for_step(&conn, user_id, id, OrderStep::Cancel)?;
+    let tx = conn.unchecked_transaction()?;
+    let before = audit::snapshot(&tx, "orders", id)?;
+    orders::cancel(&tx, &order, &reason)?;
+    record_order_change(&tx, user_id, &order, before)?;
+    tx.commit().map_err(Error::from)
+}
+
+/// Order `id` in a chart open to the user, refused unless it can take `step` from its current status
+fn order_for_step(conn: &rusqlite::Connection, user_id: i64, id: i64, step: OrderStep) -> Result<Order, Error> {
+    let order = orders::get_order(conn, id)?.ok_or_else(|| Error::not_found("Order not found"))?;
+    require_chart_access(conn, user_id, order.patient_id)?;
+    orders::check_step(&order, step)?;
+    Ok(order)
+}
+
+/// Link lab `lab_id` to a checked `order`, auditing both rows
+fn attach_to_order(conn: &rusqlite::Connection, user_id: i64, order: &Order, lab_id: i64) -> Result<(), Error> {
+    let lab = bmc_get::<Lab>(conn, lab_id)?.ok_or_else(|| Error::not_found("Lab result not found"))?;
+    let lab_before = audit::snapshot(conn, "labs", lab_id)?;
+    let order_before = audit::snapshot(conn, "orders", order.id.unwrap_or_default())?;
+    orders::attach_lab(conn, order, &lab)?;
+    audit::record_change(conn, user_id, AuditAction::Update, "labs", lab_id, lab_before)?;
+    record_order_change(conn, user_id, order, order_before)
+}
+
+/// A reviewed order whose lab now reads differently needs reviewing again
+fn reopen_review_if_result_changed(conn: &rusqlite::Connection, user_id: i64, old: &Lab, new: &Lab) -> Result<(), Error> {
+    let result_changed = old.value != new.value || old.unit != new.unit || old.interpretation != new.interpretation;
+    let Some(order_id) = new.order_id.filter(|_| result_changed) else {
+        return Ok(());
+    };
+    let Some(order) = orders::get_order(conn, order_id)?.filter(|o| o.reviewed_at.is_some()) else {
+        return Ok(());
+    };
+    let before = audit::snapshot(conn, "orders", order_id)?;
+    orders::reopen_review(conn, &order)?;
+    record_order_change(conn, user_id, &order, before)
+}
+
+fn record_order_change(conn: &rusqlite::Connection, user_id: i64, order: &Order, before: Option<serde_json::Value>) -> Result<(), Error> {
+    let id = order.id.unwrap_or_default();
+    audit::record_change(conn, user_id, AuditAction::Update, "orders", id, before).map_err(Error::from)
 }
 
 // ============ History CRUD Commands ============
 
 // Allergy commands
 #[tauri::command]
-fn db_create_allergy(state: State<DbState>, allergy: Allergy) -> Result<i64, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::create_allergy(&conn, &allergy).map_err(|e| e.to_string())
+fn db_create_allergy(state: State<DbState>, allergy: Allergy) -> Result<i64, Error> {
+    let user_id = state.authorize(Permission::EditClinical)?;
+    allergy.validate()?;
+    let conn = state.writer()?;
+    require_chart_access(&conn, user_id, allergy.patient_id)?;
+    let tx = conn.unchecked_transaction()?;
+    let id = bmc_create(&tx, &allergy)?.id;
+    audit::record_change(&tx, user_id, AuditAction::Create, "allergies", id, None)?;
+    tx.commit()?;
+    Ok(id)
 }
 
 #[tauri::command]
-fn db_update_allergy(state: State<DbState>, allergy: Allergy) -> Result<(), String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::update_allergy(&conn, &allergy).map_err(|e| e.to_string())
+fn db_update_allergy(state: State<DbState>, allergy: Allergy) -> Result<i64, Error> {
+    let user_id = state.authorize(Permission::EditClinical)?;
+    let id = allergy.id.ok_or_else(|| Error::invalid_field("id", "Allergy id is required"))?;
+    allergy.validate()?;
+    let conn = state.writer()?;
+    require_row_access::<Allergy>(&conn, user_id, id)?;
+    let tx = conn.unchecked_transaction()?;
+    let before = audit::snapshot(&tx, "allergies", id)?;
+    if bmc_update(&tx, id, &allergy)?.is_none() {
+        return Err(concurrency::conflict(before));
+    }
+    audit::record_change(&tx, user_id, AuditAction::Update, "allergies", id, before)?;
+    tx.commit()?;
+    concurrency::stored_version(&conn, "allergies", id).map_err(Error::from)
 }
 
 #[tauri::command]
-fn db_delete_allergy(state: State<DbState>, id: i64) -> Result<(), String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::delete_allergy(&conn, id).map_err(|e| e.to_string())
+fn db_delete_allergy(state: State<DbState>, id: i64) -> Result<(), Error> {
+    let user_id = state.authorize(Permission::EditClinical)?;
+    let conn = state.writer()?;
+    require_row_access::<Allergy>(&conn, user_id, id)?;
+    let tx = conn.unchecked_transaction()?;
+    let before = audit::snapshot(&tx, "allergies", id)?;
+    bmc_delete::<Allergy>(&tx, id)?;
+    audit::record_change(&tx, user_id, AuditAction::Delete, "allergies", id, before)?;
+    tx.commit().map_err(Error::from)
 }
 
 // Vaccination commands
 #[tauri::command]
-fn db_create_vaccination(state: State<DbState>, vaccination: Vaccination) -> Result<i64, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::create_vaccination(&conn, &vaccination).map_err(|e| e.to_string())
+fn db_create_vaccination(state: State<DbState>, vaccination: Vaccination) -> Result<i64, Error> {
+    let user_id = state.authorize(Permission::EditClinical)?;
+    vaccination.validate()?;
+    let conn = state.writer()?;
+    require_chart_access(&conn, user_id, vaccination.patient_id)?;
+    let tx = conn.unchecked_transaction()?;
+    let id = bmc_create(&tx, &vaccination)?.id;
+    audit::record_change(&tx, user_id, AuditAction::Create, "vaccinations", id, None)?;
+    tx.commit()?;
+    Ok(id)
 }
 
 #[tauri::command]
-fn db_update_vaccination(state: State<DbState>, vaccination: Vaccination) -> Result<(), String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::update_vaccination(&conn, &vaccination).map_err(|e| e.to_string())
+fn db_update_vaccination(state: State<DbState>, vaccination: Vaccination) -> Result<i64, Error> {
+    let user_id = state.authorize(Permission::EditClinical)?;
+    let id = vaccination.id.ok_or_else(|| Error::invalid_field("id", "Vaccination id is required"))?;
+    vaccination.validate()?;
+    let conn = state.writer()?;
+    require_row_access::<Vaccination>(&conn, user_id, id)?;
+    let tx = conn.unchecked_transaction()?;
+    let before = audit::snapshot(&tx, "vaccinations", id)?;
+    if bmc_update(&tx, id, &vaccination)?.is_none() {
+        return Err(concurrency::conflict(before));
+    }
+    audit::record_change(&tx, user_id, AuditAction::Update, "vaccinations", id, before)?;
+    tx.commit()?;
+    concurrency::stored_version(&conn, "vaccinations", id).map_err(Error::from)
 }
 
 #[tauri::command]
-fn db_delete_vaccination(state: State<DbState>, id: i64) -> Result<(), String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::delete_vaccination(&conn, id).map_err(|e| e.to_string())
+fn db_delete_vaccination(state: State<DbState>, id: i64) -> Result<(), Error> {
+    let user_id = state.authorize(Permission::EditClinical)?;
+    let conn = state.writer()?;
+    require_row_access::<Vaccination>(&conn, user_id, id)?;
+    let tx = conn.unchecked_transaction()?;
+    let before = audit::snapshot(&tx, "vaccinations", id)?;
+    bmc_delete::<Vaccination>(&tx, id)?;
+    audit::record_change(&tx, user_id, AuditAction::Delete, "vaccinations", id, before)?;
+    tx.commit().map_err(Error::from)
 }
 
 // Social History commands
 #[tauri::command]
-fn db_create_social_history(state: State<DbState>, history: SocialHistory) -> Result<i64, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::create_social_history(&conn, &history).map_err(|e| e.to_string())
+fn db_create_social_history(state: State<DbState>, history: SocialHistory) -> Result<i64, Error> {
+    let user_id = state.authorize(Permission::EditClinical)?;
+    history.validate()?;
+    let conn = state.writer()?;
+    require_chart_access(&conn, user_id, history.patient_id)?;
+    let tx = conn.unchecked_transaction()?;
+    let id = bmc_create(&tx, &history)?.id;
+    audit::record_change(&tx, user_id, AuditAction::Create, "social_history", id, None)?;
+    tx.commit()?;
+    Ok(id)
 }
 
 #[tauri::command]
-fn db_update_social_history(state: State<DbState>, history: SocialHistory) -> Result<(), String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::update_social_history(&conn, &history).map_err(|e| e.to_string())
+fn db_update_social_history(state: State<DbState>, history: SocialHistory) -> Result<i64, Error> {
+    let user_id = state.authorize(Permission::EditClinical)?;
+    let id = history.id.ok_or_else(|| Error::invalid_field("id", "Social history id is required"))?;
+    history.validate()?;
+    let conn = state.writer()?;
+    require_row_access::<SocialHistory>(&conn, user_id, id)?;
+    let tx = conn.unchecked_transaction()?;
+    let before = audit::snapshot(&tx, "social_history", id)?;
+    if bmc_update(&tx, id, &history)?.is_none() {
+        return Err(concurrency::conflict(before));
+    }
+    audit::record_change(&tx, user_id, AuditAction::Update, "social_history", id, before)?;
+    tx.commit()?;
+    concurrency::stored_version(&conn, "social_history", id).map_err(Error::from)
 }
 
 #[tauri::command]
-fn db_delete_social_history(state: State<DbState>, id: i64) -> Result<(), String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::delete_social_history(&conn, id).map_err(|e| e.to_string())
+fn db_delete_social_history(state: State<DbState>, id: i64) -> Result<(), Error> {
+    let user_id = state.authorize(Permission::EditClinical)?;
+    let conn = state.writer()?;
+    require_row_access::<SocialHistory>(&conn, user_id, id)?;
+    let tx = conn.unchecked_transaction()?;
+    let before = audit::snapshot(&tx, "social_history", id)?;
+    bmc_delete::<SocialHistory>(&tx, id)?;
+    audit::record_change(&tx, user_id, AuditAction::Delete, "social_history", id, before)?;
+    tx.commit().map_err(Error::from)
 }
 
 // Family History commands
 #[tauri::command]
-fn db_create_family_history(state: State<DbState>, history: FamilyHistory) -> Result<i64, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::create_family_history(&conn, &history).map_err(|e| e.to_string())
+fn db_create_family_history(state: State<DbState>, history: FamilyHistory) -> Result<i64, Error> {
+    let user_id = state.authorize(Permission::EditClinical)?;
+    history.validate()?;
+    let conn = state.writer()?;
+    require_chart_access(&conn, user_id, history.patient_id)?;
+    let tx = conn.unchecked_transaction()?;
+    let id = bmc_create(&tx, &history)?.id;
+    audit::record_change(&tx, user_id, AuditAction::Create, "family_history", id, None)?;
+    tx.commit()?;
+    Ok(id)
 }
 
 #[tauri::command]
-fn db_update_family_history(state: State<DbState>, history: FamilyHistory) -> Result<(), String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::update_family_history(&conn, &history).map_err(|e| e.to_string())
+fn db_update_family_history(state: State<DbState>, history: FamilyHistory) -> Result<i64, Error> {
+    let user_id = state.authorize(Permission::EditClinical)?;
+    let id = history.id.ok_or_else(|| Error::invalid_field("id", "Family history id is required"))?;
+    history.validate()?;
+    let conn = state.writer()?;
+    require_row_access::<FamilyHistory>(&conn, user_id, id)?;
+    let tx = conn.unchecked_transaction()?;
+    let before = audit::snapshot(&tx, "family_history", id)?;
+    if bmc_update(&tx, id, &history)?.is_none() {
+        return Err(concurrency::conflict(before));
+    }
+    audit::record_change(&tx, user_id, AuditAction::Update, "family_history", id, before)?;
+    tx.commit()?;
+    concurrency::stored_version(&conn, "family_history", id).map_err(Error::from)
 }
 
 #[tauri::command]
-fn db_delete_family_history(state: State<DbState>, id: i64) -> Result<(), String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    db::delete_family_history(&conn, id).map_err(|e| e.to_string())
+fn db_delete_family_history(state: State<DbState>, id: i64) -> Result<(), Error> {
+    let user_id = state.authorize(Permission::EditClinical)?;
+    let conn = state.writer()?;
+    require_row_access::<FamilyHistory>(&conn, user_id, id)?;
+    let tx = conn.unchecked_transaction()?;
+    let before = audit::snapshot(&tx, "family_history", id)?;
+    bmc_delete::<FamilyHistory>(&tx, id)?;
+    audit::record_change(&tx, user_id, AuditAction::Delete, "family_history", id, before)?;
+    tx.commit().map_err(Error::from)
 }
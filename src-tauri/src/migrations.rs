@@ -0,0 +1,1220 @@
+use rusqlite::{params, Connection, Transaction};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use ts_rs::TS;
+
+/// A single numbered schema change
+///
+/// Migrations are applied in ascending `version` order, each inside its own
+/// transaction, and recorded in `schema_migrations` once committed. Never edit
+/// or renumber a migration that has shipped; add a new one instead.
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub up: fn(&Transaction) -> rusqlite::Result<()>,
+}
+
+/// Every migration this build knows about, oldest first
+pub const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, name: "baseline_schema", up: m001_baseline_schema },
+    Migration { version: 2, name: "legacy_patient_and_settings_columns", up: m002_legacy_columns },
+    Migration { version: 3, name: "full_text_search", up: m003_full_text_search },
+    Migration { version: 4, name: "patient_status", up: m004_patient_status },
+    Migration { version: 5, name: "audit_log", up: m005_audit_log },
+    Migration { version: 6, name: "user_login_state", up: m006_user_login_state },
+    Migration { version: 7, name: "two_factor", up: m007_two_factor },
+    Migration { version: 8, name: "user_roles", up: m008_user_roles },
+    Migration { version: 9, name: "care_teams_and_break_glass", up: m009_care_teams_and_break_glass },
+    Migration { version: 10, name: "encounter_signing", up: m010_encounter_signing },
+    Migration { version: 11, name: "encounter_revisions", up: m011_encounter_revisions },
+    Migration { version: 12, name: "row_versions", up: m012_row_versions },
+    Migration { version: 13, name: "search_indexes", up: m013_search_indexes },
+    Migration { version: 14, name: "quarantined_rows", up: m014_quarantined_rows },
+    Migration { version: 15, name: "derived_vitals", up: m015_derived_vitals },
+    Migration { version: 16, name: "lab_reference_ranges", up: m016_lab_reference_ranges },
+    Migration { version: 17, name: "orders", up: m017_orders },
+];
+
+/// Highest schema version this build can produce
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+// ============ Errors ============
+
+#[derive(Debug)]
+pub enum MigrationError {
+    /// Reading or creating the `schema_migrations` bookkeeping table failed
+    Bookkeeping(rusqlite::Error),
+    /// A migration failed; its transaction was rolled back
+    Failed {
+        version: i64,
+        name: &'static str,
+        source: rusqlite::Error,
+    },
+    /// The database was written by a newer build than this one
+    NewerThanBuild { found: i64, latest: i64 },
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::Bookkeeping(e) => {
+                write!(f, "could not read schema_migrations: {}", e)
+            }
+            MigrationError::Failed { version, name, source } => write!(
+                f,
+                "schema migration {:03} ({}) failed and was rolled back: {}",
+                version, name, source
+            ),
+            MigrationError::NewerThanBuild { found, latest } => write!(
+                f,
+                "database is at schema version {} but this build only knows up to {}; refusing to open it",
+                found, latest
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MigrationError::Bookkeeping(e) => Some(e),
+            MigrationError::Failed { source, .. } => Some(source),
+            MigrationError::NewerThanBuild { .. } => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for MigrationError {
+    fn from(e: rusqlite::Error) -> Self {
+        MigrationError::Bookkeeping(e)
+    }
+}
+
+// ============ Runner ============
+
+/// Bring the database up to `latest_version()`, applying each pending migration in its own transaction
+///
+/// Returns the versions that were applied by this call. Stops at the first
+/// failing migration so the database is left at the last good version.
+pub fn run_migrations(conn: &mut Connection) -> Result<Vec<i64>, MigrationError> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TEXT DEFAULT (datetime('now', 'localtime'))
+        );",
+    )?;
+
+    let current = current_version(conn)?;
+    let latest = latest_version();
+    if current > latest {
+        return Err(MigrationError::NewerThanBuild { found: current, latest });
+    }
+
+    let mut applied = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        let fail = |source| MigrationError::Failed {
+            version: migration.version,
+            name: migration.name,
+            source,
+        };
+
+        let tx = conn.transaction().map_err(fail)?;
+        (migration.up)(&tx).map_err(fail)?;
+        tx.execute(
+            "INSERT INTO schema_migrations (version, name) VALUES (?1, ?2)",
+            params![migration.version, migration.name],
+        )
+        .map_err(fail)?;
+        tx.commit().map_err(fail)?;
+        applied.push(migration.version);
+    }
+
+    Ok(applied)
+}
+
+/// Highest applied migration version, or 0 for a database that predates migrations
+pub fn current_version(conn: &Connection) -> rusqlite::Result<i64> {
+    conn.query_row("SELECT COALESCE(MAX(version), 0) FROM schema_migrations", [], |row| row.get(0))
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
+pub struct AppliedMigration {
+    #[ts(as = "f64")]
+    pub version: i64,
+    pub name: String,
+    pub applied_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct SchemaStatus {
+    #[ts(as = "f64")]
+    pub current_version: i64,
+    #[ts(as = "f64")]
+    pub latest_version: i64,
+    pub applied: Vec<AppliedMigration>,
+}
+
+/// Report which schema version this database is on and how it got there
+pub fn get_schema_status(conn: &Connection) -> rusqlite::Result<SchemaStatus> {
+    let mut stmt = conn.prepare(
+        "SELECT version, name, applied_at FROM schema_migrations ORDER BY version"
+    )?;
+
+    let applied = stmt.query_map([], |row| {
+        Ok(AppliedMigration {
+            version: row.get(0)?,
+            name: row.get(1)?,
+            applied_at: row.get(2)?,
+        })
+    })?.collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(SchemaStatus {
+        current_version: applied.last().map(|m| m.version).unwrap_or(0),
+        latest_version: latest_version(),
+        applied,
+    })
+}
+
+// ============ Helpers ============
+
+/// Whether `table` already has a column called `column`
+pub fn column_exists(conn: &Connection, table: &str, column: &str) -> rusqlite::Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let name: String = row.get(1)?;
+        if name == column {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// `ALTER TABLE ... ADD COLUMN` that is a no-op when the column is already there
+///
+/// SQLite has no `ADD COLUMN IF NOT EXISTS`. Unlike the old probing code this
+/// only skips the column when it exists; any other failure is returned.
+pub fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> rusqlite::Result<()> {
+    if !column_exists(conn, table, column)? {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+    }
+    Ok(())
+}
+
+/// (Re)create `<table>_fts` over `columns` of `table`, with the triggers that keep it current
+///
+/// The index is an external-content FTS5 table, so every column named here
+/// must exist on `table` under the same name: FTS5 reads the text back from
+/// the source row for `snippet()` and column lookups. Columns prefixed with
+/// `=` are stored but not tokenized (`UNINDEXED`).
+fn create_search_index(tx: &Transaction, table: &str, columns: &[&str]) -> rusqlite::Result<()> {
+    let fts = format!("{}_fts", table);
+    let definitions: Vec<String> = columns
+        .iter()
+        .map(|c| match c.strip_prefix('=') {
+            Some(name) => format!("{} UNINDEXED", name),
+            None => c.to_string(),
+        })
+        .collect();
+    let names: Vec<&str> = columns.iter().map(|c| c.trim_start_matches('=')).collect();
+    let list = names.join(", ");
+    let new_values = names.iter().map(|c| format!("new.{}", c)).collect::<Vec<_>>().join(", ");
+    let old_values = names.iter().map(|c| format!("old.{}", c)).collect::<Vec<_>>().join(", ");
+
+    tx.execute_batch(&format!(
+        "
+        DROP TRIGGER IF EXISTS {table}_ai;
+        DROP TRIGGER IF EXISTS {table}_ad;
+        DROP TRIGGER IF EXISTS {table}_au;
+        DROP TABLE IF EXISTS {fts};
+
+        CREATE VIRTUAL TABLE {fts} USING fts5(
+            {definitions},
+            content='{table}',
+            content_rowid='id'
+        );
+
+        CREATE TRIGGER {table}_ai AFTER INSERT ON {table} BEGIN
+            INSERT INTO {fts}(rowid, {list}) VALUES (new.id, {new_values});
+        END;
+
+        CREATE TRIGGER {table}_ad AFTER DELETE ON {table} BEGIN
+            INSERT INTO {fts}({fts}, rowid, {list}) VALUES ('delete', old.id, {old_values});
+        END;
+
+        CREATE TRIGGER {table}_au AFTER UPDATE ON {table} BEGIN
+            INSERT INTO {fts}({fts}, rowid, {list}) VALUES ('delete', old.id, {old_values});
+            INSERT INTO {fts}(rowid, {list}) VALUES (new.id, {new_values});
+        END;
+
+        INSERT INTO {fts}({fts}) VALUES ('rebuild');
+        ",
+        definitions = definitions.join(",\n            "),
+    ))
+}
+
+// ============ Migrations ============
+
+/// 001: Core clinical, user and patient list tables as they stood before versioning
+///
+/// Uses `IF NOT EXISTS` throughout so databases created by earlier builds
+/// adopt this version without changes.
+fn m001_baseline_schema(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        -- Patients table
+        CREATE TABLE IF NOT EXISTS patients (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            first_name TEXT NOT NULL,
+            last_name TEXT NOT NULL,
+            dob TEXT NOT NULL,
+            sex TEXT NOT NULL,
+            gender TEXT,
+            address TEXT,
+            phone TEXT,
+            email TEXT,
+            photo_url TEXT,
+            ai_summary TEXT,
+            preferred_pharmacy TEXT,
+            insurance_provider TEXT,
+            insurance_policy_number TEXT,
+            insurance_group_number TEXT,
+            created_at TEXT DEFAULT (datetime('now', 'localtime')),
+            updated_at TEXT DEFAULT (datetime('now', 'localtime'))
+        );
+
+        -- Appointments table
+        CREATE TABLE IF NOT EXISTS appointments (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            patient_id INTEGER NOT NULL,
+            appointment_time TEXT NOT NULL,
+            duration_minutes INTEGER DEFAULT 30,
+            reason TEXT,
+            status TEXT DEFAULT 'scheduled',
+            notes TEXT,
+            created_at TEXT DEFAULT (datetime('now', 'localtime')),
+            updated_at TEXT DEFAULT (datetime('now', 'localtime')),
+            FOREIGN KEY (patient_id) REFERENCES patients(id)
+        );
+
+        -- Messages table
+        CREATE TABLE IF NOT EXISTS messages (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            patient_id INTEGER,
+            subject TEXT NOT NULL,
+            body TEXT NOT NULL,
+            is_read INTEGER DEFAULT 0,
+            created_at TEXT DEFAULT (datetime('now', 'localtime')),
+            FOREIGN KEY (patient_id) REFERENCES patients(id)
+        );
+
+        -- Diagnoses table
+        CREATE TABLE IF NOT EXISTS diagnoses (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            patient_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            description TEXT,
+            icd_code TEXT,
+            onset_date TEXT,
+            status TEXT DEFAULT 'active',
+            category TEXT,
+            notes TEXT,
+            created_at TEXT DEFAULT (datetime('now', 'localtime')),
+            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
+        );
+
+        -- Medications table
+        CREATE TABLE IF NOT EXISTS medications (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            patient_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            dose TEXT,
+            dosage TEXT,
+            frequency TEXT,
+            route TEXT,
+            prescriber TEXT,
+            start_date TEXT,
+            end_date TEXT,
+            status TEXT DEFAULT 'active',
+            notes TEXT,
+            created_at TEXT DEFAULT (datetime('now', 'localtime')),
+            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
+        );
+
+        -- Junction table for diagnosis-medication relationships
+        CREATE TABLE IF NOT EXISTS diagnosis_medications (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            diagnosis_id INTEGER NOT NULL,
+            medication_id INTEGER NOT NULL,
+            created_at TEXT DEFAULT (datetime('now', 'localtime')),
+            FOREIGN KEY (diagnosis_id) REFERENCES diagnoses(id) ON DELETE CASCADE,
+            FOREIGN KEY (medication_id) REFERENCES medications(id) ON DELETE CASCADE,
+            UNIQUE(diagnosis_id, medication_id)
+        );
+
+        -- Vitals table
+        CREATE TABLE IF NOT EXISTS vitals (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            patient_id INTEGER NOT NULL,
+            vital_type TEXT NOT NULL,
+            value REAL NOT NULL,
+            value_secondary REAL,
+            unit TEXT NOT NULL,
+            recorded_at TEXT NOT NULL,
+            created_at TEXT DEFAULT (datetime('now', 'localtime')),
+            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
+        );
+
+        -- Labs table
+        CREATE TABLE IF NOT EXISTS labs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            patient_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            test_name TEXT,
+            value REAL NOT NULL,
+            result TEXT,
+            unit TEXT,
+            reference_range_low REAL,
+            reference_range_high REAL,
+            is_abnormal INTEGER DEFAULT 0,
+            notes TEXT,
+            recorded_at TEXT NOT NULL,
+            created_at TEXT DEFAULT (datetime('now', 'localtime')),
+            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
+        );
+
+        -- Clinical scores table (PHQ-9, GAD-7, etc.)
+        CREATE TABLE IF NOT EXISTS clinical_scores (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            patient_id INTEGER NOT NULL,
+            score_type TEXT NOT NULL,
+            score INTEGER NOT NULL,
+            max_score INTEGER,
+            interpretation TEXT,
+            recorded_at TEXT NOT NULL,
+            created_at TEXT DEFAULT (datetime('now', 'localtime')),
+            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
+        );
+
+        -- Encounters table
+        CREATE TABLE IF NOT EXISTS encounters (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            patient_id INTEGER NOT NULL,
+            encounter_date TEXT NOT NULL,
+            encounter_type TEXT NOT NULL,
+            chief_complaint TEXT,
+            summary TEXT,
+            note_content TEXT,
+            provider TEXT,
+            location TEXT,
+            created_at TEXT DEFAULT (datetime('now', 'localtime')),
+            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
+        );
+
+        -- Allergies table
+        CREATE TABLE IF NOT EXISTS allergies (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            patient_id INTEGER NOT NULL,
+            allergen TEXT NOT NULL,
+            reaction TEXT,
+            severity TEXT,
+            onset_date TEXT,
+            status TEXT DEFAULT 'active',
+            created_at TEXT DEFAULT (datetime('now', 'localtime')),
+            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
+        );
+
+        -- Vaccinations table
+        CREATE TABLE IF NOT EXISTS vaccinations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            patient_id INTEGER NOT NULL,
+            vaccine_name TEXT NOT NULL,
+            date_given TEXT NOT NULL,
+            lot_number TEXT,
+            site TEXT,
+            administered_by TEXT,
+            created_at TEXT DEFAULT (datetime('now', 'localtime')),
+            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
+        );
+
+        -- Social history table
+        CREATE TABLE IF NOT EXISTS social_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            patient_id INTEGER NOT NULL,
+            category TEXT NOT NULL,
+            detail TEXT NOT NULL,
+            status TEXT,
+            start_date TEXT,
+            end_date TEXT,
+            created_at TEXT DEFAULT (datetime('now', 'localtime')),
+            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
+        );
+
+        -- Family history table
+        CREATE TABLE IF NOT EXISTS family_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            patient_id INTEGER NOT NULL,
+            relation TEXT NOT NULL,
+            condition TEXT NOT NULL,
+            age_at_onset INTEGER,
+            notes TEXT,
+            created_at TEXT DEFAULT (datetime('now', 'localtime')),
+            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
+        );
+
+        -- To-dos table
+        CREATE TABLE IF NOT EXISTS todos (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            patient_id INTEGER NOT NULL,
+            diagnosis_id INTEGER,
+            description TEXT NOT NULL,
+            due_date TEXT,
+            priority TEXT DEFAULT 'normal',
+            status TEXT DEFAULT 'pending',
+            created_at TEXT DEFAULT (datetime('now', 'localtime')),
+            updated_at TEXT DEFAULT (datetime('now', 'localtime')),
+            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE,
+            FOREIGN KEY (diagnosis_id) REFERENCES diagnoses(id) ON DELETE SET NULL
+        );
+
+        -- Patient goals table
+        CREATE TABLE IF NOT EXISTS goals (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            patient_id INTEGER NOT NULL,
+            description TEXT NOT NULL,
+            target_date TEXT,
+            status TEXT DEFAULT 'in_progress',
+            progress INTEGER DEFAULT 0,
+            created_at TEXT DEFAULT (datetime('now', 'localtime')),
+            updated_at TEXT DEFAULT (datetime('now', 'localtime')),
+            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
+        );
+
+        -- Timeline events table
+        CREATE TABLE IF NOT EXISTS timeline_events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            patient_id INTEGER NOT NULL,
+            event_type TEXT NOT NULL,
+            description TEXT NOT NULL,
+            event_date TEXT NOT NULL,
+            icon TEXT,
+            color TEXT,
+            related_id INTEGER,
+            related_table TEXT,
+            created_at TEXT DEFAULT (datetime('now', 'localtime')),
+            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
+        );
+
+        -- Users/Providers table
+        CREATE TABLE IF NOT EXISTS users (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            username TEXT NOT NULL UNIQUE,
+            password_hash TEXT NOT NULL,
+            first_name TEXT NOT NULL,
+            last_name TEXT NOT NULL,
+            degree_type TEXT,
+            specialty TEXT,
+            subspecialty TEXT,
+            npi_number TEXT,
+            photo_url TEXT,
+            bio TEXT,
+            created_at TEXT DEFAULT (datetime('now', 'localtime')),
+            updated_at TEXT DEFAULT (datetime('now', 'localtime'))
+        );
+
+        -- User education/training table
+        CREATE TABLE IF NOT EXISTS user_education (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            education_type TEXT NOT NULL,
+            institution TEXT NOT NULL,
+            degree TEXT,
+            field_of_study TEXT,
+            start_year INTEGER,
+            end_year INTEGER,
+            created_at TEXT DEFAULT (datetime('now', 'localtime')),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        );
+
+        -- User badges/awards table
+        CREATE TABLE IF NOT EXISTS user_badges (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            badge_name TEXT NOT NULL,
+            badge_type TEXT NOT NULL,
+            description TEXT,
+            icon TEXT,
+            color TEXT,
+            awarded_date TEXT,
+            created_at TEXT DEFAULT (datetime('now', 'localtime')),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        );
+
+        -- User settings table
+        CREATE TABLE IF NOT EXISTS user_settings (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL UNIQUE,
+            language TEXT DEFAULT 'en',
+            notifications_enabled INTEGER DEFAULT 1,
+            email_notifications INTEGER DEFAULT 1,
+            sms_notifications INTEGER DEFAULT 0,
+            two_factor_enabled INTEGER DEFAULT 0,
+            two_factor_secret TEXT,
+            zen_mode_default INTEGER DEFAULT 0,
+            created_at TEXT DEFAULT (datetime('now', 'localtime')),
+            updated_at TEXT DEFAULT (datetime('now', 'localtime')),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        );
+
+        -- Patient lists (custom patient groupings per user)
+        CREATE TABLE IF NOT EXISTS patient_lists (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            description TEXT,
+            color TEXT DEFAULT '#3B82F6',
+            icon TEXT DEFAULT 'fa-list',
+            is_default INTEGER DEFAULT 0,
+            sort_order INTEGER DEFAULT 0,
+            created_at TEXT DEFAULT (datetime('now', 'localtime')),
+            updated_at TEXT DEFAULT (datetime('now', 'localtime')),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        );
+
+        -- Patient list members (join table)
+        CREATE TABLE IF NOT EXISTS patient_list_members (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            list_id INTEGER NOT NULL,
+            patient_id INTEGER NOT NULL,
+            added_at TEXT DEFAULT (datetime('now', 'localtime')),
+            notes TEXT,
+            FOREIGN KEY (list_id) REFERENCES patient_lists(id) ON DELETE CASCADE,
+            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE,
+            UNIQUE(list_id, patient_id)
+        );
+
+        -- Patient list columns (custom columns for each list)
+        CREATE TABLE IF NOT EXISTS patient_list_columns (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            list_id INTEGER NOT NULL,
+            column_key TEXT NOT NULL,
+            column_label TEXT NOT NULL,
+            column_type TEXT DEFAULT 'text',
+            is_visible INTEGER DEFAULT 1,
+            sort_order INTEGER DEFAULT 0,
+            width INTEGER DEFAULT 150,
+            FOREIGN KEY (list_id) REFERENCES patient_lists(id) ON DELETE CASCADE
+        );
+
+        -- Prescriptions table (tracks prescription history)
+        CREATE TABLE IF NOT EXISTS prescriptions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            patient_id INTEGER NOT NULL,
+            medication_id INTEGER NOT NULL,
+            quantity INTEGER NOT NULL,
+            days_supply INTEGER NOT NULL,
+            refills INTEGER DEFAULT 0,
+            sig TEXT NOT NULL,
+            pharmacy TEXT,
+            prescriber_id INTEGER,
+            status TEXT DEFAULT 'sent',
+            prescribed_date TEXT DEFAULT (datetime('now', 'localtime')),
+            filled_date TEXT,
+            notes TEXT,
+            created_at TEXT DEFAULT (datetime('now', 'localtime')),
+            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE,
+            FOREIGN KEY (medication_id) REFERENCES medications(id) ON DELETE CASCADE,
+            FOREIGN KEY (prescriber_id) REFERENCES users(id) ON DELETE SET NULL
+        );
+
+        -- Indexes for performance
+        CREATE INDEX IF NOT EXISTS idx_prescriptions_patient ON prescriptions(patient_id);
+        CREATE INDEX IF NOT EXISTS idx_prescriptions_medication ON prescriptions(medication_id);
+        CREATE INDEX IF NOT EXISTS idx_diagnoses_patient ON diagnoses(patient_id);
+        CREATE INDEX IF NOT EXISTS idx_medications_patient ON medications(patient_id);
+        CREATE INDEX IF NOT EXISTS idx_vitals_patient_date ON vitals(patient_id, recorded_at);
+        CREATE INDEX IF NOT EXISTS idx_labs_patient_date ON labs(patient_id, recorded_at);
+        CREATE INDEX IF NOT EXISTS idx_scores_patient_date ON clinical_scores(patient_id, recorded_at);
+        CREATE INDEX IF NOT EXISTS idx_encounters_patient_date ON encounters(patient_id, encounter_date);
+        CREATE INDEX IF NOT EXISTS idx_timeline_patient_date ON timeline_events(patient_id, event_date);
+        CREATE INDEX IF NOT EXISTS idx_user_education_user ON user_education(user_id);
+        CREATE INDEX IF NOT EXISTS idx_user_badges_user ON user_badges(user_id);
+        CREATE INDEX IF NOT EXISTS idx_patient_lists_user ON patient_lists(user_id);
+        CREATE INDEX IF NOT EXISTS idx_patient_list_members_list ON patient_list_members(list_id);
+        CREATE INDEX IF NOT EXISTS idx_patient_list_columns_list ON patient_list_columns(list_id);
+        "
+    )
+}
+
+/// 002: Columns that earlier builds added by probing with `SELECT` and ignoring `ALTER TABLE` failures
+fn m002_legacy_columns(tx: &Transaction) -> rusqlite::Result<()> {
+    add_column_if_missing(tx, "patients", "photo_url", "TEXT")?;
+    add_column_if_missing(tx, "patients", "ai_summary", "TEXT")?;
+    add_column_if_missing(tx, "patients", "preferred_pharmacy", "TEXT")?;
+    add_column_if_missing(tx, "patients", "insurance_provider", "TEXT")?;
+    add_column_if_missing(tx, "patients", "insurance_policy_number", "TEXT")?;
+    add_column_if_missing(tx, "patients", "insurance_group_number", "TEXT")?;
+    add_column_if_missing(tx, "diagnoses", "category", "TEXT")?;
+    add_column_if_missing(tx, "user_settings", "zen_mode_default", "INTEGER DEFAULT 0")?;
+    Ok(())
+}
+
+/// 003: FTS5 virtual tables and the triggers that keep them in sync
+fn m003_full_text_search(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        -- FTS5 Full-Text Search virtual tables for fast global search
+        CREATE VIRTUAL TABLE IF NOT EXISTS patients_fts USING fts5(
+            patient_id UNINDEXED,
+            first_name,
+            last_name,
+            phone,
+            email,
+            address,
+            ai_summary,
+            content='patients',
+            content_rowid='id'
+        );
+
+        CREATE VIRTUAL TABLE IF NOT EXISTS encounters_fts USING fts5(
+            encounter_id UNINDEXED,
+            patient_id UNINDEXED,
+            encounter_type,
+            chief_complaint,
+            summary,
+            note_content,
+            provider,
+            content='encounters',
+            content_rowid='id'
+        );
+
+        CREATE VIRTUAL TABLE IF NOT EXISTS diagnoses_fts USING fts5(
+            diagnosis_id UNINDEXED,
+            patient_id UNINDEXED,
+            icd_code,
+            description,
+            category,
+            notes,
+            content='diagnoses',
+            content_rowid='id'
+        );
+
+        CREATE VIRTUAL TABLE IF NOT EXISTS medications_fts USING fts5(
+            medication_id UNINDEXED,
+            patient_id UNINDEXED,
+            name,
+            dosage,
+            frequency,
+            prescriber,
+            notes,
+            content='medications',
+            content_rowid='id'
+        );
+
+        CREATE VIRTUAL TABLE IF NOT EXISTS labs_fts USING fts5(
+            lab_id UNINDEXED,
+            patient_id UNINDEXED,
+            test_name,
+            result,
+            unit,
+            notes,
+            content='labs',
+            content_rowid='id'
+        );
+
+        -- Triggers to keep FTS tables in sync with source tables
+        CREATE TRIGGER IF NOT EXISTS patients_ai AFTER INSERT ON patients BEGIN
+            INSERT INTO patients_fts(rowid, patient_id, first_name, last_name, phone, email, address, ai_summary)
+            VALUES (new.id, new.id, new.first_name, new.last_name, new.phone, new.email, new.address, new.ai_summary);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS patients_ad AFTER DELETE ON patients BEGIN
+            INSERT INTO patients_fts(patients_fts, rowid, patient_id, first_name, last_name, phone, email, address, ai_summary)
+            VALUES ('delete', old.id, old.id, old.first_name, old.last_name, old.phone, old.email, old.address, old.ai_summary);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS patients_au AFTER UPDATE ON patients BEGIN
+            INSERT INTO patients_fts(patients_fts, rowid, patient_id, first_name, last_name, phone, email, address, ai_summary)
+            VALUES ('delete', old.id, old.id, old.first_name, old.last_name, old.phone, old.email, old.address, old.ai_summary);
+            INSERT INTO patients_fts(rowid, patient_id, first_name, last_name, phone, email, address, ai_summary)
+            VALUES (new.id, new.id, new.first_name, new.last_name, new.phone, new.email, new.address, new.ai_summary);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS encounters_ai AFTER INSERT ON encounters BEGIN
+            INSERT INTO encounters_fts(rowid, encounter_id, patient_id, encounter_type, chief_complaint, summary, note_content, provider)
+            VALUES (new.id, new.id, new.patient_id, new.encounter_type, new.chief_complaint, new.summary, new.note_content, new.provider);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS encounters_ad AFTER DELETE ON encounters BEGIN
+            INSERT INTO encounters_fts(encounters_fts, rowid, encounter_id, patient_id, encounter_type, chief_complaint, summary, note_content, provider)
+            VALUES ('delete', old.id, old.id, old.patient_id, old.encounter_type, old.chief_complaint, old.summary, old.note_content, old.provider);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS encounters_au AFTER UPDATE ON encounters BEGIN
+            INSERT INTO encounters_fts(encounters_fts, rowid, encounter_id, patient_id, encounter_type, chief_complaint, summary, note_content, provider)
+            VALUES ('delete', old.id, old.id, old.patient_id, old.encounter_type, old.chief_complaint, old.summary, old.note_content, old.provider);
+            INSERT INTO encounters_fts(rowid, encounter_id, patient_id, encounter_type, chief_complaint, summary, note_content, provider)
+            VALUES (new.id, new.id, new.patient_id, new.encounter_type, new.chief_complaint, new.summary, new.note_content, new.provider);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS diagnoses_ai AFTER INSERT ON diagnoses BEGIN
+            INSERT INTO diagnoses_fts(rowid, diagnosis_id, patient_id, icd_code, description, category, notes)
+            VALUES (new.id, new.id, new.patient_id, new.icd_code, new.description, new.category, new.notes);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS diagnoses_ad AFTER DELETE ON diagnoses BEGIN
+            INSERT INTO diagnoses_fts(diagnoses_fts, rowid, diagnosis_id, patient_id, icd_code, description, category, notes)
+            VALUES ('delete', old.id, old.id, old.patient_id, old.icd_code, old.description, old.category, old.notes);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS diagnoses_au AFTER UPDATE ON diagnoses BEGIN
+            INSERT INTO diagnoses_fts(diagnoses_fts, rowid, diagnosis_id, patient_id, icd_code, description, category, notes)
+            VALUES ('delete', old.id, old.id, old.patient_id, old.icd_code, old.description, old.category, old.notes);
+            INSERT INTO diagnoses_fts(rowid, diagnosis_id, patient_id, icd_code, description, category, notes)
+            VALUES (new.id, new.id, new.patient_id, new.icd_code, new.description, new.category, new.notes);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS medications_ai AFTER INSERT ON medications BEGIN
+            INSERT INTO medications_fts(rowid, medication_id, patient_id, name, dosage, frequency, prescriber, notes)
+            VALUES (new.id, new.id, new.patient_id, new.name, new.dosage, new.frequency, new.prescriber, new.notes);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS medications_ad AFTER DELETE ON medications BEGIN
+            INSERT INTO medications_fts(medications_fts, rowid, medication_id, patient_id, name, dosage, frequency, prescriber, notes)
+            VALUES ('delete', old.id, old.id, old.patient_id, old.name, old.dosage, old.frequency, old.prescriber, old.notes);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS medications_au AFTER UPDATE ON medications BEGIN
+            INSERT INTO medications_fts(medications_fts, rowid, medication_id, patient_id, name, dosage, frequency, prescriber, notes)
+            VALUES ('delete', old.id, old.id, old.patient_id, old.name, old.dosage, old.frequency, old.prescriber, old.notes);
+            INSERT INTO medications_fts(rowid, medication_id, patient_id, name, dosage, frequency, prescriber, notes)
+            VALUES (new.id, new.id, new.patient_id, new.name, new.dosage, new.frequency, new.prescriber, new.notes);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS labs_ai AFTER INSERT ON labs BEGIN
+            INSERT INTO labs_fts(rowid, lab_id, patient_id, test_name, result, unit, notes)
+            VALUES (new.id, new.id, new.patient_id, new.test_name, new.result, new.unit, new.notes);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS labs_ad AFTER DELETE ON labs BEGIN
+            INSERT INTO labs_fts(labs_fts, rowid, lab_id, patient_id, test_name, result, unit, notes)
+            VALUES ('delete', old.id, old.id, old.patient_id, old.test_name, old.result, old.unit, old.notes);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS labs_au AFTER UPDATE ON labs BEGIN
+            INSERT INTO labs_fts(labs_fts, rowid, lab_id, patient_id, test_name, result, unit, notes)
+            VALUES ('delete', old.id, old.id, old.patient_id, old.test_name, old.result, old.unit, old.notes);
+            INSERT INTO labs_fts(rowid, lab_id, patient_id, test_name, result, unit, notes)
+            VALUES (new.id, new.id, new.patient_id, new.test_name, new.result, new.unit, new.notes);
+        END;
+        "
+    )
+}
+
+/// 004: Patient lifecycle status, plus the link a merged duplicate keeps to its survivor
+fn m004_patient_status(tx: &Transaction) -> rusqlite::Result<()> {
+    add_column_if_missing(tx, "patients", "status", "TEXT NOT NULL DEFAULT 'active'")?;
+    add_column_if_missing(tx, "patients", "status_date", "TEXT")?;
+    add_column_if_missing(tx, "patients", "merged_into_id", "INTEGER REFERENCES patients(id)")?;
+    tx.execute_batch("CREATE INDEX IF NOT EXISTS idx_patients_status ON patients(status);")
+}
+
+/// 005: Append-only log of who read or changed which chart
+fn m005_audit_log(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        -- No foreign keys: entries must survive deletion of the user, patient or row
+        CREATE TABLE IF NOT EXISTS audit_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            occurred_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+            user_id INTEGER,
+            action TEXT NOT NULL,
+            entity_type TEXT NOT NULL,
+            entity_id INTEGER,
+            patient_id INTEGER,
+            detail TEXT
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_audit_log_patient ON audit_log(patient_id, occurred_at);
+        CREATE INDEX IF NOT EXISTS idx_audit_log_user ON audit_log(user_id, occurred_at);
+        CREATE INDEX IF NOT EXISTS idx_audit_log_occurred ON audit_log(occurred_at);
+
+        CREATE TRIGGER IF NOT EXISTS audit_log_no_update BEFORE UPDATE ON audit_log BEGIN
+            SELECT RAISE(ABORT, 'audit_log is append-only');
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS audit_log_no_delete BEFORE DELETE ON audit_log BEGIN
+            SELECT RAISE(ABORT, 'audit_log is append-only');
+        END;
+        "
+    )
+}
+
+/// 006: Failed-login lockout and last sign-in for each user
+fn m006_user_login_state(tx: &Transaction) -> rusqlite::Result<()> {
+    add_column_if_missing(tx, "users", "failed_login_attempts", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(tx, "users", "locked_until", "TEXT")?;
+    add_column_if_missing(tx, "users", "last_login_at", "TEXT")?;
+    Ok(())
+}
+
+/// 007: TOTP replay protection and hashed one-time recovery codes
+fn m007_two_factor(tx: &Transaction) -> rusqlite::Result<()> {
+    add_column_if_missing(tx, "user_settings", "two_factor_last_step", "INTEGER")?;
+    tx.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS user_recovery_codes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            code_hash TEXT NOT NULL,
+            used_at TEXT,
+            created_at TEXT DEFAULT (datetime('now', 'localtime')),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_user_recovery_codes_user ON user_recovery_codes(user_id);
+        "
+    )
+}
+
+/// 008: One role per user for access control
+///
+/// Accounts that predate roles had unrestricted access, so they become
+/// physicians, and the oldest account becomes the administrator.
+fn m008_user_roles(tx: &Transaction) -> rusqlite::Result<()> {
+    add_column_if_missing(
+        tx,
+        "users",
+        "role",
+        "TEXT NOT NULL DEFAULT 'physician' CHECK (role IN ('physician', 'nurse', 'front_desk', 'billing', 'admin'))",
+    )?;
+    tx.execute(
+        "UPDATE users SET role = 'admin' WHERE id = (SELECT MIN(id) FROM users)
+           AND NOT EXISTS (SELECT 1 FROM users WHERE role = 'admin')",
+        [],
+    )?;
+    Ok(())
+}
+
+/// 009: Per-patient care teams and the break-the-glass emergency access log
+fn m009_care_teams_and_break_glass(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS patient_care_team (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            patient_id INTEGER NOT NULL,
+            user_id INTEGER NOT NULL,
+            added_by INTEGER,
+            added_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+            UNIQUE (patient_id, user_id),
+            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_patient_care_team_user ON patient_care_team(user_id);
+
+        -- No foreign keys, like audit_log: the record must outlive the patient and user
+        CREATE TABLE IF NOT EXISTS break_glass_access (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            patient_id INTEGER NOT NULL,
+            reason TEXT NOT NULL,
+            granted_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+            expires_at TEXT NOT NULL,
+            reviewed_at TEXT,
+            reviewed_by INTEGER,
+            review_note TEXT
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_break_glass_user_patient ON break_glass_access(user_id, patient_id, expires_at);
+        CREATE INDEX IF NOT EXISTS idx_break_glass_unreviewed ON break_glass_access(reviewed_at);
+        "
+    )
+}
+
+/// 010: Note signatures and append-only addenda
+///
+/// Triggers back up the command-layer checks: once `signed_at` is set the
+/// note and its signature cannot change, and addenda cannot be edited or
+/// removed except by deleting the whole chart. Only `patient_id` may still
+/// change, so merging patients keeps working.
+fn m010_encounter_signing(tx: &Transaction) -> rusqlite::Result<()> {
+    add_column_if_missing(tx, "encounters", "signed_at", "TEXT")?;
+    add_column_if_missing(tx, "encounters", "signed_by", "INTEGER REFERENCES users(id)")?;
+    add_column_if_missing(tx, "encounters", "cosigned_at", "TEXT")?;
+    add_column_if_missing(tx, "encounters", "cosigned_by", "INTEGER REFERENCES users(id)")?;
+    tx.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS encounter_addenda (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            encounter_id INTEGER NOT NULL,
+            patient_id INTEGER NOT NULL,
+            author_id INTEGER REFERENCES users(id),
+            content TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+            FOREIGN KEY (encounter_id) REFERENCES encounters(id) ON DELETE CASCADE,
+            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_encounter_addenda_encounter ON encounter_addenda(encounter_id);
+
+        CREATE TRIGGER IF NOT EXISTS encounters_signed_immutable BEFORE UPDATE ON encounters
+        WHEN OLD.signed_at IS NOT NULL AND (
+            NEW.encounter_date IS NOT OLD.encounter_date
+            OR NEW.encounter_type IS NOT OLD.encounter_type
+            OR NEW.chief_complaint IS NOT OLD.chief_complaint
+            OR NEW.summary IS NOT OLD.summary
+            OR NEW.note_content IS NOT OLD.note_content
+            OR NEW.provider IS NOT OLD.provider
+            OR NEW.location IS NOT OLD.location
+            OR NEW.signed_at IS NOT OLD.signed_at
+            OR NEW.signed_by IS NOT OLD.signed_by
+            OR (OLD.cosigned_at IS NOT NULL AND (
+                NEW.cosigned_at IS NOT OLD.cosigned_at OR NEW.cosigned_by IS NOT OLD.cosigned_by))
+        )
+        BEGIN
+            SELECT RAISE(ABORT, 'encounter is signed; add an addendum instead');
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS encounter_addenda_immutable BEFORE UPDATE ON encounter_addenda
+        WHEN NEW.content IS NOT OLD.content
+            OR NEW.encounter_id IS NOT OLD.encounter_id
+            OR NEW.author_id IS NOT OLD.author_id
+            OR NEW.created_at IS NOT OLD.created_at
+        BEGIN
+            SELECT RAISE(ABORT, 'addenda cannot be edited');
+        END;
+        "
+    )
+}
+
+/// 011: Encounter revision history
+///
+/// Each existing encounter gets a first revision holding its current text,
+/// with no author since nobody knows who wrote it, so the first tracked edit
+/// has something to diff against.
+fn m011_encounter_revisions(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS encounter_revisions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            encounter_id INTEGER NOT NULL,
+            patient_id INTEGER NOT NULL,
+            revision_number INTEGER NOT NULL,
+            author_id INTEGER REFERENCES users(id),
+            chief_complaint TEXT,
+            summary TEXT,
+            note_content TEXT,
+            restored_from INTEGER REFERENCES encounter_revisions(id),
+            created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+            UNIQUE (encounter_id, revision_number),
+            FOREIGN KEY (encounter_id) REFERENCES encounters(id) ON DELETE CASCADE,
+            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
+        );
+
+        INSERT INTO encounter_revisions (encounter_id, patient_id, revision_number, chief_complaint, summary, note_content)
+        SELECT id, patient_id, 1, chief_complaint, summary, note_content FROM encounters
+        WHERE id NOT IN (SELECT encounter_id FROM encounter_revisions);
+
+        CREATE TRIGGER IF NOT EXISTS encounter_revisions_immutable BEFORE UPDATE ON encounter_revisions
+        WHEN NEW.encounter_id IS NOT OLD.encounter_id
+            OR NEW.revision_number IS NOT OLD.revision_number
+            OR NEW.author_id IS NOT OLD.author_id
+            OR NEW.chief_complaint IS NOT OLD.chief_complaint
+            OR NEW.summary IS NOT OLD.summary
+            OR NEW.note_content IS NOT OLD.note_content
+            OR NEW.restored_from IS NOT OLD.restored_from
+            OR NEW.created_at IS NOT OLD.created_at
+        BEGIN
+            SELECT RAISE(ABORT, 'revisions cannot be edited');
+        END;
+        "
+    )
+}
+
+/// 012: `row_version` on every table edited as a whole object, for optimistic concurrency
+fn m012_row_versions(tx: &Transaction) -> rusqlite::Result<()> {
+    for table in [
+        "patients",
+        "encounters",
+        "allergies",
+        "vaccinations",
+        "social_history",
+        "family_history",
+        "users",
+        "user_settings",
+        "patient_lists",
+    ] {
+        add_column_if_missing(tx, table, "row_version", "INTEGER NOT NULL DEFAULT 1")?;
+    }
+    Ok(())
+}
+
+/// 013: Rebuild the search indexes over the columns the app actually writes
+///
+/// The 003 indexes named columns that the source tables never fill (`labs.test_name`,
+/// `diagnoses.description`) or do not have at all (`patients.patient_id`), so lab
+/// and diagnosis searches missed most rows and patient lookups failed outright.
+/// Every table is re-indexed from scratch, and the remaining chart tables and
+/// messages get indexes of their own.
+fn m013_search_indexes(tx: &Transaction) -> rusqlite::Result<()> {
+    let indexes: &[(&str, &[&str])] = &[
+        ("patients", &["first_name", "last_name", "phone", "email", "address", "ai_summary"]),
+        (
+            "encounters",
+            &["=patient_id", "encounter_type", "chief_complaint", "summary", "note_content", "provider"],
+        ),
+        ("diagnoses", &["=patient_id", "name", "icd_code", "description", "category", "notes"]),
+        (
+            "medications",
+            &["=patient_id", "name", "dose", "dosage", "frequency", "route", "prescriber", "notes"],
+        ),
+        ("labs", &["=patient_id", "name", "test_name", "result", "unit", "notes"]),
+        ("allergies", &["=patient_id", "allergen", "reaction", "severity"]),
+        ("vaccinations", &["=patient_id", "vaccine_name", "lot_number", "site", "administered_by"]),
+        ("social_history", &["=patient_id", "category", "detail", "status"]),
+        ("family_history", &["=patient_id", "relation", "condition", "notes"]),
+        ("todos", &["=patient_id", "description", "priority", "status"]),
+        ("prescriptions", &["=patient_id", "sig", "pharmacy", "notes"]),
+        ("messages", &["=patient_id", "subject", "body"]),
+    ];
+    for (table, columns) in indexes {
+        create_search_index(tx, table, columns)?;
+    }
+    Ok(())
+}
+
+/// 014: Orphaned rows moved aside by the integrity checker, kept whole so they can be restored by hand
+fn m014_quarantined_rows(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        -- No foreign keys: these rows are here because what they pointed at is gone
+        CREATE TABLE IF NOT EXISTS quarantined_rows (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            quarantined_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+            quarantined_by INTEGER,
+            source_table TEXT NOT NULL,
+            source_id INTEGER NOT NULL,
+            row_data TEXT NOT NULL,
+            reason TEXT NOT NULL
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_quarantined_rows_source ON quarantined_rows(source_table, source_id);
+        ",
+    )
+}
+
+/// 015: Mark vitals calculated from others in the same set (BMI, MAP, BSA), which are recomputed rather than edited
+///
+/// Vitals are edited one reading at a time from here on, so they get their
+/// `row_version` in the same step.
+fn m015_derived_vitals(tx: &Transaction) -> rusqlite::Result<()> {
+    add_column_if_missing(tx, "vitals", "is_derived", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(tx, "vitals", "row_version", "INTEGER NOT NULL DEFAULT 1")
+}
+
+/// 016: Reference ranges by test, sex and age, which decide a lab result's flag instead of the client
+///
+/// Seeded with common adult ranges in US conventional units; a practice edits
+/// them to match its own laboratory. `sex` and the age bounds are NULL when a
+/// range applies to everyone, and `age_max_years` is exclusive.
+fn m016_lab_reference_ranges(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS lab_reference_ranges (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            test_name TEXT NOT NULL,
+            unit TEXT,
+            sex TEXT,
+            age_min_years INTEGER,
+            age_max_years INTEGER,
+            low REAL,
+            high REAL,
+            critical_low REAL,
+            critical_high REAL,
+            created_at TEXT DEFAULT (datetime('now', 'localtime'))
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_lab_reference_ranges_test ON lab_reference_ranges(test_name COLLATE NOCASE);
+
+        INSERT INTO lab_reference_ranges (test_name, unit, sex, low, high, critical_low, critical_high) VALUES
+            ('Glucose', 'mg/dL', NULL, 70, 100, 40, 400),
+            ('Hemoglobin A1c', '%', NULL, 4.0, 5.6, NULL, NULL),
+            ('Sodium', 'mmol/L', NULL, 135, 145, 120, 160),
+            ('Potassium', 'mmol/L', NULL, 3.5, 5.0, 2.5, 6.5),
+            ('Chloride', 'mmol/L', NULL, 98, 107, 80, 120),
+            ('Bicarbonate', 'mmol/L', NULL, 22, 29, 10, 40),
+            ('BUN', 'mg/dL', NULL, 7, 20, NULL, 100),
+            ('Creatinine', 'mg/dL', 'M', 0.74, 1.35, NULL, 10),
+            ('Creatinine', 'mg/dL', 'F', 0.59, 1.04, NULL, 10),
+            ('Calcium', 'mg/dL', NULL, 8.6, 10.3, 6.5, 13),
+            ('Hemoglobin', 'g/dL', 'M', 13.5, 17.5, 7, 20),
+            ('Hemoglobin', 'g/dL', 'F', 12.0, 15.5, 7, 20),
+            ('Hematocrit', '%', 'M', 41, 50, 20, 60),
+            ('Hematocrit', '%', 'F', 36, 44, 20, 60),
+            ('WBC', 'K/uL', NULL, 4.5, 11.0, 2, 30),
+            ('Platelets', 'K/uL', NULL, 150, 400, 50, 1000),
+            ('TSH', 'mIU/L', NULL, 0.4, 4.0, NULL, NULL),
+            ('ALT', 'U/L', NULL, 7, 56, NULL, 1000),
+            ('AST', 'U/L', NULL, 10, 40, NULL, 1000),
+            ('Total Cholesterol', 'mg/dL', NULL, NULL, 200, NULL, NULL),
+            ('LDL', 'mg/dL', NULL, NULL, 100, NULL, NULL),
+            ('HDL', 'mg/dL', 'M', 40, NULL, NULL, NULL),
+            ('HDL', 'mg/dL', 'F', 50, NULL, NULL, NULL),
+            ('Triglycerides', 'mg/dL', NULL, NULL, 150, NULL, 1000);
+        ",
+    )?;
+    // Which band the value fell in when it was recorded: normal, low, high, critical_low or critical_high
+    add_column_if_missing(tx, "labs", "interpretation", "TEXT")?;
+    // Results are edited one at a time from here on
+    add_column_if_missing(tx, "labs", "row_version", "INTEGER NOT NULL DEFAULT 1")
+}
+
+/// 017: Lab and imaging orders, and the link from a lab result to the order it answers
+///
+/// `status` moves ordered → collected → resulted, or to cancelled before a
+/// result arrives. A resulted order stays outstanding until a clinician
+/// records `reviewed_at`, so a result nobody looked at cannot drop off the list.
+fn m017_orders(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS orders (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            patient_id INTEGER NOT NULL,
+            encounter_id INTEGER,
+            diagnosis_id INTEGER,
+            order_type TEXT NOT NULL,
+            test_name TEXT NOT NULL,
+            priority TEXT NOT NULL DEFAULT 'routine',
+            status TEXT NOT NULL DEFAULT 'ordered',
+            notes TEXT,
+            ordered_by INTEGER,
+            ordered_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+            collected_at TEXT,
+            resulted_at TEXT,
+            result_summary TEXT,
+            reviewed_at TEXT,
+            reviewed_by INTEGER,
+            cancelled_at TEXT,
+            cancel_reason TEXT,
+            updated_at TEXT DEFAULT (datetime('now', 'localtime')),
+            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE,
+            FOREIGN KEY (encounter_id) REFERENCES encounters(id) ON DELETE SET NULL,
+            FOREIGN KEY (diagnosis_id) REFERENCES diagnoses(id) ON DELETE SET NULL,
+            FOREIGN KEY (ordered_by) REFERENCES users(id) ON DELETE SET NULL,
+            FOREIGN KEY (reviewed_by) REFERENCES users(id) ON DELETE SET NULL
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_orders_patient_status ON orders(patient_id, status);
+        CREATE INDEX IF NOT EXISTS idx_orders_status_ordered ON orders(status, ordered_at);
+        CREATE INDEX IF NOT EXISTS idx_orders_encounter ON orders(encounter_id);
+        ",
+    )?;
+    add_column_if_missing(tx, "labs", "order_id", "INTEGER REFERENCES orders(id) ON DELETE SET NULL")?;
+    tx.execute_batch("CREATE INDEX IF NOT EXISTS idx_labs_order ON labs(order_id);")
+}
@@ -1,6 +1,18 @@
-struct Appointment {
-    name: String,
-    age: u64,
-    sex: String,
-    time: String
-}
\ No newline at end of file
+use super::Entity;
+use crate::db::Appointment;
+use crate::store::Creatable;
+
+// Schedule lists join the patient's name, so only create goes through here
+
+impl Entity for Appointment {
+    const TABLE: &'static str = "appointments";
+    const COLUMNS: &'static [&'static str] = &["id", "patient_id", "appointment_time", "duration_minutes", "reason", "status", "notes"];
+    const WRITABLE: &'static [&'static str] = &["appointment_time", "duration_minutes", "reason", "status", "notes"];
+    const CREATE_ONLY: &'static [&'static str] = &["patient_id"];
+    const ORDER_BY: &'static str = "appointment_time";
+    const TOUCHES_UPDATED_AT: bool = true;
+}
+
+impl Creatable for Appointment {
+    type Entity = Appointment;
+}
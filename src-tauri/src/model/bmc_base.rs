@@ -1,12 +1,173 @@
 // reuseable low level backend model controller functions
 
-use super::{fire_model_event, ModelMutateResultData};
+use super::{fire_model_event, Entity, ModelMutateResultData};
+use crate::concurrency;
+use crate::error::Result as AppResult;
+use crate::event::ModelAction;
+use crate::store::{self, Creatable, Filterable, Object, Patchable};
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Result, Row};
 
-pub async fn bmc_get<E>(ctx: Arc<Ctx>, _entity: &'static strc, id: &str)
-    -> Result<E> where E: TryFrom<Object, Error = Error>, {
-        ctx.get_store().exec_get(&id).await?.try_into()
+/// `SELECT <columns> FROM <table>`, with each column prefixed by `alias.` when given
+pub fn select_sql<E: Entity>(alias: Option<&str>) -> String {
+    let columns: Vec<String> = E::COLUMNS
+        .iter()
+        .map(|c| match alias {
+            Some(alias) => format!("{}.{}", alias, c),
+            None => c.to_string(),
+        })
+        .collect();
+    match alias {
+        Some(alias) => format!("SELECT {} FROM {} {}", columns.join(", "), E::TABLE, alias),
+        None => format!("SELECT {} FROM {}", columns.join(", "), E::TABLE),
+    }
 }
 
-pub async fn bmc_create<D>() -> Result<ModelMutateResultData> where D: Creatable, {}
+/// Deserialize a row selected with `select_sql`
+pub fn entity_from_row<E: Entity>(row: &Row) -> Result<E> {
+    store::from_object(store::row_to_object(row, E::COLUMNS)?)
+}
+
+pub fn bmc_get<E: Entity>(conn: &Connection, id: i64) -> Result<Option<E>> {
+    let sql = format!("{} WHERE id = ?1", select_sql::<E>(None));
+    conn.query_row(&sql, params![id], entity_from_row::<E>).optional()
+}
+
+/// Rows of `E` matching every non-null field of `filter`
+///
+/// A filter may name any column of the table, not only those `E` selects, so
+/// allergies can be narrowed by `status` without carrying it.
+pub fn bmc_list<E: Entity>(conn: &Connection, filter: &impl Filterable) -> Result<Vec<E>> {
+    let mut conditions: Vec<String> = E::LIST_SCOPE.iter().map(|s| s.to_string()).collect();
+    let mut values = Vec::new();
+    for (column, value) in store::to_object(filter)? {
+        if value.is_null() {
+            continue;
+        }
+        values.push(store::to_sql(&value));
+        conditions.push(format!("{} = ?{}", column, values.len()));
+    }
+
+    let mut sql = select_sql::<E>(None);
+    if !conditions.is_empty() {
+        sql.push_str(&format!(" WHERE {}", conditions.join(" AND ")));
+    }
+    sql.push_str(&format!(" ORDER BY {}", E::ORDER_BY));
+
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params_from_iter(values), entity_from_row::<E>)?;
+    rows.collect()
+}
+
+pub fn bmc_create<D: Creatable>(conn: &Connection, data: &D) -> Result<ModelMutateResultData> {
+    let entity = <D::Entity as Entity>::TABLE;
+    // Leaving out nulls lets column defaults such as `status = 'active'` apply
+    let mut object = store::to_object(data)?;
+    object.retain(|_, value| !value.is_null());
+    let columns = writable::<D::Entity>(object, true)?;
+    let names: Vec<&str> = columns.iter().map(|(c, _)| c.as_str()).collect();
+    let placeholders: Vec<String> = (1..=names.len()).map(|i| format!("?{}", i)).collect();
+    let sql = if names.is_empty() {
+        format!("INSERT INTO {} DEFAULT VALUES", entity)
+    } else {
+        format!("INSERT INTO {} ({}) VALUES ({})", entity, names.join(", "), placeholders.join(", "))
+    };
+    conn.execute(&sql, params_from_iter(columns.into_iter().map(|(_, v)| v)))?;
+    let result = mutated::<D::Entity>(conn, conn.last_insert_rowid())?;
+    fire_model_event(conn, entity, ModelAction::Create, result);
+    Ok(result)
+}
+
+/// Write `data` over row `id`; `None` if the row is gone or, on a versioned
+/// table, `data` carries a `row_version` that is no longer current
+///
+/// On a versioned table `data` must carry its `row_version`; without one the
+/// update is refused rather than applied blind.
+pub fn bmc_update<D: Patchable>(conn: &Connection, id: i64, data: &D) -> AppResult<Option<ModelMutateResultData>> {
+    let mut object = store::to_object(data)?;
+    let expected_version = object.remove("row_version");
+    let columns = writable::<D::Entity>(object, false)?;
+
+    let mut sets: Vec<String> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, (column, _))| format!("{} = ?{}", column, i + 1))
+        .collect();
+    if <D::Entity as Entity>::TOUCHES_UPDATED_AT {
+        sets.push("updated_at = datetime('now', 'localtime')".to_string());
+    }
+    if <D::Entity as Entity>::VERSIONED {
+        sets.push("row_version = row_version + 1".to_string());
+    }
+    if sets.is_empty() {
+        return Ok(bmc_get::<D::Entity>(conn, id)?.map(|_| ModelMutateResultData { id, patient_id: None }));
+    }
+
+    let mut values: Vec<SqlValue> = columns.into_iter().map(|(_, v)| v).collect();
+    values.push(SqlValue::Integer(id));
+    let mut sql = format!(
+        "UPDATE {} SET {} WHERE id = ?{}",
+        <D::Entity as Entity>::TABLE,
+        sets.join(", "),
+        values.len()
+    );
+    if <D::Entity as Entity>::VERSIONED {
+        let version = concurrency::require_version(expected_version.and_then(|v| v.as_i64()))?;
+        values.push(SqlValue::Integer(version));
+        sql.push_str(&format!(" AND row_version = ?{}", values.len()));
+    }
 
-pub async fn bmc_update<D>() -> Result<ModelMutateResultData> where D: Patchable, {}
\ No newline at end of file
+    if conn.execute(&sql, params_from_iter(values))? == 0 {
+        return Ok(None);
+    }
+    let result = mutated::<D::Entity>(conn, id)?;
+    fire_model_event(conn, <D::Entity as Entity>::TABLE, ModelAction::Update, result);
+    Ok(Some(result))
+}
+
+pub fn bmc_delete<E: Entity>(conn: &Connection, id: i64) -> Result<Option<ModelMutateResultData>> {
+    // Read the owner first; afterwards there is no row left to ask
+    let result = mutated::<E>(conn, id)?;
+    if conn.execute(&format!("DELETE FROM {} WHERE id = ?1", E::TABLE), params![id])? == 0 {
+        return Ok(None);
+    }
+    fire_model_event(conn, E::TABLE, ModelAction::Delete, result);
+    Ok(Some(result))
+}
+
+/// The patient row `id` belongs to; `None` if there is no such row
+pub fn bmc_owner<E: Entity>(conn: &Connection, id: i64) -> Result<Option<i64>> {
+    Ok(conn
+        .query_row(
+            &format!("SELECT {} FROM {} WHERE id = ?1", E::PATIENT_COLUMN, E::TABLE),
+            params![id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .flatten())
+}
+
+/// Row `id` together with the patient it belongs to
+fn mutated<E: Entity>(conn: &Connection, id: i64) -> Result<ModelMutateResultData> {
+    Ok(ModelMutateResultData { id, patient_id: bmc_owner::<E>(conn, id)? })
+}
+
+/// The columns of `object` this entity lets a caller write
+///
+/// Read-only columns such as `id` or `status` are dropped; a key that is not a
+/// column at all is refused, since it means the struct and table disagree.
+fn writable<E: Entity>(object: Object, creating: bool) -> Result<Vec<(String, SqlValue)>> {
+    let mut columns = Vec::new();
+    for (column, value) in object {
+        if E::WRITABLE.contains(&column.as_str()) || (creating && E::CREATE_ONLY.contains(&column.as_str())) {
+            columns.push((column, store::to_sql(&value)));
+        } else if !E::COLUMNS.contains(&column.as_str()) {
+            return Err(unknown_column::<E>(&column));
+        }
+    }
+    Ok(columns)
+}
+
+fn unknown_column<E: Entity>(column: &str) -> rusqlite::Error {
+    rusqlite::Error::InvalidColumnName(format!("{}.{}", E::TABLE, column))
+}
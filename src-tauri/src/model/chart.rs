@@ -0,0 +1,82 @@
+use super::Entity;
+use crate::db::{ClinicalScore, Diagnosis, Lab, Medication, TimelineEvent, Vital};
+use crate::store::{Creatable, Patchable};
+
+// The problem list, medications and measurements shown on the chart
+
+impl Entity for Diagnosis {
+    const TABLE: &'static str = "diagnoses";
+    const COLUMNS: &'static [&'static str] = &["id", "patient_id", "name", "icd_code", "onset_date", "status", "category"];
+    const WRITABLE: &'static [&'static str] = &["name", "icd_code", "onset_date", "status", "category"];
+    const CREATE_ONLY: &'static [&'static str] = &["patient_id"];
+    const ORDER_BY: &'static str = "onset_date DESC";
+}
+
+impl Entity for Medication {
+    const TABLE: &'static str = "medications";
+    const COLUMNS: &'static [&'static str] = &[
+        "id", "patient_id", "name", "dose", "frequency", "route", "start_date", "end_date", "status",
+    ];
+    const WRITABLE: &'static [&'static str] = &["name", "dose", "frequency", "route", "start_date", "end_date", "status"];
+    const CREATE_ONLY: &'static [&'static str] = &["patient_id"];
+    const ORDER_BY: &'static str = "start_date DESC";
+}
+
+impl Entity for Vital {
+    const TABLE: &'static str = "vitals";
+    const COLUMNS: &'static [&'static str] = &[
+        "id", "patient_id", "vital_type", "value", "value_secondary", "unit", "recorded_at", "is_derived",
+        "row_version",
+    ];
+    const WRITABLE: &'static [&'static str] = &["vital_type", "value", "value_secondary", "unit", "recorded_at"];
+    const CREATE_ONLY: &'static [&'static str] = &["patient_id"];
+    const ORDER_BY: &'static str = "recorded_at ASC";
+    const VERSIONED: bool = true;
+}
+
+impl Entity for Lab {
+    const TABLE: &'static str = "labs";
+    const COLUMNS: &'static [&'static str] = &[
+        "id", "patient_id", "name", "value", "unit", "reference_range_low", "reference_range_high",
+        "is_abnormal", "interpretation", "recorded_at", "order_id", "row_version",
+    ];
+    const WRITABLE: &'static [&'static str] = &[
+        "name", "value", "unit", "reference_range_low", "reference_range_high", "is_abnormal", "interpretation",
+        "recorded_at",
+    ];
+    const CREATE_ONLY: &'static [&'static str] = &["patient_id"];
+    const ORDER_BY: &'static str = "recorded_at ASC";
+    const VERSIONED: bool = true;
+}
+
+impl Entity for ClinicalScore {
+    const TABLE: &'static str = "clinical_scores";
+    const COLUMNS: &'static [&'static str] = &["id", "patient_id", "score_type", "score", "max_score", "interpretation", "recorded_at"];
+    const WRITABLE: &'static [&'static str] = &["score_type", "score", "max_score", "interpretation", "recorded_at"];
+    const CREATE_ONLY: &'static [&'static str] = &["patient_id"];
+    const ORDER_BY: &'static str = "recorded_at ASC";
+}
+
+impl Entity for TimelineEvent {
+    const TABLE: &'static str = "timeline_events";
+    const COLUMNS: &'static [&'static str] = &["id", "patient_id", "event_type", "description", "event_date", "icon", "color"];
+    const WRITABLE: &'static [&'static str] = &["event_type", "description", "event_date", "icon", "color"];
+    const CREATE_ONLY: &'static [&'static str] = &["patient_id"];
+    const ORDER_BY: &'static str = "event_date ASC";
+}
+
+impl Creatable for Vital {
+    type Entity = Vital;
+}
+
+impl Patchable for Vital {
+    type Entity = Vital;
+}
+
+impl Creatable for Lab {
+    type Entity = Lab;
+}
+
+impl Patchable for Lab {
+    type Entity = Lab;
+}
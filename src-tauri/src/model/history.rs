@@ -0,0 +1,70 @@
+use super::Entity;
+use crate::db::{Allergy, FamilyHistory, SocialHistory, Vaccination};
+use crate::store::{Creatable, Patchable};
+
+// Allergies and the history lists are edited as whole records from the chart
+
+impl Entity for Allergy {
+    const TABLE: &'static str = "allergies";
+    const COLUMNS: &'static [&'static str] = &["id", "patient_id", "allergen", "reaction", "severity", "row_version"];
+    const WRITABLE: &'static [&'static str] = &["allergen", "reaction", "severity"];
+    const CREATE_ONLY: &'static [&'static str] = &["patient_id"];
+    const VERSIONED: bool = true;
+}
+
+impl Entity for Vaccination {
+    const TABLE: &'static str = "vaccinations";
+    const COLUMNS: &'static [&'static str] = &["id", "patient_id", "vaccine_name", "date_given", "row_version"];
+    const WRITABLE: &'static [&'static str] = &["vaccine_name", "date_given"];
+    const CREATE_ONLY: &'static [&'static str] = &["patient_id"];
+    const ORDER_BY: &'static str = "date_given DESC";
+    const VERSIONED: bool = true;
+}
+
+impl Entity for SocialHistory {
+    const TABLE: &'static str = "social_history";
+    const COLUMNS: &'static [&'static str] = &["id", "patient_id", "category", "detail", "status", "row_version"];
+    const WRITABLE: &'static [&'static str] = &["category", "detail", "status"];
+    const CREATE_ONLY: &'static [&'static str] = &["patient_id"];
+    const VERSIONED: bool = true;
+}
+
+impl Entity for FamilyHistory {
+    const TABLE: &'static str = "family_history";
+    const COLUMNS: &'static [&'static str] = &["id", "patient_id", "relation", "condition", "age_at_onset", "row_version"];
+    const WRITABLE: &'static [&'static str] = &["relation", "condition", "age_at_onset"];
+    const CREATE_ONLY: &'static [&'static str] = &["patient_id"];
+    const VERSIONED: bool = true;
+}
+
+impl Creatable for Allergy {
+    type Entity = Allergy;
+}
+
+impl Patchable for Allergy {
+    type Entity = Allergy;
+}
+
+impl Creatable for Vaccination {
+    type Entity = Vaccination;
+}
+
+impl Patchable for Vaccination {
+    type Entity = Vaccination;
+}
+
+impl Creatable for SocialHistory {
+    type Entity = SocialHistory;
+}
+
+impl Patchable for SocialHistory {
+    type Entity = SocialHistory;
+}
+
+impl Creatable for FamilyHistory {
+    type Entity = FamilyHistory;
+}
+
+impl Patchable for FamilyHistory {
+    type Entity = FamilyHistory;
+}
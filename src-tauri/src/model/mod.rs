@@ -0,0 +1,79 @@
+use crate::event::{self, ModelAction, ModelEvent};
+use rusqlite::Connection;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+pub mod appointment;
+pub mod bmc_base;
+pub mod chart;
+pub mod history;
+pub mod patient;
+pub mod task;
+
+pub use bmc_base::{bmc_create, bmc_delete, bmc_get, bmc_list, bmc_owner, bmc_update};
+
+// ============ Backend Model Controllers ============
+//
+// Tables that are plain records get their CRUD from the generic functions in
+// `bmc_base` instead of hand-written SQL. An entity only declares its table
+// and columns; field names on the struct must match the column names.
+
+/// A struct stored one-to-one as a row of `TABLE`
+pub trait Entity: Serialize + DeserializeOwned {
+    const TABLE: &'static str;
+    /// Selected in this order and deserialized by name; must include `id`
+    const COLUMNS: &'static [&'static str];
+    /// Columns that create and update may write
+    const WRITABLE: &'static [&'static str];
+    /// Columns only set on create, such as the owning `patient_id`
+    const CREATE_ONLY: &'static [&'static str] = &[];
+    const ORDER_BY: &'static str = "id";
+    /// Extra condition every list applies, e.g. hiding merged patients
+    const LIST_SCOPE: Option<&'static str> = None;
+    /// Whether the table has a `row_version` that updates check and bump
+    const VERSIONED: bool = false;
+    /// Whether the table has an `updated_at` that updates refresh
+    const TOUCHES_UPDATED_AT: bool = false;
+    /// Column naming the patient a row belongs to, for change events
+    const PATIENT_COLUMN: &'static str = "patient_id";
+}
+
+/// What a create, update or delete touched
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ModelMutateResultData {
+    pub id: i64,
+    pub patient_id: Option<i64>,
+}
+
+/// Tell open windows that `data` was created, updated or deleted in `entity` through `conn`
+///
+/// The generic controllers call this themselves; commands that write through
+/// hand-written SQL call it once the write has succeeded.
+pub fn fire_model_event(conn: &Connection, entity: &'static str, action: ModelAction, data: ModelMutateResultData) {
+    event::queue(conn, ModelEvent {
+        entity,
+        id: data.id,
+        action,
+        patient_id: data.patient_id,
+    });
+}
+
+/// The rows belonging to one patient, optionally only those with `status`
+#[derive(Debug, Serialize)]
+pub struct PatientScope {
+    pub patient_id: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<&'static str>,
+}
+
+impl PatientScope {
+    pub fn all(patient_id: i64) -> Self {
+        PatientScope { patient_id, status: None }
+    }
+
+    pub fn active(patient_id: i64) -> Self {
+        PatientScope { patient_id, status: Some("active") }
+    }
+}
+
+impl crate::store::Filterable for PatientScope {}
@@ -1,12 +1,20 @@
-#[skip_serializing_none]
-#[derive(Serialize, TS, Debug)]
-#[ts(export, export_to = "../src/bindings/")]
-pub struct Task {
-    pub id: String,
-    pub ctime: String,
-    pub doc_id: String,
+use super::Entity;
+use crate::db::{Goal, Todo};
 
-    pub done: bool,
-    pub title: String,
-    pub desc: Option<String>,
-}
\ No newline at end of file
+impl Entity for Todo {
+    const TABLE: &'static str = "todos";
+    const COLUMNS: &'static [&'static str] = &["id", "patient_id", "diagnosis_id", "description", "due_date", "priority", "status"];
+    const WRITABLE: &'static [&'static str] = &["diagnosis_id", "description", "due_date", "priority", "status"];
+    const CREATE_ONLY: &'static [&'static str] = &["patient_id"];
+    const ORDER_BY: &'static str = "due_date ASC";
+    const TOUCHES_UPDATED_AT: bool = true;
+}
+
+impl Entity for Goal {
+    const TABLE: &'static str = "goals";
+    const COLUMNS: &'static [&'static str] = &["id", "patient_id", "description", "target_date", "status", "progress"];
+    const WRITABLE: &'static [&'static str] = &["description", "target_date", "status", "progress"];
+    const CREATE_ONLY: &'static [&'static str] = &["patient_id"];
+    const ORDER_BY: &'static str = "target_date ASC";
+    const TOUCHES_UPDATED_AT: bool = true;
+}
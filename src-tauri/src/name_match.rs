@@ -0,0 +1,1037 @@
+use crate::db::Patient;
+use crate::model::bmc_get;
+use crate::validation::parse_date;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use ts_rs::TS;
+
+// ============ Fuzzy Patient Matching ============
+//
+// FTS only finds a patient whose name starts with what was typed. This
+// matcher catches the rest: misspellings (trigram similarity), names that
+// sound the same but are spelled differently (Soundex and Double Metaphone),
+// first and last name entered the wrong way round, and dates of birth with
+// the day and month swapped or one part wrong. Every candidate gets a
+// confidence between 0 and 1 and the reasons behind it.
+//
+// Candidates are scored in Rust against every unmerged patient; a practice's
+// patient table is small enough that this stays well under a keystroke.
+
+/// Lowest confidence `quick_search_patients` adds to its FTS hits
+pub const SEARCH_THRESHOLD: f64 = 0.7;
+
+/// Lowest confidence reported as a possible duplicate at registration
+pub const DUPLICATE_THRESHOLD: f64 = 0.75;
+
+/// Codes are cut to this length, as in the published algorithms
+const PHONETIC_LENGTH: usize = 4;
+
+/// A patient that may be the one being looked for
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
+pub struct PatientMatch {
+    pub patient: Patient,
+    /// 1.0 for an exact name and date of birth
+    pub confidence: f64,
+    /// Why it matched, e.g. "Last name sounds alike"
+    pub reasons: Vec<String>,
+}
+
+/// What is known about the patient being looked for
+#[derive(Debug, Clone, Default)]
+pub struct NameQuery {
+    /// Name words in first-to-last order
+    pub names: Vec<String>,
+    pub dob: Option<String>,
+}
+
+impl NameQuery {
+    /// Read free text such as `Jon Smyth`, `Smyth, Jon` or `Smyth 1980-04-02`
+    pub fn from_text(text: &str) -> Self {
+        let mut query = NameQuery::default();
+        // "Last, First" is how names are read off most paperwork
+        let text = match text.split_once(',') {
+            Some((last, first)) => format!("{} {}", first, last),
+            None => text.to_string(),
+        };
+        for word in text.split_whitespace() {
+            if let Some(dob) = normalize_date(word) {
+                query.dob = Some(dob);
+            } else if word.chars().any(char::is_alphabetic) {
+                query.names.push(word.to_string());
+            }
+        }
+        query
+    }
+
+    pub fn from_patient(patient: &Patient) -> Self {
+        NameQuery {
+            names: vec![patient.first_name.clone(), patient.last_name.clone()],
+            dob: normalize_date(&patient.dob),
+        }
+    }
+
+    /// How likely the patient with these details is the one being looked for, with the reasons
+    pub fn score(&self, first_name: &str, last_name: &str, dob: &str) -> (f64, Vec<String>) {
+        let mut reasons = Vec::new();
+
+        let name = match self.names.as_slice() {
+            [] => None,
+            [word] => {
+                let first = compare_names(word, first_name);
+                let last = compare_names(word, last_name);
+                if last.0 >= first.0 {
+                    reasons.extend(describe("Last name", last));
+                    Some(last.0)
+                } else {
+                    reasons.extend(describe("First name", first));
+                    Some(first.0)
+                }
+            }
+            [query_first, .., query_last] => {
+                let first = compare_names(query_first, first_name);
+                let last = compare_names(query_last, last_name);
+                let straight = 0.45 * first.0 + 0.55 * last.0;
+                let swapped_first = compare_names(query_last, first_name);
+                let swapped_last = compare_names(query_first, last_name);
+                // Swapped names are a common entry error, but less likely than a straight match
+                let swapped = 0.9 * (0.45 * swapped_first.0 + 0.55 * swapped_last.0);
+                if straight >= swapped {
+                    reasons.extend(describe("First name", first));
+                    reasons.extend(describe("Last name", last));
+                    Some(straight)
+                } else {
+                    reasons.push("First and last name are swapped".to_string());
+                    Some(swapped)
+                }
+            }
+        };
+
+        let dob_score = self.dob.as_deref().map(|wanted| {
+            let (score, reason) = compare_dates(wanted, dob);
+            reasons.extend(reason.map(str::to_string));
+            score
+        });
+
+        let confidence = match (name, dob_score) {
+            (Some(name), Some(dob)) => 0.7 * name + 0.3 * dob,
+            (Some(name), None) => name,
+            (None, Some(dob)) => dob,
+            (None, None) => 0.0,
+        };
+        (confidence, reasons)
+    }
+}
+
+/// Unmerged patients matching `query` with at least `min_confidence`, best first
+pub fn find_patient_matches(
+    conn: &Connection,
+    query: &NameQuery,
+    min_confidence: f64,
+    limit: usize,
+    exclude_id: Option<i64>,
+) -> rusqlite::Result<Vec<PatientMatch>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, first_name, last_name, dob FROM patients
+         WHERE status != 'merged' AND (?1 IS NULL OR id != ?1)",
+    )?;
+    let rows = stmt.query_map([exclude_id], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?))
+    })?;
+
+    let mut scored = Vec::new();
+    for row in rows {
+        let (id, first_name, last_name, dob) = row?;
+        let (confidence, reasons) = query.score(&first_name, &last_name, &dob);
+        if confidence >= min_confidence {
+            scored.push((id, confidence, reasons));
+        }
+    }
+    scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    scored.truncate(limit);
+
+    let mut matches = Vec::with_capacity(scored.len());
+    for (id, confidence, reasons) in scored {
+        if let Some(patient) = bmc_get::<Patient>(conn, id)? {
+            matches.push(PatientMatch { patient, confidence, reasons });
+        }
+    }
+    Ok(matches)
+}
+
+// ============ Comparing Names and Dates ============
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Agreement {
+    Exact,
+    SoundsAlike,
+    SpelledAlike,
+    Different,
+}
+
+fn describe(label: &str, (_, agreement): (f64, Agreement)) -> Option<String> {
+    match agreement {
+        Agreement::Exact => Some(format!("{} matches", label)),
+        Agreement::SoundsAlike => Some(format!("{} sounds alike", label)),
+        Agreement::SpelledAlike => Some(format!("{} is spelled similarly", label)),
+        Agreement::Different => None,
+    }
+}
+
+/// Similarity of two name parts, 0 to 1, and what kind of agreement it was
+fn compare_names(a: &str, b: &str) -> (f64, Agreement) {
+    let a = normalize_name(a);
+    let b = normalize_name(b);
+    if a.is_empty() || b.is_empty() {
+        return (0.0, Agreement::Different);
+    }
+    if a == b {
+        return (1.0, Agreement::Exact);
+    }
+
+    let spelled = trigram_similarity(&a, &b);
+    let (a_primary, a_alternate) = double_metaphone(&a);
+    let (b_primary, b_alternate) = double_metaphone(&b);
+    let metaphone = [&a_primary, &a_alternate]
+        .iter()
+        .any(|code| !code.is_empty() && (**code == b_primary || **code == b_alternate));
+    let sounds = if metaphone {
+        0.85
+    } else if !soundex(&a).is_empty() && soundex(&a) == soundex(&b) {
+        0.75
+    } else {
+        0.0
+    };
+
+    if sounds > 0.0 && sounds >= spelled {
+        (sounds, Agreement::SoundsAlike)
+    } else if spelled >= 0.5 {
+        (spelled, Agreement::SpelledAlike)
+    } else {
+        (spelled, Agreement::Different)
+    }
+}
+
+/// Similarity of two `YYYY-MM-DD` dates, allowing for the usual entry mistakes
+fn compare_dates(a: &str, b: &str) -> (f64, Option<&'static str>) {
+    let (Some(a), Some(b)) = (parse_date(a), normalize_date(b).as_deref().and_then(parse_date)) else {
+        return (0.0, None);
+    };
+    if a == b {
+        (1.0, Some("Date of birth matches"))
+    } else if a.0 == b.0 && a.1 == b.2 && a.2 == b.1 {
+        (0.7, Some("Date of birth matches with day and month swapped"))
+    } else if [a.0 == b.0, a.1 == b.1, a.2 == b.2].iter().filter(|same| **same).count() == 2 {
+        (0.5, Some("Date of birth differs in one part"))
+    } else {
+        (0.0, None)
+    }
+}
+
+/// `YYYY-MM-DD` for an ISO or US (`MM/DD/YYYY`) date
+pub fn normalize_date(text: &str) -> Option<String> {
+    if parse_date(text).is_some() {
+        return Some(text.to_string());
+    }
+    let mut parts = text.split('/');
+    let (month, day, year) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() || year.len() != 4 {
+        return None;
+    }
+    let date = format!("{}-{:0>2}-{:0>2}", year, month, day);
+    parse_date(&date).map(|_| date)
+}
+
+/// Uppercase letters only, with accents folded, so `O'Brien` and `obrien` or `Núñez` and `Nunez` compare equal
+fn normalize_name(name: &str) -> String {
+    let mut normalized = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_ascii_alphabetic() {
+            normalized.push(c.to_ascii_uppercase());
+        } else if let Some(folded) = fold_accent(c) {
+            normalized.push_str(folded);
+        } else if c.is_alphabetic() {
+            // Names in other scripts still compare letter for letter
+            normalized.extend(c.to_uppercase());
+        }
+    }
+    normalized
+}
+
+/// The unaccented spelling of a Latin letter, as registration clerks usually type it
+fn fold_accent(c: char) -> Option<&'static str> {
+    let folded = match c.to_lowercase().next()? {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "A",
+        'æ' => "AE",
+        'ç' | 'ć' | 'č' => "C",
+        'ď' | 'đ' | 'ð' => "D",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => "E",
+        'ğ' => "G",
+        'ì' | 'í' | 'î' | 'ï' | 'ī' | 'į' | 'ı' => "I",
+        'ł' => "L",
+        'ñ' | 'ń' | 'ň' => "N",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => "O",
+        'œ' => "OE",
+        'ř' => "R",
+        'ß' => "SS",
+        'ś' | 'š' | 'ş' => "S",
+        'ť' | 'ţ' => "T",
+        'þ' => "TH",
+        'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' => "U",
+        'ý' | 'ÿ' => "Y",
+        'ź' | 'ż' | 'ž' => "Z",
+        _ => return None,
+    };
+    Some(folded)
+}
+
+/// Shared three-letter sequences over all distinct ones, with the word padded as pg_trgm does
+pub fn trigram_similarity(a: &str, b: &str) -> f64 {
+    let a = trigrams(a);
+    let b = trigrams(b);
+    if a.is_empty() && b.is_empty() {
+        return 0.0;
+    }
+    let shared = a.intersection(&b).count();
+    shared as f64 / (a.len() + b.len() - shared) as f64
+}
+
+fn trigrams(word: &str) -> HashSet<[char; 3]> {
+    if word.trim().is_empty() {
+        return HashSet::new();
+    }
+    let padded: Vec<char> = format!("  {} ", word.to_lowercase()).chars().collect();
+    padded.windows(3).map(|w| [w[0], w[1], w[2]]).collect()
+}
+
+// ============ Soundex ============
+
+/// American Soundex: the first letter and three digits, e.g. `Robert` and `Rupert` are both `R163`
+pub fn soundex(name: &str) -> String {
+    let letters: Vec<char> = normalize_name(name).chars().filter(char::is_ascii_alphabetic).collect();
+    let Some(&first) = letters.first() else {
+        return String::new();
+    };
+
+    let mut code = String::from(first);
+    let mut last = soundex_digit(first);
+    for &c in &letters[1..] {
+        let digit = soundex_digit(c);
+        match digit {
+            // H and W do not separate letters with the same code
+            None if c == 'H' || c == 'W' => continue,
+            Some(d) if Some(d) != last => code.push(d),
+            _ => {}
+        }
+        last = digit;
+        if code.len() == PHONETIC_LENGTH {
+            break;
+        }
+    }
+    while code.len() < PHONETIC_LENGTH {
+        code.push('0');
+    }
+    code
+}
+
+fn soundex_digit(c: char) -> Option<char> {
+    match c {
+        'B' | 'F' | 'P' | 'V' => Some('1'),
+        'C' | 'G' | 'J' | 'K' | 'Q' | 'S' | 'X' | 'Z' => Some('2'),
+        'D' | 'T' => Some('3'),
+        'L' => Some('4'),
+        'M' | 'N' => Some('5'),
+        'R' => Some('6'),
+        _ => None,
+    }
+}
+
+// ============ Double Metaphone ============
+//
+// Lawrence Philips' algorithm, following the rule order of the Apache Commons
+// Codec implementation. It returns a primary code and an alternate for names
+// whose pronunciation depends on their origin (`Schmidt` is `XMT` or `SMT`).
+
+/// Primary and alternate Double Metaphone codes; the alternate equals the primary when there is no second reading
+pub fn double_metaphone(name: &str) -> (String, String) {
+    let word = Word::new(name);
+    let mut code = Code::default();
+    if word.chars.is_empty() {
+        return (String::new(), String::new());
+    }
+
+    let mut i: isize = if word.starts_silently() { 1 } else { 0 };
+    while !code.complete() && i < word.len() {
+        i = match word.at(i) {
+            'A' | 'E' | 'I' | 'O' | 'U' | 'Y' => {
+                if i == 0 {
+                    code.both("A");
+                }
+                i + 1
+            }
+            'B' => {
+                code.both("P");
+                if word.at(i + 1) == 'B' { i + 2 } else { i + 1 }
+            }
+            'Ç' => {
+                code.both("S");
+                i + 1
+            }
+            'C' => word.handle_c(&mut code, i),
+            'D' => word.handle_d(&mut code, i),
+            'F' => {
+                code.both("F");
+                if word.at(i + 1) == 'F' { i + 2 } else { i + 1 }
+            }
+            'G' => word.handle_g(&mut code, i),
+            'H' => word.handle_h(&mut code, i),
+            'J' => word.handle_j(&mut code, i),
+            'K' => {
+                code.both("K");
+                if word.at(i + 1) == 'K' { i + 2 } else { i + 1 }
+            }
+            'L' => word.handle_l(&mut code, i),
+            'M' => {
+                code.both("M");
+                if word.m_is_doubled(i) { i + 2 } else { i + 1 }
+            }
+            'N' => {
+                code.both("N");
+                if word.at(i + 1) == 'N' { i + 2 } else { i + 1 }
+            }
+            'Ñ' => {
+                code.both("N");
+                i + 1
+            }
+            'P' => word.handle_p(&mut code, i),
+            'Q' => {
+                code.both("K");
+                if word.at(i + 1) == 'Q' { i + 2 } else { i + 1 }
+            }
+            'R' => word.handle_r(&mut code, i),
+            'S' => word.handle_s(&mut code, i),
+            'T' => word.handle_t(&mut code, i),
+            'V' => {
+                code.both("F");
+                if word.at(i + 1) == 'V' { i + 2 } else { i + 1 }
+            }
+            'W' => word.handle_w(&mut code, i),
+            'X' => word.handle_x(&mut code, i),
+            'Z' => word.handle_z(&mut code, i),
+            _ => i + 1,
+        };
+    }
+    (code.primary, code.alternate)
+}
+
+#[derive(Default)]
+struct Code {
+    primary: String,
+    alternate: String,
+}
+
+impl Code {
+    fn complete(&self) -> bool {
+        self.primary.len() >= PHONETIC_LENGTH && self.alternate.len() >= PHONETIC_LENGTH
+    }
+
+    fn push(target: &mut String, value: &str) {
+        let room = PHONETIC_LENGTH.saturating_sub(target.len());
+        target.extend(value.chars().take(room));
+    }
+
+    fn both(&mut self, value: &str) {
+        self.split(value, value);
+    }
+
+    fn split(&mut self, primary: &str, alternate: &str) {
+        Code::push(&mut self.primary, primary);
+        Code::push(&mut self.alternate, alternate);
+    }
+
+    fn primary_only(&mut self, value: &str) {
+        Code::push(&mut self.primary, value);
+    }
+
+    fn alternate_only(&mut self, value: &str) {
+        Code::push(&mut self.alternate, value);
+    }
+}
+
+struct Word {
+    chars: Vec<char>,
+    slavo_germanic: bool,
+}
+
+impl Word {
+    fn new(name: &str) -> Self {
+        let upper = name.trim().to_uppercase();
+        let slavo_germanic = upper.contains('W') || upper.contains('K') || upper.contains("CZ") || upper.contains("WITZ");
+        Word { chars: upper.chars().collect(), slavo_germanic }
+    }
+
+    fn len(&self) -> isize {
+        self.chars.len() as isize
+    }
+
+    fn last(&self) -> isize {
+        self.len() - 1
+    }
+
+    /// The letter at `i`, or NUL outside the word
+    fn at(&self, i: isize) -> char {
+        if i < 0 {
+            return '\0';
+        }
+        self.chars.get(i as usize).copied().unwrap_or('\0')
+    }
+
+    /// Whether the `len` letters starting at `start` are one of `options`
+    fn is(&self, start: isize, len: usize, options: &[&str]) -> bool {
+        if start < 0 || start as usize + len > self.chars.len() {
+            return false;
+        }
+        let part: String = self.chars[start as usize..start as usize + len].iter().collect();
+        options.contains(&part.as_str())
+    }
+
+    fn is_vowel(&self, i: isize) -> bool {
+        matches!(self.at(i), 'A' | 'E' | 'I' | 'O' | 'U' | 'Y')
+    }
+
+    fn starts_silently(&self) -> bool {
+        self.is(0, 2, &["GN", "KN", "PN", "WR", "PS"])
+    }
+
+    fn germanic_prefix(&self) -> bool {
+        self.is(0, 4, &["VAN ", "VON "]) || self.is(0, 3, &["SCH"])
+    }
+
+    fn handle_c(&self, code: &mut Code, i: isize) -> isize {
+        if self.c_is_k_in_ach(i) {
+            code.both("K");
+            i + 2
+        } else if i == 0 && self.is(i, 6, &["CAESAR"]) {
+            code.both("S");
+            i + 2
+        } else if self.is(i, 2, &["CH"]) {
+            self.handle_ch(code, i)
+        } else if self.is(i, 2, &["CZ"]) && !self.is(i - 2, 4, &["WICZ"]) {
+            code.split("S", "X");
+            i + 2
+        } else if self.is(i + 1, 3, &["CIA"]) {
+            code.both("X");
+            i + 3
+        } else if self.is(i, 2, &["CC"]) && !(i == 1 && self.at(0) == 'M') {
+            self.handle_cc(code, i)
+        } else if self.is(i, 2, &["CK", "CG", "CQ"]) {
+            code.both("K");
+            i + 2
+        } else if self.is(i, 2, &["CI", "CE", "CY"]) {
+            if self.is(i, 3, &["CIO", "CIE", "CIA"]) {
+                code.split("S", "X");
+            } else {
+                code.both("S");
+            }
+            i + 2
+        } else {
+            code.both("K");
+            if self.is(i + 1, 2, &[" C", " Q", " G"]) {
+                i + 3
+            } else if self.is(i + 1, 1, &["C", "K", "Q"]) && !self.is(i + 1, 2, &["CE", "CI"]) {
+                i + 2
+            } else {
+                i + 1
+            }
+        }
+    }
+
+    /// `CHIA`, or the hard `ACH` of `BACHER`
+    fn c_is_k_in_ach(&self, i: isize) -> bool {
+        if self.is(i, 4, &["CHIA"]) {
+            return true;
+        }
+        if i <= 1 || self.is_vowel(i - 2) || !self.is(i - 1, 3, &["ACH"]) {
+            return false;
+        }
+        let next = self.at(i + 2);
+        (next != 'I' && next != 'E') || self.is(i - 2, 6, &["BACHER", "MACHER"])
+    }
+
+    fn handle_cc(&self, code: &mut Code, i: isize) -> isize {
+        if self.is(i + 2, 1, &["I", "E", "H"]) && !self.is(i + 2, 2, &["HU"]) {
+            if (i == 1 && self.at(i - 1) == 'A') || self.is(i - 1, 5, &["UCCEE", "UCCES"]) {
+                code.both("KS");
+            } else {
+                code.both("X");
+            }
+            i + 3
+        } else {
+            code.both("K");
+            i + 2
+        }
+    }
+
+    fn handle_ch(&self, code: &mut Code, i: isize) -> isize {
+        if i > 0 && self.is(i, 4, &["CHAE"]) {
+            code.split("K", "X");
+        } else if self.ch_is_greek_start(i) || self.ch_is_k(i) {
+            code.both("K");
+        } else if i > 0 {
+            if self.is(0, 2, &["MC"]) {
+                code.both("K");
+            } else {
+                code.split("X", "K");
+            }
+        } else {
+            code.both("X");
+        }
+        i + 2
+    }
+
+    /// `CHARACTER`, `CHORUS` and friends
+    fn ch_is_greek_start(&self, i: isize) -> bool {
+        i == 0
+            && (self.is(i + 1, 5, &["HARAC", "HARIS"]) || self.is(i + 1, 3, &["HOR", "HYM", "HIA", "HEM"]))
+            && !self.is(0, 5, &["CHORE"])
+    }
+
+    fn ch_is_k(&self, i: isize) -> bool {
+        self.germanic_prefix()
+            || self.is(i - 2, 6, &["ORCHES", "ARCHIT", "ORCHID"])
+            || self.is(i + 2, 1, &["T", "S"])
+            || ((self.is(i - 1, 1, &["A", "O", "U", "E"]) || i == 0)
+                && (self.is(i + 2, 1, &["L", "R", "N", "M", "B", "H", "F", "V", "W", " "]) || i + 1 == self.last()))
+    }
+
+    fn handle_d(&self, code: &mut Code, i: isize) -> isize {
+        if self.is(i, 2, &["DG"]) {
+            if self.is(i + 2, 1, &["I", "E", "Y"]) {
+                code.both("J");
+                i + 3
+            } else {
+                code.both("TK");
+                i + 2
+            }
+        } else if self.is(i, 2, &["DT", "DD"]) {
+            code.both("T");
+            i + 2
+        } else {
+            code.both("T");
+            i + 1
+        }
+    }
+
+    fn handle_g(&self, code: &mut Code, i: isize) -> isize {
+        let next = self.at(i + 1);
+        if next == 'H' {
+            self.handle_gh(code, i)
+        } else if next == 'N' {
+            if i == 1 && self.is_vowel(0) && !self.slavo_germanic {
+                code.split("KN", "N");
+            } else if !self.is(i + 2, 2, &["EY"]) && self.at(i + 1) != 'Y' && !self.slavo_germanic {
+                code.split("N", "KN");
+            } else {
+                code.both("KN");
+            }
+            i + 2
+        } else if self.is(i + 1, 2, &["LI"]) && !self.slavo_germanic {
+            code.split("KL", "L");
+            i + 2
+        } else if (i == 0
+            && (next == 'Y' || self.is(i + 1, 2, &["ES", "EP", "EB", "EL", "EY", "IB", "IL", "IN", "IE", "EI", "ER"])))
+            || ((self.is(i + 1, 2, &["ER"]) || next == 'Y')
+                && !self.is(0, 6, &["DANGER", "RANGER", "MANGER"])
+                && !self.is(i - 1, 1, &["E", "I"])
+                && !self.is(i - 1, 3, &["RGY", "OGY"]))
+        {
+            code.split("K", "J");
+            i + 2
+        } else if self.is(i + 1, 1, &["E", "I", "Y"]) || self.is(i - 1, 4, &["AGGI", "OGGI"]) {
+            if self.germanic_prefix() || self.is(i + 1, 2, &["ET"]) {
+                code.both("K");
+            } else if self.is(i + 1, 3, &["IER"]) {
+                code.both("J");
+            } else {
+                code.split("J", "K");
+            }
+            i + 2
+        } else if next == 'G' {
+            code.both("K");
+            i + 2
+        } else {
+            code.both("K");
+            i + 1
+        }
+    }
+
+    fn handle_gh(&self, code: &mut Code, i: isize) -> isize {
+        if i > 0 && !self.is_vowel(i - 1) {
+            code.both("K");
+        } else if i == 0 {
+            code.both(if self.at(i + 2) == 'I' { "J" } else { "K" });
+        } else if (i > 1 && self.is(i - 2, 1, &["B", "H", "D"]))
+            || (i > 2 && self.is(i - 3, 1, &["B", "H", "D"]))
+            || (i > 3 && self.is(i - 4, 1, &["B", "H"]))
+        {
+            // Silent, as in `HUGH` and `BOUGH`
+        } else if i > 2 && self.at(i - 1) == 'U' && self.is(i - 3, 1, &["C", "G", "L", "R", "T"]) {
+            code.both("F");
+        } else if i > 0 && self.at(i - 1) != 'I' {
+            code.both("K");
+        }
+        i + 2
+    }
+
+    fn handle_h(&self, code: &mut Code, i: isize) -> isize {
+        if (i == 0 || self.is_vowel(i - 1)) && self.is_vowel(i + 1) {
+            code.both("H");
+            i + 2
+        } else {
+            i + 1
+        }
+    }
+
+    fn handle_j(&self, code: &mut Code, i: isize) -> isize {
+        if self.is(i, 4, &["JOSE"]) || self.is(0, 4, &["SAN "]) {
+            if (i == 0 && self.at(i + 4) == ' ') || self.len() == 4 || self.is(0, 4, &["SAN "]) {
+                code.both("H");
+            } else {
+                code.split("J", "H");
+            }
+            return i + 1;
+        }
+
+        if i == 0 {
+            code.split("J", "A");
+        } else if self.is_vowel(i - 1) && !self.slavo_germanic && matches!(self.at(i + 1), 'A' | 'O') {
+            code.split("J", "H");
+        } else if i == self.last() {
+            code.primary_only("J");
+        } else if !self.is(i + 1, 1, &["L", "T", "K", "S", "N", "M", "B", "Z"]) && !self.is(i - 1, 1, &["S", "K", "L"]) {
+            code.both("J");
+        }
+        if self.at(i + 1) == 'J' { i + 2 } else { i + 1 }
+    }
+
+    fn handle_l(&self, code: &mut Code, i: isize) -> isize {
+        if self.at(i + 1) != 'L' {
+            code.both("L");
+            return i + 1;
+        }
+        // Spanish `-ILLO`, `-ILLA` and `-ALLE` have no L sound in the primary reading
+        let spanish = (i == self.len() - 3 && self.is(i - 1, 4, &["ILLO", "ILLA", "ALLE"]))
+            || ((self.is(self.len() - 2, 2, &["AS", "OS"]) || self.is(self.last(), 1, &["A", "O"]))
+                && self.is(i - 1, 4, &["ALLE"]));
+        if spanish {
+            code.primary_only("L");
+        } else {
+            code.both("L");
+        }
+        i + 2
+    }
+
+    /// `MM`, or the silent B of `DUMB` and `THUMBER`
+    fn m_is_doubled(&self, i: isize) -> bool {
+        self.at(i + 1) == 'M' || (self.is(i - 1, 3, &["UMB"]) && (i + 1 == self.last() || self.is(i + 2, 2, &["ER"])))
+    }
+
+    fn handle_p(&self, code: &mut Code, i: isize) -> isize {
+        if self.at(i + 1) == 'H' {
+            code.both("F");
+            i + 2
+        } else {
+            code.both("P");
+            if self.is(i + 1, 1, &["P", "B"]) { i + 2 } else { i + 1 }
+        }
+    }
+
+    fn handle_r(&self, code: &mut Code, i: isize) -> isize {
+        // French final R is silent: `ROGIER`
+        if i == self.last() && !self.slavo_germanic && self.is(i - 2, 2, &["IE"]) && !self.is(i - 4, 2, &["ME", "MA"]) {
+            code.alternate_only("R");
+        } else {
+            code.both("R");
+        }
+        if self.at(i + 1) == 'R' { i + 2 } else { i + 1 }
+    }
+
+    fn handle_s(&self, code: &mut Code, i: isize) -> isize {
+        if self.is(i - 1, 3, &["ISL", "YSL"]) {
+            i + 1
+        } else if i == 0 && self.is(i, 5, &["SUGAR"]) {
+            code.split("X", "S");
+            i + 1
+        } else if self.is(i, 2, &["SH"]) {
+            code.both(if self.is(i + 1, 4, &["HEIM", "HOEK", "HOLM", "HOLZ"]) { "S" } else { "X" });
+            i + 2
+        } else if self.is(i, 3, &["SIO", "SIA"]) || self.is(i, 4, &["SIAN"]) {
+            if self.slavo_germanic {
+                code.both("S");
+            } else {
+                code.split("S", "X");
+            }
+            i + 3
+        } else if (i == 0 && self.is(i + 1, 1, &["M", "N", "L", "W"])) || self.is(i + 1, 1, &["Z"]) {
+            code.split("S", "X");
+            if self.is(i + 1, 1, &["Z"]) { i + 2 } else { i + 1 }
+        } else if self.is(i, 2, &["SC"]) {
+            self.handle_sc(code, i)
+        } else {
+            // French final S is silent: `DUBOIS`
+            if i == self.last() && self.is(i - 2, 2, &["AI", "OI"]) {
+                code.alternate_only("S");
+            } else {
+                code.both("S");
+            }
+            if self.is(i + 1, 1, &["S", "Z"]) { i + 2 } else { i + 1 }
+        }
+    }
+
+    fn handle_sc(&self, code: &mut Code, i: isize) -> isize {
+        if self.at(i + 2) == 'H' {
+            if self.is(i + 3, 2, &["OO", "ER", "EN", "UY", "ED", "EM"]) {
+                if self.is(i + 3, 2, &["ER", "EN"]) {
+                    code.split("X", "SK");
+                } else {
+                    code.both("SK");
+                }
+            } else if i == 0 && !self.is_vowel(3) && self.at(3) != 'W' {
+                code.split("X", "S");
+            } else {
+                code.both("X");
+            }
+        } else if self.is(i + 2, 1, &["I", "E", "Y"]) {
+            code.both("S");
+        } else {
+            code.both("SK");
+        }
+        i + 3
+    }
+
+    fn handle_t(&self, code: &mut Code, i: isize) -> isize {
+        if self.is(i, 4, &["TION"]) || self.is(i, 3, &["TIA", "TCH"]) {
+            code.both("X");
+            i + 3
+        } else if self.is(i, 2, &["TH"]) || self.is(i, 3, &["TTH"]) {
+            if self.is(i + 2, 2, &["OM", "AM"]) || self.germanic_prefix() {
+                code.both("T");
+            } else {
+                code.split("0", "T");
+            }
+            i + 2
+        } else {
+            code.both("T");
+            if self.is(i + 1, 1, &["T", "D"]) { i + 2 } else { i + 1 }
+        }
+    }
+
+    fn handle_w(&self, code: &mut Code, i: isize) -> isize {
+        if self.is(i, 2, &["WR"]) {
+            code.both("R");
+            return i + 2;
+        }
+        if i == 0 && (self.is_vowel(i + 1) || self.is(i, 2, &["WH"])) {
+            if self.is_vowel(i + 1) {
+                code.split("A", "F");
+            } else {
+                code.both("A");
+            }
+            i + 1
+        } else if (i == self.last() && self.is_vowel(i - 1))
+            || self.is(i - 1, 5, &["EWSKI", "EWSKY", "OWSKI", "OWSKY"])
+            || self.is(0, 3, &["SCH"])
+        {
+            code.alternate_only("F");
+            i + 1
+        } else if self.is(i, 4, &["WICZ", "WITZ"]) {
+            code.split("TS", "FX");
+            i + 4
+        } else {
+            i + 1
+        }
+    }
+
+    fn handle_x(&self, code: &mut Code, i: isize) -> isize {
+        if i == 0 {
+            code.both("S");
+            return i + 1;
+        }
+        // French final X is silent: `BREAUX`
+        let silent = i == self.last() && (self.is(i - 3, 3, &["IAU", "EAU"]) || self.is(i - 2, 2, &["AU", "OU"]));
+        if !silent {
+            code.both("KS");
+        }
+        if self.is(i + 1, 1, &["C", "X"]) { i + 2 } else { i + 1 }
+    }
+
+    fn handle_z(&self, code: &mut Code, i: isize) -> isize {
+        if self.at(i + 1) == 'H' {
+            code.both("J");
+            return i + 2;
+        }
+        if self.is(i + 1, 2, &["ZO", "ZI", "ZA"]) || (self.slavo_germanic && i > 0 && self.at(i - 1) != 'T') {
+            code.split("S", "TS");
+        } else {
+            code.both("S");
+        }
+        if self.at(i + 1) == 'Z' { i + 2 } else { i + 1 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn agreement(a: &str, b: &str) -> Agreement {
+        compare_names(a, b).1
+    }
+
+    #[test]
+    fn soundex_matches_published_codes() {
+        let expected = [
+            ("Robert", "R163"),
+            ("Rupert", "R163"),
+            ("Rubin", "R150"),
+            ("Ashcraft", "A261"),
+            ("Tymczak", "T522"),
+            ("Pfister", "P236"),
+            ("Honeyman", "H555"),
+            ("Lee", "L000"),
+        ];
+        for (name, code) in expected {
+            assert_eq!(soundex(name), code, "{}", name);
+        }
+    }
+
+    #[test]
+    fn double_metaphone_matches_published_codes() {
+        let expected = [
+            ("Smith", "SM0", "XMT"),
+            ("Schmidt", "XMT", "SMT"),
+            ("Thomas", "TMS", "TMS"),
+            ("Catherine", "K0RN", "KTRN"),
+            ("Jose", "HS", "HS"),
+            ("Xavier", "SF", "SFR"),
+            ("Caesar", "SSR", "SSR"),
+            ("Gough", "KF", "KF"),
+            ("Breaux", "PR", "PR"),
+            ("Philips", "FLPS", "FLPS"),
+            ("Jack", "JK", "AK"),
+            ("Wagner", "AKNR", "FKNR"),
+        ];
+        for (name, primary, alternate) in expected {
+            assert_eq!(double_metaphone(name), (primary.to_string(), alternate.to_string()), "{}", name);
+        }
+    }
+
+    #[test]
+    fn codes_are_cut_to_four_characters() {
+        assert_eq!(soundex("Christopherson").len(), PHONETIC_LENGTH);
+        let (primary, alternate) = double_metaphone("Christopherson");
+        assert_eq!(primary, "KRST");
+        assert_eq!(alternate, "KRST");
+    }
+
+    #[test]
+    fn trigram_similarity_is_shared_over_distinct() {
+        assert_eq!(trigram_similarity("Smith", "Smith"), 1.0);
+        assert_eq!(trigram_similarity("Smith", "Jones"), 0.0);
+        // "  s", " sm", "smi" are shared, out of six distinct on each side
+        assert!((trigram_similarity("Smith", "Smyth") - 1.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn sound_alike_names_are_recognised() {
+        let pairs = [
+            ("Jon", "John"),
+            ("Smith", "Smyth"),
+            ("Catherine", "Kathryn"),
+            ("Schneider", "Snyder"),
+            ("Stephen", "Steven"),
+            ("Michael", "Micheal"),
+            ("Elizabeth", "Elisabeth"),
+            ("Christopher", "Christophr"),
+            ("Patricia", "Patrica"),
+        ];
+        for (a, b) in pairs {
+            assert_eq!(agreement(a, b), Agreement::SoundsAlike, "{} / {}", a, b);
+        }
+    }
+
+    #[test]
+    fn misspellings_that_change_the_sound_are_spelled_alike() {
+        assert_eq!(agreement("Montgomery", "Nontgomery"), Agreement::SpelledAlike);
+        assert_eq!(agreement("Smith", "Jones"), Agreement::Different);
+    }
+
+    #[test]
+    fn punctuation_case_and_accents_are_ignored() {
+        assert_eq!(compare_names("O'Brien", "obrien"), (1.0, Agreement::Exact));
+        assert_eq!(compare_names("Núñez", "Nunez"), (1.0, Agreement::Exact));
+        assert_eq!(compare_names("Müller", "MULLER"), (1.0, Agreement::Exact));
+        assert_eq!(compare_names("Strauß", "Strauss"), (1.0, Agreement::Exact));
+        assert_eq!(compare_names("François", "Francois"), (1.0, Agreement::Exact));
+    }
+
+    #[test]
+    fn names_in_other_scripts_compare_letter_for_letter() {
+        assert_eq!(compare_names("李", "李"), (1.0, Agreement::Exact));
+        assert_eq!(agreement("李", "王"), Agreement::Different);
+        assert_eq!(soundex("李"), "");
+        assert_eq!(double_metaphone("李"), (String::new(), String::new()));
+    }
+
+    #[test]
+    fn empty_input_matches_nothing() {
+        assert_eq!(soundex(""), "");
+        assert_eq!(double_metaphone(""), (String::new(), String::new()));
+        assert_eq!(trigram_similarity("", ""), 0.0);
+        assert_eq!(trigram_similarity("", "Smith"), 0.0);
+        assert_eq!(compare_names("", ""), (0.0, Agreement::Different));
+        assert_eq!(compare_names("'-", "Smith"), (0.0, Agreement::Different));
+        assert_eq!(NameQuery::from_text("").score("John", "Smith", "1980-04-02"), (0.0, Vec::new()));
+        assert_eq!(NameQuery::from_text("   ").names.len(), 0);
+    }
+
+    #[test]
+    fn free_text_reads_names_and_dates() {
+        let query = NameQuery::from_text("Smyth, Jon 04/02/1980");
+        assert_eq!(query.names, ["Jon", "Smyth"]);
+        assert_eq!(query.dob.as_deref(), Some("1980-04-02"));
+    }
+
+    #[test]
+    fn dates_allow_common_entry_mistakes() {
+        assert_eq!(normalize_date("4/2/1980").as_deref(), Some("1980-04-02"));
+        assert_eq!(normalize_date("13/02/1980"), None);
+        assert_eq!(normalize_date("1980-02-30"), None);
+        assert_eq!(compare_dates("1980-04-02", "1980-04-02").0, 1.0);
+        assert_eq!(compare_dates("1980-02-04", "1980-04-02").0, 0.7);
+        assert_eq!(compare_dates("1981-04-02", "1980-04-02").0, 0.5);
+        assert_eq!(compare_dates("1975-11-30", "1980-04-02").0, 0.0);
+    }
+
+    #[test]
+    fn confidence_against_thresholds() {
+        let patient = ("John", "Smith", "1980-04-02");
+        let confidence = |text: &str| NameQuery::from_text(text).score(patient.0, patient.1, patient.2).0;
+
+        assert_eq!(confidence("John Smith 1980-04-02"), 1.0);
+        assert!(confidence("Jon Smyth 1980-04-02") >= DUPLICATE_THRESHOLD);
+        assert!(confidence("Jon Smyth") >= SEARCH_THRESHOLD);
+        assert!(confidence("Smith John 1980-04-02") >= DUPLICATE_THRESHOLD);
+        assert!(confidence("John Smith 1980-02-04") >= DUPLICATE_THRESHOLD);
+        assert!(confidence("Mary Jones") < SEARCH_THRESHOLD);
+        assert!(confidence("Mary Jones 1975-11-30") < SEARCH_THRESHOLD);
+    }
+
+    #[test]
+    fn swapped_names_score_below_a_straight_match() {
+        let straight = NameQuery::from_text("John Smith").score("John", "Smith", "").0;
+        let (swapped, reasons) = NameQuery::from_text("Smith John").score("John", "Smith", "");
+        assert!(swapped < straight);
+        assert_eq!(reasons, ["First and last name are swapped"]);
+    }
+}
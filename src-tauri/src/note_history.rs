@@ -0,0 +1,189 @@
+use rusqlite::{params, Connection, OptionalExtension, Result};
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+use ts_rs::TS;
+
+// ============ Encounter Revisions ============
+//
+// Every save of an encounter appends a full copy of its text to
+// `encounter_revisions`, so a note clobbered from a second tab can be
+// compared with what was there before and brought back. Revisions are
+// append-only; restoring one writes its text to the encounter and records
+// that as a new revision.
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct EncounterRevision {
+    #[ts(as = "f64")]
+    pub id: i64,
+    #[ts(as = "f64")]
+    pub encounter_id: i64,
+    /// 1 for the first save, counting up per encounter
+    #[ts(as = "f64")]
+    pub revision_number: i64,
+    #[ts(as = "Option<f64>")]
+    pub author_id: Option<i64>,
+    pub author_name: Option<String>,
+    pub chief_complaint: Option<String>,
+    pub summary: Option<String>,
+    pub note_content: Option<String>,
+    /// The revision this one was restored from, if it came from a restore
+    #[ts(as = "Option<f64>")]
+    pub restored_from: Option<i64>,
+    pub created_at: String,
+}
+
+/// Copy the encounter's current text into a new revision
+///
+/// Returns `None` without writing anything when the text is identical to the
+/// latest revision, so saving an untouched note does not pad the history.
+pub fn record_revision(conn: &Connection, encounter_id: i64, author_id: i64, restored_from: Option<i64>) -> Result<Option<i64>> {
+    let unchanged: bool = conn.query_row(
+        "SELECT EXISTS (
+            SELECT 1 FROM encounters e
+            JOIN encounter_revisions r ON r.id = (
+                SELECT id FROM encounter_revisions WHERE encounter_id = e.id ORDER BY revision_number DESC LIMIT 1
+            )
+            WHERE e.id = ?1
+              AND r.chief_complaint IS e.chief_complaint
+              AND r.summary IS e.summary
+              AND r.note_content IS e.note_content
+        )",
+        params![encounter_id],
+        |row| row.get(0),
+    )?;
+    if unchanged && restored_from.is_none() {
+        return Ok(None);
+    }
+
+    conn.execute(
+        "INSERT INTO encounter_revisions
+            (encounter_id, patient_id, revision_number, author_id, chief_complaint, summary, note_content, restored_from)
+         SELECT e.id, e.patient_id,
+                COALESCE((SELECT MAX(revision_number) FROM encounter_revisions WHERE encounter_id = e.id), 0) + 1,
+                ?2, e.chief_complaint, e.summary, e.note_content, ?3
+         FROM encounters e WHERE e.id = ?1",
+        params![encounter_id, author_id, restored_from],
+    )?;
+    Ok(Some(conn.last_insert_rowid()))
+}
+
+/// Revisions newest first
+pub fn get_revisions(conn: &Connection, encounter_id: i64) -> Result<Vec<EncounterRevision>> {
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE r.encounter_id = ?1 ORDER BY r.revision_number DESC",
+        REVISION_SELECT
+    ))?;
+    let revisions = stmt.query_map(params![encounter_id], revision_from_row)?;
+    revisions.collect()
+}
+
+pub fn get_revision(conn: &Connection, id: i64) -> Result<Option<EncounterRevision>> {
+    let mut stmt = conn.prepare(&format!("{} WHERE r.id = ?1", REVISION_SELECT))?;
+    stmt.query_row(params![id], revision_from_row).optional()
+}
+
+/// Put a revision's text back on the encounter; the caller records the new revision
+pub fn restore_revision(conn: &Connection, revision: &EncounterRevision) -> Result<()> {
+    conn.execute(
+        "UPDATE encounters SET chief_complaint = ?2, summary = ?3, note_content = ?4, row_version = row_version + 1
+         WHERE id = ?1",
+        params![revision.encounter_id, revision.chief_complaint, revision.summary, revision.note_content],
+    )?;
+    Ok(())
+}
+
+const REVISION_SELECT: &str = "SELECT r.id, r.encounter_id, r.revision_number, r.author_id, u.first_name || ' ' || u.last_name,
+        r.chief_complaint, r.summary, r.note_content, r.restored_from, r.created_at
+     FROM encounter_revisions r
+     LEFT JOIN users u ON u.id = r.author_id";
+
+fn revision_from_row(row: &rusqlite::Row) -> Result<EncounterRevision> {
+    Ok(EncounterRevision {
+        id: row.get(0)?,
+        encounter_id: row.get(1)?,
+        revision_number: row.get(2)?,
+        author_id: row.get(3)?,
+        author_name: row.get(4)?,
+        chief_complaint: row.get(5)?,
+        summary: row.get(6)?,
+        note_content: row.get(7)?,
+        restored_from: row.get(8)?,
+        created_at: row.get(9)?,
+    })
+}
+
+// ============ Revision Diffs ============
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct DiffLine {
+    pub op: DiffOp,
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct FieldDiff {
+    /// `chief_complaint`, `summary` or `note_content`
+    pub field: String,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct RevisionDiff {
+    #[ts(as = "f64")]
+    pub from_revision: i64,
+    #[ts(as = "f64")]
+    pub to_revision: i64,
+    /// Only the fields that differ
+    pub fields: Vec<FieldDiff>,
+}
+
+/// Line-by-line diff of every text field that changed between two revisions
+pub fn diff_revisions(from: &EncounterRevision, to: &EncounterRevision) -> RevisionDiff {
+    let pairs = [
+        ("chief_complaint", &from.chief_complaint, &to.chief_complaint),
+        ("summary", &from.summary, &to.summary),
+        ("note_content", &from.note_content, &to.note_content),
+    ];
+
+    let fields = pairs
+        .into_iter()
+        .filter(|(_, old, new)| old != new)
+        .map(|(field, old, new)| FieldDiff {
+            field: field.to_string(),
+            lines: diff_text(old.as_deref().unwrap_or(""), new.as_deref().unwrap_or("")),
+        })
+        .collect();
+
+    RevisionDiff {
+        from_revision: from.revision_number,
+        to_revision: to.revision_number,
+        fields,
+    }
+}
+
+fn diff_text(old: &str, new: &str) -> Vec<DiffLine> {
+    TextDiff::from_lines(old, new)
+        .iter_all_changes()
+        .map(|change| DiffLine {
+            op: match change.tag() {
+                ChangeTag::Equal => DiffOp::Equal,
+                ChangeTag::Insert => DiffOp::Insert,
+                ChangeTag::Delete => DiffOp::Delete,
+            },
+            text: change.value().trim_end_matches('\n').to_string(),
+        })
+        .collect()
+}
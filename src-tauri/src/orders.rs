@@ -0,0 +1,374 @@
+use crate::concurrency;
+use crate::db::Lab;
+use crate::error::{Error, Result as AppResult};
+use crate::event::ModelAction;
+use crate::model::{fire_model_event, ModelMutateResultData};
+use crate::validation::Checks;
+use rusqlite::{params, Connection, OptionalExtension, Result, Row, ToSql};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// ============ Lab and Imaging Orders ============
+//
+// An order is placed from an encounter, usually against the diagnosis it is
+// meant to work up, and moves ordered → collected → resulted. Lab results
+// arrive as `labs` rows and are attached to their order, which marks it
+// resulted; imaging has no result table, so the report's impression is
+// recorded on the order itself. Until a result is in, an order can be
+// cancelled with a reason.
+//
+// An order stays outstanding until the result has been reviewed, not merely
+// until it arrives: the outstanding lists exist so that no result goes unseen.
+// A further result attached after review puts the order back on the list.
+
+/// An order as listed, with the names the lists show alongside it
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
+pub struct Order {
+    #[ts(as = "Option<f64>")]
+    pub id: Option<i64>,
+    #[ts(as = "f64")]
+    pub patient_id: i64,
+    #[serde(default)]
+    #[ts(optional = nullable, as = "Option<f64>")]
+    pub encounter_id: Option<i64>,
+    #[serde(default)]
+    #[ts(optional = nullable, as = "Option<f64>")]
+    pub diagnosis_id: Option<i64>,
+    /// `lab` or `imaging`
+    pub order_type: String,
+    pub test_name: String,
+    /// `routine`, `urgent` or `stat`; routine when left out
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub priority: Option<String>,
+    /// Set by the workflow commands, never by the client
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub status: Option<String>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    #[ts(optional = nullable, as = "Option<f64>")]
+    pub ordered_by: Option<i64>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub ordered_by_name: Option<String>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub ordered_at: Option<String>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub collected_at: Option<String>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub resulted_at: Option<String>,
+    /// The impression of an imaging report
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub result_summary: Option<String>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub reviewed_at: Option<String>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub reviewed_by_name: Option<String>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub cancelled_at: Option<String>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub cancel_reason: Option<String>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub patient_name: Option<String>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub diagnosis_name: Option<String>,
+    /// Lab results attached to the order, oldest first
+    #[serde(default)]
+    #[ts(as = "Vec<f64>")]
+    pub lab_ids: Vec<i64>,
+}
+
+/// A move along the workflow, checked against the order's current status by `check_step`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrderStep {
+    Collect,
+    AttachLab,
+    RecordImagingResult,
+    Review,
+    Cancel,
+}
+
+const SELECT_ORDER: &str = "
+    SELECT o.id, o.patient_id, o.encounter_id, o.diagnosis_id, o.order_type, o.test_name, o.priority,
+           o.status, o.notes, o.ordered_by, ob.first_name || ' ' || ob.last_name, o.ordered_at,
+           o.collected_at, o.resulted_at, o.result_summary, o.reviewed_at,
+           rb.first_name || ' ' || rb.last_name, o.cancelled_at, o.cancel_reason,
+           p.first_name || ' ' || p.last_name, d.name,
+           (SELECT group_concat(l.id) FROM (SELECT id FROM labs WHERE order_id = o.id ORDER BY recorded_at, id) l)
+    FROM orders o
+    JOIN patients p ON p.id = o.patient_id
+    LEFT JOIN users ob ON ob.id = o.ordered_by
+    LEFT JOIN users rb ON rb.id = o.reviewed_by
+    LEFT JOIN diagnoses d ON d.id = o.diagnosis_id";
+
+/// Still needs someone's attention: not yet resulted, or resulted and not yet reviewed
+const OUTSTANDING: &str = "(o.status IN ('ordered', 'collected') OR (o.status = 'resulted' AND o.reviewed_at IS NULL))";
+
+/// Stat first, then urgent, then routine; oldest first within each
+const BY_URGENCY: &str = "CASE o.priority WHEN 'stat' THEN 0 WHEN 'urgent' THEN 1 ELSE 2 END, o.ordered_at, o.id";
+
+fn order_from_row(row: &Row) -> Result<Order> {
+    let lab_ids: Option<String> = row.get(21)?;
+    Ok(Order {
+        id: row.get(0)?,
+        patient_id: row.get(1)?,
+        encounter_id: row.get(2)?,
+        diagnosis_id: row.get(3)?,
+        order_type: row.get(4)?,
+        test_name: row.get(5)?,
+        priority: row.get(6)?,
+        status: row.get(7)?,
+        notes: row.get(8)?,
+        ordered_by: row.get(9)?,
+        ordered_by_name: row.get(10)?,
+        ordered_at: row.get(11)?,
+        collected_at: row.get(12)?,
+        resulted_at: row.get(13)?,
+        result_summary: row.get(14)?,
+        reviewed_at: row.get(15)?,
+        reviewed_by_name: row.get(16)?,
+        cancelled_at: row.get(17)?,
+        cancel_reason: row.get(18)?,
+        patient_name: row.get(19)?,
+        diagnosis_name: row.get(20)?,
+        lab_ids: lab_ids
+            .map(|ids| ids.split(',').filter_map(|id| id.parse().ok()).collect())
+            .unwrap_or_default(),
+    })
+}
+
+pub fn get_order(conn: &Connection, id: i64) -> Result<Option<Order>> {
+    conn.query_row(&format!("{} WHERE o.id = ?1", SELECT_ORDER), params![id], order_from_row)
+        .optional()
+}
+
+/// Every order in one chart, newest first
+pub fn get_orders_for_patient(conn: &Connection, patient_id: i64) -> Result<Vec<Order>> {
+    let mut stmt = conn.prepare(&format!("{} WHERE o.patient_id = ?1 ORDER BY o.ordered_at DESC, o.id DESC", SELECT_ORDER))?;
+    let orders = stmt.query_map(params![patient_id], order_from_row)?;
+    orders.collect()
+}
+
+/// Outstanding orders for one patient, or across the clinic when `patient_id` is `None`, most urgent first
+pub fn get_outstanding_orders(conn: &Connection, patient_id: Option<i64>) -> Result<Vec<Order>> {
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE {} AND (?1 IS NULL OR o.patient_id = ?1) ORDER BY {}",
+        SELECT_ORDER, OUTSTANDING, BY_URGENCY
+    ))?;
+    let orders = stmt.query_map(params![patient_id], order_from_row)?;
+    orders.collect()
+}
+
+/// The encounter and diagnosis an order names must both be in the patient's chart
+pub fn check_links(conn: &Connection, order: &Order) -> AppResult<()> {
+    let mut checks = Checks::new();
+    let links = [("encounter_id", "encounters", order.encounter_id), ("diagnosis_id", "diagnoses", order.diagnosis_id)];
+    for (field, table, id) in links {
+        let Some(id) = id else { continue };
+        let owner: Option<i64> = conn
+            .query_row(&format!("SELECT patient_id FROM {} WHERE id = ?1", table), params![id], |row| row.get(0))
+            .optional()?;
+        if owner != Some(order.patient_id) {
+            let noun = if table == "encounters" { "encounter" } else { "diagnosis" };
+            checks.add(field, format!("That {} is not in this patient's chart", noun));
+        }
+    }
+    checks.finish()
+}
+
+/// Whether `order` can take `step` from where it is now
+pub fn check_step(order: &Order, step: OrderStep) -> AppResult<()> {
+    let status = order.status.as_deref().unwrap_or("ordered");
+    let is_lab = order.order_type == "lab";
+    let allowed = match step {
+        OrderStep::Collect => status == "ordered",
+        OrderStep::AttachLab => is_lab && status != "cancelled",
+        OrderStep::RecordImagingResult => !is_lab && matches!(status, "ordered" | "collected"),
+        OrderStep::Review => status == "resulted" && order.reviewed_at.is_none(),
+        OrderStep::Cancel => matches!(status, "ordered" | "collected"),
+    };
+    if allowed {
+        return Ok(());
+    }
+    Err(Error::invalid(match step {
+        OrderStep::AttachLab if !is_lab => "Only lab orders take lab results; record an imaging result on the order".to_string(),
+        OrderStep::RecordImagingResult if is_lab => "Lab orders are resulted by attaching their lab results".to_string(),
+        OrderStep::Review if status == "resulted" => "This result has already been reviewed".to_string(),
+        OrderStep::Review => format!("This order is {} and has no result to review yet", status),
+        _ => format!("This order is already {}", status),
+    }))
+}
+
+/// A lab can answer an order in its own chart, and only one order at a time
+pub fn check_attachable(order: &Order, lab: &Lab) -> AppResult<()> {
+    check_step(order, OrderStep::AttachLab)?;
+    if lab.patient_id != order.patient_id {
+        return Err(Error::invalid_field("order_id", "That order is for a different patient"));
+    }
+    if lab.order_id.is_some_and(|id| Some(id) != order.id) {
+        return Err(Error::invalid_field("order_id", "This result is already attached to another order; detach it first"));
+    }
+    Ok(())
+}
+
+pub fn create_order(conn: &Connection, user_id: i64, order: &Order) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO orders (patient_id, encounter_id, diagnosis_id, order_type, test_name, priority, notes, ordered_by)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            order.patient_id,
+            order.encounter_id,
+            order.diagnosis_id,
+            order.order_type.trim().to_lowercase(),
+            order.test_name.trim(),
+            order.priority.as_deref().map(str::to_lowercase).unwrap_or_else(|| "routine".to_string()),
+            order.notes,
+            user_id,
+        ],
+    )?;
+    let id = conn.last_insert_rowid();
+    changed(conn, id, order.patient_id, ModelAction::Create);
+    Ok(id)
+}
+
+/// Specimen drawn, or the study performed; `collected_at` defaults to now
+pub fn mark_collected(conn: &Connection, order: &Order, collected_at: Option<&str>) -> AppResult<()> {
+    set_status(
+        conn,
+        order,
+        "status = 'collected', collected_at = COALESCE(?2, datetime('now', 'localtime'))",
+        params![order.id, collected_at],
+    )
+}
+
+/// Link `lab` to `order` and mark the order resulted, clearing any earlier review
+pub fn attach_lab(conn: &Connection, order: &Order, lab: &Lab) -> AppResult<()> {
+    conn.execute("UPDATE labs SET order_id = ?1 WHERE id = ?2", params![order.id, lab.id])?;
+    if let Some(lab_id) = lab.id {
+        fire_model_event(conn, "labs", ModelAction::Update, ModelMutateResultData { id: lab_id, patient_id: Some(lab.patient_id) });
+    }
+    set_status(
+        conn,
+        order,
+        "status = 'resulted', collected_at = COALESCE(collected_at, ?2), resulted_at = datetime('now', 'localtime'),
+         reviewed_at = NULL, reviewed_by = NULL",
+        params![order.id, lab.recorded_at],
+    )
+}
+
+/// Unlink `lab` from `order`; an order left with no results goes back to collected
+pub fn detach_lab(conn: &Connection, order: &Order, lab: &Lab) -> AppResult<()> {
+    conn.execute("UPDATE labs SET order_id = NULL WHERE id = ?1", params![lab.id])?;
+    if let Some(lab_id) = lab.id {
+        fire_model_event(conn, "labs", ModelAction::Update, ModelMutateResultData { id: lab_id, patient_id: Some(lab.patient_id) });
+    }
+    reopen_if_unresulted(conn, order)
+}
+
+/// After a result is detached or deleted, put the order back to collected if it has none left
+pub fn reopen_if_unresulted(conn: &Connection, order: &Order) -> AppResult<()> {
+    let remaining: i64 = conn.query_row("SELECT COUNT(*) FROM labs WHERE order_id = ?1", params![order.id], |row| row.get(0))?;
+    if remaining > 0 || order.status.as_deref() != Some("resulted") {
+        return Ok(());
+    }
+    set_status(
+        conn,
+        order,
+        "status = 'collected', resulted_at = NULL, reviewed_at = NULL, reviewed_by = NULL",
+        params![order.id],
+    )
+}
+
+/// One of the order's results changed after sign-off, so it goes back on the outstanding lists
+pub fn reopen_review(conn: &Connection, order: &Order) -> AppResult<()> {
+    if order.reviewed_at.is_none() {
+        return Ok(());
+    }
+    set_status(conn, order, "reviewed_at = NULL, reviewed_by = NULL", params![order.id])
+}
+
+pub fn record_imaging_result(conn: &Connection, order: &Order, summary: &str) -> AppResult<()> {
+    set_status(
+        conn,
+        order,
+        "status = 'resulted', collected_at = COALESCE(collected_at, datetime('now', 'localtime')),
+         resulted_at = datetime('now', 'localtime'), result_summary = ?2",
+        params![order.id, summary.trim()],
+    )
+}
+
+/// Sign off the result, which takes the order off the outstanding lists
+pub fn review(conn: &Connection, order: &Order, user_id: i64) -> AppResult<()> {
+    set_status(
+        conn,
+        order,
+        "reviewed_at = datetime('now', 'localtime'), reviewed_by = ?2",
+        params![order.id, user_id],
+    )
+}
+
+pub fn cancel(conn: &Connection, order: &Order, reason: &str) -> AppResult<()> {
+    set_status(
+        conn,
+        order,
+        "status = 'cancelled', cancelled_at = datetime('now', 'localtime'), cancel_reason = ?2",
+        params![order.id, reason.trim()],
+    )
+}
+
+/// The reason for a cancellation or the impression of a report; either has to say something
+pub fn require_text(field: &str, label: &str, value: &str) -> AppResult<()> {
+    Checks::new().required(field, label, value).finish()
+}
+
+/// When a specimen was collected: a date and time, not in the future
+pub fn validate_collected_at(collected_at: Option<&str>) -> AppResult<()> {
+    let value = collected_at.unwrap_or("");
+    Checks::new()
+        .datetime("collected_at", "Collected at", value)
+        .not_future("collected_at", "Collected at", value)
+        .finish()
+}
+
+/// `?1` in `assignments` is the order id
+///
+/// Only applies while the order still has the status it was checked at; if
+/// another write moved it on in between, the caller gets a conflict instead.
+fn set_status(conn: &Connection, order: &Order, assignments: &str, values: &[&dyn ToSql]) -> AppResult<()> {
+    let mut values = values.to_vec();
+    values.push(&order.status);
+    let sql = format!(
+        "UPDATE orders SET {}, updated_at = datetime('now', 'localtime') WHERE id = ?1 AND status = ?{}",
+        assignments,
+        values.len()
+    );
+    if conn.execute(&sql, values.as_slice())? == 0 {
+        let current = match order.id {
+            Some(id) => get_order(conn, id)?,
+            None => None,
+        };
+        return Err(concurrency::conflict(current));
+    }
+    if let Some(id) = order.id {
+        changed(conn, id, order.patient_id, ModelAction::Update);
+    }
+    Ok(())
+}
+
+fn changed(conn: &Connection, id: i64, patient_id: i64, action: ModelAction) {
+    fire_model_event(conn, "orders", action, ModelMutateResultData { id, patient_id: Some(patient_id) });
+}
@@ -0,0 +1,183 @@
+use crate::error::{Error, Result};
+use rusqlite::Connection;
+use std::ops::Deref;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+// ============ Connection Pool ============
+//
+// One writer plus a handful of readers, all on the same WAL-mode database.
+// WAL lets readers run while the writer commits, so a slow global search no
+// longer blocks saving a note. Writes still serialize through the single
+// writer connection, which is what SQLite allows anyway.
+
+/// Read connections opened on unlock
+pub const READER_COUNT: usize = 4;
+
+/// How long a command waits for a free connection before giving up
+const CHECKOUT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long SQLite itself retries when another connection holds the write lock
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy)]
+enum Role {
+    Reader,
+    Writer,
+}
+
+/// A fixed set of interchangeable connections
+struct Slots {
+    idle: Mutex<Vec<Connection>>,
+    available: Condvar,
+    size: usize,
+}
+
+impl Slots {
+    fn new(conns: Vec<Connection>) -> Self {
+        Slots {
+            size: conns.len(),
+            idle: Mutex::new(conns),
+            available: Condvar::new(),
+        }
+    }
+
+    fn checkout(&self) -> Result<Connection> {
+        let mut idle = self.idle.lock()?;
+        loop {
+            if let Some(conn) = idle.pop() {
+                return Ok(conn);
+            }
+            let (guard, wait) = self
+                .available
+                .wait_timeout(idle, CHECKOUT_TIMEOUT)?;
+            idle = guard;
+            if wait.timed_out() && idle.is_empty() {
+                return Err(Error::Busy("Timed out waiting for a database connection".to_string()));
+            }
+        }
+    }
+
+    fn checkin(&self, conn: Connection) {
+        if let Ok(mut idle) = self.idle.lock() {
+            idle.push(conn);
+            self.available.notify_all();
+        }
+    }
+
+    /// Wait until every connection is back, then take them all so none can be checked out again
+    fn drain(&self) -> Result<Vec<Connection>> {
+        let mut idle = self.idle.lock()?;
+        while idle.len() < self.size {
+            let (guard, wait) = self.available.wait_timeout(idle, CHECKOUT_TIMEOUT)?;
+            idle = guard;
+            if wait.timed_out() && idle.len() < self.size {
+                return Err(Error::Busy("Timed out waiting for open reads to finish".to_string()));
+            }
+        }
+        Ok(std::mem::take(&mut *idle))
+    }
+}
+
+pub struct DbPool {
+    writer: Slots,
+    readers: Slots,
+}
+
+impl DbPool {
+    pub fn new(writer: Connection, readers: Vec<Connection>) -> Arc<Self> {
+        Arc::new(DbPool {
+            writer: Slots::new(vec![writer]),
+            readers: Slots::new(readers),
+        })
+    }
+
+    /// Borrow a read-only connection; it goes back to the pool when dropped
+    pub fn reader(self: &Arc<Self>) -> Result<PooledConn> {
+        self.checkout(Role::Reader)
+    }
+
+    /// Borrow the single writer connection; other writers wait until it is dropped
+    pub fn writer(self: &Arc<Self>) -> Result<PooledConn> {
+        self.checkout(Role::Writer)
+    }
+
+    /// Wait for every reader to come back and retire them, for a change that would pull the database out from under a read
+    ///
+    /// The pool hands out no readers afterwards; it is meant to be replaced.
+    pub fn drain_readers(&self) -> Result<()> {
+        drop(self.readers.drain()?);
+        Ok(())
+    }
+
+    fn slots(&self, role: Role) -> &Slots {
+        match role {
+            Role::Reader => &self.readers,
+            Role::Writer => &self.writer,
+        }
+    }
+
+    fn checkout(self: &Arc<Self>, role: Role) -> Result<PooledConn> {
+        let conn = self.slots(role).checkout()?;
+        Ok(PooledConn {
+            conn: Some(conn),
+            pool: Arc::clone(self),
+            role,
+        })
+    }
+}
+
+/// A connection checked out of the pool for the duration of one command
+pub struct PooledConn {
+    conn: Option<Connection>,
+    pool: Arc<DbPool>,
+    role: Role,
+}
+
+impl Deref for PooledConn {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().expect("PooledConn holds its connection until dropped")
+    }
+}
+
+impl Drop for PooledConn {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            self.pool.slots(self.role).checkin(conn);
+        }
+        // Only events whose transaction committed are still queued
+        if let Role::Writer = self.role {
+            crate::event::flush();
+        }
+    }
+}
+
+/// Put the writer in WAL mode so readers never block on it
+pub fn configure_writer(conn: &Connection) -> rusqlite::Result<()> {
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    enforce_foreign_keys(conn)?;
+    conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+    conn.pragma_update(None, "synchronous", "NORMAL")?;
+    // Change events follow the fate of the transaction that queued them
+    conn.commit_hook(Some(|| {
+        crate::event::committed();
+        false
+    }));
+    conn.rollback_hook(Some(crate::event::rolled_back));
+    Ok(())
+}
+
+/// Readers refuse writes outright, so a command routed to the wrong side fails loudly
+pub fn configure_reader(conn: &Connection) -> rusqlite::Result<()> {
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    enforce_foreign_keys(conn)?;
+    conn.pragma_update(None, "query_only", true)?;
+    Ok(())
+}
+
+/// Turn on `ON DELETE CASCADE`/`SET NULL` and reference checks, which SQLite leaves to each connection
+pub fn enforce_foreign_keys(conn: &Connection) -> rusqlite::Result<()> {
+    conn.pragma_update(None, "foreign_keys", true)
+}
@@ -0,0 +1,157 @@
+use crate::error::Error;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use ts_rs::TS;
+
+// ============ Roles and Permissions ============
+//
+// Every user has exactly one role, stored in `users.role`. Commands in
+// `main.rs` ask `DbState::authorize` for the permission they need before
+// touching the database; the matrix below is the only place that decides
+// which role gets what.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Physician,
+    Nurse,
+    /// Least privilege, so a user saved without a role gets the narrowest access
+    #[default]
+    FrontDesk,
+    Billing,
+    Admin,
+}
+
+pub const ROLES: &[Role] = &[Role::Physician, Role::Nurse, Role::FrontDesk, Role::Billing, Role::Admin];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    /// Demographics, patient lists and quick search
+    ViewPatients,
+    /// Register patients and edit demographics or status
+    EditPatients,
+    /// Hard delete, merge, and loading test data
+    ManagePatientRecords,
+    ViewSchedule,
+    ManageSchedule,
+    /// Problems, medications, vitals, labs, allergies, history and encounter headers
+    ViewClinical,
+    EditClinical,
+    /// Encounter `note_content`; without it notes come back blank
+    ViewNotes,
+    WriteNotes,
+    /// Second signature on a note someone else signed
+    CosignNotes,
+    Prescribe,
+    /// Order labs and imaging, cancel orders, and sign off their results
+    PlaceOrders,
+    ViewAuditLog,
+    /// Create accounts and assign roles
+    ManageUsers,
+    /// Change the database passphrase, check and repair it, and edit shared reference data such as lab ranges
+    ManageDatabase,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Physician => "physician",
+            Role::Nurse => "nurse",
+            Role::FrontDesk => "front_desk",
+            Role::Billing => "billing",
+            Role::Admin => "admin",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Role::Physician => "physician",
+            Role::Nurse => "nurse",
+            Role::FrontDesk => "front desk",
+            Role::Billing => "billing",
+            Role::Admin => "administrator",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Role> {
+        ROLES.iter().copied().find(|r| r.as_str() == value)
+    }
+
+    /// The permission matrix
+    pub fn permissions(&self) -> &'static [Permission] {
+        use Permission::*;
+        match self {
+            Role::Physician => &[
+                ViewPatients, EditPatients, ViewSchedule, ManageSchedule,
+                ViewClinical, EditClinical, ViewNotes, WriteNotes, CosignNotes, Prescribe, PlaceOrders,
+            ],
+            Role::Nurse => &[
+                ViewPatients, EditPatients, ViewSchedule, ManageSchedule,
+                ViewClinical, EditClinical, ViewNotes, WriteNotes,
+            ],
+            Role::FrontDesk => &[ViewPatients, EditPatients, ViewSchedule, ManageSchedule],
+            Role::Billing => &[ViewPatients, ViewSchedule, ViewClinical],
+            // Small practices often have a physician-owner as the only admin, so admin is a superset
+            Role::Admin => &[
+                ViewPatients, EditPatients, ManagePatientRecords, ViewSchedule, ManageSchedule,
+                ViewClinical, EditClinical, ViewNotes, WriteNotes, CosignNotes, Prescribe, PlaceOrders,
+                ViewAuditLog, ManageUsers, ManageDatabase,
+            ],
+        }
+    }
+
+    pub fn can(&self, permission: Permission) -> bool {
+        self.permissions().contains(&permission)
+    }
+}
+
+impl Permission {
+    /// Completes "does not have permission to ..."
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Permission::ViewPatients => "view patients",
+            Permission::EditPatients => "edit patient demographics",
+            Permission::ManagePatientRecords => "delete or merge patient records",
+            Permission::ViewSchedule => "view the schedule",
+            Permission::ManageSchedule => "book appointments",
+            Permission::ViewClinical => "view clinical data",
+            Permission::EditClinical => "edit clinical data",
+            Permission::ViewNotes => "read encounter notes",
+            Permission::WriteNotes => "write encounter notes",
+            Permission::CosignNotes => "co-sign encounter notes",
+            Permission::Prescribe => "prescribe",
+            Permission::PlaceOrders => "order labs and imaging",
+            Permission::ViewAuditLog => "view the audit log",
+            Permission::ManageUsers => "manage user accounts",
+            Permission::ManageDatabase => "manage the database and its reference data",
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl ToSql for Role {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for Role {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let text = value.as_str()?;
+        Role::parse(text).ok_or_else(|| FromSqlError::Other(format!("unknown role '{}'", text).into()))
+    }
+}
+
+/// The error a command returns when the signed-in role lacks `permission`
+pub fn denied(role: Role, permission: Permission) -> Error {
+    Error::forbidden(format!("Your role ({}) does not have permission to {}", role.label(), permission.describe()))
+}
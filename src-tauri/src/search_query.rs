@@ -0,0 +1,376 @@
+use crate::db;
+use crate::error::{Error, Result};
+use crate::validation::parse_date;
+
+// ============ Search Query Language ============
+//
+// What the user types in the command palette, turned into an FTS5 MATCH
+// expression plus the filters FTS5 cannot express:
+//
+//   heart failure          both words, each as a prefix
+//   "heart failure"        the exact phrase
+//   diabetes -"type 1"     leave out rows matching a word or phrase
+//   metformin OR insulin   either side; binds tighter than the implied AND
+//   type:lab               only one kind of result (repeat for several)
+//   patient:"Jane Smith"   only rows in charts whose name contains this
+//   before:2024-01-01      dated strictly before (or `after:`, strictly after)
+//
+// Anything that does not parse is refused with a validation error on the
+// `query` field rather than guessed at.
+
+const FILTERS: &[&str] = &["type", "patient", "before", "after"];
+
+/// A parsed search; `fts` is empty only for a blank query
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchQuery {
+    pub fts: String,
+    /// The words and phrases to find, as typed, for exact identifier matches
+    pub terms: Vec<String>,
+    /// Result types to search; empty means all
+    pub types: Vec<String>,
+    /// Text the patient's full name must contain
+    pub patient: Option<String>,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+#[derive(Debug)]
+struct Token {
+    negated: bool,
+    field: Option<String>,
+    value: String,
+    quoted: bool,
+}
+
+impl SearchQuery {
+    pub fn parse(input: &str) -> Result<SearchQuery> {
+        let mut query = SearchQuery::default();
+        // Each group is ORed inside and ANDed with the others
+        let mut groups: Vec<Vec<String>> = Vec::new();
+        let mut excluded: Vec<String> = Vec::new();
+        let mut pending_or = false;
+        let mut last_was_term = false;
+
+        for token in tokenize(input)? {
+            if token.field.is_none() && !token.quoted && !token.negated && token.value == "OR" {
+                if !last_was_term || pending_or {
+                    return Err(invalid("OR needs a search term on each side"));
+                }
+                pending_or = true;
+                last_was_term = false;
+                continue;
+            }
+
+            if let Some(field) = &token.field {
+                if pending_or {
+                    return Err(invalid(format!("OR cannot be combined with the {}: filter", field)));
+                }
+                if token.negated {
+                    return Err(invalid(format!("The {}: filter cannot be excluded with -", field)));
+                }
+                query.apply_filter(field, token.value)?;
+                last_was_term = false;
+                continue;
+            }
+
+            let term = fts_term(&token.value, token.quoted);
+            if token.negated {
+                if pending_or {
+                    return Err(invalid("OR cannot be combined with an excluded term"));
+                }
+                excluded.push(term);
+                last_was_term = false;
+            } else {
+                query.terms.push(token.value);
+                match groups.last_mut() {
+                    Some(group) if pending_or => group.push(term),
+                    _ => groups.push(vec![term]),
+                }
+                pending_or = false;
+                last_was_term = true;
+            }
+        }
+
+        if pending_or {
+            return Err(invalid("OR needs a search term on each side"));
+        }
+        if groups.is_empty() {
+            if excluded.is_empty() && query == SearchQuery::default() {
+                return Ok(query);
+            }
+            return Err(invalid("Add at least one word to search for; filters and exclusions only narrow a search"));
+        }
+        if let (Some(before), Some(after)) = (&query.before, &query.after) {
+            if before <= after {
+                return Err(invalid("before: must be a later date than after:"));
+            }
+        }
+
+        let matched = groups
+            .iter()
+            .map(|group| match group.as_slice() {
+                [term] => term.clone(),
+                terms => format!("({})", terms.join(" OR ")),
+            })
+            .collect::<Vec<_>>()
+            .join(" AND ");
+        query.fts = if excluded.is_empty() {
+            matched
+        } else {
+            format!("({}) NOT ({})", matched, excluded.join(" OR "))
+        };
+        Ok(query)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fts.is_empty()
+    }
+
+    /// Whether results of `result_type` are wanted at all
+    pub fn includes(&self, result_type: &str) -> bool {
+        self.types.is_empty() || self.types.iter().any(|t| t == result_type)
+    }
+
+    fn apply_filter(&mut self, field: &str, value: String) -> Result<()> {
+        if value.trim().is_empty() {
+            return Err(invalid(format!("The {}: filter needs a value", field)));
+        }
+        match field {
+            "type" => {
+                let value = value.to_ascii_lowercase();
+                let types = db::search_result_types();
+                if !types.contains(&value.as_str()) {
+                    return Err(invalid(format!("Unknown type:{}; use one of {}", value, types.join(", "))));
+                }
+                if !self.types.contains(&value) {
+                    self.types.push(value);
+                }
+            }
+            "patient" => set_once(&mut self.patient, field, value)?,
+            "before" | "after" => {
+                if parse_date(&value).is_none() {
+                    return Err(invalid(format!("{}: needs a date in YYYY-MM-DD form", field)));
+                }
+                let slot = if field == "before" { &mut self.before } else { &mut self.after };
+                set_once(slot, field, value)?;
+            }
+            _ => unreachable!("tokenize only yields known filters"),
+        }
+        Ok(())
+    }
+}
+
+fn set_once(slot: &mut Option<String>, field: &str, value: String) -> Result<()> {
+    if slot.is_some() {
+        return Err(invalid(format!("Use the {}: filter only once", field)));
+    }
+    *slot = Some(value);
+    Ok(())
+}
+
+fn invalid(message: impl Into<String>) -> Error {
+    Error::invalid_field("query", message)
+}
+
+/// A word matches as a prefix; a quoted phrase matches exactly
+fn fts_term(value: &str, quoted: bool) -> String {
+    let escaped = format!("\"{}\"", value.replace('"', "\"\""));
+    if quoted {
+        escaped
+    } else {
+        format!("{}*", escaped)
+    }
+}
+
+/// Split on whitespace, keeping quoted phrases together and peeling off `-` and `field:`
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        if chars[i].is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let negated = chars[i] == '-';
+        if negated {
+            if chars.get(i + 1).is_none_or(|c| c.is_whitespace()) {
+                return Err(invalid(format!("The - at character {} needs a word or phrase right after it", i + 1)));
+            }
+            i += 1;
+        }
+
+        let start = i;
+        while i < chars.len() && !chars[i].is_whitespace() && chars[i] != '"' {
+            i += 1;
+        }
+        let bare: String = chars[start..i].iter().collect();
+
+        let (field, mut value) = match bare.split_once(':') {
+            Some((name, rest)) if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphabetic()) => {
+                let name = name.to_ascii_lowercase();
+                if !FILTERS.contains(&name.as_str()) {
+                    return Err(invalid(format!(
+                        "Unknown filter {}:; use {}",
+                        name,
+                        FILTERS.iter().map(|f| format!("{}:", f)).collect::<Vec<_>>().join(", ")
+                    )));
+                }
+                (Some(name), rest.to_string())
+            }
+            _ => (None, bare),
+        };
+
+        // A quote opens a phrase when it starts the token or follows `field:`
+        let mut quoted = false;
+        if chars.get(i) == Some(&'"') && value.is_empty() {
+            let open = i;
+            i += 1;
+            let phrase_start = i;
+            while i < chars.len() && chars[i] != '"' {
+                i += 1;
+            }
+            if i == chars.len() {
+                return Err(invalid(format!("The quote at character {} is never closed", open + 1)));
+            }
+            value = chars[phrase_start..i].iter().collect();
+            quoted = true;
+            i += 1;
+        }
+
+        // `""` or lone punctuation would give FTS5 a phrase with no tokens
+        if field.is_none() && !value.chars().any(char::is_alphanumeric) {
+            continue;
+        }
+        tokens.push(Token { negated, field, value, quoted });
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    fn parse(input: &str) -> SearchQuery {
+        SearchQuery::parse(input).unwrap_or_else(|e| panic!("{:?} should parse: {:?}", input, e))
+    }
+
+    fn refused(input: &str) -> bool {
+        SearchQuery::parse(input).is_err()
+    }
+
+    /// Run the expression against a real FTS5 table, which is where a bad one would fail
+    fn fts5_accepts(fts: &str) -> bool {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE VIRTUAL TABLE docs USING fts5(body); INSERT INTO docs VALUES ('heart failure');")
+            .unwrap();
+        conn.query_row("SELECT count(*) FROM docs WHERE docs MATCH ?1", [fts], |row| row.get::<_, i64>(0)).is_ok()
+    }
+
+    #[test]
+    fn words_match_as_prefixes_and_phrases_exactly() {
+        let query = parse(r#"heart "congestive failure""#);
+        assert_eq!(query.fts, r#""heart"* AND "congestive failure""#);
+        assert_eq!(query.terms, ["heart", "congestive failure"]);
+    }
+
+    #[test]
+    fn or_binds_tighter_than_and() {
+        assert_eq!(parse("metformin OR insulin").fts, r#"("metformin"* OR "insulin"*)"#);
+        assert_eq!(
+            parse("diabetes metformin OR insulin OR glipizide").fts,
+            r#""diabetes"* AND ("metformin"* OR "insulin"* OR "glipizide"*)"#
+        );
+        // Lowercase `or` is just a word
+        assert_eq!(parse("this or that").fts, r#""this"* AND "or"* AND "that"*"#);
+    }
+
+    #[test]
+    fn exclusions_are_left_out() {
+        assert_eq!(parse(r#"diabetes -"type 1""#).fts, r#"("diabetes"*) NOT ("type 1")"#);
+        assert_eq!(parse("cough -smoker -vaping").fts, r#"("cough"*) NOT ("smoker"* OR "vaping"*)"#);
+        // A hyphen inside a word is part of the word
+        assert_eq!(parse("covid-19").fts, r#""covid-19"*"#);
+    }
+
+    #[test]
+    fn filters_are_kept_out_of_the_expression() {
+        let query = parse(r#"potassium type:lab TYPE:Lab type:medication patient:"Jane Smith" after:2023-01-01 before:2024-01-01"#);
+        assert_eq!(query.fts, r#""potassium"*"#);
+        assert_eq!(query.types, ["lab", "medication"]);
+        assert_eq!(query.patient.as_deref(), Some("Jane Smith"));
+        assert_eq!(query.after.as_deref(), Some("2023-01-01"));
+        assert_eq!(query.before.as_deref(), Some("2024-01-01"));
+        assert!(query.includes("lab"));
+        assert!(!query.includes("patient"));
+    }
+
+    #[test]
+    fn blank_input_is_an_empty_search() {
+        assert!(parse("").is_empty());
+        assert!(parse("   ").is_empty());
+        assert!(parse(r#""""#).is_empty());
+    }
+
+    #[test]
+    fn malformed_queries_are_refused() {
+        let malformed = [
+            r#""heart failure"#,
+            r#"patient:"Jane"#,
+            "x before:2024-13-01",
+            "x after:yesterday",
+            "x before:2023-01-01 after:2024-01-01",
+            "x before:2024-01-01 before:2024-02-01",
+            "-",
+            "heart - failure",
+            "heart -",
+            "heart OR",
+            "OR heart",
+            "heart OR OR failure",
+            "heart OR -failure",
+            "heart OR type:lab",
+            "x -type:lab",
+            "x type:",
+            "x type:nonsense",
+            "x colour:red",
+            "-smoker",
+            "type:lab",
+        ];
+        for input in malformed {
+            assert!(refused(input), "{:?} should be refused", input);
+        }
+    }
+
+    #[test]
+    fn expressions_are_always_valid_fts5() {
+        assert!(!fts5_accepts("heart AND"), "the check itself must catch a bad expression");
+        let inputs = [
+            "heart failure",
+            r#""heart failure" -smoker"#,
+            "metformin OR insulin OR glipizide cough",
+            r#"a"b""#,
+            r#""a""b""#,
+            r#""it's" o'brien"#,
+            "AND OR NOT",
+            "NOT heart",
+            "NEAR(heart failure)",
+            "heart*",
+            "^heart +failure",
+            "(heart) {failure}",
+            "10:30 1:2",
+            "x --double",
+            "x -\"\"",
+            "café Núñez 李",
+            "* + ^ . ,",
+        ];
+        for input in inputs {
+            let query = parse(input);
+            if !query.is_empty() {
+                assert!(fts5_accepts(&query.fts), "{:?} gave {:?}", input, query.fts);
+            }
+        }
+    }
+}
@@ -1,14 +1,88 @@
-use crate::prelude::*;
-use crate::utils::{map, XTakeVal};
-use std::collections::BTreeMap;
+use rusqlite::types::{Type, Value as SqlValue, ValueRef};
+use rusqlite::Row;
+use serde::de::{DeserializeOwned, Deserializer};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
 
-// Marker traits for types that can be used for query
-pub trait Creatable: Into<Value> {}
-pub trait Patchable: Into<Value> {}
-pub trait Filterable: Into<Value> {}
+// ============ Store ============
+//
+// The glue between serde structs and SQLite rows that lets the model
+// controllers stay generic: a struct is serialized to a JSON object whose
+// keys are column names, and a row is read back into one before being
+// deserialized. Types only need the derives they already have.
 
-pub struct Store {
-    ds:
+/// Column name to value, as read from or written to one row
+pub type Object = Map<String, Value>;
+
+/// Data that `bmc_create` can insert as a new row of `Entity`
+pub trait Creatable: Serialize {
+    type Entity: crate::model::Entity;
+}
+
+/// Data that `bmc_update` can write over an existing row
+///
+/// Every serialized key is written, so a whole-record struct replaces the
+/// row while a struct that skips `None` fields only patches what it carries.
+pub trait Patchable: Serialize {
+    type Entity: crate::model::Entity;
 }
 
-impl Store
\ No newline at end of file
+/// Equality conditions for `bmc_list`; `None` fields do not filter
+pub trait Filterable: Serialize {}
+
+pub fn to_object<T: Serialize>(data: &T) -> rusqlite::Result<Object> {
+    match serde_json::to_value(data) {
+        Ok(Value::Object(object)) => Ok(object),
+        Ok(_) => Err(rusqlite::Error::ToSqlConversionFailure("expected a struct".into())),
+        Err(e) => Err(rusqlite::Error::ToSqlConversionFailure(Box::new(e))),
+    }
+}
+
+/// The SQLite value for a JSON field; nested arrays and objects are stored as JSON text
+pub fn to_sql(value: &Value) -> SqlValue {
+    match value {
+        Value::Null => SqlValue::Null,
+        Value::Bool(b) => SqlValue::Integer(i64::from(*b)),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => SqlValue::Integer(i),
+            None => SqlValue::Real(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => SqlValue::Text(s.clone()),
+        other => SqlValue::Text(other.to_string()),
+    }
+}
+
+/// Read `columns` from `row` by position into an object keyed by name
+pub fn row_to_object(row: &Row, columns: &[&str]) -> rusqlite::Result<Object> {
+    let mut object = Object::new();
+    for (i, column) in columns.iter().enumerate() {
+        let value = match row.get_ref(i)? {
+            ValueRef::Null => Value::Null,
+            ValueRef::Integer(n) => Value::from(n),
+            ValueRef::Real(f) => Number::from_f64(f).map_or(Value::Null, Value::Number),
+            ValueRef::Text(t) => Value::String(String::from_utf8_lossy(t).into_owned()),
+            ValueRef::Blob(_) => Value::Null,
+        };
+        object.insert(column.to_string(), value);
+    }
+    Ok(object)
+}
+
+pub fn from_object<E: DeserializeOwned>(object: Object) -> rusqlite::Result<E> {
+    serde_json::from_value(Value::Object(object))
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::new(e)))
+}
+
+/// Accept `true`/`false` or SQLite's `1`/`0` for an optional boolean column
+pub fn int_bool<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<bool>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum IntOrBool {
+        Bool(bool),
+        Int(i64),
+    }
+    Ok(Option::<IntOrBool>::deserialize(deserializer)?.map(|v| match v {
+        IntOrBool::Bool(b) => b,
+        IntOrBool::Int(i) => i != 0,
+    }))
+}
@@ -0,0 +1,358 @@
+use crate::auth;
+use crate::error::Error;
+use hmac::{Hmac, Mac};
+use qrcodegen::{QrCode, QrCodeEcc};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use rusqlite::{params, Connection, OptionalExtension, Result};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use std::time::{SystemTime, UNIX_EPOCH};
+use ts_rs::TS;
+
+// ============ TOTP (RFC 6238) ============
+//
+// Standard authenticator-app parameters: HMAC-SHA1, 6 digits, 30 second
+// steps. The shared secret lives in `user_settings.two_factor_secret`, which
+// is protected by the SQLCipher key like everything else in the file.
+
+const ISSUER: &str = "rchart";
+const SECRET_BYTES: usize = 20;
+const DIGITS: u32 = 6;
+const STEP_SECS: u64 = 30;
+
+/// Steps either side of now that still verify, to absorb clock drift
+const ALLOWED_SKEW: i64 = 1;
+
+const RECOVERY_CODE_COUNT: usize = 10;
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct TwoFactorEnrollment {
+    /// Base32 secret for manual entry
+    pub secret: String,
+    pub otpauth_uri: String,
+    /// The otpauth URI as an SVG QR code, ready to drop into the page
+    pub qr_svg: String,
+}
+
+fn base32_alphabet() -> base32::Alphabet {
+    base32::Alphabet::Rfc4648 { padding: false }
+}
+
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; SECRET_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    base32::encode(base32_alphabet(), &bytes)
+}
+
+/// `otpauth://` URI understood by Google Authenticator, Authy, 1Password and friends
+pub fn otpauth_uri(secret: &str, username: &str) -> String {
+    format!(
+        "otpauth://totp/{issuer}:{account}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits={digits}&period={period}",
+        issuer = ISSUER,
+        account = percent_encode(username),
+        secret = secret,
+        digits = DIGITS,
+        period = STEP_SECS,
+    )
+}
+
+/// HOTP value (RFC 4226) for one counter
+fn hotp(key: &[u8], counter: u64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    binary % 10u32.pow(DIGITS)
+}
+
+fn current_step() -> i64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    (now / STEP_SECS) as i64
+}
+
+/// The time step `code` is valid for, if it matches now or an adjacent step
+///
+/// Callers compare the step with the last one used so a code cannot be replayed.
+pub fn verify_code(secret: &str, code: &str) -> Option<i64> {
+    let code = code.trim().replace(' ', "");
+    if code.len() != DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let code: u32 = code.parse().ok()?;
+    let key = base32::decode(base32_alphabet(), secret)?;
+    let now = current_step();
+
+    (now - ALLOWED_SKEW..=now + ALLOWED_SKEW)
+        .find(|&step| step >= 0 && hotp(&key, step as u64) == code)
+}
+
+pub fn qr_svg(text: &str) -> crate::error::Result<String> {
+    let qr = QrCode::encode_text(text, QrCodeEcc::Medium).map_err(|e| Error::internal(format!("{:?}", e)))?;
+    let border = 4;
+    let size = qr.size() + border * 2;
+
+    let mut path = String::new();
+    for y in 0..qr.size() {
+        for x in 0..qr.size() {
+            if qr.get_module(x, y) {
+                path.push_str(&format!("M{},{}h1v1h-1z", x + border, y + border));
+            }
+        }
+    }
+
+    Ok(format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {size} {size}\" shape-rendering=\"crispEdges\">\
+         <rect width=\"100%\" height=\"100%\" fill=\"#ffffff\"/>\
+         <path d=\"{path}\" fill=\"#000000\"/></svg>",
+        size = size,
+        path = path,
+    ))
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+// ============ Enrollment State ============
+
+/// `two_factor_secret` set with `two_factor_enabled = 0` means enrollment was started but not confirmed
+#[derive(Debug)]
+pub struct TwoFactorState {
+    pub enabled: bool,
+    pub secret: Option<String>,
+    pub last_step: Option<i64>,
+}
+
+pub fn get_state(conn: &Connection, user_id: i64) -> Result<TwoFactorState> {
+    let state = conn.query_row(
+        "SELECT two_factor_enabled, two_factor_secret, two_factor_last_step
+         FROM user_settings WHERE user_id = ?1",
+        params![user_id],
+        |row| {
+            let enabled: Option<i32> = row.get(0)?;
+            Ok(TwoFactorState {
+                enabled: enabled.unwrap_or(0) != 0,
+                secret: row.get(1)?,
+                last_step: row.get(2)?,
+            })
+        },
+    ).optional()?;
+
+    Ok(state.unwrap_or(TwoFactorState {
+        enabled: false,
+        secret: None,
+        last_step: None,
+    }))
+}
+
+/// Store a fresh pending secret, replacing any unconfirmed one
+pub fn begin_enrollment(conn: &Connection, user_id: i64, secret: &str) -> Result<()> {
+    conn.execute(
+        "INSERT INTO user_settings (user_id, two_factor_enabled, two_factor_secret)
+         VALUES (?1, 0, ?2)
+         ON CONFLICT(user_id) DO UPDATE SET
+            two_factor_secret = excluded.two_factor_secret,
+            two_factor_last_step = NULL,
+            updated_at = datetime('now', 'localtime')",
+        params![user_id, secret],
+    )?;
+    Ok(())
+}
+
+pub fn set_enabled(conn: &Connection, user_id: i64, last_step: i64) -> Result<()> {
+    conn.execute(
+        "UPDATE user_settings SET
+            two_factor_enabled = 1,
+            two_factor_last_step = ?2,
+            updated_at = datetime('now', 'localtime')
+         WHERE user_id = ?1",
+        params![user_id, last_step],
+    )?;
+    Ok(())
+}
+
+pub fn disable(conn: &Connection, user_id: i64) -> Result<()> {
+    conn.execute(
+        "UPDATE user_settings SET
+            two_factor_enabled = 0,
+            two_factor_secret = NULL,
+            two_factor_last_step = NULL,
+            updated_at = datetime('now', 'localtime')
+         WHERE user_id = ?1",
+        params![user_id],
+    )?;
+    conn.execute("DELETE FROM user_recovery_codes WHERE user_id = ?1", params![user_id])?;
+    Ok(())
+}
+
+/// Check a TOTP code for an enabled user, refusing a step that was already used
+pub fn verify_for_user(conn: &Connection, user_id: i64, code: &str) -> Result<bool> {
+    let state = get_state(conn, user_id)?;
+    let secret = match (state.enabled, state.secret) {
+        (true, Some(secret)) => secret,
+        _ => return Ok(false),
+    };
+
+    match verify_code(&secret, code) {
+        Some(step) if state.last_step.is_none_or(|last| step > last) => {
+            conn.execute(
+                "UPDATE user_settings SET two_factor_last_step = ?2 WHERE user_id = ?1",
+                params![user_id, step],
+            )?;
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+// ============ Recovery Codes ============
+
+/// Replace the user's recovery codes; the plain codes are returned once and only hashes are kept
+pub fn generate_recovery_codes(conn: &Connection, user_id: i64) -> crate::error::Result<Vec<String>> {
+    let alphabet = base32::Alphabet::Rfc4648Lower { padding: false };
+    let mut codes = Vec::with_capacity(RECOVERY_CODE_COUNT);
+
+    conn.execute("DELETE FROM user_recovery_codes WHERE user_id = ?1", params![user_id])?;
+    for _ in 0..RECOVERY_CODE_COUNT {
+        let mut bytes = [0u8; 10];
+        OsRng.fill_bytes(&mut bytes);
+        let raw = base32::encode(alphabet, &bytes);
+        let code = format!("{}-{}", &raw[..8], &raw[8..16]);
+
+        conn.execute(
+            "INSERT INTO user_recovery_codes (user_id, code_hash) VALUES (?1, ?2)",
+            params![user_id, auth::hash_password(&code)?],
+        )?;
+        codes.push(code);
+    }
+
+    Ok(codes)
+}
+
+/// Spend a recovery code; each one works exactly once
+pub fn use_recovery_code(conn: &Connection, user_id: i64, code: &str) -> Result<bool> {
+    let code = code.trim().to_lowercase();
+    let mut stmt = conn.prepare(
+        "SELECT id, code_hash FROM user_recovery_codes WHERE user_id = ?1 AND used_at IS NULL"
+    )?;
+    let unused = stmt
+        .query_map(params![user_id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<Result<Vec<_>>>()?;
+
+    match unused.into_iter().find(|(_, hash)| auth::verify_password(&code, hash)) {
+        Some((id, _)) => {
+            conn.execute(
+                "UPDATE user_recovery_codes SET used_at = datetime('now', 'localtime') WHERE id = ?1",
+                params![id],
+            )?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Which kind of code completed a login
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecondFactor {
+    Totp,
+    RecoveryCode,
+}
+
+/// A TOTP code or, failing that, an unused recovery code
+pub fn verify_second_factor(conn: &Connection, user_id: i64, code: &str) -> Result<Option<SecondFactor>> {
+    if verify_for_user(conn, user_id, code)? {
+        return Ok(Some(SecondFactor::Totp));
+    }
+    if use_recovery_code(conn, user_id, code)? {
+        return Ok(Some(SecondFactor::RecoveryCode));
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The shared secret of the RFC 4226 and RFC 6238 SHA-1 test vectors
+    const RFC_KEY: &[u8] = b"12345678901234567890";
+
+    fn code_at(step: i64) -> String {
+        format!("{:06}", hotp(RFC_KEY, step as u64))
+    }
+
+    /// Run `check` against a step that stays current for the whole call
+    fn at_stable_step(check: impl Fn(i64) -> bool) -> bool {
+        loop {
+            let step = current_step();
+            let result = check(step);
+            if current_step() == step {
+                return result;
+            }
+        }
+    }
+
+    #[test]
+    fn hotp_matches_rfc_4226() {
+        let expected = [755224, 287082, 359152, 969429, 338314, 254676, 287922, 162583, 399871, 520489];
+        for (counter, code) in expected.into_iter().enumerate() {
+            assert_eq!(hotp(RFC_KEY, counter as u64), code, "counter {}", counter);
+        }
+    }
+
+    #[test]
+    fn totp_matches_rfc_6238() {
+        // The RFC lists eight digits; a six-digit code is the last six of them
+        let expected = [
+            (59, 94287082),
+            (1111111109, 7081804),
+            (1111111111, 14050471),
+            (1234567890, 89005924),
+            (2000000000, 69279037),
+            (20000000000, 65353130),
+        ];
+        for (time, code) in expected {
+            assert_eq!(hotp(RFC_KEY, time / STEP_SECS), code % 1_000_000, "time {}", time);
+        }
+    }
+
+    #[test]
+    fn verify_code_accepts_the_current_and_adjacent_steps_only() {
+        let secret = base32::encode(base32_alphabet(), RFC_KEY);
+        assert!(at_stable_step(|now| verify_code(&secret, &code_at(now)) == Some(now)));
+        assert!(at_stable_step(|now| verify_code(&secret, &code_at(now - 1)) == Some(now - 1)));
+        assert!(at_stable_step(|now| verify_code(&secret, &code_at(now + 1)) == Some(now + 1)));
+        assert!(at_stable_step(|now| verify_code(&secret, &code_at(now - 2)).is_none()));
+    }
+
+    #[test]
+    fn verify_code_tolerates_spacing_and_refuses_malformed_codes() {
+        let secret = base32::encode(base32_alphabet(), RFC_KEY);
+        assert!(at_stable_step(|now| {
+            let code = code_at(now);
+            verify_code(&secret, &format!(" {} {} ", &code[..3], &code[3..])) == Some(now)
+        }));
+        for code in ["", "12345", "1234567", "12a456"] {
+            assert_eq!(verify_code(&secret, code), None, "{:?}", code);
+        }
+        assert_eq!(verify_code("not base32!", "123456"), None);
+    }
+}
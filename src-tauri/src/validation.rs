@@ -0,0 +1,453 @@
+use crate::db::{Allergy, Appointment, FamilyHistory, Lab, LabReferenceRange, Patient, Prescription, SocialHistory, Vaccination, Vital};
+use crate::error::{Error, FieldError, Result};
+use crate::orders::Order;
+use crate::vitals::{VitalsSet, DERIVED_TYPES};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// ============ Input Validation ============
+//
+// Commands check what the frontend sent before opening a transaction, so a
+// typo'd date or an implausible vital never reaches SQLite. Each check adds
+// to a list instead of stopping at the first problem, and the command gets
+// one validation error naming every bad field.
+
+/// Implemented by every struct a command writes as the user typed it
+pub trait Validate {
+    fn validate(&self) -> Result<()>;
+}
+
+/// Collects field errors; `finish` turns them into a single validation error
+#[derive(Default)]
+pub struct Checks {
+    errors: Vec<FieldError>,
+}
+
+impl Checks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, field: &str, message: impl Into<String>) {
+        self.errors.push(FieldError {
+            field: Some(field.to_string()),
+            message: message.into(),
+        });
+    }
+
+    /// Whether `field` already failed, so later checks on it can be skipped
+    fn failed(&self, field: &str) -> bool {
+        self.errors.iter().any(|e| e.field.as_deref() == Some(field))
+    }
+
+    pub fn required(&mut self, field: &str, label: &str, value: &str) -> &mut Self {
+        if value.trim().is_empty() {
+            self.add(field, format!("{} is required", label));
+        }
+        self
+    }
+
+    /// A `YYYY-MM-DD` calendar date; empty passes unless `required` flagged it
+    pub fn date(&mut self, field: &str, label: &str, value: &str) -> &mut Self {
+        if !value.trim().is_empty() && !self.failed(field) && parse_date(value).is_none() {
+            self.add(field, format!("{} must be a date in YYYY-MM-DD form", label));
+        }
+        self
+    }
+
+    pub fn optional_date(&mut self, field: &str, label: &str, value: Option<&str>) -> &mut Self {
+        self.date(field, label, value.unwrap_or(""))
+    }
+
+    /// A date with an optional `HH:MM[:SS]` time, separated by a space or `T`
+    pub fn datetime(&mut self, field: &str, label: &str, value: &str) -> &mut Self {
+        if !value.trim().is_empty() && !self.failed(field) && parse_datetime(value).is_none() {
+            self.add(field, format!("{} must be a date and time like 2024-05-10 14:30", label));
+        }
+        self
+    }
+
+    /// A date that cannot be later than today
+    pub fn not_future(&mut self, field: &str, label: &str, value: &str) -> &mut Self {
+        if !self.failed(field) {
+            if let Some(date) = parse_date(value.get(..10).unwrap_or(value)) {
+                if date > latest_date() {
+                    self.add(field, format!("{} cannot be in the future", label));
+                }
+            }
+        }
+        self
+    }
+
+    /// One of a fixed set of values, compared case-insensitively
+    pub fn one_of(&mut self, field: &str, label: &str, value: Option<&str>, allowed: &[&str]) -> &mut Self {
+        if let Some(value) = value.filter(|v| !v.is_empty()) {
+            if !allowed.iter().any(|a| a.eq_ignore_ascii_case(value)) {
+                self.add(field, format!("{} must be one of: {}", label, allowed.join(", ")));
+            }
+        }
+        self
+    }
+
+    pub fn range<T: PartialOrd + std::fmt::Display>(&mut self, field: &str, label: &str, value: T, min: T, max: T) -> &mut Self {
+        if value < min || value > max {
+            self.add(field, format!("{} must be between {} and {}", label, min, max));
+        }
+        self
+    }
+
+    pub fn finish(&mut self) -> Result<()> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Validation(std::mem::take(&mut self.errors)))
+        }
+    }
+}
+
+// ============ Dates ============
+
+/// `(year, month, day)` from a `YYYY-MM-DD` string, rejecting days the month does not have
+pub fn parse_date(value: &str) -> Option<(i32, u32, u32)> {
+    let bytes = value.as_bytes();
+    if bytes.len() != 10 || bytes[4] != b'-' || bytes[7] != b'-' {
+        return None;
+    }
+    let year: i32 = value[0..4].parse().ok()?;
+    let month: u32 = value[5..7].parse().ok()?;
+    let day: u32 = value[8..10].parse().ok()?;
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+    Some((year, month, day))
+}
+
+fn parse_datetime(value: &str) -> Option<(i32, u32, u32)> {
+    let date = parse_date(value.get(..10)?)?;
+    let time = &value[10..];
+    if time.is_empty() {
+        return Some(date);
+    }
+    let time = time.strip_prefix(' ').or_else(|| time.strip_prefix('T'))?;
+    let mut parts = time.split(':');
+    let hour: u32 = parts.next()?.parse().ok()?;
+    let minute: u32 = parts.next()?.parse().ok()?;
+    // Seconds may carry a fraction from `toISOString`
+    let second = parts.next().map(|s| s.trim_end_matches('Z').parse::<f64>()).transpose().ok()?;
+    let valid = hour < 24 && minute < 60 && second.is_none_or(|s| (0.0..61.0).contains(&s)) && parts.next().is_none();
+    valid.then_some(date)
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Tomorrow's UTC date, so "not in the future" holds in time zones ahead of UTC
+fn latest_date() -> (i32, u32, u32) {
+    let days = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() / 86_400) as i64 + 1;
+    // Civil-from-days (Howard Hinnant)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = (yoe + era * 400 + i64::from(month <= 2)) as i32;
+    (year, month, day)
+}
+
+// ============ Allowed Values ============
+
+/// Sex at birth as collected on the new-patient form
+pub const SEX_VALUES: &[&str] = &["M", "F", "I", "U"];
+pub const ALLERGY_SEVERITIES: &[&str] = &["mild", "moderate", "severe"];
+pub const SOCIAL_HISTORY_STATUSES: &[&str] = &["current", "former", "never"];
+pub const APPOINTMENT_STATUSES: &[&str] = &["scheduled", "confirmed", "checked-in", "completed", "cancelled", "no-show"];
+pub const PRESCRIPTION_STATUSES: &[&str] = &["pending", "sent", "filled", "cancelled"];
+pub const ORDER_TYPES: &[&str] = &["lab", "imaging"];
+pub const ORDER_PRIORITIES: &[&str] = &["routine", "urgent", "stat"];
+
+/// A vital sign the chart understands, with the units it may be recorded in
+struct VitalSpec {
+    vital_type: &'static str,
+    label: &'static str,
+    /// `(unit, low, high)`: the physiologically plausible range in that unit
+    units: &'static [(&'static str, f64, f64)],
+}
+
+const VITAL_SPECS: &[VitalSpec] = &[
+    VitalSpec { vital_type: "BP", label: "Systolic pressure", units: &[("mmHg", 40.0, 300.0)] },
+    VitalSpec { vital_type: "HR", label: "Heart rate", units: &[("bpm", 20.0, 300.0)] },
+    VitalSpec { vital_type: "RR", label: "Respiratory rate", units: &[("breaths/min", 2.0, 80.0), ("/min", 2.0, 80.0)] },
+    VitalSpec { vital_type: "Temp", label: "Temperature", units: &[("°F", 77.0, 113.0), ("F", 77.0, 113.0), ("°C", 25.0, 45.0), ("C", 25.0, 45.0)] },
+    VitalSpec { vital_type: "SpO2", label: "Oxygen saturation", units: &[("%", 40.0, 100.0)] },
+    VitalSpec { vital_type: "Weight", label: "Weight", units: &[("kg", 0.2, 650.0), ("lb", 0.5, 1400.0), ("lbs", 0.5, 1400.0)] },
+    VitalSpec { vital_type: "Height", label: "Height", units: &[("cm", 20.0, 275.0), ("in", 8.0, 108.0)] },
+    VitalSpec { vital_type: "Pain", label: "Pain score", units: &[("/10", 0.0, 10.0), ("", 0.0, 10.0)] },
+];
+
+/// Diastolic pressure, which travels in `value_secondary` of a `BP` reading
+const DIASTOLIC_RANGE: (f64, f64) = (10.0, 200.0);
+
+/// The fields a vital's problems are reported against
+struct VitalFields<'a> {
+    value: &'a str,
+    secondary: &'a str,
+    unit: &'a str,
+}
+
+/// Check one reading's type, unit and plausibility
+fn check_vital(checks: &mut Checks, vital: &Vital, fields: &VitalFields) {
+    let Some(spec) = VITAL_SPECS.iter().find(|s| s.vital_type.eq_ignore_ascii_case(&vital.vital_type)) else {
+        let known: Vec<&str> = VITAL_SPECS.iter().map(|s| s.vital_type).collect();
+        checks.add("vital_type", format!("Vital type must be one of: {}", known.join(", ")));
+        return;
+    };
+    if !checks.failed(fields.value) && !vital.value.is_finite() {
+        checks.add(fields.value, format!("{} must be a number", spec.label));
+    }
+    match spec.units.iter().find(|(unit, _, _)| unit.eq_ignore_ascii_case(vital.unit.trim())) {
+        Some(&(_, low, high)) => {
+            checks.range(fields.value, spec.label, vital.value, low, high);
+        }
+        None => {
+            let units: Vec<&str> = spec.units.iter().map(|(u, _, _)| *u).filter(|u| !u.is_empty()).collect();
+            checks.add(fields.unit, format!("{} must be recorded in {}", spec.label, units.join(" or ")));
+        }
+    }
+    if spec.vital_type == "BP" {
+        match vital.value_secondary {
+            Some(diastolic) => {
+                checks.range(fields.secondary, "Diastolic pressure", diastolic, DIASTOLIC_RANGE.0, DIASTOLIC_RANGE.1);
+                if diastolic >= vital.value {
+                    checks.add(fields.secondary, "Diastolic pressure must be lower than systolic");
+                }
+            }
+            None => checks.add(fields.secondary, "Diastolic pressure is required"),
+        }
+    }
+}
+
+// ============ Struct Checks ============
+
+impl Validate for Patient {
+    fn validate(&self) -> Result<()> {
+        let mut checks = Checks::new();
+        checks
+            .required("first_name", "First name", &self.first_name)
+            .required("last_name", "Last name", &self.last_name)
+            .required("dob", "Date of birth", &self.dob)
+            .date("dob", "Date of birth", &self.dob)
+            .not_future("dob", "Date of birth", &self.dob)
+            .required("sex", "Sex", &self.sex)
+            .one_of("sex", "Sex", Some(&self.sex), SEX_VALUES)
+            .one_of("status", "Status", self.status.as_deref(), &["active", "inactive", "deceased", "merged"])
+            .optional_date("status_date", "Status date", self.status_date.as_deref());
+        if let Some(email) = self.email.as_deref().map(str::trim).filter(|e| !e.is_empty()) {
+            let plausible = email
+                .split_once('@')
+                .is_some_and(|(user, domain)| !user.is_empty() && domain.contains('.') && !email.contains(char::is_whitespace));
+            if !plausible {
+                checks.add("email", "Email address is not valid");
+            }
+        }
+        if let Some(phone) = self.phone.as_deref().filter(|p| !p.trim().is_empty()) {
+            if !phone.chars().all(|c| c.is_ascii_digit() || " -()+.".contains(c)) {
+                checks.add("phone", "Phone number may only contain digits, spaces and - ( ) +");
+            }
+        }
+        checks.finish()
+    }
+}
+
+impl Validate for Appointment {
+    fn validate(&self) -> Result<()> {
+        let mut checks = Checks::new();
+        checks
+            .required("appointment_time", "Appointment time", &self.appointment_time)
+            .datetime("appointment_time", "Appointment time", &self.appointment_time)
+            .one_of("status", "Status", self.status.as_deref(), APPOINTMENT_STATUSES);
+        if let Some(minutes) = self.duration_minutes {
+            checks.range("duration_minutes", "Duration (minutes)", minutes, 5, 480);
+        }
+        checks.finish()
+    }
+}
+
+impl Validate for Vital {
+    fn validate(&self) -> Result<()> {
+        let mut checks = Checks::new();
+        checks.datetime("recorded_at", "Recorded at", &self.recorded_at);
+        if DERIVED_TYPES.iter().any(|t| t.eq_ignore_ascii_case(&self.vital_type)) {
+            checks.add("vital_type", format!("{} is calculated from the rest of the set", self.vital_type));
+            return checks.finish();
+        }
+        check_vital(&mut checks, self, &VitalFields { value: "value", secondary: "value_secondary", unit: "unit" });
+        checks.finish()
+    }
+}
+
+impl Validate for VitalsSet {
+    fn validate(&self) -> Result<()> {
+        let readings = self.readings();
+        if readings.is_empty() {
+            return Err(Error::invalid("Enter at least one vital sign"));
+        }
+        let mut checks = Checks::new();
+        checks
+            .required("recorded_at", "Recorded at", &self.recorded_at)
+            .datetime("recorded_at", "Recorded at", &self.recorded_at)
+            .not_future("recorded_at", "Recorded at", &self.recorded_at);
+        if self.systolic.is_none() && self.diastolic.is_some() {
+            checks.add("systolic", "Systolic pressure is required");
+        }
+        for (field, reading) in &readings {
+            let unit = match *field {
+                "temperature" | "weight" | "height" => format!("{}_unit", field),
+                _ => field.to_string(),
+            };
+            check_vital(&mut checks, reading, &VitalFields { value: field, secondary: "diastolic", unit: &unit });
+        }
+        checks.finish()
+    }
+}
+
+impl Validate for Lab {
+    fn validate(&self) -> Result<()> {
+        let mut checks = Checks::new();
+        checks
+            .required("name", "Test name", &self.name)
+            .required("recorded_at", "Recorded at", &self.recorded_at)
+            .datetime("recorded_at", "Recorded at", &self.recorded_at);
+        if !self.value.is_finite() {
+            checks.add("value", "Result must be a number");
+        }
+        checks.finish()
+    }
+}
+
+impl Validate for LabReferenceRange {
+    fn validate(&self) -> Result<()> {
+        let mut checks = Checks::new();
+        checks
+            .required("test_name", "Test name", &self.test_name)
+            .one_of("sex", "Sex", self.sex.as_deref().map(str::trim), SEX_VALUES);
+        if let Some(min) = self.age_min_years {
+            checks.range("age_min_years", "Minimum age", min, 0, 150);
+        }
+        if let Some(max) = self.age_max_years {
+            checks.range("age_max_years", "Maximum age", max, 1, 151);
+        }
+        if let (Some(min), Some(max)) = (self.age_min_years, self.age_max_years) {
+            if !checks.failed("age_max_years") && max <= min {
+                checks.add("age_max_years", "Maximum age must be above the minimum age");
+            }
+        }
+        let limits = [
+            ("critical_low", self.critical_low),
+            ("low", self.low),
+            ("high", self.high),
+            ("critical_high", self.critical_high),
+        ];
+        if limits.iter().all(|(_, limit)| limit.is_none()) {
+            checks.add("low", "Enter at least one limit");
+        }
+        for (field, limit) in limits {
+            if limit.is_some_and(|l| !l.is_finite()) {
+                checks.add(field, "Limit must be a number");
+            }
+        }
+        // Each limit must sit inside the next: critical low, low, high, critical high
+        let ordered: Vec<(&str, f64)> = limits.iter().filter_map(|&(field, limit)| Some((field, limit?))).collect();
+        for pair in ordered.windows(2) {
+            let ((lower_field, lower), (upper_field, upper)) = (pair[0], pair[1]);
+            if lower >= upper {
+                checks.add(upper_field, format!("{} must be above the {}", limit_label(upper_field), limit_label(lower_field).to_lowercase()));
+            }
+        }
+        checks.finish()
+    }
+}
+
+fn limit_label(field: &str) -> &'static str {
+    match field {
+        "critical_low" => "Critical low",
+        "low" => "Low end",
+        "high" => "High end",
+        _ => "Critical high",
+    }
+}
+
+impl Validate for Order {
+    fn validate(&self) -> Result<()> {
+        Checks::new()
+            .required("order_type", "Order type", &self.order_type)
+            .one_of("order_type", "Order type", Some(self.order_type.trim()), ORDER_TYPES)
+            .required("test_name", "Test", &self.test_name)
+            .one_of("priority", "Priority", self.priority.as_deref(), ORDER_PRIORITIES)
+            .finish()
+    }
+}
+
+impl Validate for Prescription {
+    fn validate(&self) -> Result<()> {
+        Checks::new()
+            .range("quantity", "Quantity", self.quantity, 1, 10_000)
+            .range("days_supply", "Days supply", self.days_supply, 1, 365)
+            .range("refills", "Refills", self.refills, 0, 11)
+            .required("sig", "Directions (sig)", &self.sig)
+            .one_of("status", "Status", self.status.as_deref(), PRESCRIPTION_STATUSES)
+            .optional_date("prescribed_date", "Prescribed date", self.prescribed_date.as_deref().map(|d| d.get(..10).unwrap_or(d)))
+            .optional_date("filled_date", "Filled date", self.filled_date.as_deref().map(|d| d.get(..10).unwrap_or(d)))
+            .finish()
+    }
+}
+
+impl Validate for Allergy {
+    fn validate(&self) -> Result<()> {
+        Checks::new()
+            .required("allergen", "Allergen", &self.allergen)
+            .one_of("severity", "Severity", self.severity.as_deref(), ALLERGY_SEVERITIES)
+            .finish()
+    }
+}
+
+impl Validate for Vaccination {
+    fn validate(&self) -> Result<()> {
+        Checks::new()
+            .required("vaccine_name", "Vaccine", &self.vaccine_name)
+            .required("date_given", "Date given", &self.date_given)
+            .date("date_given", "Date given", &self.date_given)
+            .not_future("date_given", "Date given", &self.date_given)
+            .finish()
+    }
+}
+
+impl Validate for SocialHistory {
+    fn validate(&self) -> Result<()> {
+        Checks::new()
+            .required("category", "Category", &self.category)
+            .required("detail", "Detail", &self.detail)
+            .one_of("status", "Status", self.status.as_deref(), SOCIAL_HISTORY_STATUSES)
+            .finish()
+    }
+}
+
+impl Validate for FamilyHistory {
+    fn validate(&self) -> Result<()> {
+        let mut checks = Checks::new();
+        checks
+            .required("relation", "Relation", &self.relation)
+            .required("condition", "Condition", &self.condition);
+        if let Some(age) = self.age_at_onset {
+            checks.range("age_at_onset", "Age at onset", age, 0, 130);
+        }
+        checks.finish()
+    }
+}
@@ -0,0 +1,279 @@
+use crate::audit::{self, AuditAction};
+use crate::db::Vital;
+use crate::event::ModelAction;
+use crate::model::{bmc_create, bmc_list, fire_model_event, ModelMutateResultData};
+use crate::store::Filterable;
+use rusqlite::{params, Connection, Result};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// ============ Vitals Sets and Derived Measurements ============
+//
+// A rooming intake is recorded as one `VitalsSet`: every reading in it shares
+// `recorded_at`, which is what ties them together afterwards. BMI, mean
+// arterial pressure and body surface area are calculated from readings in the
+// same set and stored as rows of their own with `is_derived` set, so the chart
+// and its trends read them like any other vital. They are never edited
+// directly; whenever a reading they depend on is added, changed or removed,
+// `refresh_derived` recalculates the set.
+
+/// Vital types that are only ever calculated
+pub const DERIVED_TYPES: &[&str] = &["BMI", "MAP", "BSA"];
+
+const KG_PER_LB: f64 = 0.453_592_37;
+const CM_PER_IN: f64 = 2.54;
+
+/// Everything measured at one rooming; leave out what was not taken
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
+pub struct VitalsSet {
+    #[ts(as = "f64")]
+    pub patient_id: i64,
+    pub recorded_at: String,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub systolic: Option<f64>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub diastolic: Option<f64>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub heart_rate: Option<f64>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub respiratory_rate: Option<f64>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub temperature: Option<f64>,
+    /// `°F` or `°C`
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub temperature_unit: Option<String>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub spo2: Option<f64>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub weight: Option<f64>,
+    /// `kg` or `lb`
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub weight_unit: Option<String>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub height: Option<f64>,
+    /// `cm` or `in`
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub height_unit: Option<String>,
+    /// 0 to 10
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub pain: Option<f64>,
+}
+
+impl VitalsSet {
+    /// The measured readings, each paired with the field of the set it came from
+    ///
+    /// A value without its unit still produces a reading (with an empty unit)
+    /// so validation can point at the missing unit.
+    pub fn readings(&self) -> Vec<(&'static str, Vital)> {
+        let reading = |vital_type: &str, value: f64, unit: &str| Vital {
+            id: None,
+            patient_id: self.patient_id,
+            vital_type: vital_type.to_string(),
+            value,
+            value_secondary: None,
+            unit: unit.to_string(),
+            recorded_at: self.recorded_at.clone(),
+            is_derived: None,
+            row_version: None,
+        };
+        let unit = |unit: &Option<String>| unit.as_deref().unwrap_or("").trim().to_string();
+
+        let mut readings = Vec::new();
+        if self.systolic.is_some() || self.diastolic.is_some() {
+            let mut bp = reading("BP", self.systolic.unwrap_or(f64::NAN), "mmHg");
+            bp.value_secondary = self.diastolic;
+            readings.push(("systolic", bp));
+        }
+        if let Some(hr) = self.heart_rate {
+            readings.push(("heart_rate", reading("HR", hr, "bpm")));
+        }
+        if let Some(rr) = self.respiratory_rate {
+            readings.push(("respiratory_rate", reading("RR", rr, "breaths/min")));
+        }
+        if let Some(temp) = self.temperature {
+            readings.push(("temperature", reading("Temp", temp, &unit(&self.temperature_unit))));
+        }
+        if let Some(spo2) = self.spo2 {
+            readings.push(("spo2", reading("SpO2", spo2, "%")));
+        }
+        if let Some(weight) = self.weight {
+            readings.push(("weight", reading("Weight", weight, &unit(&self.weight_unit))));
+        }
+        if let Some(height) = self.height {
+            readings.push(("height", reading("Height", height, &unit(&self.height_unit))));
+        }
+        if let Some(pain) = self.pain {
+            readings.push(("pain", reading("Pain", pain, "/10")));
+        }
+        readings
+    }
+}
+
+/// The readings of one patient taken at one moment
+#[derive(Serialize)]
+struct SetScope<'a> {
+    patient_id: i64,
+    recorded_at: &'a str,
+}
+
+impl Filterable for SetScope<'_> {}
+
+/// Store a validated set and its derived values in one transaction; returns every row of the set
+pub fn record_set(conn: &Connection, user_id: i64, set: &VitalsSet) -> Result<Vec<Vital>> {
+    let tx = conn.unchecked_transaction()?;
+    for (_, reading) in set.readings() {
+        let id = bmc_create(&tx, &reading)?.id;
+        audit::record_change(&tx, user_id, AuditAction::Create, "vitals", id, None)?;
+    }
+    refresh_derived(&tx, user_id, set.patient_id, &set.recorded_at)?;
+    tx.commit()?;
+    bmc_list(conn, &SetScope { patient_id: set.patient_id, recorded_at: &set.recorded_at })
+}
+
+/// Recalculate the derived vitals of the set taken at `recorded_at`, adding, updating or removing rows to match
+pub fn refresh_derived(conn: &Connection, user_id: i64, patient_id: i64, recorded_at: &str) -> Result<()> {
+    let set: Vec<Vital> = bmc_list(conn, &SetScope { patient_id, recorded_at })?;
+    let measured = |vital_type: &str| set.iter().rev().find(|v| v.is_derived != Some(true) && v.vital_type.eq_ignore_ascii_case(vital_type));
+
+    let bp = measured("BP").and_then(|v| Some((v.value, v.value_secondary?)));
+    let weight_kg = measured("Weight").and_then(|v| to_kg(v.value, &v.unit));
+    let height_cm = measured("Height").and_then(|v| to_cm(v.value, &v.unit));
+    let derived = derive(bp, weight_kg, height_cm);
+
+    for vital_type in DERIVED_TYPES {
+        let existing = set.iter().find(|v| v.is_derived == Some(true) && v.vital_type == *vital_type);
+        let wanted = derived.iter().find(|(t, _, _)| t == vital_type);
+        match (existing.and_then(|v| v.id), wanted) {
+            (Some(id), Some(&(_, value, _))) => {
+                if existing.is_some_and(|v| v.value != value) {
+                    let before = audit::snapshot(conn, "vitals", id)?;
+                    conn.execute("UPDATE vitals SET value = ?1, row_version = row_version + 1 WHERE id = ?2", params![value, id])?;
+                    audit::record_change(conn, user_id, AuditAction::Update, "vitals", id, before)?;
+                    fire_model_event(conn, "vitals", ModelAction::Update, ModelMutateResultData { id, patient_id: Some(patient_id) });
+                }
+            }
+            (None, Some(&(vital_type, value, unit))) => {
+                conn.execute(
+                    "INSERT INTO vitals (patient_id, vital_type, value, unit, recorded_at, is_derived)
+                     VALUES (?1, ?2, ?3, ?4, ?5, 1)",
+                    params![patient_id, vital_type, value, unit, recorded_at],
+                )?;
+                let id = conn.last_insert_rowid();
+                audit::record_change(conn, user_id, AuditAction::Create, "vitals", id, None)?;
+                fire_model_event(conn, "vitals", ModelAction::Create, ModelMutateResultData { id, patient_id: Some(patient_id) });
+            }
+            (Some(id), None) => {
+                let before = audit::snapshot(conn, "vitals", id)?;
+                conn.execute("DELETE FROM vitals WHERE id = ?1", params![id])?;
+                audit::record_change(conn, user_id, AuditAction::Delete, "vitals", id, before)?;
+                fire_model_event(conn, "vitals", ModelAction::Delete, ModelMutateResultData { id, patient_id: Some(patient_id) });
+            }
+            (None, None) => {}
+        }
+    }
+    Ok(())
+}
+
+/// `(vital_type, value, unit)` for each value the inputs allow
+///
+/// MAP is (SBP + 2 × DBP) / 3; BSA uses the Mosteller formula, √(cm × kg / 3600).
+pub fn derive(bp: Option<(f64, f64)>, weight_kg: Option<f64>, height_cm: Option<f64>) -> Vec<(&'static str, f64, &'static str)> {
+    let mut derived = Vec::new();
+    if let Some((systolic, diastolic)) = bp {
+        derived.push(("MAP", round_to((systolic + 2.0 * diastolic) / 3.0, 0), "mmHg"));
+    }
+    if let (Some(kg), Some(cm)) = (weight_kg, height_cm) {
+        if kg > 0.0 && cm > 0.0 {
+            let m = cm / 100.0;
+            derived.push(("BMI", round_to(kg / (m * m), 1), "kg/m²"));
+            derived.push(("BSA", round_to((cm * kg / 3600.0).sqrt(), 2), "m²"));
+        }
+    }
+    derived
+}
+
+fn to_kg(value: f64, unit: &str) -> Option<f64> {
+    match unit.trim().to_ascii_lowercase().as_str() {
+        "kg" => Some(value),
+        "lb" | "lbs" => Some(value * KG_PER_LB),
+        _ => None,
+    }
+}
+
+fn to_cm(value: f64, unit: &str) -> Option<f64> {
+    match unit.trim().to_ascii_lowercase().as_str() {
+        "cm" => Some(value),
+        "in" => Some(value * CM_PER_IN),
+        _ => None,
+    }
+}
+
+fn round_to(value: f64, places: i32) -> f64 {
+    let factor = 10f64.powi(places);
+    (value * factor).round() / factor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(derived: &[(&'static str, f64, &'static str)], vital_type: &str) -> Option<f64> {
+        derived.iter().find(|(t, _, _)| *t == vital_type).map(|&(_, v, _)| v)
+    }
+
+    #[test]
+    fn map_is_a_third_of_the_way_from_diastolic_to_systolic() {
+        assert_eq!(value(&derive(Some((120.0, 80.0)), None, None), "MAP"), Some(93.0));
+        assert_eq!(value(&derive(Some((90.0, 60.0)), None, None), "MAP"), Some(70.0));
+        assert_eq!(value(&derive(Some((200.0, 100.0)), None, None), "MAP"), Some(133.0));
+    }
+
+    #[test]
+    fn bmi_and_bsa_from_metric_readings() {
+        let derived = derive(None, Some(70.0), Some(175.0));
+        assert_eq!(value(&derived, "BMI"), Some(22.9));
+        // Mosteller: sqrt(175 * 70 / 3600)
+        assert_eq!(value(&derived, "BSA"), Some(1.84));
+        assert_eq!(value(&derived, "MAP"), None);
+    }
+
+    #[test]
+    fn bmi_and_bsa_from_imperial_readings() {
+        let kg = to_kg(150.0, "lb").unwrap();
+        let cm = to_cm(69.0, "in").unwrap();
+        assert!((kg - 68.038_855_5).abs() < 1e-6);
+        assert!((cm - 175.26).abs() < 1e-9);
+        let derived = derive(None, Some(kg), Some(cm));
+        assert_eq!(value(&derived, "BMI"), Some(22.2));
+        assert_eq!(value(&derived, "BSA"), Some(1.82));
+    }
+
+    #[test]
+    fn units_are_matched_loosely_and_unknown_ones_refused() {
+        assert_eq!(to_kg(70.0, " KG "), Some(70.0));
+        assert_eq!(to_kg(2.0, "LBS"), Some(2.0 * KG_PER_LB));
+        assert_eq!(to_kg(11.0, "st"), None);
+        assert_eq!(to_cm(10.0, "In"), Some(25.4));
+        assert_eq!(to_cm(1.75, "m"), None);
+    }
+
+    #[test]
+    fn nothing_is_derived_without_both_weight_and_height() {
+        assert!(derive(None, Some(70.0), None).is_empty());
+        assert!(derive(None, None, Some(175.0)).is_empty());
+        assert!(derive(None, Some(0.0), Some(175.0)).is_empty());
+    }
+}
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type Allergy = { id: number | null, patient_id: number, allergen: string, reaction: string | null, severity: string | null, row_version?: number | null, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type Appointment = { id: number | null, patient_id: number, appointment_time: string, duration_minutes: number | null, reason: string | null, status: string | null, notes: string | null, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type AppointmentWithPatient = { id: number, patient_id: number, patient_name: string, appointment_time: string, duration_minutes: number, reason: string | null, status: string, };
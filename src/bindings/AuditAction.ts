@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type AuditAction = "read" | "search" | "create" | "update" | "delete" | "merge" | "login" | "login_failed" | "logout" | "break_glass";
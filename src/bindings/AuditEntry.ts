@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { JsonValue } from "./serde_json/JsonValue";
+
+/**
+ * A stored audit log row
+ */
+export type AuditEntry = { id: number, occurred_at: string, user_id: number | null, username: string | null, action: string, entity_type: string, entity_id: number | null, patient_id: number | null, detail: JsonValue | null, };
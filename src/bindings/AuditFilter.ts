@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AuditAction } from "./AuditAction";
+
+/**
+ * Filters for access reports; every field is optional and they combine with AND
+ */
+export type AuditFilter = { patient_id: number | null, user_id: number | null, 
+/**
+ * Inclusive start date, `YYYY-MM-DD`
+ */
+from_date: string | null, 
+/**
+ * Inclusive end date, `YYYY-MM-DD`
+ */
+to_date: string | null, action: AuditAction | null, limit: number | null, };
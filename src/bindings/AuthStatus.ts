@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SessionInfo } from "./SessionInfo";
+
+export type AuthStatus = { 
+/**
+ * False on a brand-new database, where the first account still has to be created
+ */
+has_users: boolean, session: SessionInfo | null, };
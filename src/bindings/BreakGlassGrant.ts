@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type BreakGlassGrant = { id: number, user_id: number, username: string | null, patient_id: number, patient_name: string | null, reason: string, granted_at: string, expires_at: string, reviewed_at: string | null, reviewed_by: number | null, review_note: string | null, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type CareTeamMember = { user_id: number, username: string, first_name: string, last_name: string, added_at: string, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type ClinicalScore = { id: number | null, patient_id: number, score_type: string, score: number, max_score: number | null, interpretation: string | null, recorded_at: string, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type Diagnosis = { id: number | null, patient_id: number, name: string, icd_code: string | null, onset_date: string | null, status: string | null, category: string | null, };
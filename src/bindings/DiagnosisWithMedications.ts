@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Diagnosis } from "./Diagnosis";
+
+export type DiagnosisWithMedications = { diagnosis: Diagnosis, medication_ids: Array<number>, };
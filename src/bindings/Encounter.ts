@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { EncounterAddendum } from "./EncounterAddendum";
+
+export type Encounter = { id: number | null, patient_id: number, encounter_date: string, encounter_type: string, chief_complaint: string | null, summary: string | null, note_content: string | null, provider: string | null, location: string | null, 
+/**
+ * Set by `sign_encounter`; a signed note can no longer be edited, only amended
+ */
+signed_at?: string | null, signed_by?: number | null, signed_by_name?: string | null, cosigned_at?: string | null, cosigned_by?: number | null, cosigned_by_name?: string | null, 
+/**
+ * Filled in by `get_encounter_by_id` only; chart lists leave it empty
+ */
+addenda: Array<EncounterAddendum>, row_version?: number | null, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type EncounterAddendum = { id: number | null, encounter_id: number, author_id: number | null, author_name: string | null, content: string, created_at: string | null, };
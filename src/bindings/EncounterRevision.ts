@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type EncounterRevision = { id: number, encounter_id: number, 
+/**
+ * 1 for the first save, counting up per encounter
+ */
+revision_number: number, author_id: number | null, author_name: string | null, chief_complaint: string | null, summary: string | null, note_content: string | null, 
+/**
+ * The revision this one was restored from, if it came from a restore
+ */
+restored_from: number | null, created_at: string, };
@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type EncryptionStatus = { 
+/**
+ * `rchart.db` exists on disk
+ */
+database_exists: boolean, 
+/**
+ * The file on disk is SQLCipher-encrypted (false for a legacy plaintext file or no file yet)
+ */
+encrypted: boolean, 
+/**
+ * A keyed connection is open and commands can run
+ */
+unlocked: boolean, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type FamilyHistory = { id: number | null, patient_id: number, relation: string, condition: string, age_at_onset: number | null, row_version?: number | null, };
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DiffLine } from "./DiffLine";
+
+export type FieldDiff = { 
+/**
+ * `chief_complaint`, `summary` or `note_content`
+ */
+field: string, lines: Array<DiffLine>, };
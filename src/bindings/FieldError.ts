@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * One refused input, with the offending field when it is known
+ */
+export type FieldError = { field: string | null, message: string, };
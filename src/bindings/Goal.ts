@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type Goal = { id: number | null, patient_id: number, description: string, target_date: string | null, status: string | null, progress: number | null, };
@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { OrphanedRows } from "./OrphanedRows";
+
+export type IntegrityReport = { 
+/**
+ * What `PRAGMA integrity_check` found wrong with the file itself; empty when it is sound
+ */
+storage_errors: Array<string>, orphans: Array<OrphanedRows>, 
+/**
+ * Tables whose `<table>_fts` search index no longer matches their rows
+ */
+stale_search_indexes: Array<string>, };
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { LabInterpretation } from "./LabInterpretation";
+
+export type Lab = { id: number | null, patient_id: number, name: string, value: number, unit: string | null, reference_range_low: number | null, reference_range_high: number | null, is_abnormal?: boolean | null, interpretation?: LabInterpretation | null, recorded_at: string, 
+/**
+ * The order this result answers; set through `orders::attach_lab`, never by a plain update
+ */
+order_id?: number | null, row_version?: number | null, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Where a result falls against its reference range
+ */
+export type LabInterpretation = "normal" | "low" | "high" | "critical_low" | "critical_high";
@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Normal and critical limits for one test; NULL columns match any unit, sex or age
+ */
+export type LabReferenceRange = { id?: number | null, test_name: string, unit?: string | null, sex?: string | null, age_min_years?: number | null, 
+/**
+ * Exclusive, so 0–18 and 18– do not overlap
+ */
+age_max_years?: number | null, low?: number | null, high?: number | null, critical_low?: number | null, critical_high?: number | null, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SessionInfo } from "./SessionInfo";
+
+/**
+ * Outcome of the password step: either a session, or a request for the second factor
+ */
+export type LoginResult = { session: SessionInfo | null, two_factor_required: boolean, };
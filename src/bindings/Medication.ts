@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type Medication = { id: number | null, patient_id: number, name: string, dose: string | null, frequency: string | null, route: string | null, start_date: string | null, end_date: string | null, status: string | null, };
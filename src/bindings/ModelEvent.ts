@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ModelAction } from "./ModelAction";
+
+export type ModelEvent = { 
+/**
+ * Table the row lives in (`patients`, `allergies`, `appointments`, ...)
+ */
+entity: string, id: number, action: ModelAction, 
+/**
+ * Whose chart the row belongs to; a patient's own row carries its id
+ */
+patient_id: number | null, };
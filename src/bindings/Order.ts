@@ -0,0 +1,26 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * An order as listed, with the names the lists show alongside it
+ */
+export type Order = { id: number | null, patient_id: number, encounter_id?: number | null, diagnosis_id?: number | null, 
+/**
+ * `lab` or `imaging`
+ */
+order_type: string, test_name: string, 
+/**
+ * `routine`, `urgent` or `stat`; routine when left out
+ */
+priority?: string | null, 
+/**
+ * Set by the workflow commands, never by the client
+ */
+status?: string | null, notes?: string | null, ordered_by?: number | null, ordered_by_name?: string | null, ordered_at?: string | null, collected_at?: string | null, resulted_at?: string | null, 
+/**
+ * The impression of an imaging report
+ */
+result_summary?: string | null, reviewed_at?: string | null, reviewed_by_name?: string | null, cancelled_at?: string | null, cancel_reason?: string | null, patient_name?: string | null, diagnosis_name?: string | null, 
+/**
+ * Lab results attached to the order, oldest first
+ */
+lab_ids: Array<number>, };
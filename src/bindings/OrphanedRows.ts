@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Rows of `table` whose `column` names a `parent` row that does not exist
+ */
+export type OrphanedRows = { table: string, column: string, parent: string, 
+/**
+ * The key's `ON DELETE` action: `CASCADE`, `SET NULL` or `NO ACTION`
+ */
+on_delete: string, 
+/**
+ * Whether the column allows NULL, so the reference can be cleared instead of the row removed
+ */
+nullable: boolean, row_ids: Array<number>, };
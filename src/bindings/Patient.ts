@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type Patient = { id: number | null, first_name: string, last_name: string, dob: string, sex: string, gender: string | null, address: string | null, phone: string | null, email: string | null, photo_url: string | null, ai_summary: string | null, preferred_pharmacy: string | null, insurance_provider: string | null, insurance_policy_number: string | null, insurance_group_number: string | null, 
+/**
+ * `active`, `inactive`, `deceased` or `merged`; set by the lifecycle commands, not by create/update
+ */
+status?: string | null, 
+/**
+ * When the status last changed (date of death for `deceased`)
+ */
+status_date?: string | null, 
+/**
+ * The version this copy was read at; updates from an older copy are rejected as conflicts
+ */
+row_version?: number | null, };
@@ -0,0 +1,17 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Allergy } from "./Allergy";
+import type { ClinicalScore } from "./ClinicalScore";
+import type { DiagnosisWithMedications } from "./DiagnosisWithMedications";
+import type { Encounter } from "./Encounter";
+import type { FamilyHistory } from "./FamilyHistory";
+import type { Goal } from "./Goal";
+import type { Lab } from "./Lab";
+import type { Medication } from "./Medication";
+import type { Patient } from "./Patient";
+import type { SocialHistory } from "./SocialHistory";
+import type { TimelineEvent } from "./TimelineEvent";
+import type { Todo } from "./Todo";
+import type { Vaccination } from "./Vaccination";
+import type { Vital } from "./Vital";
+
+export type PatientFullData = { patient: Patient, diagnoses: Array<DiagnosisWithMedications>, medications: Array<Medication>, vitals: Array<Vital>, labs: Array<Lab>, clinical_scores: Array<ClinicalScore>, encounters: Array<Encounter>, allergies: Array<Allergy>, vaccinations: Array<Vaccination>, social_history: Array<SocialHistory>, family_history: Array<FamilyHistory>, todos: Array<Todo>, goals: Array<Goal>, timeline_events: Array<TimelineEvent>, };
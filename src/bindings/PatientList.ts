@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type PatientList = { id: number | null, user_id: number, name: string, description: string | null, color: string | null, icon: string | null, is_default: boolean, sort_order: number, row_version?: number | null, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type PatientListColumn = { id: number | null, list_id: number, column_key: string, column_label: string, column_type: string | null, is_visible: boolean, sort_order: number, width: number | null, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Patient } from "./Patient";
+import type { PatientList } from "./PatientList";
+import type { PatientListColumn } from "./PatientListColumn";
+
+export type PatientListWithPatients = { list: PatientList, columns: Array<PatientListColumn>, patients: Array<Patient>, };
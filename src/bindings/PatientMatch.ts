@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Patient } from "./Patient";
+
+/**
+ * A patient that may be the one being looked for
+ */
+export type PatientMatch = { patient: Patient, 
+/**
+ * 1.0 for an exact name and date of birth
+ */
+confidence: number, 
+/**
+ * Why it matched, e.g. "Last name sounds alike"
+ */
+reasons: Array<string>, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type Permission = "view_patients" | "edit_patients" | "manage_patient_records" | "view_schedule" | "manage_schedule" | "view_clinical" | "edit_clinical" | "view_notes" | "write_notes" | "cosign_notes" | "prescribe" | "place_orders" | "view_audit_log" | "manage_users" | "manage_database";
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type Prescription = { id: number | null, patient_id: number, medication_id: number, quantity: number, days_supply: number, refills: number, sig: string, pharmacy: string | null, prescriber_id: number | null, status: string | null, prescribed_date: string | null, filled_date: string | null, notes: string | null, };
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { IntegrityReport } from "./IntegrityReport";
+
+export type RepairSummary = { references_cleared: number, rows_quarantined: number, search_indexes_rebuilt: Array<string>, 
+/**
+ * A fresh check after the repair; storage errors cannot be fixed from inside the app
+ */
+report: IntegrityReport, };
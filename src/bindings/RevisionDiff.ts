@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FieldDiff } from "./FieldDiff";
+
+export type RevisionDiff = { from_revision: number, to_revision: number, 
+/**
+ * Only the fields that differ
+ */
+fields: Array<FieldDiff>, };
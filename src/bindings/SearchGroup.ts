@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SearchResult } from "./SearchResult";
+
+/**
+ * The hits in one patient's chart, for "3 matches in John Smith's chart"
+ */
+export type SearchGroup = { 
+/**
+ * `None` for hits that belong to no chart, such as general messages
+ */
+patient_id: number | null, patient_name: string | null, 
+/**
+ * The best score in the group
+ */
+score: number, results: Array<SearchResult>, };
@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Search result item with type information
+ */
+export type SearchResult = { 
+/**
+ * `result_type` of the `SEARCH_INDEXES` entry that matched (`patient`, `lab`, `allergy`, ...)
+ */
+result_type: string, id: number, patient_id: number | null, 
+/**
+ * Full name of the chart the hit is in
+ */
+patient_name: string | null, title: string, subtitle: string | null, snippet: string | null, 
+/**
+ * Relevance after weighting and boosts; higher is better and comparable across result types
+ */
+score: number, };
@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Permission } from "./Permission";
+import type { Role } from "./Role";
+
+/**
+ * What the frontend sees of the current session
+ */
+export type SessionInfo = { user_id: number, username: string, role: Role, 
+/**
+ * So the frontend can hide what the backend would refuse anyway
+ */
+permissions: Array<Permission>, started_at: string, idle_timeout_secs: number, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type SocialHistory = { id: number | null, patient_id: number, category: string, detail: string, status: string | null, row_version?: number | null, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type TimelineEvent = { id: number | null, patient_id: number, event_type: string, description: string, event_date: string, icon: string | null, color: string | null, };
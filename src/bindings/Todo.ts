@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type Todo = { id: number | null, patient_id: number, diagnosis_id: number | null, description: string, due_date: string | null, priority: string | null, status: string | null, };
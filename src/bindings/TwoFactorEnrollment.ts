@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type TwoFactorEnrollment = { 
+/**
+ * Base32 secret for manual entry
+ */
+secret: string, otpauth_uri: string, 
+/**
+ * The otpauth URI as an SVG QR code, ready to drop into the page
+ */
+qr_svg: string, };
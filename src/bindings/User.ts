@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Role } from "./Role";
+
+export type User = { id: number | null, username: string, first_name: string, last_name: string, degree_type: string | null, specialty: string | null, subspecialty: string | null, npi_number: string | null, photo_url: string | null, bio: string | null, 
+/**
+ * Changed only through `set_user_role`; `update_user` leaves it alone
+ */
+role: Role, row_version?: number | null, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type UserBadge = { id: number | null, user_id: number, badge_name: string, badge_type: string, description: string | null, icon: string | null, color: string | null, awarded_date: string | null, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type UserEducation = { id: number | null, user_id: number, education_type: string, institution: string, degree: string | null, field_of_study: string | null, start_year: number | null, end_year: number | null, };
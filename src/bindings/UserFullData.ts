@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { User } from "./User";
+import type { UserBadge } from "./UserBadge";
+import type { UserEducation } from "./UserEducation";
+import type { UserSettings } from "./UserSettings";
+
+export type UserFullData = { user: User, education: Array<UserEducation>, badges: Array<UserBadge>, settings: UserSettings, };
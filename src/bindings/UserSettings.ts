@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type UserSettings = { id: number | null, user_id: number, language: string | null, notifications_enabled: boolean | null, email_notifications: boolean | null, sms_notifications: boolean | null, two_factor_enabled: boolean | null, zen_mode_default: boolean | null, row_version?: number | null, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type Vaccination = { id: number | null, patient_id: number, vaccine_name: string, date_given: string, row_version?: number | null, };
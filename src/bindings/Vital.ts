@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type Vital = { id: number | null, patient_id: number, vital_type: string, value: number, value_secondary: number | null, unit: string, recorded_at: string, 
+/**
+ * Calculated from other readings taken at the same time; see `vitals`
+ */
+is_derived?: boolean | null, row_version?: number | null, };
@@ -0,0 +1,22 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Everything measured at one rooming; leave out what was not taken
+ */
+export type VitalsSet = { patient_id: number, recorded_at: string, systolic?: number | null, diastolic?: number | null, heart_rate?: number | null, respiratory_rate?: number | null, temperature?: number | null, 
+/**
+ * `°F` or `°C`
+ */
+temperature_unit?: string | null, spo2?: number | null, weight?: number | null, 
+/**
+ * `kg` or `lb`
+ */
+weight_unit?: string | null, height?: number | null, 
+/**
+ * `cm` or `in`
+ */
+height_unit?: string | null, 
+/**
+ * 0 to 10
+ */
+pain?: number | null, };
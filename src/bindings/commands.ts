@@ -0,0 +1,460 @@
+// This file was generated by `cargo test export_bindings` from src-tauri/src/main.rs. Do not edit this file manually.
+import { invoke } from '@tauri-apps/api/tauri';
+import type { Allergy } from './Allergy';
+import type { Appointment } from './Appointment';
+import type { AppointmentWithPatient } from './AppointmentWithPatient';
+import type { AuditEntry } from './AuditEntry';
+import type { AuditFilter } from './AuditFilter';
+import type { AuthStatus } from './AuthStatus';
+import type { BreakGlassGrant } from './BreakGlassGrant';
+import type { CareTeamMember } from './CareTeamMember';
+import type { Encounter } from './Encounter';
+import type { EncounterAddendum } from './EncounterAddendum';
+import type { EncounterRevision } from './EncounterRevision';
+import type { EncryptionStatus } from './EncryptionStatus';
+import type { FamilyHistory } from './FamilyHistory';
+import type { IntegrityReport } from './IntegrityReport';
+import type { Lab } from './Lab';
+import type { LabReferenceRange } from './LabReferenceRange';
+import type { LegacyAppointment } from './LegacyAppointment';
+import type { LoginResult } from './LoginResult';
+import type { Order } from './Order';
+import type { OrphanFix } from './OrphanFix';
+import type { Patient } from './Patient';
+import type { PatientFullData } from './PatientFullData';
+import type { PatientList } from './PatientList';
+import type { PatientListColumn } from './PatientListColumn';
+import type { PatientListWithPatients } from './PatientListWithPatients';
+import type { PatientMatch } from './PatientMatch';
+import type { Prescription } from './Prescription';
+import type { RepairSummary } from './RepairSummary';
+import type { RevisionDiff } from './RevisionDiff';
+import type { Role } from './Role';
+import type { SchemaStatus } from './SchemaStatus';
+import type { SearchGroup } from './SearchGroup';
+import type { SearchResult } from './SearchResult';
+import type { SessionInfo } from './SessionInfo';
+import type { SocialHistory } from './SocialHistory';
+import type { TwoFactorEnrollment } from './TwoFactorEnrollment';
+import type { User } from './User';
+import type { UserFullData } from './UserFullData';
+import type { UserSettings } from './UserSettings';
+import type { Vaccination } from './Vaccination';
+import type { Vital } from './Vital';
+import type { VitalsSet } from './VitalsSet';
+
+export function getMonth(): Promise<string> {
+	return invoke<string>('get_month');
+}
+
+export function greet(name: string): Promise<string> {
+	return invoke<string>('greet', { name });
+}
+
+export function getAppointments(): Promise<Array<LegacyAppointment>> {
+	return invoke<Array<LegacyAppointment>>('get_appointments');
+}
+
+export function dbGetEncryptionStatus(): Promise<EncryptionStatus> {
+	return invoke<EncryptionStatus>('db_get_encryption_status');
+}
+
+export function dbUnlock(passphrase: string): Promise<void> {
+	return invoke<void>('db_unlock', { passphrase });
+}
+
+export function dbLock(): Promise<void> {
+	return invoke<void>('db_lock');
+}
+
+export function dbChangePassphrase(currentPassphrase: string, newPassphrase: string): Promise<void> {
+	return invoke<void>('db_change_passphrase', { currentPassphrase, newPassphrase });
+}
+
+export function dbCheckIntegrity(): Promise<IntegrityReport> {
+	return invoke<IntegrityReport>('db_check_integrity');
+}
+
+export function dbRepairIntegrity(fix: OrphanFix): Promise<RepairSummary> {
+	return invoke<RepairSummary>('db_repair_integrity', { fix });
+}
+
+export function dbGetAuthStatus(): Promise<AuthStatus> {
+	return invoke<AuthStatus>('db_get_auth_status');
+}
+
+export function dbLogin(username: string, password: string): Promise<LoginResult> {
+	return invoke<LoginResult>('db_login', { username, password });
+}
+
+export function dbVerifyTwoFactor(code: string): Promise<SessionInfo> {
+	return invoke<SessionInfo>('db_verify_two_factor', { code });
+}
+
+export function dbLogout(): Promise<void> {
+	return invoke<void>('db_logout');
+}
+
+export function dbCreateFirstUser(user: User, password: string): Promise<SessionInfo> {
+	return invoke<SessionInfo>('db_create_first_user', { user, password });
+}
+
+export function dbCreatePatient(patient: Patient): Promise<number> {
+	return invoke<number>('db_create_patient', { patient });
+}
+
+export function dbFindDuplicatePatients(patient: Patient): Promise<Array<PatientMatch>> {
+	return invoke<Array<PatientMatch>>('db_find_duplicate_patients', { patient });
+}
+
+export function dbGetAllPatients(): Promise<Array<Patient>> {
+	return invoke<Array<Patient>>('db_get_all_patients');
+}
+
+export function dbGetPatient(id: number): Promise<Patient | null> {
+	return invoke<Patient | null>('db_get_patient', { id });
+}
+
+export function dbUpdatePatient(patient: Patient): Promise<number> {
+	return invoke<number>('db_update_patient', { patient });
+}
+
+export function dbSetPatientStatus(patientId: number, status: string, statusDate: string | null): Promise<void> {
+	return invoke<void>('db_set_patient_status', { patientId, status, statusDate });
+}
+
+export function dbDeletePatient(patientId: number): Promise<void> {
+	return invoke<void>('db_delete_patient', { patientId });
+}
+
+export function dbMergePatients(survivorId: number, duplicateId: number): Promise<void> {
+	return invoke<void>('db_merge_patients', { survivorId, duplicateId });
+}
+
+export function dbCreateAppointment(appointment: Appointment): Promise<number> {
+	return invoke<number>('db_create_appointment', { appointment });
+}
+
+export function dbGetAppointmentsForDate(date: string): Promise<Array<AppointmentWithPatient>> {
+	return invoke<Array<AppointmentWithPatient>>('db_get_appointments_for_date', { date });
+}
+
+export function dbGetAllAppointments(): Promise<Array<AppointmentWithPatient>> {
+	return invoke<Array<AppointmentWithPatient>>('db_get_all_appointments');
+}
+
+export function dbSeedTestData(): Promise<string> {
+	return invoke<string>('db_seed_test_data');
+}
+
+export function dbGetSchemaStatus(): Promise<SchemaStatus> {
+	return invoke<SchemaStatus>('db_get_schema_status');
+}
+
+export function dbGetPatientFull(id: number): Promise<PatientFullData | null> {
+	return invoke<PatientFullData | null>('db_get_patient_full', { id });
+}
+
+export function dbGetEncounter(encounterId: number): Promise<Encounter | null> {
+	return invoke<Encounter | null>('db_get_encounter', { encounterId });
+}
+
+export function dbSeedPatientDetailTestData(patientId: number, forceReseed: boolean | null): Promise<string> {
+	return invoke<string>('db_seed_patient_detail_test_data', { patientId, forceReseed });
+}
+
+export function dbCreateEncounter(encounter: Encounter): Promise<number> {
+	return invoke<number>('db_create_encounter', { encounter });
+}
+
+export function dbUpdateEncounter(encounter: Encounter): Promise<number> {
+	return invoke<number>('db_update_encounter', { encounter });
+}
+
+export function dbSignEncounter(encounterId: number): Promise<Encounter> {
+	return invoke<Encounter>('db_sign_encounter', { encounterId });
+}
+
+export function dbCosignEncounter(encounterId: number): Promise<Encounter> {
+	return invoke<Encounter>('db_cosign_encounter', { encounterId });
+}
+
+export function dbCreateAddendum(encounterId: number, content: string): Promise<number> {
+	return invoke<number>('db_create_addendum', { encounterId, content });
+}
+
+export function dbGetAddenda(encounterId: number): Promise<Array<EncounterAddendum>> {
+	return invoke<Array<EncounterAddendum>>('db_get_addenda', { encounterId });
+}
+
+export function dbGetEncounterRevisions(encounterId: number): Promise<Array<EncounterRevision>> {
+	return invoke<Array<EncounterRevision>>('db_get_encounter_revisions', { encounterId });
+}
+
+export function dbDiffEncounterRevisions(encounterId: number, fromRevisionId: number, toRevisionId: number): Promise<RevisionDiff> {
+	return invoke<RevisionDiff>('db_diff_encounter_revisions', { encounterId, fromRevisionId, toRevisionId });
+}
+
+export function dbRestoreEncounterRevision(encounterId: number, revisionId: number): Promise<Encounter> {
+	return invoke<Encounter>('db_restore_encounter_revision', { encounterId, revisionId });
+}
+
+export function dbGetCurrentUser(): Promise<UserFullData | null> {
+	return invoke<UserFullData | null>('db_get_current_user');
+}
+
+export function dbGetUserFull(id: number): Promise<UserFullData | null> {
+	return invoke<UserFullData | null>('db_get_user_full', { id });
+}
+
+export function dbUpdateUser(user: User): Promise<number> {
+	return invoke<number>('db_update_user', { user });
+}
+
+export function dbUpdateUserSettings(settings: UserSettings): Promise<number> {
+	return invoke<number>('db_update_user_settings', { settings });
+}
+
+export function dbGetUsers(): Promise<Array<User>> {
+	return invoke<Array<User>>('db_get_users');
+}
+
+export function dbCreateUser(user: User, password: string): Promise<number> {
+	return invoke<number>('db_create_user', { user, password });
+}
+
+export function dbSetUserRole(userId: number, role: Role): Promise<void> {
+	return invoke<void>('db_set_user_role', { userId, role });
+}
+
+export function dbBeginTwoFactorEnrollment(): Promise<TwoFactorEnrollment> {
+	return invoke<TwoFactorEnrollment>('db_begin_two_factor_enrollment');
+}
+
+export function dbConfirmTwoFactorEnrollment(code: string): Promise<Array<string>> {
+	return invoke<Array<string>>('db_confirm_two_factor_enrollment', { code });
+}
+
+export function dbDisableTwoFactor(password: string, code: string): Promise<void> {
+	return invoke<void>('db_disable_two_factor', { password, code });
+}
+
+export function dbUpdatePassword(currentPassword: string, newPassword: string): Promise<void> {
+	return invoke<void>('db_update_password', { currentPassword, newPassword });
+}
+
+export function dbSeedUserData(): Promise<string> {
+	return invoke<string>('db_seed_user_data');
+}
+
+export function dbGetPatientLists(): Promise<Array<PatientList>> {
+	return invoke<Array<PatientList>>('db_get_patient_lists');
+}
+
+export function dbGetPatientList(listId: number): Promise<PatientListWithPatients | null> {
+	return invoke<PatientListWithPatients | null>('db_get_patient_list', { listId });
+}
+
+export function dbCreatePatientList(list: PatientList): Promise<number> {
+	return invoke<number>('db_create_patient_list', { list });
+}
+
+export function dbUpdatePatientList(list: PatientList): Promise<number> {
+	return invoke<number>('db_update_patient_list', { list });
+}
+
+export function dbDeletePatientList(listId: number): Promise<void> {
+	return invoke<void>('db_delete_patient_list', { listId });
+}
+
+export function dbGetPatientsInList(listId: number): Promise<Array<Patient>> {
+	return invoke<Array<Patient>>('db_get_patients_in_list', { listId });
+}
+
+export function dbAddPatientToList(listId: number, patientId: number, notes: string | null): Promise<number> {
+	return invoke<number>('db_add_patient_to_list', { listId, patientId, notes });
+}
+
+export function dbRemovePatientFromList(listId: number, patientId: number): Promise<void> {
+	return invoke<void>('db_remove_patient_from_list', { listId, patientId });
+}
+
+export function dbUpdateListColumns(listId: number, columns: Array<PatientListColumn>): Promise<void> {
+	return invoke<void>('db_update_list_columns', { listId, columns });
+}
+
+export function dbSeedPatientLists(): Promise<string> {
+	return invoke<string>('db_seed_patient_lists');
+}
+
+export function dbGetAuditLog(filter: AuditFilter): Promise<Array<AuditEntry>> {
+	return invoke<Array<AuditEntry>>('db_get_audit_log', { filter });
+}
+
+export function dbGetCareTeam(patientId: number): Promise<Array<CareTeamMember>> {
+	return invoke<Array<CareTeamMember>>('db_get_care_team', { patientId });
+}
+
+export function dbAddCareTeamMember(patientId: number, memberId: number): Promise<void> {
+	return invoke<void>('db_add_care_team_member', { patientId, memberId });
+}
+
+export function dbRemoveCareTeamMember(patientId: number, memberId: number): Promise<void> {
+	return invoke<void>('db_remove_care_team_member', { patientId, memberId });
+}
+
+export function dbBreakGlass(patientId: number, reason: string): Promise<BreakGlassGrant> {
+	return invoke<BreakGlassGrant>('db_break_glass', { patientId, reason });
+}
+
+export function dbGetBreakGlassQueue(includeReviewed: boolean | null): Promise<Array<BreakGlassGrant>> {
+	return invoke<Array<BreakGlassGrant>>('db_get_break_glass_queue', { includeReviewed });
+}
+
+export function dbReviewBreakGlass(grantId: number, note: string | null): Promise<void> {
+	return invoke<void>('db_review_break_glass', { grantId, note });
+}
+
+export function dbGlobalSearch(query: string, limit: number | null): Promise<Array<SearchGroup>> {
+	return invoke<Array<SearchGroup>>('db_global_search', { query, limit });
+}
+
+export function dbSearchPatientData(patientId: number, query: string, limit: number | null): Promise<Array<SearchResult>> {
+	return invoke<Array<SearchResult>>('db_search_patient_data', { patientId, query, limit });
+}
+
+export function dbQuickSearchPatients(query: string, limit: number | null): Promise<Array<Patient>> {
+	return invoke<Array<Patient>>('db_quick_search_patients', { query, limit });
+}
+
+export function dbCreatePrescriptions(prescriptions: Array<Prescription>): Promise<Array<number>> {
+	return invoke<Array<number>>('db_create_prescriptions', { prescriptions });
+}
+
+export function dbGetPrescriptions(patientId: number): Promise<Array<Prescription>> {
+	return invoke<Array<Prescription>>('db_get_prescriptions', { patientId });
+}
+
+export function dbRecordVitals(vitals: VitalsSet): Promise<Array<Vital>> {
+	return invoke<Array<Vital>>('db_record_vitals', { vitals });
+}
+
+export function dbCreateVital(vital: Vital): Promise<number> {
+	return invoke<number>('db_create_vital', { vital });
+}
+
+export function dbUpdateVital(vital: Vital): Promise<number> {
+	return invoke<number>('db_update_vital', { vital });
+}
+
+export function dbDeleteVital(id: number): Promise<void> {
+	return invoke<void>('db_delete_vital', { id });
+}
+
+export function dbCreateLab(lab: Lab): Promise<number> {
+	return invoke<number>('db_create_lab', { lab });
+}
+
+export function dbUpdateLab(lab: Lab): Promise<number> {
+	return invoke<number>('db_update_lab', { lab });
+}
+
+export function dbDeleteLab(id: number): Promise<void> {
+	return invoke<void>('db_delete_lab', { id });
+}
+
+export function dbGetLabReferenceRanges(): Promise<Array<LabReferenceRange>> {
+	return invoke<Array<LabReferenceRange>>('db_get_lab_reference_ranges');
+}
+
+export function dbSaveLabReferenceRange(range: LabReferenceRange): Promise<number> {
+	return invoke<number>('db_save_lab_reference_range', { range });
+}
+
+export function dbDeleteLabReferenceRange(id: number): Promise<void> {
+	return invoke<void>('db_delete_lab_reference_range', { id });
+}
+
+export function dbCreateOrder(order: Order): Promise<number> {
+	return invoke<number>('db_create_order', { order });
+}
+
+export function dbGetOrders(patientId: number): Promise<Array<Order>> {
+	return invoke<Array<Order>>('db_get_orders', { patientId });
+}
+
+export function dbGetOutstandingOrders(patientId: number | null): Promise<Array<Order>> {
+	return invoke<Array<Order>>('db_get_outstanding_orders', { patientId });
+}
+
+export function dbCollectOrder(id: number, collectedAt: string | null): Promise<void> {
+	return invoke<void>('db_collect_order', { id, collectedAt });
+}
+
+export function dbAttachLabToOrder(orderId: number, labId: number): Promise<void> {
+	return invoke<void>('db_attach_lab_to_order', { orderId, labId });
+}
+
+export function dbDetachLabFromOrder(labId: number): Promise<void> {
+	return invoke<void>('db_detach_lab_from_order', { labId });
+}
+
+export function dbRecordImagingResult(id: number, summary: string): Promise<void> {
+	return invoke<void>('db_record_imaging_result', { id, summary });
+}
+
+export function dbReviewOrder(id: number): Promise<void> {
+	return invoke<void>('db_review_order', { id });
+}
+
+export function dbCancelOrder(id: number, reason: string): Promise<void> {
+	return invoke<void>('db_cancel_order', { id, reason });
+}
+
+export function dbCreateAllergy(allergy: Allergy): Promise<number> {
+	return invoke<number>('db_create_allergy', { allergy });
+}
+
+export function dbUpdateAllergy(allergy: Allergy): Promise<number> {
+	return invoke<number>('db_update_allergy', { allergy });
+}
+
+export function dbDeleteAllergy(id: number): Promise<void> {
+	return invoke<void>('db_delete_allergy', { id });
+}
+
+export function dbCreateVaccination(vaccination: Vaccination): Promise<number> {
+	return invoke<number>('db_create_vaccination', { vaccination });
+}
+
+export function dbUpdateVaccination(vaccination: Vaccination): Promise<number> {
+	return invoke<number>('db_update_vaccination', { vaccination });
+}
+
+export function dbDeleteVaccination(id: number): Promise<void> {
+	return invoke<void>('db_delete_vaccination', { id });
+}
+
+export function dbCreateSocialHistory(history: SocialHistory): Promise<number> {
+	return invoke<number>('db_create_social_history', { history });
+}
+
+export function dbUpdateSocialHistory(history: SocialHistory): Promise<number> {
+	return invoke<number>('db_update_social_history', { history });
+}
+
+export function dbDeleteSocialHistory(id: number): Promise<void> {
+	return invoke<void>('db_delete_social_history', { id });
+}
+
+export function dbCreateFamilyHistory(history: FamilyHistory): Promise<number> {
+	return invoke<number>('db_create_family_history', { history });
+}
+
+export function dbUpdateFamilyHistory(history: FamilyHistory): Promise<number> {
+	return invoke<number>('db_update_family_history', { history });
+}
+
+export function dbDeleteFamilyHistory(id: number): Promise<void> {
+	return invoke<void>('db_delete_family_history', { id });
+}
@@ -0,0 +1,15 @@
+// Stale-write conflicts returned by the update commands
+
+import { isAppError } from './error';
+
+export interface Conflict<T = Record<string, unknown>> {
+	message: string;
+	/** The server copy, or null if the record was deleted */
+	current: T | null;
+}
+
+/** The conflict carried by an invoke error, or null for any other error */
+export function parseConflict<T = Record<string, unknown>>(error: unknown): Conflict<T> | null {
+	if (!isAppError(error) || error.kind !== 'conflict') return null;
+	return { message: error.message, current: (error.current ?? null) as T | null };
+}
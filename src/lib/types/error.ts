@@ -0,0 +1,58 @@
+// Errors returned by every Tauri command
+//
+// `Error` has a hand-written Serialize impl, so this shape is kept here rather
+// than generated; only `FieldError` comes from src/bindings.
+
+import type { FieldError } from '../../bindings/FieldError';
+
+export type ErrorKind =
+	| 'not_found'
+	| 'validation'
+	| 'conflict'
+	| 'unauthenticated'
+	| 'forbidden'
+	| 'restricted'
+	| 'locked'
+	| 'busy'
+	| 'database'
+	| 'internal';
+
+export type { FieldError };
+
+export interface AppError {
+	kind: ErrorKind;
+	message: string;
+	/** The input field that was refused, for validation errors */
+	field: string | null;
+	/** Whether the same call may succeed if tried again shortly */
+	retryable: boolean;
+	/** For a validation error: every refused field */
+	errors?: FieldError[];
+	/** For a conflict: the record as saved now, or null if it was deleted */
+	current?: unknown;
+}
+
+export function isAppError(error: unknown): error is AppError {
+	return (
+		typeof error === 'object' &&
+		error !== null &&
+		typeof (error as AppError).kind === 'string' &&
+		typeof (error as AppError).message === 'string'
+	);
+}
+
+/** Text to show for anything thrown by `invoke` */
+export function errorMessage(error: unknown): string {
+	if (isAppError(error)) return error.message;
+	return error instanceof Error ? error.message : String(error);
+}
+
+/** Validation messages keyed by field, for marking form inputs */
+export function fieldErrors(error: unknown): Record<string, string> {
+	const result: Record<string, string> = {};
+	if (!isAppError(error) || error.kind !== 'validation') return result;
+	for (const { field, message } of error.errors ?? []) {
+		if (field && !(field in result)) result[field] = message;
+	}
+	return result;
+}
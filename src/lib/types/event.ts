@@ -0,0 +1,28 @@
+// Change events the backend pushes after every create, update or delete
+
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+import type { ModelEvent } from '../../bindings/ModelEvent';
+
+export type { ModelAction } from '../../bindings/ModelAction';
+export type { ModelEvent };
+
+export interface ModelEventFilter {
+	/** Only changes to this patient's chart */
+	patientId?: number;
+	/** Only changes to these tables */
+	entities?: string[];
+}
+
+const MODEL_EVENT = 'model-change';
+
+/**
+ * Call `handler` for each change matching `filter`. A patient filter listens
+ * on that patient's own channel, so other charts' traffic never arrives.
+ */
+export function onModelChange(handler: (event: ModelEvent) => void, filter: ModelEventFilter = {}): Promise<UnlistenFn> {
+	const name = filter.patientId === undefined ? MODEL_EVENT : `${MODEL_EVENT}:patient:${filter.patientId}`;
+	return listen<ModelEvent>(name, ({ payload }) => {
+		if (filter.entities && !filter.entities.includes(payload.entity)) return;
+		handler(payload);
+	});
+}
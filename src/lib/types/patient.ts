@@ -1,22 +1,42 @@
-// TypeScript interfaces for patient data
-
-export interface Patient {
-	id: number | null;
-	first_name: string;
-	last_name: string;
-	dob: string;
-	sex: string;
-	gender: string | null;
-	address: string | null;
-	phone: string | null;
-	email: string | null;
-	photo_url: string | null;
-	ai_summary: string | null;
-	preferred_pharmacy: string | null;
-	insurance_provider: string | null;
-	insurance_policy_number: string | null;
-	insurance_group_number: string | null;
-}
+// TypeScript types for patient data
+//
+// The record shapes are generated from the Rust structs into src/bindings
+// (`cargo test export_bindings` in src-tauri). This file re-exports them and
+// narrows the few string fields the UI treats as a closed set.
+
+import type { Patient as PatientRecord } from '../../bindings/Patient';
+import type { Diagnosis as DiagnosisRecord } from '../../bindings/Diagnosis';
+import type { DiagnosisWithMedications as DiagnosisWithMedicationsRecord } from '../../bindings/DiagnosisWithMedications';
+import type { PatientFullData as PatientFullDataRecord } from '../../bindings/PatientFullData';
+import type { PatientMatch as PatientMatchRecord } from '../../bindings/PatientMatch';
+
+export type { Medication } from '../../bindings/Medication';
+export type { Prescription } from '../../bindings/Prescription';
+export type { Vital } from '../../bindings/Vital';
+export type { VitalsSet } from '../../bindings/VitalsSet';
+export type { Lab } from '../../bindings/Lab';
+export type { LabInterpretation } from '../../bindings/LabInterpretation';
+export type { LabReferenceRange } from '../../bindings/LabReferenceRange';
+export type { Order } from '../../bindings/Order';
+export type { ClinicalScore } from '../../bindings/ClinicalScore';
+export type { Encounter } from '../../bindings/Encounter';
+export type { EncounterAddendum } from '../../bindings/EncounterAddendum';
+export type { EncounterRevision } from '../../bindings/EncounterRevision';
+export type { RevisionDiff } from '../../bindings/RevisionDiff';
+export type { Allergy } from '../../bindings/Allergy';
+export type { Vaccination } from '../../bindings/Vaccination';
+export type { SocialHistory } from '../../bindings/SocialHistory';
+export type { FamilyHistory } from '../../bindings/FamilyHistory';
+export type { Todo } from '../../bindings/Todo';
+export type { Goal } from '../../bindings/Goal';
+export type { TimelineEvent } from '../../bindings/TimelineEvent';
+
+export type Patient = Omit<PatientRecord, 'status'> & { status?: PatientStatus | null };
+
+export type PatientStatus = 'active' | 'inactive' | 'deceased' | 'merged';
+
+/** A possible duplicate found at registration */
+export type PatientMatch = Omit<PatientMatchRecord, 'patient'> & { patient: Patient };
 
 // Diagnosis category types for the pie chart
 export type DiagnosisCategory =
@@ -68,166 +88,11 @@ export const CATEGORY_NAMES: Record<DiagnosisCategory, string> = {
 	social: 'Social'
 };
 
-export interface Diagnosis {
-	id: number | null;
-	patient_id: number;
-	name: string;
-	icd_code: string | null;
-	onset_date: string | null;
-	status: string | null;
-	category: DiagnosisCategory | null;
-}
-
-export interface Medication {
-	id: number | null;
-	patient_id: number;
-	name: string;
-	dose: string | null;
-	frequency: string | null;
-	route: string | null;
-	start_date: string | null;
-	end_date: string | null;
-	status: string | null;
-}
-
-export interface DiagnosisWithMedications {
-	diagnosis: Diagnosis;
-	medication_ids: number[];
-}
-
-export interface Prescription {
-	id: number | null;
-	patient_id: number;
-	medication_id: number;
-	quantity: number;
-	days_supply: number;
-	refills: number;
-	sig: string;
-	pharmacy: string | null;
-	prescriber_id: number | null;
-	status: string | null;
-	prescribed_date: string | null;
-	filled_date: string | null;
-	notes: string | null;
-}
-
-export interface Vital {
-	id: number | null;
-	patient_id: number;
-	vital_type: string;
-	value: number;
-	value_secondary: number | null;
-	unit: string;
-	recorded_at: string;
-}
-
-export interface Lab {
-	id: number | null;
-	patient_id: number;
-	name: string;
-	value: number;
-	unit: string | null;
-	reference_range_low: number | null;
-	reference_range_high: number | null;
-	is_abnormal: boolean | null;
-	recorded_at: string;
-}
-
-export interface ClinicalScore {
-	id: number | null;
-	patient_id: number;
-	score_type: string;
-	score: number;
-	max_score: number | null;
-	interpretation: string | null;
-	recorded_at: string;
-}
-
-export interface Encounter {
-	id: number | null;
-	patient_id: number;
-	encounter_date: string;
-	encounter_type: string;
-	chief_complaint: string | null;
-	summary: string | null;
-	note_content: string | null;
-	provider: string | null;
-	location: string | null;
-}
+export type Diagnosis = Omit<DiagnosisRecord, 'category'> & { category: DiagnosisCategory | null };
 
-export interface Allergy {
-	id: number | null;
-	patient_id: number;
-	allergen: string;
-	reaction: string | null;
-	severity: string | null;
-}
+export type DiagnosisWithMedications = Omit<DiagnosisWithMedicationsRecord, 'diagnosis'> & { diagnosis: Diagnosis };
 
-export interface Vaccination {
-	id: number | null;
-	patient_id: number;
-	vaccine_name: string;
-	date_given: string;
-}
-
-export interface SocialHistory {
-	id: number | null;
-	patient_id: number;
-	category: string;
-	detail: string;
-	status: string | null;
-}
-
-export interface FamilyHistory {
-	id: number | null;
-	patient_id: number;
-	relation: string;
-	condition: string;
-	age_at_onset: number | null;
-}
-
-export interface Todo {
-	id: number | null;
-	patient_id: number;
-	diagnosis_id: number | null;
-	description: string;
-	due_date: string | null;
-	priority: string | null;
-	status: string | null;
-}
-
-export interface Goal {
-	id: number | null;
-	patient_id: number;
-	description: string;
-	target_date: string | null;
-	status: string | null;
-	progress: number | null;
-}
-
-export interface TimelineEvent {
-	id: number | null;
-	patient_id: number;
-	event_type: string;
-	description: string;
-	event_date: string;
-	icon: string | null;
-	color: string | null;
-}
-
-export interface PatientFullData {
+export type PatientFullData = Omit<PatientFullDataRecord, 'patient' | 'diagnoses'> & {
 	patient: Patient;
 	diagnoses: DiagnosisWithMedications[];
-	medications: Medication[];
-	vitals: Vital[];
-	labs: Lab[];
-	clinical_scores: ClinicalScore[];
-	encounters: Encounter[];
-	allergies: Allergy[];
-	vaccinations: Vaccination[];
-	social_history: SocialHistory[];
-	family_history: FamilyHistory[];
-	todos: Todo[];
-	goals: Goal[];
-	timeline_events: TimelineEvent[];
-}
+};
@@ -1,13 +1,12 @@
-export interface PatientList {
-	id: number | null;
-	user_id: number;
-	name: string;
-	description: string | null;
-	color: string | null;
-	icon: string | null;
-	is_default: boolean;
-	sort_order: number;
-}
+// TypeScript types for patient lists; the records are generated into src/bindings
+
+import type { PatientListWithPatients as PatientListWithPatientsRecord } from '../../bindings/PatientListWithPatients';
+import type { Patient } from './patient';
+
+export type { PatientList } from '../../bindings/PatientList';
+export type { PatientListColumn } from '../../bindings/PatientListColumn';
+
+export type PatientListWithPatients = Omit<PatientListWithPatientsRecord, 'patients'> & { patients: Patient[] };
 
 export interface PatientListMember {
 	id: number | null;
@@ -17,23 +16,6 @@ export interface PatientListMember {
 	notes: string | null;
 }
 
-export interface PatientListColumn {
-	id: number | null;
-	list_id: number;
-	column_key: string;
-	column_label: string;
-	column_type: string | null;
-	is_visible: boolean;
-	sort_order: number;
-	width: number | null;
-}
-
-export interface PatientListWithPatients {
-	list: PatientList;
-	columns: PatientListColumn[];
-	patients: import('./patient').Patient[];
-}
-
 // Available column options for creating new lists
 export const AVAILABLE_COLUMNS: { key: string; label: string; type: string; defaultVisible: boolean }[] = [
 	{ key: 'name', label: 'Name', type: 'text', defaultVisible: true },
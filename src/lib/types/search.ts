@@ -1,14 +1,29 @@
-// TypeScript interfaces for search functionality
-
-export interface SearchResult {
-	result_type: 'patient' | 'encounter' | 'diagnosis' | 'medication' | 'lab';
-	id: number;
-	patient_id: number | null;
-	title: string;
-	subtitle: string | null;
-	snippet: string | null;
-	rank: number;
-}
+// TypeScript types for search functionality
+//
+// Result shapes are generated into src/bindings; `result_type` is narrowed to
+// the kinds of record the palette knows how to open.
+
+import type { SearchResult as SearchResultRecord } from '../../bindings/SearchResult';
+import type { SearchGroup as SearchGroupRecord } from '../../bindings/SearchGroup';
+
+export type SearchResultType =
+	| 'patient'
+	| 'encounter'
+	| 'diagnosis'
+	| 'medication'
+	| 'lab'
+	| 'allergy'
+	| 'vaccination'
+	| 'social_history'
+	| 'family_history'
+	| 'todo'
+	| 'prescription'
+	| 'message';
+
+export type SearchResult = Omit<SearchResultRecord, 'result_type'> & { result_type: SearchResultType };
+
+/** Global search hits gathered by chart, best group first */
+export type SearchGroup = Omit<SearchGroupRecord, 'results'> & { results: SearchResult[] };
 
 export interface SearchContext {
 	mode: 'local' | 'patient' | 'global';
@@ -22,7 +37,14 @@ export const SEARCH_RESULT_ICONS: Record<string, string> = {
 	encounter: 'fa-file-medical',
 	diagnosis: 'fa-stethoscope',
 	medication: 'fa-pills',
-	lab: 'fa-flask'
+	lab: 'fa-flask',
+	allergy: 'fa-triangle-exclamation',
+	vaccination: 'fa-syringe',
+	social_history: 'fa-people-group',
+	family_history: 'fa-people-roof',
+	todo: 'fa-list-check',
+	prescription: 'fa-prescription',
+	message: 'fa-envelope'
 };
 
 // Colors for different result types
@@ -31,7 +53,14 @@ export const SEARCH_RESULT_COLORS: Record<string, string> = {
 	encounter: 'text-green-500',
 	diagnosis: 'text-purple-500',
 	medication: 'text-orange-500',
-	lab: 'text-cyan-500'
+	lab: 'text-cyan-500',
+	allergy: 'text-red-500',
+	vaccination: 'text-teal-500',
+	social_history: 'text-amber-500',
+	family_history: 'text-indigo-500',
+	todo: 'text-yellow-500',
+	prescription: 'text-orange-600',
+	message: 'text-gray-500'
 };
 
 // Labels for different result types
@@ -40,5 +69,12 @@ export const SEARCH_RESULT_LABELS: Record<string, string> = {
 	encounter: 'Note',
 	diagnosis: 'Diagnosis',
 	medication: 'Medication',
-	lab: 'Lab Result'
+	lab: 'Lab Result',
+	allergy: 'Allergy',
+	vaccination: 'Vaccination',
+	social_history: 'Social History',
+	family_history: 'Family History',
+	todo: 'To-do',
+	prescription: 'Prescription',
+	message: 'Message'
 };
@@ -1,58 +1,22 @@
-// TypeScript interfaces for user/provider data
-
-export interface User {
-	id: number | null;
-	username: string;
-	password_hash: string;
-	first_name: string;
-	last_name: string;
-	degree_type: string | null;
-	specialty: string | null;
-	subspecialty: string | null;
-	npi_number: string | null;
-	photo_url: string | null;
-	bio: string | null;
-}
-
-export interface UserEducation {
-	id: number | null;
-	user_id: number;
-	education_type: string;
-	institution: string;
-	degree: string | null;
-	field_of_study: string | null;
-	start_year: number | null;
-	end_year: number | null;
-}
-
-export interface UserBadge {
-	id: number | null;
-	user_id: number;
-	badge_name: string;
-	badge_type: string;
-	description: string | null;
-	icon: string | null;
-	color: string | null;
-	awarded_date: string | null;
-}
-
-export interface UserSettings {
-	id: number | null;
-	user_id: number;
-	language: string | null;
-	notifications_enabled: boolean | null;
-	email_notifications: boolean | null;
-	sms_notifications: boolean | null;
-	two_factor_enabled: boolean | null;
-	zen_mode_default: boolean | null;
-}
-
-export interface UserFullData {
-	user: User;
-	education: UserEducation[];
-	badges: UserBadge[];
-	settings: UserSettings;
-}
+// TypeScript types for user/provider data; the records are generated into src/bindings
+
+import type { Role } from '../../bindings/Role';
+
+export type { Role } from '../../bindings/Role';
+export type { Permission } from '../../bindings/Permission';
+export type { User } from '../../bindings/User';
+export type { UserEducation } from '../../bindings/UserEducation';
+export type { UserBadge } from '../../bindings/UserBadge';
+export type { UserSettings } from '../../bindings/UserSettings';
+export type { UserFullData } from '../../bindings/UserFullData';
+
+export const ROLE_OPTIONS: { value: Role; label: string }[] = [
+	{ value: 'physician', label: 'Physician' },
+	{ value: 'nurse', label: 'Nurse' },
+	{ value: 'front_desk', label: 'Front Desk' },
+	{ value: 'billing', label: 'Billing' },
+	{ value: 'admin', label: 'Administrator' }
+];
 
 // Education type display names
 export const EDUCATION_TYPE_NAMES: Record<string, string> = {
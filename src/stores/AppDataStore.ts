@@ -5,57 +5,18 @@ import { writable, get } from 'svelte/store';
 import { invoke } from '@tauri-apps/api/tauri';
 import type { Patient } from '$lib/types/patient';
 import type { PatientList, PatientListWithPatients } from '$lib/types/patientList';
+import type { Permission, UserFullData } from '$lib/types/user';
+import type { AppointmentWithPatient } from '../bindings/AppointmentWithPatient';
+import type { AuthStatus } from '../bindings/AuthStatus';
+import type { EncryptionStatus } from '../bindings/EncryptionStatus';
+import type { LoginResult } from '../bindings/LoginResult';
+import type { SessionInfo } from '../bindings/SessionInfo';
+import { errorMessage } from '$lib/types/error';
+import { onModelChange, type ModelEvent } from '$lib/types/event';
+import type { UnlistenFn } from '@tauri-apps/api/event';
 
-export interface AppointmentWithPatient {
-	id: number;
-	patient_id: number;
-	patient_name: string;
-	appointment_time: string;
-	duration_minutes: number;
-	reason: string | null;
-	status: string | null;
-}
-
-export interface UserFullData {
-	user: {
-		id: number;
-		username: string;
-		first_name: string;
-		last_name: string;
-		degree_type: string | null;
-		specialty: string | null;
-		subspecialty: string | null;
-		npi_number: string | null;
-		photo_url: string | null;
-		bio: string | null;
-	};
-	education: Array<{
-		id: number;
-		education_type: string;
-		institution: string;
-		degree: string | null;
-		field_of_study: string | null;
-		start_year: number | null;
-		end_year: number | null;
-	}>;
-	badges: Array<{
-		id: number;
-		badge_name: string;
-		badge_type: string;
-		description: string | null;
-		icon: string | null;
-		color: string | null;
-		awarded_date: string | null;
-	}>;
-	settings: {
-		language: string;
-		notifications_enabled: boolean;
-		email_notifications: boolean;
-		sms_notifications: boolean;
-		two_factor_enabled: boolean;
-		zen_mode_default: boolean;
-	} | null;
-}
+export type { AppointmentWithPatient, AuthStatus, EncryptionStatus, LoginResult, SessionInfo, UserFullData };
+export type { TwoFactorEnrollment } from '../bindings/TwoFactorEnrollment';
 
 export interface AppData {
 	patients: Patient[];
@@ -63,6 +24,11 @@ export interface AppData {
 	patientListsWithPatients: Map<number, PatientListWithPatients>;
 	appointments: AppointmentWithPatient[];
 	currentUser: UserFullData | null;
+	encryptionStatus: EncryptionStatus | null;
+	needsUnlock: boolean;
+	authStatus: AuthStatus | null;
+	needsLogin: boolean;
+	needsTwoFactor: boolean;
 	isLoaded: boolean;
 	isLoading: boolean;
 	loadingStep: string;
@@ -76,6 +42,11 @@ const initialState: AppData = {
 	patientListsWithPatients: new Map(),
 	appointments: [],
 	currentUser: null,
+	encryptionStatus: null,
+	needsUnlock: false,
+	authStatus: null,
+	needsLogin: false,
+	needsTwoFactor: false,
 	isLoaded: false,
 	isLoading: false,
 	loadingStep: '',
@@ -83,8 +54,66 @@ const initialState: AppData = {
 	error: null
 };
 
+// How often to check whether the backend has ended an idle session
+const SESSION_CHECK_INTERVAL_MS = 30_000;
+
+// Which cached collections a change to each table makes stale
+const REFRESH_ON: Record<string, Array<'patients' | 'patientLists' | 'appointments'>> = {
+	patients: ['patients', 'patientLists', 'appointments'],
+	patient_lists: ['patientLists'],
+	appointments: ['appointments']
+};
+
 function createAppDataStore() {
 	const { subscribe, set, update } = writable<AppData>(initialState);
+	let sessionTimer: ReturnType<typeof setInterval> | null = null;
+	let stopWatchingChanges: UnlistenFn | null = null;
+
+	// Drop back to the sign-in screen once the backend reports the session gone
+	function watchSession() {
+		if (sessionTimer) clearInterval(sessionTimer);
+		sessionTimer = setInterval(async () => {
+			const authStatus = await invoke<AuthStatus>('db_get_auth_status').catch(() => null);
+			if (!authStatus?.session) {
+				if (sessionTimer) clearInterval(sessionTimer);
+				sessionTimer = null;
+				update((state) => ({ ...state, authStatus, needsLogin: true, isLoaded: false }));
+			}
+		}, SESSION_CHECK_INTERVAL_MS);
+	}
+
+	// Keep the cached patients, lists and appointments current as the backend reports
+	// changes; a burst of events from one command causes a single refetch of each.
+	async function watchChanges(store: { refreshPatients(): Promise<void>; refreshPatientLists(): Promise<void>; refreshAppointments(): Promise<void> }) {
+		stopWatchingChanges?.();
+		const stale = new Set<string>();
+		let timer: ReturnType<typeof setTimeout> | null = null;
+		stopWatchingChanges = await onModelChange((event: ModelEvent) => {
+			for (const collection of REFRESH_ON[event.entity] ?? []) stale.add(collection);
+			if (stale.size === 0 || timer) return;
+			timer = setTimeout(() => {
+				timer = null;
+				const refreshes = [...stale];
+				stale.clear();
+				for (const collection of refreshes) {
+					const refresh =
+						collection === 'patients'
+							? store.refreshPatients()
+							: collection === 'patientLists'
+								? store.refreshPatientLists()
+								: store.refreshAppointments();
+					refresh.catch((e) => console.error(`Failed to refresh ${collection}:`, e));
+				}
+			}, 50);
+		});
+	}
+
+	function stopWatching() {
+		if (sessionTimer) clearInterval(sessionTimer);
+		sessionTimer = null;
+		stopWatchingChanges?.();
+		stopWatchingChanges = null;
+	}
 
 	return {
 		subscribe,
@@ -99,6 +128,30 @@ function createAppDataStore() {
 			}));
 
 			try {
+				// The database is encrypted and stays locked until the user enters the passphrase
+				const encryptionStatus = await invoke<EncryptionStatus>('db_get_encryption_status');
+				if (!encryptionStatus.unlocked) {
+					update((state) => ({
+						...state,
+						encryptionStatus,
+						needsUnlock: true,
+						isLoading: false
+					}));
+					return;
+				}
+
+				// Every data command needs a signed-in user
+				const authStatus = await invoke<AuthStatus>('db_get_auth_status');
+				if (!authStatus.session) {
+					update((state) => ({
+						...state,
+						authStatus,
+						needsLogin: true,
+						isLoading: false
+					}));
+					return;
+				}
+
 				// Step 1: Seed user data
 				update((state) => ({
 					...state,
@@ -129,7 +182,7 @@ function createAppDataStore() {
 					loadingStep: 'Organizing patient lists...',
 					loadingProgress: 50
 				}));
-				await invoke('db_seed_patient_lists', { userId: 1 });
+				await invoke('db_seed_patient_lists');
 
 				// Step 5: Load patient lists
 				update((state) => ({
@@ -137,7 +190,7 @@ function createAppDataStore() {
 					loadingStep: 'Loading patient lists...',
 					loadingProgress: 60
 				}));
-				const patientLists = await invoke<PatientList[]>('db_get_patient_lists', { userId: 1 });
+				const patientLists = await invoke<PatientList[]>('db_get_patient_lists');
 
 				// Step 6: Load each patient list with patients
 				update((state) => ({
@@ -181,20 +234,74 @@ function createAppDataStore() {
 					patientListsWithPatients,
 					appointments,
 					currentUser,
+					authStatus,
 					isLoaded: true,
 					isLoading: false,
 					loadingStep: 'Ready!',
 					loadingProgress: 100
 				}));
+				watchSession();
+				await watchChanges(this);
 			} catch (e) {
 				update((state) => ({
 					...state,
 					isLoading: false,
-					error: e instanceof Error ? e.message : String(e)
+					error: errorMessage(e)
 				}));
 			}
 		},
 
+		// Unlock the encrypted database, then load everything
+		async unlock(passphrase: string) {
+			await invoke('db_unlock', { passphrase });
+			update((state) => ({ ...state, needsUnlock: false }));
+			await this.initialize();
+		},
+
+		// Sign in, then load everything; accounts with two-factor stop at the code prompt
+		async login(username: string, password: string) {
+			const result = await invoke<LoginResult>('db_login', { username, password });
+			if (result.two_factor_required) {
+				update((state) => ({ ...state, needsTwoFactor: true }));
+				return;
+			}
+			update((state) => ({ ...state, needsLogin: false }));
+			await this.initialize();
+		},
+
+		// Second sign-in step: an authenticator code or a recovery code
+		async verifyTwoFactor(code: string) {
+			await invoke<SessionInfo>('db_verify_two_factor', { code });
+			update((state) => ({ ...state, needsLogin: false, needsTwoFactor: false }));
+			await this.initialize();
+		},
+
+		cancelTwoFactor() {
+			update((state) => ({ ...state, needsTwoFactor: false }));
+		},
+
+		// Create the first account on a new database; it is signed in straight away
+		async createFirstUser(user: { username: string; first_name: string; last_name: string }, password: string) {
+			await invoke<SessionInfo>('db_create_first_user', {
+				user: { id: null, degree_type: null, specialty: null, subspecialty: null, npi_number: null, photo_url: null, bio: null, ...user },
+				password
+			});
+			update((state) => ({ ...state, needsLogin: false }));
+			await this.initialize();
+		},
+
+		// Whether the signed-in role has a permission; the backend enforces it regardless
+		can(permission: Permission): boolean {
+			const state = get({ subscribe });
+			return state.authStatus?.session?.permissions.includes(permission) ?? false;
+		},
+
+		async logout() {
+			stopWatching();
+			await invoke('db_logout');
+			update((state) => ({ ...state, needsLogin: true, needsTwoFactor: false, isLoaded: false, currentUser: null }));
+		},
+
 		// Get a specific patient list with patients
 		getPatientListWithPatients(listId: number): PatientListWithPatients | undefined {
 			const state = get({ subscribe });
@@ -209,7 +316,7 @@ function createAppDataStore() {
 
 		// Refresh patient lists
 		async refreshPatientLists() {
-			const patientLists = await invoke<PatientList[]>('db_get_patient_lists', { userId: 1 });
+			const patientLists = await invoke<PatientList[]>('db_get_patient_lists');
 			const patientListsWithPatients = new Map<number, PatientListWithPatients>();
 			for (const list of patientLists) {
 				if (list.id) {
@@ -232,6 +339,7 @@ function createAppDataStore() {
 
 		// Reset store
 		reset() {
+			stopWatching();
 			set(initialState);
 		}
 	};
@@ -1,5 +1,5 @@
 import { writable, derived } from 'svelte/store';
-import type { SearchResult } from '$lib/types/search';
+import type { SearchGroup, SearchResult } from '$lib/types/search';
 import type { Patient } from '$lib/types/patient';
 
 // Search mode: local (filter current list), patient (search within patient), global (full DB search)
@@ -11,6 +11,8 @@ interface SearchState {
 	mode: SearchMode;
 	patientId: number | null;
 	results: SearchResult[];
+	// Global results by chart; `results` holds the same hits flattened, in display order
+	groups: SearchGroup[];
 	localResults: Patient[];
 	isLoading: boolean;
 	selectedIndex: number;
@@ -24,6 +26,7 @@ const initialState: SearchState = {
 	mode: 'local',
 	patientId: null,
 	results: [],
+	groups: [],
 	localResults: [],
 	isLoading: false,
 	selectedIndex: 0,
@@ -61,6 +64,7 @@ function createSearchStore() {
 				patientId,
 				query: '',
 				results: [],
+				groups: [],
 				localResults: [],
 				selectedIndex: 0
 			}));
@@ -73,6 +77,7 @@ function createSearchStore() {
 				isOpen: false,
 				query: '',
 				results: [],
+				groups: [],
 				localResults: [],
 				selectedIndex: 0
 			}));
@@ -91,6 +96,7 @@ function createSearchStore() {
 					patientId,
 					query: '',
 					results: [],
+					groups: [],
 					localResults: [],
 					selectedIndex: 0
 				};
@@ -112,6 +118,7 @@ function createSearchStore() {
 				...state,
 				mode,
 				results: [],
+				groups: [],
 				localResults: [],
 				selectedIndex: 0
 			}));
@@ -122,11 +129,22 @@ function createSearchStore() {
 			update((state) => ({ ...state, isLoading }));
 		},
 
-		// Set global search results
+		// Set patient search results
 		setResults: (results: SearchResult[]) => {
 			update((state) => ({
 				...state,
 				results,
+				groups: [],
+				isLoading: false
+			}));
+		},
+
+		// Set global search results, grouped by chart
+		setGroups: (groups: SearchGroup[]) => {
+			update((state) => ({
+				...state,
+				results: groups.flatMap((group) => group.results),
+				groups,
 				isLoading: false
 			}));
 		},